@@ -0,0 +1,242 @@
+#[cfg(feature = "xwayland")]
+use std::ffi::OsString;
+use std::{
+    sync::{atomic::Ordering, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    drawing::*,
+    render::*,
+    state::{post_repaint, take_presentation_feedback, AnvilState, Backend, CalloopData},
+};
+
+use smithay::{
+    backend::{
+        headless::{FrameTimer, HeadlessBackend, HeadlessEvent},
+        renderer::{
+            damage::OutputDamageTracker, element::AsRenderElements, pixman::PixmanRenderer, ImportMemWl,
+        },
+    },
+    input::{
+        keyboard::LedState,
+        pointer::{CursorImageAttributes, CursorImageStatus},
+    },
+    output::{Mode, Output},
+    reexports::{
+        calloop::EventLoop,
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_server::{protocol::wl_surface, Display},
+    },
+    utils::{IsAlive, Scale},
+    wayland::compositor,
+};
+use tracing::{error, info, trace};
+
+pub const OUTPUT_NAME: &str = "headless";
+
+#[derive(Debug)]
+pub struct HeadlessData {
+    render: bool,
+    backend: HeadlessBackend,
+    damage_tracker: OutputDamageTracker,
+}
+
+impl Backend for HeadlessData {
+    fn seat_name(&self) -> String {
+        "headless".to_owned()
+    }
+    fn reset_buffers(&mut self, output: &Output) {
+        self.backend.reset_buffers(output);
+    }
+    fn early_import(&mut self, _surface: &wl_surface::WlSurface) {}
+    fn update_led_state(&mut self, _led_state: LedState) {}
+}
+
+pub fn run_headless() {
+    let mut event_loop = EventLoop::try_new().unwrap();
+    let display = Display::new().unwrap();
+    let mut display_handle = display.handle();
+
+    let mut backend = match HeadlessBackend::new() {
+        Ok(backend) => backend,
+        Err(err) => {
+            error!("Failed to initialize headless backend: {}", err);
+            return;
+        }
+    };
+
+    let mode = Mode {
+        size: (1920, 1080).into(),
+        refresh: 60_000,
+    };
+    let output = backend.create_output(OUTPUT_NAME, mode);
+    let _global = output.create_global::<AnvilState<HeadlessData>>(&display.handle());
+    output.change_current_state(None, None, None, Some((0, 0).into()));
+
+    let data = HeadlessData {
+        render: true,
+        backend,
+        damage_tracker: OutputDamageTracker::from_output(&output),
+    };
+
+    let mut state = AnvilState::init(display, event_loop.handle(), data, true);
+    state
+        .shm_state
+        .update_formats(state.backend_data.backend.renderer().shm_formats());
+    state.space.map_output(&output, (0, 0));
+
+    event_loop
+        .handle()
+        .insert_source(FrameTimer::new(&output), |event, _, data| match event {
+            HeadlessEvent::Frame { .. } => {
+                data.state.backend_data.render = true;
+            }
+        })
+        .expect("Failed to insert frame timer into event loop");
+
+    #[cfg(feature = "xwayland")]
+    if let Err(e) = state.xwayland.start(
+        state.handle.clone(),
+        None,
+        std::iter::empty::<(OsString, OsString)>(),
+        true,
+        |_| {},
+    ) {
+        error!("Failed to start XWayland: {}", e);
+    }
+    info!("Initialization completed, starting the main loop.");
+
+    let mut pointer_element = PointerElement::default();
+
+    while state.running.load(Ordering::SeqCst) {
+        if state.backend_data.render {
+            profiling::scope!("render_frame");
+
+            let backend_data = &mut state.backend_data;
+            backend_data.render = false;
+
+            let age = match backend_data.backend.bind(&output) {
+                Ok(age) => age,
+                Err(err) => {
+                    error!("Error while binding buffer: {}", err);
+                    profiling::finish_frame!();
+                    continue;
+                }
+            };
+
+            let mut cursor_guard = state.cursor_status.lock().unwrap();
+            let mut elements: Vec<CustomRenderElements<PixmanRenderer>> = Vec::new();
+
+            // draw the cursor as relevant
+            // reset the cursor if the surface is no longer alive
+            let mut reset = false;
+            if let CursorImageStatus::Surface(ref surface) = *cursor_guard {
+                reset = !surface.alive();
+            }
+            if reset {
+                *cursor_guard = CursorImageStatus::default_named();
+            }
+
+            let scale = Scale::from(output.current_scale().fractional_scale());
+            let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = *cursor_guard {
+                compositor::with_states(surface, |states| {
+                    states
+                        .data_map
+                        .get::<Mutex<CursorImageAttributes>>()
+                        .unwrap()
+                        .lock()
+                        .unwrap()
+                        .hotspot
+                })
+            } else {
+                (0, 0).into()
+            };
+            let cursor_pos = state.pointer.current_location() - cursor_hotspot.to_f64();
+            let cursor_pos_scaled = cursor_pos.to_physical(scale).to_i32_round();
+
+            pointer_element.set_status(cursor_guard.clone());
+            elements.extend(pointer_element.render_elements(
+                backend_data.backend.renderer(),
+                cursor_pos_scaled,
+                scale,
+                1.0,
+            ));
+
+            // draw the dnd icon if any
+            if let Some(surface) = state.dnd_icon.as_ref() {
+                if surface.alive() {
+                    elements.extend(AsRenderElements::<PixmanRenderer>::render_elements(
+                        &smithay::desktop::space::SurfaceTree::from_surface(surface),
+                        backend_data.backend.renderer(),
+                        cursor_pos_scaled,
+                        scale,
+                        1.0,
+                    ));
+                }
+            }
+
+            let render_res = render_output(
+                &output,
+                &state.space,
+                elements,
+                backend_data.backend.renderer(),
+                &mut backend_data.damage_tracker,
+                age,
+                state.show_window_preview,
+            );
+
+            match render_res {
+                Ok(render_output_result) => {
+                    trace!("Finished rendering");
+                    if let Err(err) = backend_data.backend.submit(&output) {
+                        backend_data.backend.reset_buffers(&output);
+                        error!("Failed to submit buffer: {}", err);
+                    }
+
+                    // Send frame events so that client start drawing their next frame
+                    let time = state.clock.now();
+                    post_repaint(&output, &render_output_result.states, &state.space, None, time);
+
+                    if render_output_result.damage.is_some() {
+                        let mut output_presentation_feedback =
+                            take_presentation_feedback(&output, &state.space, &render_output_result.states);
+                        output_presentation_feedback.presented(
+                            time,
+                            output
+                                .current_mode()
+                                .map(|mode| Duration::from_secs_f64(1_000f64 / mode.refresh as f64))
+                                .unwrap_or_default(),
+                            0,
+                            wp_presentation_feedback::Kind::empty(),
+                        )
+                    }
+                }
+                Err(err) => {
+                    backend_data.backend.reset_buffers(&output);
+                    error!("Rendering error: {}", err);
+                }
+            }
+
+            profiling::finish_frame!();
+        }
+
+        let mut calloop_data = CalloopData {
+            state,
+            display_handle,
+        };
+        let result = event_loop.dispatch(None, &mut calloop_data);
+        CalloopData {
+            state,
+            display_handle,
+        } = calloop_data;
+
+        if result.is_err() {
+            state.running.store(false, Ordering::SeqCst);
+        } else {
+            state.space.refresh();
+            state.popups.cleanup();
+            display_handle.flush_clients().unwrap();
+        }
+    }
+}
@@ -18,7 +18,7 @@ use smithay::{
     },
     desktop::{layer_map_for_output, WindowSurfaceType},
     input::{
-        keyboard::{keysyms as xkb, FilterResult, Keysym, ModifiersState},
+        keyboard::{BindingModifiers, BindingTrigger, FilterResult, KeyBindings, KeyCombo, Keysym},
         pointer::{AxisFrame, ButtonEvent, MotionEvent},
     },
     output::Scale,
@@ -62,7 +62,7 @@ use smithay::{
         RelativeMotionEvent,
     },
     wayland::{
-        pointer_constraints::{with_pointer_constraint, PointerConstraint},
+        pointer_constraints::with_pointer_constraint,
         seat::WaylandFocus,
         tablet_manager::{TabletDescriptor, TabletSeatTrait},
     },
@@ -152,8 +152,8 @@ impl<BackendData: Backend> AnvilState<BackendData> {
         debug!(keycode, ?state, "key");
         let serial = SCOUNTER.next_serial();
         let time = Event::time_msec(&evt);
-        let mut suppressed_keys = self.suppressed_keys.clone();
         let keyboard = self.seat.get_keyboard().unwrap();
+        keyboard.set_active_device(self, &evt.device());
 
         for layer in self.layer_shell_state.layer_surfaces().rev() {
             let data = with_states(layer.wl_surface(), |states| {
@@ -187,50 +187,26 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             .map(|inhibitor| inhibitor.is_active())
             .unwrap_or(false);
 
-        let action = keyboard
-            .input(self, keycode, state, serial, time, |_, modifiers, handle| {
-                let keysym = handle.modified_sym();
-
+        keyboard
+            .input(self, keycode, state, serial, time, |data, modifiers, handle| {
                 debug!(
                     ?state,
                     mods = ?modifiers,
-                    keysym = ::xkbcommon::xkb::keysym_get_name(keysym),
+                    keysym = ::xkbcommon::xkb::keysym_get_name(handle.modified_sym()),
                     "keysym"
                 );
 
-                // If the key is pressed and triggered a action
-                // we will not forward the key to the client.
-                // Additionally add the key to the suppressed keys
-                // so that we can decide on a release if the key
-                // should be forwarded to the client or not.
-                if let KeyState::Pressed = state {
-                    if !inhibited {
-                        let action = process_keyboard_shortcut(*modifiers, keysym);
-
-                        if action.is_some() {
-                            suppressed_keys.push(keysym);
-                        }
-
-                        action
-                            .map(FilterResult::Intercept)
-                            .unwrap_or(FilterResult::Forward)
-                    } else {
-                        FilterResult::Forward
-                    }
+                // The key bindings intercept the keys triggering an action, including
+                // their release. While shortcuts are inhibited new presses are forwarded
+                // to the client, but releases of already intercepted keys are not.
+                if inhibited && state == KeyState::Pressed {
+                    FilterResult::Forward
                 } else {
-                    let suppressed = suppressed_keys.contains(&keysym);
-                    if suppressed {
-                        suppressed_keys.retain(|k| *k != keysym);
-                        FilterResult::Intercept(KeyAction::None)
-                    } else {
-                        FilterResult::Forward
-                    }
+                    data.key_bindings.process(modifiers, &handle, state, time)
                 }
             })
-            .unwrap_or(KeyAction::None);
-
-        self.suppressed_keys = suppressed_keys;
-        action
+            .flatten()
+            .unwrap_or(KeyAction::None)
     }
 
     fn on_pointer_button<B: InputBackend>(&mut self, evt: B::PointerButtonEvent) {
@@ -738,7 +714,15 @@ impl AnvilState<UdevData> {
                         .add_tablet::<Self>(dh, &TabletDescriptor::from(&device));
                 }
             }
+            InputEvent::SwitchToggle { event } => {
+                let seat = self.seat.clone();
+                seat.process_switch_event::<B>(self, &event);
+            }
             InputEvent::DeviceRemoved { device } => {
+                if device.has_capability(DeviceCapability::Switch) {
+                    let seat = self.seat.clone();
+                    seat.remove_switch_device(self, &device);
+                }
                 if device.has_capability(DeviceCapability::TabletTool) {
                     let tablet_seat = self.seat.tablet_seat();
 
@@ -763,38 +747,9 @@ impl AnvilState<UdevData> {
         let pointer = self.pointer.clone();
         let under = self.surface_under(pointer_location);
 
-        let mut pointer_locked = false;
-        let mut pointer_confined = false;
-        let mut confine_region = None;
-        if let Some((surface, surface_loc)) = under
-            .as_ref()
-            .and_then(|(target, l)| Some((target.wl_surface()?, l)))
-        {
-            with_pointer_constraint(&surface, &pointer, |constraint| match constraint {
-                Some(constraint) if constraint.is_active() => {
-                    // Constraint does not apply if not within region
-                    if !constraint.region().map_or(true, |x| {
-                        x.contains(pointer_location.to_i32_round() - *surface_loc)
-                    }) {
-                        return;
-                    }
-                    match &*constraint {
-                        PointerConstraint::Locked(_locked) => {
-                            pointer_locked = true;
-                        }
-                        PointerConstraint::Confined(confine) => {
-                            pointer_confined = true;
-                            confine_region = confine.region().cloned();
-                        }
-                    }
-                }
-                _ => {}
-            });
-        }
-
         pointer.relative_motion(
             self,
-            under.clone(),
+            under,
             &RelativeMotionEvent {
                 delta: evt.delta(),
                 delta_unaccel: evt.delta_unaccel(),
@@ -802,39 +757,22 @@ impl AnvilState<UdevData> {
             },
         );
 
-        // If pointer is locked, only emit relative motion
-        if pointer_locked {
-            pointer.frame(self);
-            return;
-        }
-
-        pointer_location += evt.delta();
+        // keep the pointer out of the dead zones between outputs of different sizes,
+        // before looking up the focus at its new location
+        self.pointer_barriers.set_space_outputs(&self.space, None);
+        pointer_location = self
+            .pointer_barriers
+            .apply(pointer_location, pointer_location + evt.delta());
 
         // clamp to screen limits
         // this event is never generated by winit
         pointer_location = self.clamp_coords(pointer_location);
 
+        // locked and confined pointers are taken care of by the pointer
         let new_under = self.surface_under(pointer_location);
-
-        // If confined, don't move pointer if it would go outside surface or region
-        if pointer_confined {
-            if let Some((surface, surface_loc)) = &under {
-                if new_under.as_ref().and_then(|(under, _)| under.wl_surface()) != surface.wl_surface() {
-                    pointer.frame(self);
-                    return;
-                }
-                if let Some(region) = confine_region {
-                    if !region.contains(pointer_location.to_i32_round() - *surface_loc) {
-                        pointer.frame(self);
-                        return;
-                    }
-                }
-            }
-        }
-
         pointer.motion(
             self,
-            under,
+            new_under,
             &MotionEvent {
                 location: pointer_location,
                 serial,
@@ -845,8 +783,10 @@ impl AnvilState<UdevData> {
 
         // If pointer is now in a constraint region, activate it
         // TODO Anywhere else pointer is moved needs to do this
-        if let Some((under, surface_location)) =
-            new_under.and_then(|(target, loc)| Some((target.wl_surface()?, loc)))
+        let pointer_location = pointer.current_location();
+        if let Some((under, surface_location)) = self
+            .surface_under(pointer_location)
+            .and_then(|(target, loc)| Some((target.wl_surface()?, loc)))
         {
             with_pointer_constraint(&under, &pointer, |constraint| match constraint {
                 Some(constraint) if !constraint.is_active() => {
@@ -1178,8 +1118,8 @@ impl AnvilState<UdevData> {
 }
 
 /// Possible results of a keyboard action
-#[derive(Debug)]
-enum KeyAction {
+#[derive(Debug, Clone)]
+pub enum KeyAction {
     /// Quit the compositor
     Quit,
     /// Trigger a vt-switch
@@ -1198,35 +1138,57 @@ enum KeyAction {
     None,
 }
 
-fn process_keyboard_shortcut(modifiers: ModifiersState, keysym: Keysym) -> Option<KeyAction> {
-    if modifiers.ctrl && modifiers.alt && keysym == Keysym::BackSpace || modifiers.logo && keysym == Keysym::q
-    {
-        // ctrl+alt+backspace = quit
-        // logo + q = quit
-        Some(KeyAction::Quit)
-    } else if (xkb::KEY_XF86Switch_VT_1..=xkb::KEY_XF86Switch_VT_12).contains(&keysym.raw()) {
-        // VTSwitch
-        Some(KeyAction::VtSwitch(
-            (keysym.raw() - xkb::KEY_XF86Switch_VT_1 + 1) as i32,
-        ))
-    } else if modifiers.logo && keysym == Keysym::Return {
-        // run terminal
-        Some(KeyAction::Run("weston-terminal".into()))
-    } else if modifiers.logo && (xkb::KEY_1..=xkb::KEY_9).contains(&keysym.raw()) {
-        Some(KeyAction::Screen((keysym.raw() - xkb::KEY_1) as usize))
-    } else if modifiers.logo && modifiers.shift && keysym == Keysym::M {
-        Some(KeyAction::ScaleDown)
-    } else if modifiers.logo && modifiers.shift && keysym == Keysym::P {
-        Some(KeyAction::ScaleUp)
-    } else if modifiers.logo && modifiers.shift && keysym == Keysym::W {
-        Some(KeyAction::TogglePreview)
-    } else if modifiers.logo && modifiers.shift && keysym == Keysym::R {
-        Some(KeyAction::RotateOutput)
-    } else if modifiers.logo && modifiers.shift && keysym == Keysym::T {
-        Some(KeyAction::ToggleTint)
-    } else if modifiers.logo && modifiers.shift && keysym == Keysym::D {
-        Some(KeyAction::ToggleDecorations)
-    } else {
-        None
+/// The key bindings of anvil
+///
+/// Keys are matched independently of the active layout, see
+/// [`BindingKey::Keysym`](smithay::input::keyboard::BindingKey::Keysym).
+pub fn key_bindings() -> KeyBindings<KeyAction> {
+    let mut bindings = KeyBindings::new();
+    let mut bind = |modifiers: BindingModifiers, keysym: Keysym, action: KeyAction| {
+        bindings.add(
+            "default",
+            [KeyCombo::new(modifiers, keysym)],
+            BindingTrigger::Press,
+            action,
+        );
+    };
+    let logo_shift = BindingModifiers::LOGO | BindingModifiers::SHIFT;
+
+    // ctrl+alt+backspace = quit
+    // logo + q = quit
+    bind(
+        BindingModifiers::CTRL | BindingModifiers::ALT,
+        Keysym::BackSpace,
+        KeyAction::Quit,
+    );
+    bind(BindingModifiers::LOGO, Keysym::q, KeyAction::Quit);
+    // ctrl+alt+Fn = VTSwitch
+    for vt in 1..=12 {
+        bind(
+            BindingModifiers::CTRL | BindingModifiers::ALT,
+            Keysym::new(Keysym::F1.raw() + vt - 1),
+            KeyAction::VtSwitch(vt as i32),
+        );
+    }
+    // run terminal
+    bind(
+        BindingModifiers::LOGO,
+        Keysym::Return,
+        KeyAction::Run("weston-terminal".into()),
+    );
+    for screen in 0..9 {
+        bind(
+            BindingModifiers::LOGO,
+            Keysym::new(Keysym::_1.raw() + screen),
+            KeyAction::Screen(screen as usize),
+        );
     }
+    bind(logo_shift, Keysym::m, KeyAction::ScaleDown);
+    bind(logo_shift, Keysym::p, KeyAction::ScaleUp);
+    bind(logo_shift, Keysym::w, KeyAction::TogglePreview);
+    bind(logo_shift, Keysym::r, KeyAction::RotateOutput);
+    bind(logo_shift, Keysym::t, KeyAction::ToggleTint);
+    bind(logo_shift, Keysym::d, KeyAction::ToggleDecorations);
+
+    bindings
 }
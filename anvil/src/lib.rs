@@ -10,6 +10,8 @@
 pub mod cursor;
 pub mod drawing;
 pub mod focus;
+#[cfg(feature = "headless")]
+pub mod headless;
 pub mod input_handler;
 pub mod render;
 pub mod shell;
@@ -12,10 +12,11 @@ use smithay::{
     },
     delegate_compositor, delegate_data_control, delegate_data_device, delegate_fractional_scale,
     delegate_input_method_manager, delegate_keyboard_shortcuts_inhibit, delegate_layer_shell,
-    delegate_output, delegate_pointer_constraints, delegate_pointer_gestures, delegate_presentation,
-    delegate_primary_selection, delegate_relative_pointer, delegate_seat, delegate_security_context,
-    delegate_shm, delegate_tablet_manager, delegate_text_input_manager, delegate_viewporter,
-    delegate_virtual_keyboard_manager, delegate_xdg_activation, delegate_xdg_decoration, delegate_xdg_shell,
+    delegate_output, delegate_pointer_constraints, delegate_pointer_gestures, delegate_pointer_warp,
+    delegate_presentation, delegate_primary_selection, delegate_relative_pointer, delegate_seat,
+    delegate_security_context, delegate_shm, delegate_tablet_manager, delegate_text_input_manager,
+    delegate_viewporter, delegate_virtual_keyboard_manager, delegate_xdg_activation, delegate_xdg_decoration,
+    delegate_xdg_shell,
     desktop::{
         space::SpaceElement,
         utils::{
@@ -25,8 +26,9 @@ use smithay::{
         PopupKind, PopupManager, Space,
     },
     input::{
-        keyboard::{Keysym, LedState, XkbConfig},
-        pointer::{CursorImageStatus, PointerHandle},
+        keyboard::{KeyBindings, LedState, XkbConfig},
+        pointer::{CursorImageStatus, MotionEvent, PointerBarriers, PointerHandle},
+        switch::SwitchStates,
         Seat, SeatHandler, SeatState,
     },
     output::Output,
@@ -41,9 +43,11 @@ use smithay::{
             Display, DisplayHandle, Resource,
         },
     },
-    utils::{Clock, Monotonic, Rectangle},
+    utils::{Clock, Logical, Monotonic, Point, Rectangle, SERIAL_COUNTER},
     wayland::{
-        compositor::{get_parent, with_states, CompositorClientState, CompositorState},
+        compositor::{
+            get_parent, with_states, CompositorClientState, CompositorState, SubsurfaceCachedState,
+        },
         dmabuf::DmabufFeedback,
         fractional_scale::{with_fractional_scale, FractionalScaleHandler, FractionalScaleManagerState},
         input_method::{InputMethodHandler, InputMethodManagerState, PopupSurface},
@@ -53,6 +57,7 @@ use smithay::{
         output::{OutputHandler, OutputManagerState},
         pointer_constraints::{with_pointer_constraint, PointerConstraintsHandler, PointerConstraintsState},
         pointer_gestures::PointerGesturesState,
+        pointer_warp::{PointerWarpHandler, PointerWarpManagerState},
         presentation::PresentationState,
         relative_pointer::RelativePointerManagerState,
         seat::WaylandFocus,
@@ -89,11 +94,15 @@ use smithay::{
 
 #[cfg(feature = "xwayland")]
 use crate::cursor::Cursor;
-use crate::{focus::FocusTarget, shell::WindowElement};
+use crate::{
+    focus::FocusTarget,
+    input_handler::{key_bindings, KeyAction},
+    shell::WindowElement,
+};
 #[cfg(feature = "xwayland")]
 use smithay::{
     delegate_xwayland_keyboard_grab,
-    utils::{Point, Size},
+    utils::Size,
     wayland::selection::{SelectionSource, SelectionTarget},
     wayland::xwayland_keyboard_grab::{XWaylandKeyboardGrabHandler, XWaylandKeyboardGrabState},
     xwayland::{X11Wm, XWayland, XWaylandEvent},
@@ -148,12 +157,13 @@ pub struct AnvilState<BackendData: Backend + 'static> {
     pub dnd_icon: Option<WlSurface>,
 
     // input-related fields
-    pub suppressed_keys: Vec<Keysym>,
+    pub key_bindings: KeyBindings<KeyAction>,
     pub cursor_status: Arc<Mutex<CursorImageStatus>>,
     pub seat_name: String,
     pub seat: Seat<AnvilState<BackendData>>,
     pub clock: Clock<Monotonic>,
     pub pointer: PointerHandle<AnvilState<BackendData>>,
+    pub pointer_barriers: PointerBarriers,
 
     #[cfg(feature = "xwayland")]
     pub xwayland: XWayland,
@@ -269,6 +279,10 @@ impl<BackendData: Backend> SeatHandler for AnvilState<BackendData> {
     fn led_state_changed(&mut self, _seat: &Seat<Self>, led_state: LedState) {
         self.backend_data.update_led_state(led_state)
     }
+
+    fn switches_changed(&mut self, _seat: &Seat<Self>, states: SwitchStates) {
+        BackendData::switches_changed(self, states)
+    }
 }
 delegate_seat!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
 
@@ -330,6 +344,56 @@ impl<BackendData: Backend> PointerConstraintsHandler for AnvilState<BackendData>
 }
 delegate_pointer_constraints!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
 
+impl<BackendData: Backend> PointerWarpHandler for AnvilState<BackendData> {
+    fn warp_pointer(
+        &mut self,
+        surface: WlSurface,
+        pointer: PointerHandle<Self>,
+        location: Point<f64, Logical>,
+    ) {
+        let Some((focus, focus_location)) = self.surface_under(pointer.current_location()) else {
+            return;
+        };
+        // The focus and the requested surface may both be part of the same surface tree,
+        // compare their roots and offset the location by the position of the subsurface.
+        let root_of = |mut surface: WlSurface| {
+            let mut offset = Point::<i32, Logical>::default();
+            while let Some(parent) = get_parent(&surface) {
+                offset += with_states(&surface, |states| {
+                    states.cached_state.current::<SubsurfaceCachedState>().location
+                });
+                surface = parent;
+            }
+            (surface, offset)
+        };
+        let Some((focus_root, focus_offset)) = focus.wl_surface().map(root_of) else {
+            return;
+        };
+        let (root, offset) = root_of(surface);
+        if focus_root != root {
+            return;
+        }
+
+        // Only warp inside of the surface
+        let location = (focus_location - focus_offset + offset).to_f64() + location;
+        if self.surface_under(location).map(|(under, _)| under).as_ref() != Some(&focus) {
+            return;
+        }
+
+        pointer.motion(
+            self,
+            Some((focus, focus_location)),
+            &MotionEvent {
+                location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: Duration::from(self.clock.now()).as_millis() as u32,
+            },
+        );
+        pointer.frame(self);
+    }
+}
+delegate_pointer_warp!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
+
 delegate_viewporter!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
 
 impl<BackendData: Backend> XdgActivationHandler for AnvilState<BackendData> {
@@ -577,6 +641,7 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
             RelativePointerManagerState::new::<Self>(&dh);
         }
         PointerConstraintsState::new::<Self>(&dh);
+        PointerWarpManagerState::new::<Self>(&dh);
         if BackendData::HAS_GESTURES {
             PointerGesturesState::new::<Self>(&dh);
         }
@@ -664,11 +729,12 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
             presentation_state,
             fractional_scale_manager_state,
             dnd_icon: None,
-            suppressed_keys: Vec::new(),
+            key_bindings: key_bindings(),
             cursor_status,
             seat_name,
             seat,
             pointer,
+            pointer_barriers: PointerBarriers::new(),
             clock,
             #[cfg(feature = "xwayland")]
             xwayland,
@@ -799,4 +865,9 @@ pub trait Backend {
     fn reset_buffers(&mut self, output: &Output);
     fn early_import(&mut self, surface: &WlSurface);
     fn update_led_state(&mut self, led_state: LedState);
+    fn switches_changed(_state: &mut AnvilState<Self>, _states: SwitchStates)
+    where
+        Self: Sized,
+    {
+    }
 }
@@ -60,6 +60,7 @@ use smithay::{
     input::{
         keyboard::LedState,
         pointer::{CursorImageAttributes, CursorImageStatus},
+        switch::{is_internal_connector, SwitchStates},
     },
     output::{Mode as WlMode, Output, PhysicalProperties, Subpixel},
     reexports::{
@@ -208,6 +209,10 @@ impl Backend for UdevData {
             keyboard.led_update(led_state.into());
         }
     }
+
+    fn switches_changed(state: &mut AnvilState<Self>, states: SwitchStates) {
+        state.update_internal_outputs(states);
+    }
 }
 
 pub fn run_udev() {
@@ -962,6 +967,12 @@ impl AnvilState<UdevData> {
     }
 
     fn connector_connected(&mut self, node: DrmNode, connector: connector::Info, crtc: crtc::Handle) {
+        // the internal panel stays off while the lid is closed
+        if !self.seat.switch_states().output_enabled(connector.interface()) {
+            info!(?crtc, "Skipping internal connector while the lid is closed");
+            return;
+        }
+
         let device = if let Some(device) = self.backend_data.backends.get_mut(&node) {
             device
         } else {
@@ -1217,6 +1228,41 @@ impl AnvilState<UdevData> {
         crate::shell::fixup_positions(&mut self.space, self.pointer.current_location());
     }
 
+    /// Turns the internal panels off while the lid is closed and back on once it is opened
+    fn update_internal_outputs(&mut self, states: SwitchStates) {
+        let connectors = self
+            .backend_data
+            .backends
+            .iter()
+            .flat_map(|(node, device)| {
+                device
+                    .drm_scanner
+                    .crtcs()
+                    .filter(|(info, _)| is_internal_connector(info.interface()))
+                    .map(|(info, crtc)| (*node, info.clone(), crtc))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        for (node, connector, crtc) in connectors {
+            let enabled = self
+                .backend_data
+                .backends
+                .get(&node)
+                .map(|device| device.surfaces.contains_key(&crtc))
+                .unwrap_or(false);
+            if states.output_enabled(connector.interface()) {
+                if !enabled {
+                    self.connector_connected(node, connector, crtc);
+                }
+            } else if enabled {
+                self.connector_disconnected(node, connector, crtc);
+            }
+        }
+
+        crate::shell::fixup_positions(&mut self.space, self.pointer.current_location());
+    }
+
     fn device_removed(&mut self, node: DrmNode) {
         let device = if let Some(device) = self.backend_data.backends.get_mut(&node) {
             device
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use smithay::{
+    backend::{
+        headless::{FrameTimer, HeadlessBackend, HeadlessEvent},
+        renderer::{
+            damage::OutputDamageTracker, element::surface::WaylandSurfaceRenderElement,
+            pixman::PixmanRenderer,
+        },
+    },
+    output::Mode,
+    reexports::calloop::EventLoop,
+};
+
+use crate::{CalloopData, Smallvil};
+
+pub fn init_headless(
+    event_loop: &mut EventLoop<CalloopData>,
+    data: &mut CalloopData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let display_handle = &mut data.display_handle;
+    let state = &mut data.state;
+
+    let mut backend = HeadlessBackend::new()?;
+
+    let mode = Mode {
+        size: (1280, 800).into(),
+        refresh: 60_000,
+    };
+
+    let output = backend.create_output("headless", mode);
+    let _global = output.create_global::<Smallvil>(display_handle);
+    output.change_current_state(None, None, None, Some((0, 0).into()));
+
+    state.space.map_output(&output, (0, 0));
+
+    let mut damage_tracker = OutputDamageTracker::from_output(&output);
+
+    std::env::set_var("WAYLAND_DISPLAY", &state.socket_name);
+
+    event_loop
+        .handle()
+        .insert_source(FrameTimer::new(&output), move |event, _, data| {
+            let display = &mut data.display_handle;
+            let state = &mut data.state;
+
+            match event {
+                HeadlessEvent::Frame { output } => {
+                    let age = backend.bind(&output).unwrap();
+                    smithay::desktop::space::render_output::<
+                        _,
+                        WaylandSurfaceRenderElement<PixmanRenderer>,
+                        _,
+                        _,
+                    >(
+                        &output,
+                        backend.renderer(),
+                        1.0,
+                        age,
+                        [&state.space],
+                        &[],
+                        &mut damage_tracker,
+                        [0.1, 0.1, 0.1, 1.0],
+                    )
+                    .unwrap();
+                    backend.submit(&output).unwrap();
+
+                    state.space.elements().for_each(|window| {
+                        window.send_frame(
+                            &output,
+                            state.start_time.elapsed(),
+                            Some(Duration::ZERO),
+                            |_, _| Some(output.clone()),
+                        )
+                    });
+
+                    state.space.refresh();
+                    state.popups.cleanup();
+                    let _ = display.flush_clients();
+                }
+            }
+        })?;
+
+    Ok(())
+}
@@ -3,6 +3,7 @@
 mod handlers;
 
 mod grabs;
+mod headless;
 mod input;
 mod state;
 mod winit;
@@ -36,9 +37,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         display_handle,
     };
 
-    crate::winit::init_winit(&mut event_loop, &mut data)?;
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "--headless").is_some() {
+        crate::headless::init_headless(&mut event_loop, &mut data)?;
+    } else {
+        crate::winit::init_winit(&mut event_loop, &mut data)?;
+    }
 
-    let mut args = std::env::args().skip(1);
     let flag = args.next();
     let arg = args.next();
 
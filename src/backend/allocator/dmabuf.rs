@@ -265,7 +265,10 @@ impl Dmabuf {
         let size = rustix::fs::seek(&plane.fd, rustix::fs::SeekFrom::End(0)).map_err(std::io::Error::from)?;
         rustix::fs::seek(&plane.fd, rustix::fs::SeekFrom::Start(0)).map_err(std::io::Error::from)?;
 
-        let len = (size - plane.offset as u64) as usize;
+        // mmap requires the offset to be page aligned, which is usually not the case
+        // for planes sharing a buffer with the previous plane
+        let offset = plane.offset as usize % rustix::param::page_size();
+        let len = (size - plane.offset as u64) as usize + offset;
         let ptr = unsafe {
             rustix::mm::mmap(
                 std::ptr::null_mut(),
@@ -273,11 +276,11 @@ impl Dmabuf {
                 mode.into(),
                 rustix::mm::MapFlags::SHARED,
                 &plane.fd,
-                plane.offset as u64,
+                plane.offset as u64 - offset as u64,
             )
         }
         .map_err(std::io::Error::from)?;
-        Ok(DmabufMapping { len, ptr })
+        Ok(DmabufMapping { len, ptr, offset })
     }
 
     /// Synchronize access for the plane at the specified index
@@ -374,17 +377,23 @@ type DmaBufSync = WriteOpcode<b'b', 0, dma_buf_sync>;
 pub struct DmabufMapping {
     ptr: *mut std::ffi::c_void,
     len: usize,
+    // offset of the start of the plane into the mapping
+    offset: usize,
 }
 
 impl DmabufMapping {
     /// Access the raw pointer of the mapping
+    ///
+    /// The pointer points to the start of the plane.
     pub fn ptr(&self) -> *mut std::ffi::c_void {
-        self.ptr
+        unsafe { self.ptr.cast::<u8>().add(self.offset).cast() }
     }
 
     /// Access the length of the mapping
+    ///
+    /// This is the number of bytes accessible from [`DmabufMapping::ptr`].
     pub fn length(&self) -> usize {
-        self.len
+        self.len - self.offset
     }
 }
 
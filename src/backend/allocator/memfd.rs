@@ -0,0 +1,229 @@
+//! Module for buffers in shared memory backed by a memfd
+//!
+//! Unlike the other allocators, the [`MemfdAllocator`] does not require any DRM node and can
+//! thus be used for purely CPU-based rendering, e.g. with the
+//! [`PixmanRenderer`](crate::backend::renderer::pixman::PixmanRenderer).
+
+use std::{
+    ffi::CStr,
+    fmt, io,
+    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
+    rc::Rc,
+};
+
+use rustix::{
+    fs::MemfdFlags,
+    mm::{MapFlags, ProtFlags},
+};
+use tracing::instrument;
+
+use super::{format::get_bpp, Allocator, Buffer, Format, Fourcc, Modifier};
+use crate::utils::{Buffer as BufferCoords, Size};
+
+/// A single-plane buffer in memory backed by a memfd
+///
+/// The buffer is mapped into the address space of the compositor for its whole lifetime.
+/// It is cheaply clonable, all clones refer to the same memory.
+#[derive(Clone)]
+pub struct MemfdBuffer(Rc<MemfdBufferInner>);
+
+struct MemfdBufferInner {
+    fd: OwnedFd,
+    ptr: *mut std::ffi::c_void,
+    len: usize,
+    stride: u32,
+    size: Size<i32, BufferCoords>,
+    format: Format,
+}
+
+impl Drop for MemfdBufferInner {
+    fn drop(&mut self) {
+        let _ = unsafe { rustix::mm::munmap(self.ptr, self.len) };
+    }
+}
+
+impl fmt::Debug for MemfdBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemfdBuffer")
+            .field("fd", &self.0.fd)
+            .field("stride", &self.0.stride)
+            .field("size", &self.0.size)
+            .field("format", &self.0.format)
+            .finish()
+    }
+}
+
+impl PartialEq for MemfdBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl MemfdBuffer {
+    /// Number of bytes per row of the buffer
+    pub fn stride(&self) -> u32 {
+        self.0.stride
+    }
+
+    /// Access the raw pointer of the mapping of the buffer
+    pub fn ptr(&self) -> *mut std::ffi::c_void {
+        self.0.ptr
+    }
+
+    /// Access the length of the mapping of the buffer in bytes
+    pub fn length(&self) -> usize {
+        self.0.len
+    }
+
+    /// Copy the pixel contents of the buffer
+    ///
+    /// The data is laid out in rows of [`stride`](MemfdBuffer::stride) bytes
+    /// in the [`format`](Buffer::format) of the buffer.
+    ///
+    /// The contents are copied out, as the mapping may be written to by a renderer the buffer
+    /// is bound to at any time, so no reference into it can be handed out safely.
+    pub fn copy_data(&self) -> Vec<u8> {
+        let mut data = vec![0; self.0.len];
+        // SAFETY: the mapping is valid for `len` bytes for the lifetime of `self`
+        // and does not overlap with the freshly allocated vector
+        unsafe { std::ptr::copy_nonoverlapping(self.0.ptr as *const u8, data.as_mut_ptr(), self.0.len) };
+        data
+    }
+}
+
+impl AsFd for MemfdBuffer {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.fd.as_fd()
+    }
+}
+
+impl Buffer for MemfdBuffer {
+    fn size(&self) -> Size<i32, BufferCoords> {
+        self.0.size
+    }
+
+    fn format(&self) -> Format {
+        self.0.format
+    }
+}
+
+/// Allocator for [`MemfdBuffer`]s
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemfdAllocator;
+
+impl MemfdAllocator {
+    /// Create a new [`MemfdAllocator`]
+    pub fn new() -> Self {
+        MemfdAllocator
+    }
+}
+
+impl Allocator for MemfdAllocator {
+    type Buffer = MemfdBuffer;
+    type Error = io::Error;
+
+    #[instrument(level = "trace", err)]
+    #[profiling::function]
+    fn create_buffer(
+        &mut self,
+        width: u32,
+        height: u32,
+        fourcc: Fourcc,
+        modifiers: &[Modifier],
+    ) -> Result<MemfdBuffer, Self::Error> {
+        // memfd buffers are always linear
+        if !modifiers.is_empty()
+            && modifiers
+                .iter()
+                .all(|&x| x != Modifier::Invalid && x != Modifier::Linear)
+        {
+            return Err(rustix::io::Errno::INVAL.into());
+        }
+
+        let bpp = get_bpp(fourcc).ok_or(rustix::io::Errno::INVAL)? as u32;
+        // align the rows to 4 bytes, as required by pixman
+        let stride = width
+            .checked_mul(bpp)
+            .and_then(|bits| (bits / 8).checked_add(3))
+            .map(|bytes| bytes & !3)
+            .ok_or(rustix::io::Errno::INVAL)?;
+        let len = (stride as usize)
+            .checked_mul(height as usize)
+            .filter(|len| *len != 0)
+            .ok_or(rustix::io::Errno::INVAL)?;
+
+        let name = CStr::from_bytes_with_nul(b"smithay-memfd-buffer\0").unwrap();
+        let fd = rustix::fs::memfd_create(name, MemfdFlags::CLOEXEC)?;
+        rustix::fs::ftruncate(&fd, len as u64)?;
+        let ptr = unsafe {
+            rustix::mm::mmap(
+                std::ptr::null_mut(),
+                len,
+                ProtFlags::READ | ProtFlags::WRITE,
+                MapFlags::SHARED,
+                &fd,
+                0,
+            )
+        }?;
+
+        Ok(MemfdBuffer(Rc::new(MemfdBufferInner {
+            fd,
+            ptr,
+            len,
+            stride,
+            size: (width as i32, height as i32).into(),
+            format: Format {
+                code: fourcc,
+                modifier: Modifier::Linear,
+            },
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::allocator::Swapchain;
+
+    #[test]
+    fn stride_is_aligned() {
+        let buffer = MemfdAllocator
+            .create_buffer(3, 2, Fourcc::Rgb888, &[Modifier::Linear])
+            .unwrap();
+        assert_eq!(buffer.stride(), 12);
+        assert_eq!(buffer.length(), 24);
+        assert_eq!(buffer.size(), (3, 2).into());
+        assert_eq!(buffer.format().modifier, Modifier::Linear);
+        assert!(buffer.copy_data().iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn rejects_overflowing_sizes() {
+        assert!(MemfdAllocator
+            .create_buffer(u32::MAX, 1, Fourcc::Argb8888, &[Modifier::Linear])
+            .is_err());
+        assert!(MemfdAllocator
+            .create_buffer(0, 16, Fourcc::Argb8888, &[Modifier::Linear])
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_tiled_modifiers() {
+        assert!(MemfdAllocator
+            .create_buffer(16, 16, Fourcc::Argb8888, &[Modifier::I915_x_tiled])
+            .is_err());
+    }
+
+    #[test]
+    fn swapchain_reuses_buffers() {
+        let mut swapchain = Swapchain::new(MemfdAllocator, 16, 16, Fourcc::Argb8888, vec![Modifier::Linear]);
+        let first = swapchain.acquire().unwrap().unwrap();
+        let buffer = (*first).clone();
+        swapchain.submitted(&first);
+        drop(first);
+
+        let second = swapchain.acquire().unwrap().unwrap();
+        assert_eq!(*second, buffer);
+        assert_eq!(second.age(), 1);
+    }
+}
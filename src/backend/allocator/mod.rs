@@ -6,11 +6,13 @@
 //! Allocators provided:
 //! - Dumb Buffers through [`crate::backend::drm::DrmDevice`]
 //! - Gbm Buffers through [`::gbm::Device`]
+//! - Memfd Buffers through [`memfd::MemfdAllocator`]
 //!
 //! Buffer types supported:
 //! - [DumbBuffers](dumb::DumbBuffer)
 //! - [GbmBuffers](::gbm::BufferObject)
 //! - [DmaBufs](dmabuf::Dmabuf)
+//! - [MemfdBuffers](memfd::MemfdBuffer)
 //!
 //! Helpers:
 //! - [`Swapchain`] to help with buffer management for framebuffers
@@ -21,6 +23,8 @@ pub mod dumb;
 pub mod format;
 #[cfg(feature = "backend_gbm")]
 pub mod gbm;
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+pub mod memfd;
 #[cfg(feature = "backend_vulkan")]
 pub mod vulkan;
 
@@ -0,0 +1,450 @@
+//! Headless backend
+//!
+//! This module provides a backend that does not require any display hardware or host
+//! display server, which makes it possible to run a compositor in CI or on render servers.
+//!
+//! The [`HeadlessBackend`] creates virtual [`Output`]s with a configurable mode and renders
+//! them through a [`PixmanRenderer`] into a swapchain of [`MemfdBuffer`]s.
+//! Frames are paced by a [`FrameTimer`], a calloop event source that fires at the refresh
+//! rate of the current mode of an output:
+//!
+//! ```no_run
+//! use smithay::backend::headless::{FrameTimer, HeadlessBackend, HeadlessEvent};
+//! use smithay::output::Mode;
+//! # let event_loop = calloop::EventLoop::<()>::try_new().unwrap();
+//!
+//! let mut backend = HeadlessBackend::new().unwrap();
+//! let output = backend.create_output(
+//!     "HEADLESS-1",
+//!     Mode {
+//!         size: (1920, 1080).into(),
+//!         refresh: 60_000,
+//!     },
+//! );
+//!
+//! event_loop
+//!     .handle()
+//!     .insert_source(FrameTimer::new(&output), |event, _, _| match event {
+//!         HeadlessEvent::Frame { output } => {
+//!             // bind the output, render into `backend.renderer()` and submit the frame
+//!         }
+//!     })
+//!     .unwrap();
+//! ```
+//!
+//! Rendering follows the usual pattern: [`HeadlessBackend::bind`] binds the next free buffer of
+//! the output to the renderer and returns its age for damage tracking, and [`HeadlessBackend::submit`]
+//! marks it as presented. The contents of the last presented frame can be read back using
+//! [`HeadlessBackend::presented_buffer`].
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    backend::{
+        allocator::{
+            memfd::{MemfdAllocator, MemfdBuffer},
+            Fourcc, Modifier, Slot, Swapchain,
+        },
+        renderer::{
+            pixman::{PixmanError, PixmanRenderBuffer, PixmanRenderer},
+            Bind, Unbind,
+        },
+        SwapBuffersError,
+    },
+    output::{Mode, Output, PhysicalProperties, Subpixel, WeakOutput},
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+/// Pixel format of the buffers rendered by the headless backend
+const HEADLESS_FORMAT: Fourcc = Fourcc::Xrgb8888;
+
+/// Refresh rate used for outputs without a (valid) mode, in mHz
+const FALLBACK_REFRESH: i32 = 60_000;
+
+/// Errors of the headless backend
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessError {
+    /// The output was not created by this backend or has been removed
+    #[error("The output is not managed by this backend")]
+    UnknownOutput,
+    /// The output has no current mode
+    #[error("The output has no current mode")]
+    NoMode,
+    /// All buffers of the swapchain are in use
+    #[error("No free buffer available in the swapchain")]
+    NoFreeSlots,
+    /// Allocating a buffer failed
+    #[error("Failed to allocate a buffer: {0}")]
+    Allocation(#[from] std::io::Error),
+    /// [`HeadlessBackend::submit`] was called without binding the output first
+    #[error("No buffer is bound for the output")]
+    NotBound,
+    /// The pixman renderer encountered an error
+    #[error("Rendering failed: {0}")]
+    Rendering(#[from] PixmanError),
+}
+
+impl From<HeadlessError> for SwapBuffersError {
+    fn from(err: HeadlessError) -> SwapBuffersError {
+        match err {
+            HeadlessError::Rendering(err) => err.into(),
+            x @ HeadlessError::NoFreeSlots | x @ HeadlessError::Allocation(_) => {
+                SwapBuffersError::TemporaryFailure(Box::new(x))
+            }
+            x => SwapBuffersError::ContextLost(Box::new(x)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct VirtualOutput {
+    output: Output,
+    swapchain: Swapchain<MemfdAllocator>,
+    bound: Option<Slot<MemfdBuffer>>,
+    presented: Option<Slot<MemfdBuffer>>,
+}
+
+/// Backend rendering virtual outputs into CPU memory
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct HeadlessBackend {
+    renderer: PixmanRenderer,
+    outputs: Vec<VirtualOutput>,
+}
+
+impl HeadlessBackend {
+    /// Create a new headless backend without any outputs
+    pub fn new() -> Result<HeadlessBackend, HeadlessError> {
+        Ok(HeadlessBackend {
+            renderer: PixmanRenderer::new()?,
+            outputs: Vec::new(),
+        })
+    }
+
+    /// Access the underlying renderer
+    pub fn renderer(&mut self) -> &mut PixmanRenderer {
+        &mut self.renderer
+    }
+
+    /// Create a new virtual output with the given mode
+    ///
+    /// The mode is set as the current and preferred mode of the output.
+    /// Later mode changes done through [`Output::change_current_state`] are picked up
+    /// by the next call to [`bind`](HeadlessBackend::bind).
+    pub fn create_output(&mut self, name: impl Into<String>, mode: Mode) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Headless".into(),
+            },
+        );
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+
+        let swapchain = Swapchain::new(
+            MemfdAllocator,
+            mode.size.w as u32,
+            mode.size.h as u32,
+            HEADLESS_FORMAT,
+            vec![Modifier::Linear],
+        );
+        self.outputs.push(VirtualOutput {
+            output: output.clone(),
+            swapchain,
+            bound: None,
+            presented: None,
+        });
+
+        output
+    }
+
+    /// Remove a virtual output from the backend
+    ///
+    /// Any [`FrameTimer`] of the output stops once all other references to the output are dropped.
+    pub fn remove_output(&mut self, output: &Output) {
+        if let Some(pos) = self.outputs.iter().position(|o| &o.output == output) {
+            if self.outputs[pos].bound.is_some() {
+                let _ = self.renderer.unbind();
+            }
+            self.outputs.remove(pos);
+        }
+    }
+
+    /// Iterate over the virtual outputs of this backend
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.outputs.iter().map(|o| &o.output)
+    }
+
+    /// Bind the next buffer of the given output to the renderer
+    ///
+    /// Returns the age of the bound buffer, which can be used for damage tracking.
+    pub fn bind(&mut self, output: &Output) -> Result<usize, HeadlessError> {
+        if self.outputs.iter().any(|o| o.bound.is_some()) {
+            self.renderer.unbind()?;
+            for virtual_output in &mut self.outputs {
+                virtual_output.bound = None;
+            }
+        }
+
+        let virtual_output = self
+            .outputs
+            .iter_mut()
+            .find(|o| &o.output == output)
+            .ok_or(HeadlessError::UnknownOutput)?;
+        let size = output.current_mode().ok_or(HeadlessError::NoMode)?.size;
+        virtual_output.swapchain.resize(size.w as u32, size.h as u32);
+
+        let slot = virtual_output
+            .swapchain
+            .acquire()?
+            .ok_or(HeadlessError::NoFreeSlots)?;
+        self.renderer.bind(PixmanRenderBuffer::try_from(&*slot)?)?;
+
+        let age = slot.age() as usize;
+        virtual_output.bound = Some(slot);
+        Ok(age)
+    }
+
+    /// Submit the currently bound buffer of the given output
+    ///
+    /// The buffer becomes the [presented buffer](HeadlessBackend::presented_buffer) of the output.
+    pub fn submit(&mut self, output: &Output) -> Result<(), HeadlessError> {
+        let virtual_output = self
+            .outputs
+            .iter_mut()
+            .find(|o| &o.output == output)
+            .ok_or(HeadlessError::UnknownOutput)?;
+        let slot = virtual_output.bound.take().ok_or(HeadlessError::NotBound)?;
+        self.renderer.unbind()?;
+
+        virtual_output.swapchain.submitted(&slot);
+        virtual_output.presented = Some(slot);
+        Ok(())
+    }
+
+    /// Access the last submitted buffer of the given output
+    pub fn presented_buffer(&self, output: &Output) -> Option<&MemfdBuffer> {
+        self.outputs
+            .iter()
+            .find(|o| &o.output == output)
+            .and_then(|o| o.presented.as_deref())
+    }
+
+    /// Reset the buffer ages of the given output, forcing a full redraw for the next frame
+    pub fn reset_buffers(&mut self, output: &Output) {
+        if let Some(virtual_output) = self.outputs.iter_mut().find(|o| &o.output == output) {
+            virtual_output.swapchain.reset_buffer_ages();
+        }
+    }
+}
+
+/// Events generated by a [`FrameTimer`]
+#[derive(Debug)]
+pub enum HeadlessEvent {
+    /// A new frame should be rendered for the output
+    Frame {
+        /// Output the frame is due for
+        output: Output,
+    },
+}
+
+/// Event source pacing the frames of a virtual output
+///
+/// The timer fires at the refresh rate of the current mode of the output. Frames that are
+/// missed, e.g. because the event loop was busy, are skipped instead of being delivered
+/// in a burst. The source removes itself once the output has been dropped.
+#[derive(Debug)]
+pub struct FrameTimer {
+    output: WeakOutput,
+    timer: Timer,
+}
+
+impl FrameTimer {
+    /// Create a new frame timer for the given output
+    ///
+    /// The first frame is due one refresh interval after creation.
+    pub fn new(output: &Output) -> FrameTimer {
+        FrameTimer {
+            output: output.downgrade(),
+            timer: Timer::from_duration(frame_interval(output)),
+        }
+    }
+}
+
+fn frame_interval(output: &Output) -> Duration {
+    let refresh = output
+        .current_mode()
+        .map(|mode| mode.refresh)
+        .filter(|refresh| *refresh > 0)
+        .unwrap_or(FALLBACK_REFRESH);
+    Duration::from_secs_f64(1_000f64 / refresh as f64)
+}
+
+impl EventSource for FrameTimer {
+    type Event = HeadlessEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = std::io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let weak_output = &self.output;
+        self.timer.process_events(readiness, token, |deadline, _| {
+            let Some(output) = weak_output.upgrade() else {
+                return TimeoutAction::Drop;
+            };
+            let interval = frame_interval(&output);
+            callback(HeadlessEvent::Frame { output }, &mut ());
+
+            let now = Instant::now();
+            let next = deadline + interval;
+            if next > now {
+                TimeoutAction::ToInstant(next)
+            } else {
+                TimeoutAction::ToInstant(now + interval)
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.timer.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{
+            allocator::Buffer,
+            renderer::{Frame, Renderer},
+        },
+        utils::{Rectangle, Transform},
+    };
+
+    fn mode() -> Mode {
+        Mode {
+            size: (64, 32).into(),
+            refresh: 60_000,
+        }
+    }
+
+    #[test]
+    fn render_into_presented_buffer() {
+        let mut backend = HeadlessBackend::new().unwrap();
+        let output = backend.create_output("HEADLESS-1", mode());
+        assert!(backend.presented_buffer(&output).is_none());
+
+        let age = backend.bind(&output).unwrap();
+        assert_eq!(age, 0);
+        let mut frame = backend
+            .renderer()
+            .render((64, 32).into(), Transform::Normal)
+            .unwrap();
+        frame
+            .clear(
+                [1.0, 0.0, 0.0, 1.0],
+                &[Rectangle::from_loc_and_size((0, 0), (64, 32))],
+            )
+            .unwrap();
+        let _ = frame.finish().unwrap();
+        backend.submit(&output).unwrap();
+
+        let buffer = backend.presented_buffer(&output).unwrap();
+        assert_eq!(buffer.size(), (64, 32).into());
+        // xrgb8888 is stored as little endian b, g, r, x
+        assert_eq!(&buffer.copy_data()[0..3], &[0, 0, 255]);
+
+        assert_eq!(backend.bind(&output).unwrap(), 0);
+        backend.submit(&output).unwrap();
+        assert_eq!(backend.bind(&output).unwrap(), 2);
+    }
+
+    #[test]
+    fn submit_without_bind() {
+        let mut backend = HeadlessBackend::new().unwrap();
+        let output = backend.create_output("HEADLESS-1", mode());
+        assert!(matches!(backend.submit(&output), Err(HeadlessError::NotBound)));
+
+        backend.remove_output(&output);
+        assert!(matches!(backend.bind(&output), Err(HeadlessError::UnknownOutput)));
+    }
+
+    #[test]
+    fn mode_change_resizes_buffers() {
+        let mut backend = HeadlessBackend::new().unwrap();
+        let output = backend.create_output("HEADLESS-1", mode());
+        let new_mode = Mode {
+            size: (16, 8).into(),
+            refresh: 30_000,
+        };
+        output.change_current_state(Some(new_mode), None, None, None);
+
+        backend.bind(&output).unwrap();
+        backend.submit(&output).unwrap();
+        assert_eq!(backend.presented_buffer(&output).unwrap().size(), (16, 8).into());
+        assert_eq!(frame_interval(&output), Duration::from_secs_f64(1.0 / 30.0));
+    }
+
+    #[test]
+    fn frame_timer_fires_until_output_is_dropped() {
+        let mut event_loop = calloop::EventLoop::<u32>::try_new().unwrap();
+        let mut backend = HeadlessBackend::new().unwrap();
+        let output = backend.create_output(
+            "HEADLESS-1",
+            Mode {
+                size: (16, 16).into(),
+                refresh: 1_000_000,
+            },
+        );
+        event_loop
+            .handle()
+            .insert_source(
+                FrameTimer::new(&output),
+                |HeadlessEvent::Frame { .. }, _, frames| {
+                    *frames += 1;
+                },
+            )
+            .unwrap();
+
+        let mut frames = 0;
+        while frames < 3 {
+            event_loop
+                .dispatch(Some(Duration::from_millis(10)), &mut frames)
+                .unwrap();
+        }
+
+        backend.remove_output(&output);
+        drop(output);
+        event_loop
+            .dispatch(Some(Duration::from_millis(10)), &mut frames)
+            .unwrap();
+        let count = frames;
+        event_loop
+            .dispatch(Some(Duration::from_millis(10)), &mut frames)
+            .unwrap();
+        assert_eq!(frames, count);
+    }
+}
@@ -0,0 +1,372 @@
+//! Backend-agnostic description of input device configuration
+//!
+//! A [`DeviceConfig`] describes settings like pointer acceleration, tap-to-click or
+//! scroll behavior independently of the input backend in use. Together with a
+//! [`DeviceMatcher`] it can be handed to a backend capable of configuring its devices,
+//! like the [`LibinputInputBackend`](crate::backend::libinput::LibinputInputBackend).
+//!
+//! All settings are optional, unset settings leave the respective device defaults untouched.
+
+use std::fmt;
+
+use super::{Device, DeviceCapability};
+
+/// Pointer acceleration profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccelProfile {
+    /// Pointer motion is accelerated by a constant factor
+    Flat,
+    /// Pointer acceleration depends on the input speed
+    Adaptive,
+}
+
+/// Method used to generate software-emulated buttons on clickpads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClickMethod {
+    /// Use software-button areas to generate button events
+    ButtonAreas,
+    /// The number of fingers decides which button press to generate
+    Clickfinger,
+}
+
+/// Method used to generate scroll events instead of pointer motion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollMethod {
+    /// Never send scroll events instead of pointer motion events
+    NoScroll,
+    /// Send scroll events when two fingers are logically down on the device
+    TwoFinger,
+    /// Send scroll events when a finger moves along the bottom or right edge of the device
+    Edge,
+    /// Send scroll events when a button is down and the device moves along a scroll-capable axis
+    OnButtonDown,
+}
+
+/// Mapping of 1/2/3 finger taps to buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TapButtonMap {
+    /// 1/2/3 finger tap maps to left/right/middle
+    LeftRightMiddle,
+    /// 1/2/3 finger tap maps to left/middle/right
+    LeftMiddleRight,
+}
+
+/// Mode controlling when a device sends events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendEventsMode {
+    /// Send events from this device normally
+    Enabled,
+    /// Do not send events from this device
+    Disabled,
+    /// Do not send events while an external pointer device is plugged in
+    DisabledOnExternalMouse,
+}
+
+/// Configuration to be applied to an input device
+///
+/// Every setting left as `None` is not touched when applying the configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceConfig {
+    /// Pointer acceleration profile
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer acceleration speed, normalized to a range of `[-1, 1]`
+    pub accel_speed: Option<f64>,
+    /// Enable tap-to-click
+    pub tap: Option<bool>,
+    /// Mapping of multi-finger taps to buttons
+    pub tap_button_map: Option<TapButtonMap>,
+    /// Enable tap-and-drag
+    pub tap_drag: Option<bool>,
+    /// Enable drag-lock while tap-and-dragging
+    pub tap_drag_lock: Option<bool>,
+    /// Enable natural (inverted) scrolling
+    pub natural_scroll: Option<bool>,
+    /// Method used to generate scroll events
+    pub scroll_method: Option<ScrollMethod>,
+    /// Button used for [`ScrollMethod::OnButtonDown`]
+    pub scroll_button: Option<u32>,
+    /// Method used to generate software-emulated buttons
+    pub click_method: Option<ClickMethod>,
+    /// Swap primary and secondary buttons
+    pub left_handed: Option<bool>,
+    /// Emulate a middle button by pressing left and right buttons simultaneously
+    pub middle_emulation: Option<bool>,
+    /// Disable the device while typing
+    pub disable_while_typing: Option<bool>,
+    /// Clockwise rotation of the device in degrees
+    pub rotation_angle: Option<u32>,
+    /// Calibration matrix for absolute devices, as the first two rows of a 3x3 matrix
+    pub calibration_matrix: Option<[f32; 6]>,
+    /// When the device is allowed to send events
+    pub send_events: Option<SendEventsMode>,
+}
+
+impl DeviceConfig {
+    /// Merge another configuration into this one
+    ///
+    /// Settings set in `other` take precedence over settings of `self`.
+    pub fn merge(&mut self, other: &DeviceConfig) {
+        macro_rules! merge {
+            ($($field:ident),*) => {
+                $(
+                    if other.$field.is_some() {
+                        self.$field = other.$field;
+                    }
+                )*
+            };
+        }
+        merge!(
+            accel_profile,
+            accel_speed,
+            tap,
+            tap_button_map,
+            tap_drag,
+            tap_drag_lock,
+            natural_scroll,
+            scroll_method,
+            scroll_button,
+            click_method,
+            left_handed,
+            middle_emulation,
+            disable_while_typing,
+            rotation_angle,
+            calibration_matrix,
+            send_events
+        );
+    }
+}
+
+/// Selects the devices a [`DeviceConfig`] applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceMatcher {
+    /// Match every device
+    Any,
+    /// Match devices with the given name, see [`Device::name`]
+    Name(String),
+    /// Match devices providing the given capability
+    Capability(DeviceCapability),
+    /// Match devices carrying the given udev input tag, e.g. `ID_INPUT_TOUCHPAD`
+    ///
+    /// Only devices exposing a [`syspath`](Device::syspath) can be matched this way
+    /// and matching requires the `backend_udev` feature.
+    UdevTag(String),
+    /// Match devices matched by all of the given matchers
+    All(Vec<DeviceMatcher>),
+}
+
+impl DeviceMatcher {
+    /// Test if the given device is matched
+    pub fn matches<D: Device>(&self, device: &D) -> bool {
+        match self {
+            DeviceMatcher::Any => true,
+            DeviceMatcher::Name(name) => device.name() == *name,
+            DeviceMatcher::Capability(capability) => device.has_capability(*capability),
+            DeviceMatcher::UdevTag(tag) => has_udev_tag(device, tag),
+            DeviceMatcher::All(matchers) => matchers.iter().all(|matcher| matcher.matches(device)),
+        }
+    }
+}
+
+#[cfg(feature = "backend_udev")]
+fn has_udev_tag<D: Device>(device: &D, tag: &str) -> bool {
+    device
+        .syspath()
+        .and_then(|path| udev::Device::from_syspath(&path).ok())
+        .and_then(|device| device.property_value(tag).map(|value| value == "1"))
+        .unwrap_or(false)
+}
+
+#[cfg(not(feature = "backend_udev"))]
+fn has_udev_tag<D: Device>(_device: &D, _tag: &str) -> bool {
+    false
+}
+
+/// Individual setting of a [`DeviceConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)] // self explanatory, see `DeviceConfig`
+pub enum ConfigOption {
+    AccelProfile,
+    AccelSpeed,
+    Tap,
+    TapButtonMap,
+    TapDrag,
+    TapDragLock,
+    NaturalScroll,
+    ScrollMethod,
+    ScrollButton,
+    ClickMethod,
+    LeftHanded,
+    MiddleEmulation,
+    DisableWhileTyping,
+    RotationAngle,
+    CalibrationMatrix,
+    SendEvents,
+}
+
+impl fmt::Display for ConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConfigOption::AccelProfile => "acceleration profile",
+            ConfigOption::AccelSpeed => "acceleration speed",
+            ConfigOption::Tap => "tap-to-click",
+            ConfigOption::TapButtonMap => "tap button map",
+            ConfigOption::TapDrag => "tap-and-drag",
+            ConfigOption::TapDragLock => "tap drag lock",
+            ConfigOption::NaturalScroll => "natural scrolling",
+            ConfigOption::ScrollMethod => "scroll method",
+            ConfigOption::ScrollButton => "scroll button",
+            ConfigOption::ClickMethod => "click method",
+            ConfigOption::LeftHanded => "left-handed mode",
+            ConfigOption::MiddleEmulation => "middle button emulation",
+            ConfigOption::DisableWhileTyping => "disable-while-typing",
+            ConfigOption::RotationAngle => "rotation angle",
+            ConfigOption::CalibrationMatrix => "calibration matrix",
+            ConfigOption::SendEvents => "send events mode",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Reason a setting could not be applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigErrorKind {
+    /// The setting is not supported by the device
+    Unsupported,
+    /// The value is out of the range accepted by the device
+    Invalid,
+}
+
+impl fmt::Display for ConfigErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigErrorKind::Unsupported => f.write_str("unsupported by the device"),
+            ConfigErrorKind::Invalid => f.write_str("invalid value"),
+        }
+    }
+}
+
+/// Error applying a [`DeviceConfig`] to a device
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Failed to apply {option} to device {device}: {kind}")]
+pub struct DeviceConfigError {
+    /// Name of the affected device
+    pub device: String,
+    /// Setting that failed to apply
+    pub option: ConfigOption,
+    /// Reason for the failure
+    pub kind: ConfigErrorKind,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct TestDevice {
+        name: &'static str,
+        capabilities: &'static [DeviceCapability],
+    }
+
+    impl std::hash::Hash for TestDevice {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.name.hash(state);
+        }
+    }
+
+    impl Device for TestDevice {
+        fn id(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn has_capability(&self, capability: DeviceCapability) -> bool {
+            self.capabilities.contains(&capability)
+        }
+
+        fn usb_id(&self) -> Option<(u32, u32)> {
+            None
+        }
+
+        fn syspath(&self) -> Option<PathBuf> {
+            None
+        }
+    }
+
+    const TOUCHPAD: TestDevice = TestDevice {
+        name: "Synaptics TM3276-022",
+        capabilities: &[DeviceCapability::Pointer, DeviceCapability::Gesture],
+    };
+    const KEYBOARD: TestDevice = TestDevice {
+        name: "AT Translated Set 2 keyboard",
+        capabilities: &[DeviceCapability::Keyboard],
+    };
+
+    #[test]
+    fn matcher_name_and_capability() {
+        assert!(DeviceMatcher::Any.matches(&TOUCHPAD));
+        assert!(DeviceMatcher::Name("Synaptics TM3276-022".into()).matches(&TOUCHPAD));
+        assert!(!DeviceMatcher::Name("Synaptics".into()).matches(&TOUCHPAD));
+        assert!(DeviceMatcher::Capability(DeviceCapability::Gesture).matches(&TOUCHPAD));
+        assert!(!DeviceMatcher::Capability(DeviceCapability::Gesture).matches(&KEYBOARD));
+    }
+
+    #[test]
+    fn matcher_all() {
+        let matcher = DeviceMatcher::All(vec![
+            DeviceMatcher::Capability(DeviceCapability::Pointer),
+            DeviceMatcher::Name("Synaptics TM3276-022".into()),
+        ]);
+        assert!(matcher.matches(&TOUCHPAD));
+        assert!(!matcher.matches(&KEYBOARD));
+        assert!(DeviceMatcher::All(Vec::new()).matches(&KEYBOARD));
+    }
+
+    #[test]
+    fn matcher_udev_tag_without_syspath() {
+        // devices without a syspath can never carry udev tags
+        assert!(!DeviceMatcher::UdevTag("ID_INPUT_TOUCHPAD".into()).matches(&TOUCHPAD));
+    }
+
+    #[test]
+    fn merge_prefers_other() {
+        let mut config = DeviceConfig {
+            tap: Some(false),
+            accel_speed: Some(0.5),
+            left_handed: Some(true),
+            ..Default::default()
+        };
+        config.merge(&DeviceConfig {
+            tap: Some(true),
+            scroll_method: Some(ScrollMethod::TwoFinger),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            config,
+            DeviceConfig {
+                tap: Some(true),
+                accel_speed: Some(0.5),
+                left_handed: Some(true),
+                scroll_method: Some(ScrollMethod::TwoFinger),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn merge_empty_is_noop() {
+        let mut config = DeviceConfig {
+            calibration_matrix: Some([1.0, 0.0, 0.0, 0.0, 1.0, 0.0]),
+            send_events: Some(SendEventsMode::DisabledOnExternalMouse),
+            ..Default::default()
+        };
+        let expected = config.clone();
+        config.merge(&DeviceConfig::default());
+        assert_eq!(config, expected);
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::path::PathBuf;
 
+pub mod config;
 mod tablet;
 
 pub use tablet::{
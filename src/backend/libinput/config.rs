@@ -0,0 +1,165 @@
+use crate::backend::input::config::{
+    AccelProfile, ClickMethod, ConfigErrorKind, ConfigOption, DeviceConfig, DeviceConfigError, ScrollMethod,
+    SendEventsMode, TapButtonMap,
+};
+
+use input as libinput;
+
+impl From<AccelProfile> for libinput::AccelProfile {
+    fn from(profile: AccelProfile) -> Self {
+        match profile {
+            AccelProfile::Flat => libinput::AccelProfile::Flat,
+            AccelProfile::Adaptive => libinput::AccelProfile::Adaptive,
+        }
+    }
+}
+
+impl From<ClickMethod> for libinput::ClickMethod {
+    fn from(method: ClickMethod) -> Self {
+        match method {
+            ClickMethod::ButtonAreas => libinput::ClickMethod::ButtonAreas,
+            ClickMethod::Clickfinger => libinput::ClickMethod::Clickfinger,
+        }
+    }
+}
+
+impl From<ScrollMethod> for libinput::ScrollMethod {
+    fn from(method: ScrollMethod) -> Self {
+        match method {
+            ScrollMethod::NoScroll => libinput::ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger => libinput::ScrollMethod::TwoFinger,
+            ScrollMethod::Edge => libinput::ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown => libinput::ScrollMethod::OnButtonDown,
+        }
+    }
+}
+
+impl From<TapButtonMap> for libinput::TapButtonMap {
+    fn from(map: TapButtonMap) -> Self {
+        match map {
+            TapButtonMap::LeftRightMiddle => libinput::TapButtonMap::LeftRightMiddle,
+            TapButtonMap::LeftMiddleRight => libinput::TapButtonMap::LeftMiddleRight,
+        }
+    }
+}
+
+impl From<SendEventsMode> for libinput::SendEventsMode {
+    fn from(mode: SendEventsMode) -> Self {
+        match mode {
+            SendEventsMode::Enabled => libinput::SendEventsMode::ENABLED,
+            SendEventsMode::Disabled => libinput::SendEventsMode::DISABLED,
+            SendEventsMode::DisabledOnExternalMouse => libinput::SendEventsMode::DISABLED_ON_EXTERNAL_MOUSE,
+        }
+    }
+}
+
+impl From<libinput::DeviceConfigError> for ConfigErrorKind {
+    fn from(err: libinput::DeviceConfigError) -> Self {
+        match err {
+            libinput::DeviceConfigError::Unsupported => ConfigErrorKind::Unsupported,
+            libinput::DeviceConfigError::Invalid => ConfigErrorKind::Invalid,
+        }
+    }
+}
+
+/// Applies every setting of `config` to `device`, returning the settings that failed
+pub(super) fn apply_config(device: &mut libinput::Device, config: &DeviceConfig) -> Vec<DeviceConfigError> {
+    let mut errors = Vec::new();
+    let name = device.name().to_string();
+    let mut check = |option: ConfigOption, result: libinput::DeviceConfigResult| {
+        if let Err(err) = result {
+            errors.push(DeviceConfigError {
+                device: name.clone(),
+                option,
+                kind: err.into(),
+            });
+        }
+    };
+
+    if let Some(profile) = config.accel_profile {
+        check(
+            ConfigOption::AccelProfile,
+            device.config_accel_set_profile(profile.into()),
+        );
+    }
+    if let Some(speed) = config.accel_speed {
+        check(ConfigOption::AccelSpeed, device.config_accel_set_speed(speed));
+    }
+    if let Some(enabled) = config.tap {
+        check(ConfigOption::Tap, device.config_tap_set_enabled(enabled));
+    }
+    if let Some(map) = config.tap_button_map {
+        check(
+            ConfigOption::TapButtonMap,
+            device.config_tap_set_button_map(map.into()),
+        );
+    }
+    if let Some(enabled) = config.tap_drag {
+        check(ConfigOption::TapDrag, device.config_tap_set_drag_enabled(enabled));
+    }
+    if let Some(enabled) = config.tap_drag_lock {
+        check(
+            ConfigOption::TapDragLock,
+            device.config_tap_set_drag_lock_enabled(enabled),
+        );
+    }
+    if let Some(enabled) = config.natural_scroll {
+        check(
+            ConfigOption::NaturalScroll,
+            device.config_scroll_set_natural_scroll_enabled(enabled),
+        );
+    }
+    if let Some(method) = config.scroll_method {
+        check(
+            ConfigOption::ScrollMethod,
+            device.config_scroll_set_method(method.into()),
+        );
+    }
+    if let Some(button) = config.scroll_button {
+        check(
+            ConfigOption::ScrollButton,
+            device.config_scroll_set_button(button),
+        );
+    }
+    if let Some(method) = config.click_method {
+        check(
+            ConfigOption::ClickMethod,
+            device.config_click_set_method(method.into()),
+        );
+    }
+    if let Some(enabled) = config.left_handed {
+        check(ConfigOption::LeftHanded, device.config_left_handed_set(enabled));
+    }
+    if let Some(enabled) = config.middle_emulation {
+        check(
+            ConfigOption::MiddleEmulation,
+            device.config_middle_emulation_set_enabled(enabled),
+        );
+    }
+    if let Some(enabled) = config.disable_while_typing {
+        check(
+            ConfigOption::DisableWhileTyping,
+            device.config_dwt_set_enabled(enabled),
+        );
+    }
+    if let Some(angle) = config.rotation_angle {
+        check(
+            ConfigOption::RotationAngle,
+            device.config_rotation_set_angle(angle),
+        );
+    }
+    if let Some(matrix) = config.calibration_matrix {
+        check(
+            ConfigOption::CalibrationMatrix,
+            device.config_calibration_set_matrix(matrix),
+        );
+    }
+    if let Some(mode) = config.send_events {
+        check(
+            ConfigOption::SendEvents,
+            device.config_send_events_set_mode(mode.into()),
+        );
+    }
+
+    errors
+}
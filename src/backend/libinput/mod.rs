@@ -1,7 +1,9 @@
 //! Implementation of input backend trait for types provided by `libinput`
 
 use crate::backend::input::{
-    self as backend, Axis, AxisRelativeDirection, AxisSource, InputBackend, InputEvent,
+    self as backend,
+    config::{DeviceConfig, DeviceConfigError, DeviceMatcher},
+    Axis, AxisRelativeDirection, AxisSource, InputBackend, InputEvent,
 };
 #[cfg(feature = "backend_session")]
 use crate::backend::session::{AsErrno, Session};
@@ -18,17 +20,25 @@ use std::{os::unix::io::OwnedFd, path::Path};
 
 use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory};
 
-use tracing::{debug_span, info, trace};
+use tracing::{debug_span, info, trace, warn};
 
+mod config;
 mod tablet;
 
 /// Libinput based [`InputBackend`].
 ///
 /// Tracks input of all devices given manually or via a udev seat to a provided libinput
 /// context.
+///
+/// Devices can be configured via [`DeviceConfig`]s registered with
+/// [`LibinputInputBackend::add_device_config`], which are applied to all matching
+/// devices, including ones added later on.
 #[derive(Debug)]
 pub struct LibinputInputBackend {
     context: libinput::Libinput,
+    devices: Vec<libinput::Device>,
+    device_configs: Vec<(DeviceMatcher, DeviceConfig)>,
+    config_errors: Vec<DeviceConfigError>,
     token: Option<Token>,
     span: tracing::Span,
 }
@@ -45,6 +55,9 @@ impl LibinputInputBackend {
         drop(_guard);
         LibinputInputBackend {
             context,
+            devices: Vec::new(),
+            device_configs: Vec::new(),
+            config_errors: Vec::new(),
             token: None,
             span,
         }
@@ -54,6 +67,69 @@ impl LibinputInputBackend {
     pub fn context(&self) -> &libinput::Libinput {
         &self.context
     }
+
+    /// Registers a device configuration for all devices matched by `matcher`
+    ///
+    /// The configuration is immediately applied to all currently known matching devices
+    /// and will be applied to matching devices added in the future before their
+    /// [`InputEvent::DeviceAdded`] event is emitted. If multiple configurations match a
+    /// device, they are applied in the order they were added. Settings failing to apply
+    /// to added devices can be queried via [`LibinputInputBackend::take_device_config_errors`].
+    ///
+    /// Returns the settings that could not be applied to the currently known devices.
+    pub fn add_device_config(
+        &mut self,
+        matcher: DeviceMatcher,
+        config: DeviceConfig,
+    ) -> Vec<DeviceConfigError> {
+        let errors = self.apply_device_config(&matcher, &config);
+        self.device_configs.push((matcher, config));
+        errors
+    }
+
+    /// Applies a device configuration once to all currently known devices matched by `matcher`
+    ///
+    /// Unlike [`LibinputInputBackend::add_device_config`] the configuration is not
+    /// remembered for devices added later on.
+    ///
+    /// Returns the settings that could not be applied.
+    pub fn apply_device_config(
+        &mut self,
+        matcher: &DeviceMatcher,
+        config: &DeviceConfig,
+    ) -> Vec<DeviceConfigError> {
+        let _guard = self.span.enter();
+        self.devices
+            .iter_mut()
+            .filter(|device| matcher.matches(&**device))
+            .flat_map(|device| config::apply_config(device, config))
+            .collect()
+    }
+
+    /// Returns the registered device configurations
+    pub fn device_configs(&self) -> &[(DeviceMatcher, DeviceConfig)] {
+        &self.device_configs
+    }
+
+    /// Removes all registered device configurations
+    ///
+    /// This does not reset settings already applied to devices.
+    pub fn clear_device_configs(&mut self) {
+        self.device_configs.clear();
+    }
+
+    /// Returns the settings that could not be applied to devices added since the last call
+    ///
+    /// The errors of a device are available once its [`InputEvent::DeviceAdded`] event
+    /// has been emitted.
+    pub fn take_device_config_errors(&mut self) -> Vec<DeviceConfigError> {
+        std::mem::take(&mut self.config_errors)
+    }
+
+    /// Returns all devices currently known to this backend
+    pub fn devices(&self) -> &[libinput::Device] {
+        &self.devices
+    }
 }
 
 impl backend::Device for libinput::Device {
@@ -715,10 +791,20 @@ impl EventSource for LibinputInputBackend {
                 match event {
                     libinput::Event::Device(device_event) => match device_event {
                         event::DeviceEvent::Added(device_added_event) => {
-                            let added = event::EventTrait::device(&device_added_event);
+                            let mut added = event::EventTrait::device(&device_added_event);
 
                             info!("New device {:?}", added.sysname(),);
 
+                            for (matcher, config) in &self.device_configs {
+                                if matcher.matches(&added) {
+                                    for err in config::apply_config(&mut added, config) {
+                                        warn!("{}", err);
+                                        self.config_errors.push(err);
+                                    }
+                                }
+                            }
+                            self.devices.push(added.clone());
+
                             callback(InputEvent::DeviceAdded { device: added }, &mut ());
                         }
                         event::DeviceEvent::Removed(device_removed_event) => {
@@ -726,6 +812,8 @@ impl EventSource for LibinputInputBackend {
 
                             info!("Removed device {:?}", removed.sysname(),);
 
+                            self.devices.retain(|device| *device != removed);
+
                             callback(InputEvent::DeviceRemoved { device: removed }, &mut ());
                         }
                         _ => {
@@ -28,6 +28,10 @@
 //! [`libinput`] module, gated by the `backend_libinput` cargo feature. The winit backend
 //! (see below) also provides an input provider.
 //!
+//! The input events of any input provider can be recorded and replayed later on using
+//! the [`replay`] module. For tests, the [`virtual_input`] module provides an input provider
+//! whose devices and events are created programmatically.
+//!
 //! ### Graphics
 //!
 //! Combining content from the clients and displaying it on the screen is the central role of
@@ -78,15 +82,26 @@
 //! development and debugging. That backend is both a renderer and an input provider, and is
 //! accessible in the [`winit`] module, gated by the `backend_winit` cargo feature.
 //!
+//! ## Headless backend
+//!
+//! For environments without any display hardware or host display server, like CI or render
+//! servers, Smithay provides a headless backend creating virtual outputs, which are rendered
+//! into CPU memory using the pixman renderer. It is accessible in the [`headless`] module, gated
+//! by the `backend_headless` cargo feature.
+//!
 
 pub mod allocator;
 pub mod input;
 pub mod renderer;
+pub mod replay;
+pub mod virtual_input;
 
 #[cfg(feature = "backend_drm")]
 pub mod drm;
 #[cfg(feature = "backend_egl")]
 pub mod egl;
+#[cfg(feature = "backend_headless")]
+pub mod headless;
 #[cfg(feature = "backend_libinput")]
 pub mod libinput;
 #[cfg(feature = "backend_session")]
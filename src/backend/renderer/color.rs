@@ -0,0 +1,283 @@
+//! Renderer-agnostic color transformations
+//!
+//! A [`ColorTransform`] describes a post-processing step applied to already rendered
+//! content, consisting of a 4x4 color matrix and an optional 3D lookup table ([`ColorLut`]).
+//! This can be used to implement e.g. color-blindness filters, night-light without hardware
+//! gamma support or inverting the screen.
+//!
+//! Transformations are usually applied through
+//! [`OutputDamageTracker::set_color_transform`](super::damage::OutputDamageTracker::set_color_transform),
+//! which takes care of running [`Frame::apply_color_transform`](super::Frame::apply_color_transform)
+//! after all elements have been rendered and forces a full redraw, whenever the transformation changes.
+//!
+//! ```
+//! use smithay::backend::renderer::color::{ColorLut, ColorTransform};
+//!
+//! // reduce the blue and green channels for a simple night-light
+//! let night_light = ColorTransform::from_matrix([
+//!     [1.0, 0.0, 0.0, 0.0],
+//!     [0.0, 0.8, 0.0, 0.0],
+//!     [0.0, 0.0, 0.6, 0.0],
+//!     [0.0, 0.0, 0.0, 1.0],
+//! ]);
+//! assert_eq!(night_light.apply([1.0, 1.0, 1.0]), [1.0, 0.8, 0.6]);
+//!
+//! // or use a lookup table to apply a gamma curve
+//! let gamma = ColorLut::from_fn(17, |[r, g, b]| [r.powf(2.2), g.powf(2.2), b.powf(2.2)]);
+//! let transform = ColorTransform::default().with_lut(gamma);
+//! # assert!(!transform.is_identity());
+//! ```
+//!
+//! ## Precision
+//!
+//! The pixman renderer applies transformations on the CPU with full `f32` precision,
+//! which is also what the snapshot tests are rendered with.
+//! The gles renderer interpolates the lookup table on the GPU instead. It is uploaded as a half-float
+//! texture if the renderer supports `Capability::ColorTransformations` and with 8 bits per channel
+//! otherwise, so results of the two renderers may differ by a few values per channel.
+
+use std::sync::Arc;
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Color transformation consisting of a color matrix and an optional 3D lookup table
+///
+/// The matrix is applied first, the lookup table (if any) is applied to the clamped result.
+/// Both operate on non-premultiplied colors, the alpha channel is never modified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorTransform {
+    matrix: [[f32; 4]; 4],
+    lut: Option<ColorLut>,
+}
+
+impl Default for ColorTransform {
+    fn default() -> Self {
+        ColorTransform {
+            matrix: IDENTITY,
+            lut: None,
+        }
+    }
+}
+
+impl ColorTransform {
+    /// Create a new color transform from a row-major 4x4 matrix
+    ///
+    /// The matrix is multiplied with the color vector `(r, g, b, 1.0)`,
+    /// so the last column can be used to add a constant offset to each channel.
+    /// The last row is ignored.
+    pub fn from_matrix(matrix: [[f32; 4]; 4]) -> Self {
+        ColorTransform { matrix, lut: None }
+    }
+
+    /// Color transform inverting all color channels
+    pub fn inverted_colors() -> Self {
+        ColorTransform::from_matrix([
+            [-1.0, 0.0, 0.0, 1.0],
+            [0.0, -1.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Add a 3D lookup table, that is applied after the color matrix
+    pub fn with_lut(mut self, lut: ColorLut) -> Self {
+        self.lut = Some(lut);
+        self
+    }
+
+    /// Returns the row-major color matrix of this transform
+    pub fn matrix(&self) -> [[f32; 4]; 4] {
+        self.matrix
+    }
+
+    /// Returns the 3D lookup table of this transform, if any
+    pub fn lut(&self) -> Option<&ColorLut> {
+        self.lut.as_ref()
+    }
+
+    /// Returns true if this transform does not alter any colors
+    pub fn is_identity(&self) -> bool {
+        self.lut.is_none() && self.matrix[..3] == IDENTITY[..3]
+    }
+
+    /// Apply this transform to a single non-premultiplied color
+    ///
+    /// This is the reference implementation renderers are expected to match.
+    /// Every channel of the result is clamped to `0.0..=1.0`.
+    pub fn apply(&self, color: [f32; 3]) -> [f32; 3] {
+        let input = [color[0], color[1], color[2], 1.0];
+        let mut output = [0.0; 3];
+        for (row, out) in self.matrix.iter().zip(output.iter_mut()) {
+            let value: f32 = row.iter().zip(input.iter()).map(|(m, c)| m * c).sum();
+            *out = value.clamp(0.0, 1.0);
+        }
+
+        match self.lut.as_ref() {
+            Some(lut) => lut.sample(output),
+            None => output,
+        }
+    }
+}
+
+/// 3D color lookup table
+///
+/// The table is sampled with trilinear interpolation.
+/// Cloning a lookup table is cheap, as the data is shared.
+#[derive(Debug, Clone)]
+pub struct ColorLut {
+    size: usize,
+    data: Arc<[[f32; 3]]>,
+}
+
+impl PartialEq for ColorLut {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && (Arc::ptr_eq(&self.data, &other.data) || self.data == other.data)
+    }
+}
+
+impl ColorLut {
+    /// Create a new lookup table with `size` entries per dimension
+    ///
+    /// `data` is indexed by `r + g * size + b * size * size` and needs to contain
+    /// exactly `size^3` entries. Returns `None` if `size` is less than 2
+    /// or the length of `data` does not match.
+    pub fn new(size: usize, data: Vec<[f32; 3]>) -> Option<Self> {
+        if size < 2 || data.len() != size * size * size {
+            return None;
+        }
+
+        Some(ColorLut {
+            size,
+            data: data.into(),
+        })
+    }
+
+    /// Create a new lookup table by evaluating `func` at every grid point
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is less than 2.
+    pub fn from_fn(size: usize, mut func: impl FnMut([f32; 3]) -> [f32; 3]) -> Self {
+        assert!(
+            size >= 2,
+            "A color lookup table needs at least two entries per dimension"
+        );
+
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push(func([r as f32 / max, g as f32 / max, b as f32 / max]));
+                }
+            }
+        }
+
+        ColorLut {
+            size,
+            data: data.into(),
+        }
+    }
+
+    /// Returns the number of entries per dimension
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the entries of this table
+    pub fn data(&self) -> &[[f32; 3]] {
+        &self.data
+    }
+
+    /// Sample the lookup table at the given color
+    pub fn sample(&self, color: [f32; 3]) -> [f32; 3] {
+        let size = self.size;
+        let max = (size - 1) as f32;
+        let pos = color.map(|c| c.clamp(0.0, 1.0) * max);
+        let base = pos.map(|p| (p.floor() as usize).min(size - 2));
+        let frac = [
+            pos[0] - base[0] as f32,
+            pos[1] - base[1] as f32,
+            pos[2] - base[2] as f32,
+        ];
+
+        let mut result = [0.0; 3];
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let weight: f32 = (0..3)
+                .map(|i| if offset[i] == 1 { frac[i] } else { 1.0 - frac[i] })
+                .product();
+            if weight == 0.0 {
+                continue;
+            }
+
+            let index =
+                (base[0] + offset[0]) + (base[1] + offset[1]) * size + (base[2] + offset[2]) * size * size;
+            for (out, value) in result.iter_mut().zip(self.data[index].iter()) {
+                *out += weight * value;
+            }
+        }
+
+        result.map(|c| c.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_eq(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn identity() {
+        let transform = ColorTransform::default();
+        assert!(transform.is_identity());
+        assert_color_eq(transform.apply([0.2, 0.4, 0.6]), [0.2, 0.4, 0.6]);
+        assert!(!ColorTransform::inverted_colors().is_identity());
+    }
+
+    #[test]
+    fn matrix_offset_and_clamp() {
+        let transform = ColorTransform::inverted_colors();
+        assert_color_eq(transform.apply([0.2, 0.4, 1.0]), [0.8, 0.6, 0.0]);
+
+        let transform = ColorTransform::from_matrix([
+            [2.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, -0.5],
+            [1.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_color_eq(transform.apply([0.75, 0.25, 0.0]), [1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn lut_sampling() {
+        assert!(ColorLut::new(1, vec![[0.0; 3]]).is_none());
+        assert!(ColorLut::new(2, vec![[0.0; 3]; 7]).is_none());
+
+        // identity tables interpolate to the input
+        let lut = ColorLut::from_fn(5, |c| c);
+        assert_color_eq(lut.sample([0.1, 0.5, 0.93]), [0.1, 0.5, 0.93]);
+        assert_color_eq(lut.sample([1.0, 0.0, 1.0]), [1.0, 0.0, 1.0]);
+
+        // channels are looked up in the right dimension
+        let lut = ColorLut::from_fn(2, |[r, g, b]| [b, r, g]);
+        assert_color_eq(lut.sample([0.25, 0.5, 0.75]), [0.75, 0.25, 0.5]);
+
+        let transform = ColorTransform::inverted_colors().with_lut(lut.clone());
+        assert_color_eq(transform.apply([0.25, 0.5, 0.75]), [0.25, 0.75, 0.5]);
+        assert_eq!(lut, ColorLut::from_fn(2, |[r, g, b]| [b, r, g]));
+    }
+}
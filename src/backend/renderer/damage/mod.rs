@@ -200,7 +200,7 @@ use smallvec::{smallvec, SmallVec};
 use tracing::{info_span, instrument, trace};
 
 use crate::{
-    backend::renderer::{element::RenderElementPresentationState, Frame},
+    backend::renderer::{color::ColorTransform, element::RenderElementPresentationState, Frame},
     output::{Output, OutputModeSource, OutputNoMode},
     utils::{Physical, Rectangle, Scale, Size, Transform},
 };
@@ -254,6 +254,7 @@ struct RendererState {
     elements: IndexMap<Id, ElementState>,
     old_damage: VecDeque<Vec<Rectangle<i32, Physical>>>,
     opaque_regions: Vec<Rectangle<i32, Physical>>,
+    color_transform: Option<ColorTransform>,
 }
 
 /// Damage tracker for a single output
@@ -262,6 +263,7 @@ pub struct OutputDamageTracker {
     mode: OutputModeSource,
     last_state: RendererState,
     damage_shaper: DamageShaper,
+    color_transform: Option<ColorTransform>,
     span: tracing::Span,
 }
 
@@ -321,6 +323,7 @@ impl OutputDamageTracker {
             },
             last_state: Default::default(),
             damage_shaper: Default::default(),
+            color_transform: None,
             span: info_span!("renderer_damage"),
         }
     }
@@ -335,6 +338,7 @@ impl OutputDamageTracker {
             mode: OutputModeSource::Auto(output.clone()),
             damage_shaper: Default::default(),
             last_state: Default::default(),
+            color_transform: None,
             span: info_span!("renderer_damage", output = output.name()),
         }
     }
@@ -350,6 +354,7 @@ impl OutputDamageTracker {
             span: info_span!("render_damage"),
             damage_shaper: Default::default(),
             last_state: Default::default(),
+            color_transform: None,
         }
     }
 
@@ -358,6 +363,24 @@ impl OutputDamageTracker {
         &self.mode
     }
 
+    /// Set a [`ColorTransform`] applied to every rendered frame
+    ///
+    /// The transformation is applied as a post-processing step through
+    /// [`Frame::apply_color_transform`] after all elements have been rendered.
+    /// Changing the transformation damages the whole output on the next render.
+    ///
+    /// Passing `None` or an identity transformation disables the post-processing step.
+    /// Renderers not supporting color transformations, see [`Renderer::supports_color_transform`],
+    /// render the frames unchanged.
+    pub fn set_color_transform(&mut self, transform: Option<ColorTransform>) {
+        self.color_transform = transform.filter(|transform| !transform.is_identity());
+    }
+
+    /// Returns the [`ColorTransform`] currently applied to rendered frames
+    pub fn color_transform(&self) -> Option<&ColorTransform> {
+        self.color_transform.as_ref()
+    }
+
     /// Render this output with the provided [`Renderer`] in the provided buffer
     ///
     /// - `elements` for this output in front-to-back order
@@ -604,6 +627,7 @@ impl OutputDamageTracker {
 
         if self.last_state.size != Some(output_geo.size)
             || self.last_state.transform != Some(output_transform)
+            || self.last_state.color_transform != self.color_transform
         {
             // The output geometry or transform changed, so just damage everything
             trace!(
@@ -611,7 +635,7 @@ impl OutputDamageTracker {
                 current_geometry = ?output_geo.size,
                 previous_transform = ?self.last_state.transform,
                 current_transform = ?output_transform,
-                "Output geometry or transform changed, damaging whole output geometry");
+                "Output geometry, transform or color transform changed, damaging whole output geometry");
             *damage = vec![output_geo];
         }
 
@@ -693,6 +717,7 @@ impl OutputDamageTracker {
 
         self.last_state.size = Some(output_geo.size);
         self.last_state.transform = Some(output_transform);
+        self.last_state.color_transform = self.color_transform.clone();
         self.last_state.elements = new_elements_state;
         self.last_state.old_damage.push_front(new_damage);
         self.last_state.opaque_regions.clear();
@@ -811,6 +836,11 @@ impl OutputDamageTracker {
                 element.draw(&mut frame, element.src(), element_geometry, &element_damage)?;
             }
 
+            if let Some(color_transform) = self.color_transform.as_ref() {
+                trace!("applying color transform to damage {:?}", damage);
+                frame.apply_color_transform(color_transform, &damage)?;
+            }
+
             frame.finish()
         })();
 
@@ -829,3 +859,59 @@ impl OutputDamageTracker {
         }
     }
 }
+
+#[cfg(all(test, feature = "renderer_pixman"))]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::{
+        color::ColorLut,
+        snapshot::{squares, SnapshotTest},
+    };
+
+    #[test]
+    fn color_transform_invert() {
+        let mut test = SnapshotTest::new((16, 16)).color_transform(ColorTransform::inverted_colors());
+        assert!(test.renderer().supports_color_transform());
+        test.assert_snapshot("color_transform_invert", &squares());
+    }
+
+    #[test]
+    fn color_transform_lut() {
+        // map every color to its luminance
+        let lut = ColorLut::from_fn(9, |[r, g, b]| {
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+            [luma, luma, luma]
+        });
+        let mut test = SnapshotTest::new((16, 16)).color_transform(ColorTransform::default().with_lut(lut));
+        test.assert_snapshot("color_transform_lut", &squares());
+    }
+
+    #[test]
+    fn color_transform_change_damages_output() {
+        let mut test = SnapshotTest::new((16, 16));
+        test.bind_buffer();
+
+        let elements = squares();
+        let full_damage = Some(vec![Rectangle::from_loc_and_size((0, 0), (16, 16))]);
+        let mut damage_tracker = OutputDamageTracker::new((16, 16), 1.0, Transform::Normal);
+        let mut render = |damage_tracker: &mut OutputDamageTracker| {
+            damage_tracker
+                .render_output(test.renderer(), 1, &elements, [0.0, 0.0, 0.0, 1.0])
+                .unwrap()
+                .damage
+        };
+
+        assert_eq!(render(&mut damage_tracker), full_damage);
+        assert_eq!(render(&mut damage_tracker), None);
+
+        damage_tracker.set_color_transform(Some(ColorTransform::inverted_colors()));
+        assert_eq!(render(&mut damage_tracker), full_damage);
+        damage_tracker.set_color_transform(Some(ColorTransform::inverted_colors()));
+        assert_eq!(render(&mut damage_tracker), None);
+
+        // identity transforms are treated like no transform at all
+        damage_tracker.set_color_transform(Some(ColorTransform::default()));
+        assert!(damage_tracker.color_transform().is_none());
+        assert_eq!(render(&mut damage_tracker), full_damage);
+    }
+}
@@ -214,6 +214,7 @@ use std::{
     sync::Arc,
 };
 
+use cgmath::Matrix3;
 use tracing::{instrument, trace, warn};
 
 use crate::{
@@ -227,7 +228,7 @@ use crate::{
     utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
 };
 
-use super::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use super::{Element, Id, Kind, RenderElement, TransformableRenderElement, UnderlyingStorage};
 
 /// A buffer storing pixel data backed by system memory
 #[derive(Debug, Clone)]
@@ -794,3 +795,29 @@ where
         Some(UnderlyingStorage::Memory(buf.mem.clone()))
     }
 }
+
+impl<R> TransformableRenderElement<R> for MemoryRenderBufferRenderElement<R>
+where
+    R: Renderer + ImportMem,
+    <R as Renderer>::TextureId: 'static,
+{
+    #[instrument(level = "trace", skip(self, frame))]
+    #[profiling::function]
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        let mut guard = self.buffer.inner.borrow_mut();
+        let transform = guard.transform;
+        let Some(texture) = guard.get_texture::<R>(frame.id()) else {
+            warn!("trying to render texture from different renderer");
+            return Ok(());
+        };
+
+        frame.render_texture_with_matrix(texture, src, dst, damage, matrix, transform, self.alpha)
+    }
+}
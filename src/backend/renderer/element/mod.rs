@@ -12,6 +12,7 @@
 //! - [`texture`] - Texture based render element
 //! - [`surface`] - Wayland surface render element
 //! - [`solid`] - Solid color render element
+//! - [`offscreen`] - Render element caching other elements in a texture
 //!
 //! The [`render_elements!`] macro provides an easy way to aggregate multiple different [RenderElement]s
 //! into a single enum.
@@ -19,6 +20,7 @@
 //! See the [`damage`](crate::backend::renderer::damage) module for more information on
 //! damage tracking.
 
+use cgmath::Matrix3;
 use std::{collections::HashMap, sync::Arc};
 
 #[cfg(feature = "wayland_frontend")]
@@ -34,6 +36,7 @@ use super::utils::Buffer;
 use super::{utils::CommitCounter, Renderer};
 
 pub mod memory;
+pub mod offscreen;
 pub mod solid;
 #[cfg(feature = "wayland_frontend")]
 pub mod surface;
@@ -398,6 +401,25 @@ pub trait RenderElement<R: Renderer>: Element {
     }
 }
 
+/// A [`RenderElement`] that can be drawn with an arbitrary affine transformation
+///
+/// This is used by [`TransformRenderElement`](utils::TransformRenderElement) to draw the
+/// wrapped element.
+pub trait TransformableRenderElement<R: Renderer>: RenderElement<R> {
+    /// Draw this element into `dst` and apply the affine transformation described by `matrix`
+    ///
+    /// The damage is given in the coordinate space of the frame after applying the transformation,
+    /// see [`Frame::render_texture_with_matrix`](super::Frame::render_texture_with_matrix).
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error>;
+}
+
 /// Types that can be converted into [`RenderElement`]s
 pub trait AsRenderElements<R>
 where
@@ -484,6 +506,23 @@ where
     }
 }
 
+impl<R, E> TransformableRenderElement<R> for &E
+where
+    R: Renderer,
+    E: TransformableRenderElement<R> + Element,
+{
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        (*self).draw_transformed(frame, src, dst, matrix, damage)
+    }
+}
+
 #[macro_export]
 #[doc(hidden)]
 macro_rules! render_elements_internal {
@@ -615,6 +654,9 @@ macro_rules! render_elements_internal {
     (@call $name:ident; $($x:ident),*) => {
         $crate::backend::renderer::element::Element::$name($($x),*)
     };
+    (@call $renderer:ty; draw_transformed; $($x:ident),*) => {
+        $crate::backend::renderer::element::TransformableRenderElement::<$renderer>::draw_transformed($($x),*)
+    };
     (@call $renderer:ty; $name:ident; $($x:ident),*) => {
         $crate::backend::renderer::element::RenderElement::<$renderer>::$name($($x),*)
     };
@@ -833,6 +875,36 @@ macro_rules! render_elements_internal {
             }
         }
     };
+    // The variants bound their field types, which can't be made conditional on attributes like `cfg`.
+    // So the impl is only generated for enums without attributes on their variants.
+    (@transform [$($generics:tt)*] $name:ty; $renderer:ident; [$($bounds:tt)*]; $($body:ident=$field:ty $(as <$other_renderer:ty>)?),* $(,)?) => {
+        impl<$($generics)*> $crate::backend::renderer::element::TransformableRenderElement<$renderer> for $name
+        where
+            $renderer: $crate::backend::renderer::Renderer,
+            <$renderer as $crate::backend::renderer::Renderer>::TextureId: 'static,
+            $($bounds)*
+            $(
+                $field: $crate::backend::renderer::element::TransformableRenderElement<$renderer>,
+            )*
+        {
+            fn draw_transformed(
+                &self,
+                frame: &mut <$renderer as $crate::backend::renderer::Renderer>::Frame<'_>,
+                src: $crate::utils::Rectangle<f64, $crate::utils::Buffer>,
+                dst: $crate::utils::Rectangle<i32, $crate::utils::Physical>,
+                matrix: $crate::reexports::cgmath::Matrix3<f32>,
+                damage: &[$crate::utils::Rectangle<i32, $crate::utils::Physical>],
+            ) -> Result<(), <$renderer as $crate::backend::renderer::Renderer>::Error> {
+                match self {
+                    $(
+                        Self::$body(x) => $crate::render_elements_internal!(@call $renderer; draw_transformed; x, frame, src, dst, matrix, damage)
+                    ),*,
+                    Self::_GenericCatcher(_) => unreachable!(),
+                }
+            }
+        }
+    };
+    (@transform [$($generics:tt)*] $name:ty; $renderer:ident; [$($bounds:tt)*]; $($tail:tt)*) => {};
     // Generic renderer
     (@impl $name:ident<$renderer:ident> $(where $($target:ty: $bound:tt $(+ $additional_bound:tt)*),+)?; $($tail:tt)*) => {
         impl<$renderer> $crate::backend::renderer::element::Element for $name<$renderer>
@@ -851,6 +923,7 @@ macro_rules! render_elements_internal {
         {
             $crate::render_elements_internal!(@draw <$renderer>; $($tail)*);
         }
+        $crate::render_elements_internal!(@transform [$renderer] $name<$renderer>; $renderer; [$($($target: $bound $(+ $additional_bound)*,)+)?]; $($tail)*);
     };
     (@impl $name:ident<$lt:lifetime, $renderer:ident> $(where $($target:ty: $bound:tt $(+ $additional_bound:tt)*),+)?; $($tail:tt)*) => {
         impl<$lt, $renderer> $crate::backend::renderer::element::Element for $name<$lt, $renderer>
@@ -869,6 +942,7 @@ macro_rules! render_elements_internal {
         {
             $crate::render_elements_internal!(@draw <$renderer>; $($tail)*);
         }
+        $crate::render_elements_internal!(@transform [$lt, $renderer] $name<$lt, $renderer>; $renderer; [$($($target: $bound $(+ $additional_bound)*,)+)?]; $($tail)*);
     };
     (@impl $name:ident<$renderer:ident, $($custom:ident),+> $(where $($target:ty: $bound:tt $(+ $additional_bound:tt)*),+)?; $($tail:tt)*) => {
         impl<$renderer, $($custom),+> $crate::backend::renderer::element::Element for $name<$renderer, $($custom),+>
@@ -893,6 +967,7 @@ macro_rules! render_elements_internal {
         {
             $crate::render_elements_internal!(@draw <$renderer>; $($tail)*);
         }
+        $crate::render_elements_internal!(@transform [$renderer, $($custom),+] $name<$renderer, $($custom),+>; $renderer; [$($custom: $crate::backend::renderer::element::RenderElement<$renderer> + $crate::backend::renderer::element::Element,)+ $($($target: $bound $(+ $additional_bound)*,)+)?]; $($tail)*);
     };
     (@impl $name:ident<$lt:lifetime, $renderer:ident, $($custom:ident),+> $(where $($target:ty: $bound:tt $(+ $additional_bound:tt)*),+)?; $($tail:tt)*) => {
         impl<$lt, $renderer, $($custom),+> $crate::backend::renderer::element::Element for $name<$lt, $renderer, $($custom),+>
@@ -917,6 +992,7 @@ macro_rules! render_elements_internal {
         {
             $crate::render_elements_internal!(@draw <$renderer>; $($tail)*);
         }
+        $crate::render_elements_internal!(@transform [$lt, $renderer, $($custom),+] $name<$lt, $renderer, $($custom),+>; $renderer; [$($custom: $crate::backend::renderer::element::RenderElement<$renderer> + $crate::backend::renderer::element::Element,)+ $($($target: $bound $(+ $additional_bound)*,)+)?]; $($tail)*);
     };
     (@impl $name:ident; $renderer:ident; $($tail:tt)*) => {
         impl $crate::backend::renderer::element::Element for $name
@@ -930,6 +1006,7 @@ macro_rules! render_elements_internal {
         {
             $crate::render_elements_internal!(@draw <$renderer>; $($tail)*);
         }
+        $crate::render_elements_internal!(@transform [$renderer] $name; $renderer; []; $($tail)*);
     };
 
     // Specific renderer
@@ -1232,6 +1309,10 @@ macro_rules! render_elements_internal {
 /// }
 /// ```
 ///
+/// Enums generic over the [`Renderer`] also implement [`TransformableRenderElement`] if all
+/// of their variants do. This is not the case for enums with attributes on their variants,
+/// like `#[cfg(...)]`, as the required bounds can not be made conditional.
+///
 /// If your elements require a specific [`Renderer`] instead of being
 /// generic over it you can specify the type like in the following example.
 ///
@@ -1477,6 +1558,23 @@ where
     }
 }
 
+impl<R, C> TransformableRenderElement<R> for Wrap<C>
+where
+    R: Renderer,
+    C: TransformableRenderElement<R>,
+{
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        self.0.draw_transformed(frame, src, dst, matrix, damage)
+    }
+}
+
 #[cfg(all(test, feature = "renderer_gl"))]
 #[allow(dead_code)]
 mod tests;
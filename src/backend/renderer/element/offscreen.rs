@@ -0,0 +1,398 @@
+//! Element to render a group of elements through an offscreen texture
+//!
+//! An [`OffscreenBuffer`] flattens a list of [`RenderElement`]s, for example a window
+//! together with its subsurfaces and popups, into a single texture. The texture is
+//! presented as one [`TextureRenderElement`], which makes it easy to apply animations
+//! or effects to the whole group.
+//!
+//! The buffer is persistent and should be kept for as long as the group is displayed.
+//! Every call to [`OffscreenBuffer::render_elements`] tracks the damage of the child
+//! elements and only re-renders the texture if their commits, damage or placement
+//! relative to each other changed. The returned element reports the re-rendered regions
+//! as its damage and the area covered by opaque child elements as its opaque regions.
+//!
+//! Moving the whole group does not cause a re-render, as the elements are rendered
+//! relative to the top-left corner of their bounding box.
+//!
+//! Rendering into the texture changes the currently bound target of the renderer,
+//! so the texture has to be updated before binding the target of the output.
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{
+//! #     element::{Kind, RenderElement},
+//! #     damage::Error,
+//! #     Offscreen, Renderer, Texture,
+//! # };
+//! # use smithay::utils::Scale;
+//! use smithay::backend::{allocator::Fourcc, renderer::element::offscreen::OffscreenBuffer};
+//!
+//! # fn example<R, T, E>(renderer: &mut R, window_elements: &[E]) -> Result<(), Error<R>>
+//! # where
+//! #     R: Renderer<TextureId = T> + Offscreen<T>,
+//! #     T: Texture + Clone + 'static,
+//! #     E: RenderElement<R>,
+//! # {
+//! // Create the buffer once, e.g. when the window is mapped
+//! let mut buffer = OffscreenBuffer::<T>::new(Fourcc::Abgr8888);
+//!
+//! // For every frame, before binding the output, update the buffer
+//! // and use the returned element instead of the window elements
+//! let element = buffer.render_elements(renderer, Scale::from(1.0), window_elements, 1.0, Kind::Unspecified)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{Error, OutputDamageTracker},
+            utils::DamageBag,
+            Offscreen, Renderer, Texture,
+        },
+    },
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::{
+    texture::TextureRenderElement,
+    utils::{Relocate, RelocateRenderElement},
+    Element, Id, Kind, RenderElement,
+};
+
+/// A texture caching the rendered contents of a group of elements
+///
+/// See the [module documentation](self) for more information.
+#[derive(Debug)]
+pub struct OffscreenBuffer<T> {
+    id: Id,
+    format: Fourcc,
+    damage: DamageBag<i32, Buffer>,
+    state: Option<OffscreenState<T>>,
+}
+
+#[derive(Debug)]
+struct OffscreenState<T> {
+    texture: T,
+    renderer_id: usize,
+    scale: Scale<f64>,
+    logical_size: Size<i32, Logical>,
+    damage_tracker: OutputDamageTracker,
+    opaque_regions: Vec<Rectangle<i32, Buffer>>,
+}
+
+impl<T> OffscreenBuffer<T> {
+    /// Create a new [`OffscreenBuffer`]
+    ///
+    /// The texture is created lazily with the given format on the first call
+    /// to [`render_elements`](OffscreenBuffer::render_elements). The format
+    /// should contain an alpha channel, as the area not covered by any element
+    /// is left transparent.
+    pub fn new(format: Fourcc) -> Self {
+        OffscreenBuffer {
+            id: Id::new(),
+            format,
+            damage: DamageBag::default(),
+            state: None,
+        }
+    }
+
+    /// Returns the [`Id`] of the elements created by this buffer
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+
+    /// Drop the texture, so that the next call to
+    /// [`render_elements`](OffscreenBuffer::render_elements) renders everything again
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+impl<T: Texture + Clone> OffscreenBuffer<T> {
+    /// Render the elements into the texture if they changed and
+    /// return an element displaying the texture
+    ///
+    /// The elements are expected in the same order and coordinate space as
+    /// for rendering them directly with the given scale. The returned element
+    /// is placed at the top-left corner of their bounding box.
+    ///
+    /// The `alpha` is applied to the texture as a whole, instead of to every
+    /// element individually.
+    ///
+    /// Returns `None` if the elements have no visible area. The renderer is left
+    /// without any bound target.
+    #[profiling::function]
+    pub fn render_elements<R, E>(
+        &mut self,
+        renderer: &mut R,
+        scale: impl Into<Scale<f64>>,
+        elements: &[E],
+        alpha: f32,
+        kind: Kind,
+    ) -> Result<Option<TextureRenderElement<T>>, Error<R>>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+        E: RenderElement<R>,
+    {
+        let scale = scale.into();
+        let bbox = match elements
+            .iter()
+            .map(|element| element.geometry(scale))
+            .reduce(|bbox, geometry| bbox.merge(geometry))
+        {
+            Some(bbox) if !bbox.is_empty() => bbox,
+            _ => return Ok(None),
+        };
+
+        // The element maps the logical size back to physical pixels by rounding, so pick a
+        // logical size covering the bounding box and size the texture to match it exactly
+        let logical_size = Size::<i32, Logical>::from((
+            (bbox.size.w as f64 / scale.x).ceil() as i32,
+            (bbox.size.h as f64 / scale.y).ceil() as i32,
+        ));
+        let texture_size = Size::<i32, Physical>::from((
+            (logical_size.w as f64 * scale.x).round() as i32,
+            (logical_size.h as f64 * scale.y).round() as i32,
+        ));
+
+        let needs_texture = self
+            .state
+            .as_ref()
+            .map(|state| {
+                state.renderer_id != renderer.id()
+                    || state.scale != scale
+                    || state.logical_size != logical_size
+            })
+            .unwrap_or(true);
+        let age = if needs_texture {
+            let texture = renderer
+                .create_buffer(self.format, (texture_size.w, texture_size.h).into())
+                .map_err(Error::Rendering)?;
+            self.state = Some(OffscreenState {
+                texture,
+                renderer_id: renderer.id(),
+                scale,
+                logical_size,
+                damage_tracker: OutputDamageTracker::new(texture_size, scale, Transform::Normal),
+                opaque_regions: Vec::new(),
+            });
+            self.damage.reset();
+            0
+        } else {
+            1
+        };
+        let state = self.state.as_mut().unwrap();
+
+        let offset = Point::<i32, Physical>::from((-bbox.loc.x, -bbox.loc.y));
+        let elements = elements
+            .iter()
+            .map(|element| RelocateRenderElement::from_element(element, offset, Relocate::Relative))
+            .collect::<Vec<_>>();
+
+        renderer.bind(state.texture.clone()).map_err(Error::Rendering)?;
+        let result = state
+            .damage_tracker
+            .render_output(renderer, age, &elements, [0.0, 0.0, 0.0, 0.0]);
+        renderer.unbind().map_err(Error::Rendering)?;
+
+        if let Some(damage) = result?.damage {
+            // the texture is rendered untransformed, so physical and buffer coordinates match
+            self.damage.add(damage.into_iter().map(|rect| {
+                Rectangle::from_loc_and_size((rect.loc.x, rect.loc.y), (rect.size.w, rect.size.h))
+            }));
+
+            let ratio = Scale::from((
+                logical_size.w as f64 / texture_size.w as f64,
+                logical_size.h as f64 / texture_size.h as f64,
+            ));
+            state.opaque_regions = elements
+                .iter()
+                .filter(|element| element.alpha() >= 1.0)
+                .flat_map(|element| {
+                    let location = element.geometry(scale).loc;
+                    element.opaque_regions(scale).into_iter().map(move |mut region| {
+                        region.loc += location;
+                        region
+                    })
+                })
+                .filter_map(|region| opaque_buffer_region(region, ratio))
+                .collect();
+        }
+
+        Ok(self.render_element(bbox.loc.to_f64(), alpha, kind))
+    }
+
+    /// Returns an element displaying the last rendered contents of the texture
+    ///
+    /// Unlike [`render_elements`](OffscreenBuffer::render_elements) this does not
+    /// render anything, which allows to keep displaying the contents after the
+    /// elements themselves are gone, e.g. to animate a closed window.
+    ///
+    /// Returns `None` if nothing was rendered yet.
+    pub fn render_element(
+        &self,
+        location: impl Into<Point<f64, Physical>>,
+        alpha: f32,
+        kind: Kind,
+    ) -> Option<TextureRenderElement<T>> {
+        let state = self.state.as_ref()?;
+        let texture_size = state.texture.size();
+        Some(TextureRenderElement::from_texture_with_damage(
+            self.id.clone(),
+            state.renderer_id,
+            location,
+            state.texture.clone(),
+            1,
+            Transform::Normal,
+            Some(alpha),
+            Some(Rectangle::from_loc_and_size(
+                (0.0, 0.0),
+                (texture_size.w as f64, texture_size.h as f64),
+            )),
+            Some(state.logical_size),
+            Some(state.opaque_regions.clone()),
+            self.damage.snapshot(),
+            kind,
+        ))
+    }
+}
+
+/// Shrinks an opaque region of the texture, so it stays opaque after the
+/// [`TextureRenderElement`] converted it to logical coordinates and back
+///
+/// `ratio` is the scale from buffer to logical coordinates.
+fn opaque_buffer_region(
+    region: Rectangle<i32, Physical>,
+    ratio: Scale<f64>,
+) -> Option<Rectangle<i32, Buffer>> {
+    let (x, w) = opaque_span(region.loc.x, region.size.w, ratio.x)?;
+    let (y, h) = opaque_span(region.loc.y, region.size.h, ratio.y)?;
+    Some(Rectangle::from_loc_and_size((x, y), (w, h)))
+}
+
+fn opaque_span(start: i32, len: i32, ratio: f64) -> Option<(i32, i32)> {
+    // logical units fully covered by the region
+    let logical_start = (start as f64 * ratio).ceil();
+    let logical_end = ((start + len) as f64 * ratio).floor();
+    if logical_start >= logical_end {
+        return None;
+    }
+
+    let mut buffer_start = (logical_start / ratio).ceil() as i32;
+    let mut buffer_len = (logical_end / ratio).floor() as i32 - buffer_start;
+    // guard against rounding errors, mirroring the conversion of the element
+    while (buffer_start as f64 * ratio).floor() < logical_start {
+        buffer_start += 1;
+        buffer_len -= 1;
+    }
+    while buffer_len > 0 && (buffer_start as f64 * ratio + buffer_len as f64 * ratio).ceil() > logical_end {
+        buffer_len -= 1;
+    }
+
+    (buffer_len > 0).then_some((buffer_start, buffer_len))
+}
+
+#[cfg(all(test, feature = "renderer_pixman"))]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::{
+        element::solid::SolidColorRenderElement,
+        pixman::{PixmanRenderer, PixmanTexture},
+        snapshot::{solid, SnapshotTest},
+    };
+
+    fn render(
+        buffer: &mut OffscreenBuffer<PixmanTexture>,
+        renderer: &mut PixmanRenderer,
+        scale: f64,
+        elements: &[SolidColorRenderElement],
+    ) -> TextureRenderElement<PixmanTexture> {
+        buffer
+            .render_elements(renderer, scale, elements, 1.0, Kind::Unspecified)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn flattens_elements() {
+        let mut test = SnapshotTest::new((32, 32));
+        let (top, bottom) = (Id::new(), Id::new());
+        let elements = [
+            solid(&top, 0, (8, 4), (8, 8), [0.0, 0.5, 0.0, 0.5]),
+            solid(&bottom, 0, (4, 8), (16, 16), [1.0, 0.0, 0.0, 1.0]),
+        ];
+
+        let mut buffer = OffscreenBuffer::new(Fourcc::Abgr8888);
+        let element = render(&mut buffer, test.renderer(), 1.0, &elements);
+        assert_eq!(
+            element.geometry(1.0.into()),
+            Rectangle::from_loc_and_size((4, 4), (16, 20))
+        );
+
+        test.assert_snapshot("offscreen", &[element]);
+    }
+
+    #[test]
+    fn rerenders_only_on_changes() {
+        let mut test = SnapshotTest::new((32, 32));
+        let (top, bottom) = (Id::new(), Id::new());
+        let mut buffer = OffscreenBuffer::new(Fourcc::Abgr8888);
+
+        let elements = [
+            solid(&top, 0, (0, 0), (4, 4), [0.0, 0.0, 1.0, 1.0]),
+            solid(&bottom, 0, (0, 0), (10, 10), [1.0, 0.0, 0.0, 1.0]),
+        ];
+        let first = render(&mut buffer, test.renderer(), 1.0, &elements);
+
+        // moving the whole group reuses the texture
+        let moved = [
+            solid(&top, 0, (20, 20), (4, 4), [0.0, 0.0, 1.0, 1.0]),
+            solid(&bottom, 0, (20, 20), (10, 10), [1.0, 0.0, 0.0, 1.0]),
+        ];
+        let second = render(&mut buffer, test.renderer(), 1.0, &moved);
+        assert_eq!(first.current_commit(), second.current_commit());
+        assert_eq!(second.geometry(1.0.into()).loc, (20, 20).into());
+
+        // a new commit of a child is reported as damage of the element
+        let changed = [
+            solid(&top, 1, (20, 20), (4, 4), [0.0, 1.0, 0.0, 1.0]),
+            solid(&bottom, 0, (20, 20), (10, 10), [1.0, 0.0, 0.0, 1.0]),
+        ];
+        let third = render(&mut buffer, test.renderer(), 1.0, &changed);
+        assert_ne!(second.current_commit(), third.current_commit());
+        assert_eq!(
+            Element::damage_since(&third, 1.0.into(), Some(second.current_commit())),
+            vec![Rectangle::from_loc_and_size((0, 0), (4, 4))]
+        );
+    }
+
+    #[test]
+    fn opaque_regions() {
+        let mut test = SnapshotTest::new((32, 32));
+        let mut buffer = OffscreenBuffer::new(Fourcc::Abgr8888);
+
+        let elements = [
+            solid(&Id::new(), 0, (0, 0), (4, 4), [0.0, 0.0, 0.5, 0.5]),
+            solid(&Id::new(), 0, (4, 0), (6, 10), [1.0, 0.0, 0.0, 1.0]),
+        ];
+        let element = render(&mut buffer, test.renderer(), 1.0, &elements);
+        assert_eq!(
+            element.opaque_regions(1.0.into()),
+            vec![Rectangle::from_loc_and_size((4, 0), (6, 10))]
+        );
+
+        // at fractional scales the opaque regions only cover whole logical pixels
+        let elements = [solid(&Id::new(), 0, (1, 0), (5, 4), [1.0, 0.0, 0.0, 1.0])];
+        let element = render(&mut buffer, test.renderer(), 1.5, &elements);
+        let geometry = element.geometry(1.5.into());
+        let regions = element.opaque_regions(1.5.into());
+        assert!(!regions.is_empty());
+        for region in regions {
+            let region = Rectangle::from_loc_and_size(geometry.loc + region.loc, region.size);
+            assert!(Rectangle::from_loc_and_size((1, 0), (5, 4)).contains_rect(region));
+        }
+    }
+}
@@ -145,12 +145,14 @@
 //!         .expect("failed to render output");
 //! }
 //! ```
+use cgmath::Matrix3;
+
 use crate::{
     backend::renderer::{utils::CommitCounter, Frame, Renderer},
     utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
 };
 
-use super::{AsRenderElements, Element, Id, Kind, RenderElement};
+use super::{AsRenderElements, Element, Id, Kind, RenderElement, TransformableRenderElement};
 
 /// A single color buffer
 #[derive(Debug, Clone)]
@@ -324,6 +326,20 @@ impl<R: Renderer> RenderElement<R> for SolidColorRenderElement {
     }
 }
 
+impl<R: Renderer> TransformableRenderElement<R> for SolidColorRenderElement {
+    #[profiling::function]
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        frame.draw_solid_with_matrix(dst, damage, matrix, self.color)
+    }
+}
+
 impl<R> AsRenderElements<R> for SolidColorBuffer
 where
     R: Renderer,
@@ -216,6 +216,7 @@
 //! }
 //! ```
 
+use cgmath::Matrix3;
 use std::{fmt, marker::PhantomData};
 
 use tracing::{instrument, warn};
@@ -227,7 +228,7 @@ use crate::{
     wayland::compositor::{self, SurfaceData, TraversalAction},
 };
 
-use super::{CommitCounter, Element, Id, Kind, RenderElement, UnderlyingStorage};
+use super::{CommitCounter, Element, Id, Kind, RenderElement, TransformableRenderElement, UnderlyingStorage};
 
 /// Retrieve the [`WaylandSurfaceRenderElement`]s for a surface tree
 #[instrument(level = "trace", skip(renderer, location, scale))]
@@ -541,3 +542,43 @@ where
         })
     }
 }
+
+impl<R> TransformableRenderElement<R> for WaylandSurfaceRenderElement<R>
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: Texture + 'static,
+{
+    #[instrument(level = "trace", skip(frame))]
+    #[profiling::function]
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        compositor::with_states(&self.surface, |states| {
+            let data = states.data_map.get::<RendererSurfaceStateUserData>();
+            if let Some(data) = data {
+                let data = data.borrow();
+
+                if let Some(texture) = data.texture::<R>(frame.id()) {
+                    frame.render_texture_with_matrix(
+                        texture,
+                        src,
+                        dst,
+                        damage,
+                        matrix,
+                        data.buffer_transform,
+                        self.alpha,
+                    )?;
+                } else {
+                    warn!("trying to render texture from different renderer");
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
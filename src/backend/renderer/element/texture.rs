@@ -400,6 +400,7 @@
 
 use std::sync::{Arc, Mutex};
 
+use cgmath::Matrix3;
 use tracing::{instrument, warn};
 
 use crate::{
@@ -413,7 +414,7 @@ use crate::{
     utils::{Buffer, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
 };
 
-use super::{CommitCounter, Element, Id, Kind, RenderElement};
+use super::{CommitCounter, Element, Id, Kind, RenderElement, TransformableRenderElement};
 
 /// A single texture buffer
 #[derive(Debug, Clone)]
@@ -912,3 +913,35 @@ where
         frame.render_texture_from_to(&self.texture, src, dst, damage, self.transform, self.alpha)
     }
 }
+
+impl<R, T> TransformableRenderElement<R> for TextureRenderElement<T>
+where
+    R: Renderer<TextureId = T>,
+    T: Texture,
+{
+    #[instrument(level = "trace", skip(self, frame))]
+    #[profiling::function]
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        if frame.id() != self.renderer_id {
+            warn!("trying to render texture from different renderer");
+            return Ok(());
+        }
+
+        frame.render_texture_with_matrix(
+            &self.texture,
+            src,
+            dst,
+            damage,
+            matrix,
+            self.transform,
+            self.alpha,
+        )
+    }
+}
@@ -1,11 +1,16 @@
 //! Utilities and helpers around the `Element` trait.
 
+use cgmath::{Matrix3, Vector2};
+
 use crate::{
     backend::renderer::{
-        element::{AsRenderElements, Element, Id, Kind, RenderElement, UnderlyingStorage},
+        element::{
+            AsRenderElements, Element, Id, Kind, RenderElement, TransformableRenderElement, UnderlyingStorage,
+        },
+        utils::{transform_bbox, CommitCounter, DamageBag, DamageSnapshot},
         Renderer,
     },
-    utils::{Buffer, Physical, Point, Rectangle, Scale},
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Transform},
 };
 
 /// A element that allows to re-scale another element
@@ -110,6 +115,21 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RescaleRenderElement
     }
 }
 
+impl<R: Renderer, E: TransformableRenderElement<R>> TransformableRenderElement<R>
+    for RescaleRenderElement<E>
+{
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        self.element.draw_transformed(frame, src, dst, matrix, damage)
+    }
+}
+
 /// A element that allows to crop another element
 #[derive(Debug)]
 pub struct CropRenderElement<E> {
@@ -292,6 +312,19 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for CropRenderElement<E>
     }
 }
 
+impl<R: Renderer, E: TransformableRenderElement<R>> TransformableRenderElement<R> for CropRenderElement<E> {
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        self.element.draw_transformed(frame, src, dst, matrix, damage)
+    }
+}
+
 /// Defines how the location parameter should apply in [`RelocateRenderElement::from_element`]
 #[derive(Debug, Copy, Clone)]
 pub enum Relocate {
@@ -392,6 +425,250 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RelocateRenderElemen
     }
 }
 
+impl<R: Renderer, E: TransformableRenderElement<R>> TransformableRenderElement<R>
+    for RelocateRenderElement<E>
+{
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        self.element.draw_transformed(frame, src, dst, matrix, damage)
+    }
+}
+
+/// Tolerance for snapping transformed coordinates to whole pixels
+///
+/// This absorbs rounding errors of matrices like rotations by multiples of 90 degrees.
+const TRANSFORM_EPSILON: f64 = 1e-4;
+
+fn snap_to_pixels(rect: Rectangle<f64, Physical>) -> Rectangle<f64, Physical> {
+    let snap = |value: f64| {
+        let rounded = value.round();
+        if (value - rounded).abs() < TRANSFORM_EPSILON {
+            rounded
+        } else {
+            value
+        }
+    };
+    let max = rect.loc + rect.size.to_point();
+    Rectangle::from_extemities((snap(rect.loc.x), snap(rect.loc.y)), (snap(max.x), snap(max.y)))
+}
+
+/// Persistent state of a [`TransformRenderElement`]
+///
+/// The state tracks the matrix and the commits of the wrapped element between frames
+/// to provide the damage of the transformed element. Keep one state per transformed
+/// element and pass it to every call of [`TransformRenderElement::from_element`].
+#[derive(Debug, Default)]
+pub struct TransformState {
+    matrix: Option<Matrix3<f32>>,
+    scale: Option<Scale<f64>>,
+    element_commit: Option<CommitCounter>,
+    damage: DamageBag<i32, Physical>,
+}
+
+/// A element that allows to apply an arbitrary affine transformation to another element
+///
+/// The transformation can rotate or skew the element, which is not possible with the other
+/// wrappers. Drawing uses [`Frame::render_texture_with_matrix`](crate::backend::renderer::Frame::render_texture_with_matrix),
+/// so the wrapped element has to implement [`TransformableRenderElement`].
+///
+/// The geometry and damage of the element are the bounding boxes of the transformed geometry
+/// and damage of the wrapped element. Opaque regions are only kept if the matrix keeps
+/// the element axis-aligned.
+///
+/// Changing the matrix damages the whole element, see [`TransformState`].
+#[derive(Debug)]
+pub struct TransformRenderElement<E> {
+    element: E,
+    scale: Scale<f64>,
+    matrix: Matrix3<f32>,
+    damage: DamageSnapshot<i32, Physical>,
+}
+
+impl<E: Element> TransformRenderElement<E> {
+    /// Create a new transform element for an existing element
+    ///
+    /// The matrix is applied relative to the origin, so for example a rotation
+    /// with the origin at the center of the element rotates it around its center.
+    ///
+    /// The scale is used to calculate the geometry of the wrapped element while drawing
+    /// and should therefore equal the scale the element is rendered with.
+    ///
+    /// The damage since the last frame is recorded in `state`.
+    pub fn from_element(
+        element: E,
+        scale: impl Into<Scale<f64>>,
+        origin: Point<i32, Physical>,
+        matrix: Matrix3<f32>,
+        state: &mut TransformState,
+    ) -> Self {
+        let origin = Vector2::new(origin.x as f32, origin.y as f32);
+        let mut element = TransformRenderElement {
+            element,
+            scale: scale.into(),
+            matrix: Matrix3::from_translation(origin) * matrix * Matrix3::from_translation(-origin),
+            damage: DamageSnapshot::empty(),
+        };
+        element.update_state(state);
+        element
+    }
+
+    fn update_state(&mut self, state: &mut TransformState) {
+        let element_commit = self.element.current_commit();
+        if state.matrix != Some(self.matrix) || state.scale != Some(self.scale) {
+            // the whole element might look different
+            state.damage.reset();
+        } else if state.element_commit != Some(element_commit) {
+            let element_geometry = self.element.geometry(self.scale);
+            let geometry = self.geometry(self.scale);
+            let damage = self
+                .element
+                .damage_since(self.scale, state.element_commit)
+                .into_iter()
+                .map(|mut rect| {
+                    rect.loc += element_geometry.loc;
+                    let mut rect = self.transform_rect(rect).to_i32_up();
+                    rect.loc -= geometry.loc;
+                    rect
+                })
+                .collect::<Vec<_>>();
+            state.damage.add(damage);
+        }
+
+        state.matrix = Some(self.matrix);
+        state.scale = Some(self.scale);
+        state.element_commit = Some(element_commit);
+        self.damage = state.damage.snapshot();
+    }
+
+    fn transform_rect(&self, rect: Rectangle<i32, Physical>) -> Rectangle<f64, Physical> {
+        snap_to_pixels(transform_bbox(rect.to_f64(), &self.matrix))
+    }
+
+    /// Whether the matrix maps axis-aligned rectangles to axis-aligned rectangles
+    fn is_axis_aligned(&self) -> bool {
+        let m = &self.matrix;
+        let is_zero = |value: f32| (value as f64).abs() < TRANSFORM_EPSILON;
+        (is_zero(m.x.y) && is_zero(m.y.x)) || (is_zero(m.x.x) && is_zero(m.y.y))
+    }
+
+    /// The matrix to draw the element with when drawn into `dst`
+    fn frame_matrix(&self, dst: Rectangle<i32, Physical>) -> Matrix3<f32> {
+        // the element might get drawn somewhere else than its geometry, e.g. when relocated
+        let offset = dst.loc - self.geometry(self.scale).loc;
+        Matrix3::from_translation(Vector2::new(offset.x as f32, offset.y as f32)) * self.matrix
+    }
+}
+
+impl<E: Element> Element for TransformRenderElement<E> {
+    fn id(&self) -> &Id {
+        self.element.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.damage.current_commit()
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.element.src()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.transform_rect(self.element.geometry(scale)).to_i32_up()
+    }
+
+    fn transform(&self) -> Transform {
+        self.element.transform()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        self.damage
+            .damage_since(commit)
+            .unwrap_or_else(|| vec![Rectangle::from_loc_and_size((0, 0), self.geometry(scale).size)])
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
+        // a rotated or skewed rectangle covers only parts of the pixels of its bounding box
+        if !self.is_axis_aligned() {
+            return Vec::new();
+        }
+
+        let element_geometry = self.element.geometry(scale);
+        let geometry = self.geometry(scale);
+
+        self.element
+            .opaque_regions(scale)
+            .into_iter()
+            .filter_map(|mut rect| {
+                rect.loc += element_geometry.loc;
+                let mut rect = self.transform_rect(rect).to_i32_down();
+                rect.loc -= geometry.loc;
+                (!rect.is_empty()).then_some(rect)
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.element.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.element.kind()
+    }
+}
+
+impl<R: Renderer, E: TransformableRenderElement<R>> RenderElement<R> for TransformRenderElement<E> {
+    fn draw(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        let damage = damage
+            .iter()
+            .map(|rect| Rectangle::from_loc_and_size(rect.loc + dst.loc, rect.size))
+            .collect::<Vec<_>>();
+        self.element.draw_transformed(
+            frame,
+            src,
+            self.element.geometry(self.scale),
+            self.frame_matrix(dst),
+            &damage,
+        )
+    }
+}
+
+impl<R: Renderer, E: TransformableRenderElement<R>> TransformableRenderElement<R>
+    for TransformRenderElement<E>
+{
+    fn draw_transformed(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        matrix: Matrix3<f32>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        self.element.draw_transformed(
+            frame,
+            src,
+            self.element.geometry(self.scale),
+            matrix * self.frame_matrix(dst),
+            damage,
+        )
+    }
+}
+
 /// Defines the scale behavior for the constrain
 #[derive(Debug, Copy, Clone)]
 pub enum ConstrainScaleBehavior {
@@ -564,3 +841,295 @@ where
         .map(move |e| RelocateRenderElement::from_element(e, offset, Relocate::Relative))
         .filter_map(move |e| CropRenderElement::from_element(e, scale, constrain))
 }
+
+#[cfg(all(test, feature = "renderer_pixman"))]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::renderer::{
+            element::{solid::SolidColorRenderElement, texture::TextureRenderElement},
+            pixman::PixmanRenderer,
+            snapshot::{opaque_quadrants, quadrants, solid, SnapshotTest},
+        },
+        utils::Transform,
+    };
+    use cgmath::Deg;
+
+    #[test]
+    fn rescale() {
+        let mut test = SnapshotTest::new((32, 32));
+        let element = quadrants(test.renderer(), (4, 4));
+        let element = RescaleRenderElement::from_element(element, (4, 4).into(), 2.0);
+        test.assert_snapshot("rescale", &[element]);
+    }
+
+    #[test]
+    fn rescale_fractional_output_scale() {
+        let mut test = SnapshotTest::new((32, 32)).scale(1.5);
+        let element = quadrants(test.renderer(), (3, 3));
+        let element = RescaleRenderElement::from_element(element, (0, 0).into(), 1.25);
+        test.assert_snapshot("rescale_fractional_output_scale", &[element]);
+    }
+
+    #[test]
+    fn crop() {
+        let mut test = SnapshotTest::new((32, 32));
+        let element = quadrants(test.renderer(), (4, 4));
+        let element = RescaleRenderElement::from_element(element, (4, 4).into(), 2.0);
+        let element =
+            CropRenderElement::from_element(element, 1.0, Rectangle::from_loc_and_size((10, 6), (12, 7)))
+                .unwrap();
+        test.assert_snapshot("crop", &[element]);
+    }
+
+    #[test]
+    fn crop_without_overlap() {
+        let mut test = SnapshotTest::new((32, 32));
+        let element = quadrants(test.renderer(), (4, 4));
+        assert!(CropRenderElement::from_element(
+            element,
+            1.0,
+            Rectangle::from_loc_and_size((20, 20), (4, 4))
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn relocate() {
+        // relocating must not introduce any interpolation
+        let mut test = SnapshotTest::new((32, 32)).tolerance(0);
+        let relative = RelocateRenderElement::from_element(
+            quadrants(test.renderer(), (4, 4)),
+            (10, 3),
+            Relocate::Relative,
+        );
+        let absolute = RelocateRenderElement::from_element(
+            quadrants(test.renderer(), (4, 4)),
+            (2, 20),
+            Relocate::Absolute,
+        );
+        test.assert_snapshot("relocate", &[relative, absolute]);
+    }
+
+    #[test]
+    fn relocate_transformed_output() {
+        let mut test = SnapshotTest::new((16, 32)).transform(Transform::_90);
+        let element = RelocateRenderElement::from_element(
+            quadrants(test.renderer(), (0, 0)),
+            (20, 4),
+            Relocate::Relative,
+        );
+        test.assert_snapshot("relocate_transformed_output", &[element]);
+    }
+
+    #[test]
+    fn constrain_fit() {
+        let mut test = SnapshotTest::new((32, 32)).clear_color([0.2, 0.2, 0.2, 1.0]);
+        // the elements are rendered at the origin of the constrain rect
+        let elements = [
+            quadrants(test.renderer(), (2, 4)),
+            quadrants(test.renderer(), (10, 8)),
+        ];
+        let reference = Rectangle::from_loc_and_size((0, 0), (16, 12));
+        let elements = constrain_render_elements(
+            elements,
+            (2, 4),
+            Rectangle::from_loc_and_size((2, 4), (28, 14)),
+            reference,
+            ConstrainScaleBehavior::Fit,
+            ConstrainAlign::CENTER,
+            1.0,
+        )
+        .collect::<Vec<_>>();
+        test.assert_snapshot("constrain_fit", &elements);
+    }
+
+    #[test]
+    fn constrain_cut_off() {
+        let mut test = SnapshotTest::new((32, 32)).clear_color([0.2, 0.2, 0.2, 1.0]);
+        let elements = [
+            quadrants(test.renderer(), (4, 4)),
+            quadrants(test.renderer(), (12, 8)),
+        ];
+        let reference = Rectangle::from_loc_and_size((0, 0), (16, 12));
+        let elements = constrain_render_elements(
+            elements,
+            (4, 4),
+            Rectangle::from_loc_and_size((4, 4), (10, 10)),
+            reference,
+            ConstrainScaleBehavior::CutOff,
+            ConstrainAlign::TOP_LEFT,
+            1.0,
+        )
+        .collect::<Vec<_>>();
+        test.assert_snapshot("constrain_cut_off", &elements);
+    }
+
+    #[test]
+    fn transform_rotate_90() {
+        let mut test = SnapshotTest::new((32, 32));
+        let element = opaque_quadrants(test.renderer(), (8, 8));
+        let element = TransformRenderElement::from_element(
+            element,
+            1.0,
+            (12, 12).into(),
+            Matrix3::from_angle_z(Deg(90.0)),
+            &mut TransformState::default(),
+        );
+        // rotating around the center keeps the geometry and the opaque regions
+        assert_eq!(
+            element.geometry(1.0.into()),
+            Rectangle::from_loc_and_size((8, 8), (8, 8))
+        );
+        assert_eq!(
+            element.opaque_regions(1.0.into()),
+            vec![Rectangle::from_loc_and_size((0, 0), (8, 8))]
+        );
+        test.assert_snapshot("transform_rotate_90", &[element]);
+    }
+
+    #[test]
+    fn transform_rotate_45() {
+        let mut test = SnapshotTest::new((32, 32));
+        let element = opaque_quadrants(test.renderer(), (12, 12));
+        let element = TransformRenderElement::from_element(
+            element,
+            1.0,
+            (16, 16).into(),
+            Matrix3::from_angle_z(Deg(45.0)) * Matrix3::from_scale(2.0),
+            &mut TransformState::default(),
+        );
+        // the bounding box of the rotated square with a diagonal of 16 * sqrt(2)
+        assert_eq!(
+            element.geometry(1.0.into()),
+            Rectangle::from_loc_and_size((4, 4), (24, 24))
+        );
+        assert!(element.opaque_regions(1.0.into()).is_empty());
+        test.assert_snapshot("transform_rotate_45", &[element]);
+    }
+
+    #[test]
+    fn transform_skew_transformed_output() {
+        let mut test = SnapshotTest::new((16, 32)).transform(Transform::_90);
+        let element = quadrants(test.renderer(), (4, 4));
+        let skew = Matrix3::new(1.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.0, 0.0, 1.0);
+        let element = TransformRenderElement::from_element(
+            element,
+            1.0,
+            (4, 4).into(),
+            skew,
+            &mut TransformState::default(),
+        );
+        assert_eq!(
+            element.geometry(1.0.into()),
+            Rectangle::from_loc_and_size((4, 4), (12, 8))
+        );
+        let element = RelocateRenderElement::from_element(element, (8, 2), Relocate::Relative);
+        test.assert_snapshot("transform_skew_transformed_output", &[element]);
+    }
+
+    crate::backend::renderer::element::render_elements! {
+        TestRenderElements<R>;
+        Texture=TextureRenderElement<<R as Renderer>::TextureId>,
+        Solid=SolidColorRenderElement,
+    }
+
+    #[test]
+    fn transform_render_elements() {
+        let mut test = SnapshotTest::new((32, 32));
+        let texture = quadrants(test.renderer(), (4, 4));
+        let solid = solid(&Id::new(), 0, (16, 16), (8, 8), [1.0, 0.0, 1.0, 1.0]);
+        let elements = [
+            TestRenderElements::<PixmanRenderer>::from(texture),
+            TestRenderElements::from(solid),
+        ]
+        .into_iter()
+        .map(|element| {
+            let geometry = element.geometry(1.0.into());
+            let center = (
+                geometry.loc.x + geometry.size.w / 2,
+                geometry.loc.y + geometry.size.h / 2,
+            );
+            TransformRenderElement::from_element(
+                element,
+                1.0,
+                center.into(),
+                Matrix3::from_angle_z(Deg(45.0)),
+                &mut TransformState::default(),
+            )
+        })
+        .collect::<Vec<_>>();
+        test.assert_snapshot("transform_render_elements", &elements);
+    }
+
+    #[test]
+    fn transform_damage() {
+        let mut test = SnapshotTest::new((32, 32));
+        let element = quadrants(test.renderer(), (8, 8));
+        let mut state = TransformState::default();
+        let rotate = Matrix3::from_angle_z(Deg(180.0));
+        let rotated = TransformRenderElement::from_element(&element, 1.0, (8, 8).into(), rotate, &mut state);
+        assert_eq!(
+            rotated.geometry(1.0.into()),
+            Rectangle::from_loc_and_size((0, 0), (8, 8))
+        );
+        let commit = rotated.current_commit();
+        assert!(rotated.damage_since(1.0.into(), Some(commit)).is_empty());
+        assert_eq!(
+            rotated.damage_since(1.0.into(), None),
+            vec![Rectangle::from_loc_and_size((0, 0), (8, 8))]
+        );
+
+        // the next frame with the same matrix and an unchanged element has no damage
+        let rotated = TransformRenderElement::from_element(&element, 1.0, (8, 8).into(), rotate, &mut state);
+        assert_eq!(rotated.current_commit(), commit);
+        assert!(rotated.damage_since(1.0.into(), Some(commit)).is_empty());
+
+        // a different matrix resulting in the same geometry has to damage the whole element
+        let mirrored = TransformRenderElement::from_element(
+            &element,
+            1.0,
+            (8, 8).into(),
+            // mirrors the element along the diagonal through the origin
+            Matrix3::new(0.0, -1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0),
+            &mut state,
+        );
+        assert_eq!(mirrored.geometry(1.0.into()), rotated.geometry(1.0.into()));
+        assert_ne!(mirrored.current_commit(), commit);
+        assert_eq!(
+            mirrored.damage_since(1.0.into(), Some(commit)),
+            vec![Rectangle::from_loc_and_size((0, 0), (8, 8))]
+        );
+    }
+
+    #[test]
+    fn transform_damage_of_wrapped_element() {
+        let id = Id::new();
+        let mut state = TransformState::default();
+        let shift = Matrix3::from_translation(Vector2::new(4.0, 2.0));
+        let transform = |commit: usize, state: &mut TransformState| {
+            let element = solid(&id, commit, (0, 0), (8, 4), [1.0, 0.0, 0.0, 1.0]);
+            TransformRenderElement::from_element(element, 1.0, (0, 0).into(), shift, state)
+        };
+
+        let first = transform(0, &mut state);
+        let commit = first.current_commit();
+        assert_eq!(
+            first.geometry(1.0.into()),
+            Rectangle::from_loc_and_size((4, 2), (8, 4))
+        );
+
+        // new commits of the wrapped element are tracked across frames
+        let unchanged = transform(0, &mut state);
+        assert_eq!(unchanged.current_commit(), commit);
+        let changed = transform(1, &mut state);
+        assert_ne!(changed.current_commit(), commit);
+        assert_eq!(
+            changed.damage_since(1.0.into(), Some(commit)),
+            vec![Rectangle::from_loc_and_size((0, 0), (8, 4))]
+        );
+        assert!(changed
+            .damage_since(1.0.into(), Some(changed.current_commit()))
+            .is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 //! Implementation of the rendering traits using OpenGL ES 2
 
-use cgmath::{prelude::*, Matrix3, Vector2};
+use cgmath::{prelude::*, Matrix3, Vector2, Vector3};
 use core::slice;
 use std::{
     borrow::Cow,
@@ -38,8 +38,11 @@ pub use uniform::*;
 use self::version::GlVersion;
 
 use super::{
-    sync::SyncPoint, Bind, Blit, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer,
-    Texture, TextureFilter, TextureMapping, Unbind,
+    color::{ColorLut, ColorTransform},
+    sync::SyncPoint,
+    utils::transform_bbox,
+    Bind, Blit, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer, Texture,
+    TextureFilter, TextureMapping, Unbind,
 };
 use crate::backend::egl::{
     ffi::egl::{self as ffi_egl, types::EGLImage},
@@ -400,10 +403,14 @@ pub struct GlesRenderer {
     // color-transformation shaders
     // TODO new tex/solid? shaders
     output_program: Option<GlesColorOutputProgram>,
+    // post-processing shader, compiled on first use
+    color_transform_program: Option<GlesTexProgram>,
 
     // caches
     buffers: Vec<GlesBuffer>,
     dmabuf_cache: std::collections::HashMap<WeakDmabuf, GlesTexture>,
+    color_lut: Option<(ColorLut, GlesTexture)>,
+    color_transform_texture: Option<GlesTexture>,
     vbos: [ffi::types::GLuint; 3],
 
     // cleanup
@@ -745,6 +752,7 @@ impl GlesRenderer {
             tex_program,
             solid_program,
             output_program,
+            color_transform_program: None,
             vbos,
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
@@ -752,6 +760,8 @@ impl GlesRenderer {
             target: None,
             buffers: Vec::new(),
             dmabuf_cache: std::collections::HashMap::new(),
+            color_lut: None,
+            color_transform_texture: None,
 
             destruction_callback: rx,
             destruction_callback_sender: tx,
@@ -2352,6 +2362,10 @@ impl Renderer for GlesRenderer {
         self.debug_flags
     }
 
+    fn supports_color_transform(&self) -> bool {
+        true
+    }
+
     #[profiling::function]
     fn wait(&mut self, sync: &super::sync::SyncPoint) -> Result<(), Self::Error> {
         self.make_current()?;
@@ -2516,6 +2530,41 @@ impl<'frame> Frame for GlesFrame<'frame> {
         self.render_texture_from_to(texture, src, dest, damage, transform, alpha, None, &[])
     }
 
+    fn draw_solid_with_matrix(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        color: [f32; 4],
+    ) -> Result<(), Self::Error> {
+        self.draw_solid_with_matrix(dst, damage, matrix, color)
+    }
+
+    #[instrument(level = "trace", skip(self), parent = &self.span)]
+    #[profiling::function]
+    fn render_texture_with_matrix(
+        &mut self,
+        texture: &GlesTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        transform: Transform,
+        alpha: f32,
+    ) -> Result<(), GlesError> {
+        self.render_texture_with_matrix(texture, src, dest, damage, matrix, transform, alpha, None, &[])
+    }
+
+    #[instrument(level = "trace", skip(self), parent = &self.span)]
+    #[profiling::function]
+    fn apply_color_transform(
+        &mut self,
+        transform: &ColorTransform,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        self.apply_color_transform_internal(transform, damage)
+    }
+
     fn transformation(&self) -> Transform {
         self.transform
     }
@@ -2652,9 +2701,6 @@ impl<'frame> GlesFrame<'frame> {
             return Ok(());
         }
 
-        let mut mat = Matrix3::<f32>::identity();
-        mat = self.current_projection * mat;
-
         let instances = damage
             .iter()
             .flat_map(|rect| {
@@ -2677,6 +2723,58 @@ impl<'frame> GlesFrame<'frame> {
             })
             .collect::<Vec<_>>();
 
+        self.draw_solid_instances(self.current_projection, &instances, color);
+        Ok(())
+    }
+
+    /// Draw a solid color into the rectangle described by dest and apply an arbitrary affine
+    /// transformation to it.
+    ///
+    /// See [`Frame::draw_solid_with_matrix`] for the meaning of the arguments.
+    #[instrument(level = "trace", skip(self), parent = &self.span)]
+    #[profiling::function]
+    pub fn draw_solid_with_matrix(
+        &mut self,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        color: [f32; 4],
+    ) -> Result<(), GlesError> {
+        if dest.is_empty() {
+            return Ok(());
+        }
+
+        let bbox = transform_bbox(dest.to_f64(), &matrix).to_i32_up();
+        let damage = damage
+            .iter()
+            .filter_map(|rect| rect.intersection(bbox))
+            .filter(|rect| !rect.is_empty())
+            .collect::<Vec<_>>();
+
+        // Like for textures the whole rectangle is drawn once for every damaged area
+        // and limited to the damage using the scissor test.
+        let mat = self.current_projection
+            * matrix
+            * Matrix3::from_translation(Vector2::new(dest.loc.x as f32, dest.loc.y as f32));
+        let instances = [0.0, 0.0, dest.size.w as f32, dest.size.h as f32];
+        for rect in damage {
+            let (x, y, w, h) = self.scissor_box(rect);
+            unsafe {
+                self.renderer.gl.Scissor(x, y, w, h);
+            }
+            self.draw_solid_instances(mat, &instances, color);
+        }
+
+        let viewport = self.transform.transform_size(self.size);
+        unsafe {
+            self.renderer.gl.Scissor(0, 0, viewport.w, viewport.h);
+        }
+
+        Ok(())
+    }
+
+    /// Draw rectangles given as instances of `[x, y, w, h]` with a solid color
+    fn draw_solid_instances(&mut self, mat: Matrix3<f32>, instances: &[f32], color: [f32; 4]) {
         let gl = &self.renderer.gl;
         unsafe {
             gl.UseProgram(self.renderer.solid_program.program);
@@ -2707,7 +2805,7 @@ impl<'frame> GlesFrame<'frame> {
 
             // Damage vertices.
             let vertices = if self.renderer.capabilities.contains(&Capability::Instancing) {
-                instances
+                instances.to_vec()
             } else {
                 // Add the 4 f32s per damage rectangle for each of the 6 vertices.
                 let mut vertices = Vec::with_capacity(instances.len() * 6);
@@ -2737,7 +2835,7 @@ impl<'frame> GlesFrame<'frame> {
                 std::ptr::null(),
             );
 
-            let damage_len = damage.len() as i32;
+            let damage_len = (instances.len() / 4) as i32;
             if self.renderer.capabilities.contains(&Capability::Instancing) {
                 gl.VertexAttribDivisor(self.renderer.solid_program.attrib_vert as u32, 0);
 
@@ -2770,8 +2868,6 @@ impl<'frame> GlesFrame<'frame> {
             gl.DisableVertexAttribArray(self.renderer.solid_program.attrib_vert as u32);
             gl.DisableVertexAttribArray(self.renderer.solid_program.attrib_position as u32);
         }
-
-        Ok(())
     }
 
     /// Render part of a texture as given by src to the current target into the rectangle described by dst
@@ -2844,6 +2940,319 @@ impl<'frame> GlesFrame<'frame> {
         )
     }
 
+    /// Render part of a texture as given by src to the current target into the rectangle described by dest
+    /// and apply an arbitrary affine transformation to it.
+    ///
+    /// See [`Frame::render_texture_with_matrix`] for the meaning of the arguments.
+    ///
+    /// Optionally allows a custom texture program and matching additional uniforms to be passed in.
+    #[instrument(level = "trace", skip(self), parent = &self.span)]
+    #[profiling::function]
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_texture_with_matrix(
+        &mut self,
+        texture: &GlesTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        transform: Transform,
+        alpha: f32,
+        program: Option<&GlesTexProgram>,
+        additional_uniforms: &[Uniform<'_>],
+    ) -> Result<(), GlesError> {
+        let tex_size = texture.size();
+        if src.size.is_empty() || tex_size.is_empty() || dest.is_empty() {
+            return Ok(());
+        }
+
+        let bbox = transform_bbox(dest.to_f64(), &matrix).to_i32_up();
+        let damage = damage
+            .iter()
+            .filter_map(|rect| rect.intersection(bbox))
+            .filter(|rect| !rect.is_empty())
+            .collect::<Vec<_>>();
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        let mut tex_mat = build_texture_mat(src, dest, tex_size, transform);
+        if texture.0.y_inverted {
+            tex_mat = Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0) * tex_mat;
+        }
+        let mat = matrix * Matrix3::from_translation(Vector2::new(dest.loc.x as f32, dest.loc.y as f32));
+
+        // The damage can not be expressed as instances of the untransformed rectangle,
+        // so we render the whole rectangle once for every damaged area and limit it
+        // to the damage using the scissor test.
+        let instances = [0.0, 0.0, dest.size.w as f32, dest.size.h as f32];
+        let res = damage.iter().try_for_each(|rect| {
+            let (x, y, w, h) = self.scissor_box(*rect);
+            unsafe {
+                self.renderer.gl.Scissor(x, y, w, h);
+            }
+            self.render_texture(
+                texture,
+                tex_mat,
+                mat,
+                Some(&instances),
+                alpha,
+                program,
+                additional_uniforms,
+            )
+        });
+
+        let viewport = self.transform.transform_size(self.size);
+        unsafe {
+            self.renderer.gl.Scissor(0, 0, viewport.w, viewport.h);
+        }
+
+        res
+    }
+
+    fn apply_color_transform_internal(
+        &mut self,
+        transform: &ColorTransform,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let viewport = Rectangle::from_loc_and_size((0, 0), self.transform.transform_size(self.size));
+        let boxes = damage
+            .iter()
+            .filter_map(|rect| {
+                let (x, y, w, h) = self.scissor_box(*rect);
+                Rectangle::<i32, Physical>::from_loc_and_size((x, y), (w, h)).intersection(viewport)
+            })
+            .filter(|rect| !rect.is_empty())
+            .collect::<Vec<_>>();
+        let Some(bbox) = boxes.iter().copied().reduce(|a, b| a.merge(b)) else {
+            return Ok(());
+        };
+
+        let program = match self.renderer.color_transform_program.as_ref() {
+            Some(program) => program.clone(),
+            None => {
+                let program = unsafe {
+                    texture_program(
+                        &self.renderer.gl,
+                        shaders::FRAGMENT_SHADER_COLOR_TRANSFORM,
+                        &[
+                            UniformName::new("color_matrix", UniformType::Matrix4x4),
+                            UniformName::new("lut_size", UniformType::_1f),
+                            UniformName::new("lut", UniformType::_1i),
+                        ],
+                        self.renderer.destruction_callback_sender.clone(),
+                    )?
+                };
+                self.renderer.color_transform_program = Some(program.clone());
+                program
+            }
+        };
+        let lut = transform
+            .lut()
+            .map(|lut| self.color_lut_texture(lut))
+            .transpose()?;
+
+        // The damaged parts of the framebuffer are copied into a temporary texture first,
+        // so overlapping boxes are read before anything is written back.
+        // The texture is kept around and reused as long as the damage has the same size.
+        let mut alpha_bits = 0;
+        unsafe {
+            self.renderer.gl.GetIntegerv(ffi::ALPHA_BITS, &mut alpha_bits);
+        }
+        let opaque = alpha_bits == 0;
+        let texture = match self.renderer.color_transform_texture.as_ref() {
+            Some(texture)
+                if texture.0.size == (bbox.size.w, bbox.size.h).into() && texture.0.has_alpha != opaque =>
+            {
+                texture.clone()
+            }
+            _ => {
+                let format = if opaque { ffi::RGB } else { ffi::RGBA };
+                let gl = &self.renderer.gl;
+                let mut tex = 0;
+                unsafe {
+                    gl.GenTextures(1, &mut tex);
+                    gl.BindTexture(ffi::TEXTURE_2D, tex);
+                    gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+                    gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+                    gl.TexImage2D(
+                        ffi::TEXTURE_2D,
+                        0,
+                        format as i32,
+                        bbox.size.w,
+                        bbox.size.h,
+                        0,
+                        format,
+                        ffi::UNSIGNED_BYTE,
+                        std::ptr::null(),
+                    );
+                    gl.BindTexture(ffi::TEXTURE_2D, 0);
+                }
+                let texture = unsafe {
+                    GlesTexture::from_raw(
+                        self.renderer,
+                        Some(ffi::RGBA),
+                        opaque,
+                        tex,
+                        (bbox.size.w, bbox.size.h).into(),
+                    )
+                };
+                self.renderer.color_transform_texture = Some(texture.clone());
+                texture
+            }
+        };
+
+        let gl = &self.renderer.gl;
+        unsafe {
+            gl.ActiveTexture(ffi::TEXTURE0);
+            gl.BindTexture(ffi::TEXTURE_2D, texture.0.texture);
+            for rect in &boxes {
+                gl.CopyTexSubImage2D(
+                    ffi::TEXTURE_2D,
+                    0,
+                    rect.loc.x - bbox.loc.x,
+                    rect.loc.y - bbox.loc.y,
+                    rect.loc.x,
+                    rect.loc.y,
+                    rect.size.w,
+                    rect.size.h,
+                );
+            }
+            gl.BindTexture(ffi::TEXTURE_2D, 0);
+
+            if let Some(lut) = lut.as_ref() {
+                gl.ActiveTexture(ffi::TEXTURE1);
+                gl.BindTexture(ffi::TEXTURE_2D, lut.0.texture);
+                gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::LINEAR as i32);
+                gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::LINEAR as i32);
+                gl.ActiveTexture(ffi::TEXTURE0);
+            }
+        }
+
+        // The instances are given in framebuffer pixels relative to the bounding box,
+        // so undo the projection of this frame and map them to normalized device coordinates directly.
+        let to_ndc = Matrix3::from_translation(Vector2::new(-1.0, -1.0))
+            * Matrix3::from_nonuniform_scale(2.0 / viewport.size.w as f32, 2.0 / viewport.size.h as f32)
+            * Matrix3::from_translation(Vector2::new(bbox.loc.x as f32, bbox.loc.y as f32));
+        let matrix = self
+            .current_projection
+            .invert()
+            .expect("The projection matrix is always invertible")
+            * to_ndc;
+        let tex_matrix = Matrix3::from_nonuniform_scale(1.0 / bbox.size.w as f32, 1.0 / bbox.size.h as f32);
+        let instances = boxes
+            .iter()
+            .flat_map(|rect| {
+                [
+                    (rect.loc.x - bbox.loc.x) as f32,
+                    (rect.loc.y - bbox.loc.y) as f32,
+                    rect.size.w as f32,
+                    rect.size.h as f32,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let color_matrix = transform.matrix();
+        let mut columns = [0f32; 16];
+        for (index, value) in columns.iter_mut().enumerate() {
+            *value = color_matrix[index % 4][index / 4];
+        }
+        let uniforms = [
+            Uniform::new(
+                "color_matrix",
+                UniformValue::Matrix4x4 {
+                    matrices: vec![columns],
+                    transpose: false,
+                },
+            ),
+            Uniform::new("lut_size", transform.lut().map_or(0.0, |lut| lut.size() as f32)),
+            Uniform::new("lut", 1),
+        ];
+
+        unsafe {
+            self.renderer.gl.Disable(ffi::BLEND);
+        }
+
+        let res = self.render_texture(
+            &texture,
+            tex_matrix,
+            matrix,
+            Some(&instances),
+            1.0,
+            Some(&program),
+            &uniforms,
+        );
+
+        unsafe {
+            self.renderer.gl.Enable(ffi::BLEND);
+            self.renderer.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+        }
+
+        res
+    }
+
+    /// Returns the texture holding the given [`ColorLut`], uploading it if necessary
+    fn color_lut_texture(&mut self, lut: &ColorLut) -> Result<GlesTexture, GlesError> {
+        if let Some((cached, texture)) = self.renderer.color_lut.as_ref() {
+            if cached == lut {
+                return Ok(texture.clone());
+            }
+        }
+
+        // The lut is stored as a 2d texture with all slices for a given blue value placed side by side.
+        // If supported it is uploaded as half-floats, as 8 bits per channel result in visible banding
+        // for smooth gradients. Without `Capability::ColorTransformations` the renderer is limited to
+        // 8 bit textures, which only matches the precision of the framebuffer we are transforming anyway.
+        let size = lut.size();
+        let half_float = self
+            .renderer
+            .capabilities
+            .contains(&Capability::ColorTransformations);
+        let (format, bytes_per_pixel) = if half_float {
+            (Fourcc::Abgr16161616f, 8)
+        } else {
+            (Fourcc::Abgr8888, 4)
+        };
+        let mut data = vec![0u8; size * size * size * bytes_per_pixel];
+        for (index, color) in lut.data().iter().enumerate() {
+            let (r, g, b) = (index % size, (index / size) % size, index / (size * size));
+            let offset = (g * size * size + b * size + r) * bytes_per_pixel;
+            let pixel = &mut data[offset..offset + bytes_per_pixel];
+            let channels = [color[0], color[1], color[2], 1.0];
+            if half_float {
+                for (bytes, value) in pixel.chunks_exact_mut(2).zip(channels) {
+                    bytes.copy_from_slice(&f16_bits(value.clamp(0.0, 1.0)).to_ne_bytes());
+                }
+            } else {
+                for (byte, value) in pixel.iter_mut().zip(channels) {
+                    *byte = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            }
+        }
+
+        let texture =
+            self.renderer
+                .import_memory(&data, format, ((size * size) as i32, size as i32).into(), false)?;
+        self.renderer.color_lut = Some((lut.clone(), texture.clone()));
+        Ok(texture)
+    }
+
+    /// Returns the box in framebuffer coordinates covered by a rectangle of this frame
+    fn scissor_box(&self, rect: Rectangle<i32, Physical>) -> (i32, i32, i32, i32) {
+        let viewport = self.transform.transform_size(self.size).to_f64();
+        let to_framebuffer = |x: i32, y: i32| {
+            let ndc = self.current_projection * Vector3::new(x as f32, y as f32, 1.0);
+            (
+                ((ndc.x as f64 + 1.0) / 2.0 * viewport.w).round() as i32,
+                ((ndc.y as f64 + 1.0) / 2.0 * viewport.h).round() as i32,
+            )
+        };
+
+        let (x1, y1) = to_framebuffer(rect.loc.x, rect.loc.y);
+        let (x2, y2) = to_framebuffer(rect.loc.x + rect.size.w, rect.loc.y + rect.size.h);
+        (x1.min(x2), y1.min(y2), (x2 - x1).abs(), (y2 - y1).abs())
+    }
+
     /// Render a texture to the current target using given projection matrix and alpha.
     ///
     /// The instances are used to define the regions which should get drawn.
@@ -3221,6 +3630,19 @@ impl<'frame> Drop for GlesFrame<'frame> {
     }
 }
 
+/// Converts a value in the range `0.0..=1.0` to the bits of a half-float, rounding to the nearest value
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent <= 0 {
+        // subnormal half-floats are multiples of 2^-24
+        return (value * (1 << 24) as f32).round() as u16;
+    }
+    let mantissa = bits & 0x7f_ffff;
+    // a carry of the rounding correctly overflows into the exponent
+    (((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1)) as u16
+}
+
 fn build_texture_mat(
     src: Rectangle<f64, BufferCoord>,
     dest: Rectangle<i32, Physical>,
@@ -0,0 +1,66 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+uniform mat4 color_matrix;
+// number of entries per dimension, 0.0 if no lut is used
+uniform float lut_size;
+// the slices of the 3d lut for every blue value placed side by side
+uniform sampler2D lut;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+vec3 sample_lut(vec3 color) {
+    float max_index = lut_size - 1.0;
+    vec3 pos = color * max_index;
+    float slice = min(floor(pos.b), max_index - 1.0);
+    vec2 coords = vec2((pos.r + 0.5) / (lut_size * lut_size), (pos.g + 0.5) / lut_size);
+
+    vec3 low = texture2D(lut, coords + vec2(slice / lut_size, 0.0)).rgb;
+    vec3 high = texture2D(lut, coords + vec2((slice + 1.0) / lut_size, 0.0)).rgb;
+    return mix(low, high, pos.b - slice);
+}
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    if (color.a > 0.0) {
+        vec3 rgb = clamp((color_matrix * vec4(color.rgb / color.a, 1.0)).rgb, 0.0, 1.0);
+        if (lut_size > 1.0)
+            rgb = sample_lut(rgb);
+        color = vec4(rgb * color.a, color.a);
+    }
+
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.3, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
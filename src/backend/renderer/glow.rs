@@ -14,6 +14,7 @@ use crate::{
         allocator::{dmabuf::Dmabuf, Format, Fourcc},
         egl::EGLContext,
         renderer::{
+            color::ColorTransform,
             element::UnderlyingStorage,
             gles::{element::*, *},
             sync, Bind, Blit, DebugFlags, ExportMem, ImportDma, ImportMem, Offscreen, Renderer,
@@ -26,6 +27,7 @@ use crate::{
 #[cfg(feature = "wayland_frontend")]
 use wayland_server::protocol::{wl_buffer, wl_shm};
 
+use cgmath::Matrix3;
 use glow::Context;
 use std::{
     borrow::{Borrow, BorrowMut},
@@ -216,6 +218,10 @@ impl Renderer for GlowRenderer {
         self.gl.debug_flags()
     }
 
+    fn supports_color_transform(&self) -> bool {
+        self.gl.supports_color_transform()
+    }
+
     #[profiling::function]
     fn render(
         &mut self,
@@ -280,6 +286,47 @@ impl<'frame> Frame for GlowFrame<'frame> {
         )
     }
 
+    fn draw_solid_with_matrix(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        color: [f32; 4],
+    ) -> Result<(), Self::Error> {
+        Frame::draw_solid_with_matrix(self.frame.as_mut().unwrap(), dst, damage, matrix, color)
+    }
+
+    #[profiling::function]
+    fn render_texture_with_matrix(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
+        Frame::render_texture_with_matrix(
+            self.frame.as_mut().unwrap(),
+            texture,
+            src,
+            dst,
+            damage,
+            matrix,
+            src_transform,
+            alpha,
+        )
+    }
+
+    fn apply_color_transform(
+        &mut self,
+        transform: &ColorTransform,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Self::Error> {
+        Frame::apply_color_transform(self.frame.as_mut().unwrap(), transform, damage)
+    }
+
     fn transformation(&self) -> Transform {
         self.frame.as_ref().unwrap().transformation()
     }
@@ -43,6 +43,8 @@ pub mod multigpu;
 
 pub mod utils;
 
+pub mod color;
+
 pub mod element;
 
 pub mod damage;
@@ -52,6 +54,10 @@ pub mod sync;
 #[cfg(feature = "renderer_test")]
 pub mod test;
 
+#[cfg(all(test, feature = "renderer_pixman"))]
+#[allow(dead_code)]
+pub(crate) mod snapshot;
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 /// Texture filtering methods
 pub enum TextureFilter {
@@ -222,6 +228,82 @@ pub trait Frame {
         alpha: f32,
     ) -> Result<(), Self::Error>;
 
+    /// Render part of a texture as given by src to the current target into the rectangle described by dst
+    /// like [`Frame::render_texture_from_to`] and afterwards apply an arbitrary affine transformation to it.
+    ///
+    /// The `matrix` transforms the rendered rectangle in the coordinate space of this frame (before
+    /// applying the output transformation). Unlike in [`Frame::render_texture_from_to`] the `damage` is
+    /// not relative to `dst`, but given in the coordinate space of this frame after applying `matrix`.
+    ///
+    /// Implementing this is optional, the default implementation falls back to rendering the texture
+    /// into the bounding box of the transformed rectangle. This is only correct for matrices, that
+    /// do not rotate or skew the rectangle.
+    #[allow(clippy::too_many_arguments)]
+    fn render_texture_with_matrix(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
+        let dst = utils::transform_bbox(dst.to_f64(), &matrix).to_i32_round();
+        let damage = damage
+            .iter()
+            .filter_map(|rect| rect.intersection(dst))
+            .map(|mut rect| {
+                rect.loc -= dst.loc;
+                rect
+            })
+            .collect::<Vec<_>>();
+        self.render_texture_from_to(texture, src, dst, &damage, src_transform, alpha)
+    }
+
+    /// Draw a solid color into the rectangle described by dst like [`Frame::draw_solid`]
+    /// and afterwards apply an arbitrary affine transformation to it.
+    ///
+    /// The `matrix` and `damage` are handled like in [`Frame::render_texture_with_matrix`].
+    ///
+    /// Implementing this is optional, the default implementation falls back to drawing the color
+    /// into the bounding box of the transformed rectangle. This is only correct for matrices, that
+    /// do not rotate or skew the rectangle.
+    fn draw_solid_with_matrix(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        color: [f32; 4],
+    ) -> Result<(), Self::Error> {
+        let dst = utils::transform_bbox(dst.to_f64(), &matrix).to_i32_round();
+        let damage = damage
+            .iter()
+            .filter_map(|rect| rect.intersection(dst))
+            .map(|mut rect| {
+                rect.loc -= dst.loc;
+                rect
+            })
+            .collect::<Vec<_>>();
+        self.draw_solid(dst, &damage, color)
+    }
+
+    /// Apply a color transformation to everything rendered to the current target so far
+    ///
+    /// Only the regions described by `damage` (in the coordinate space of this frame) are transformed.
+    /// Overlapping rectangles must not cause any pixels to be transformed more than once.
+    ///
+    /// Implementing this is optional, the default implementation leaves the contents unchanged.
+    /// Renderers implementing it have to return `true` from [`Renderer::supports_color_transform`].
+    fn apply_color_transform(
+        &mut self,
+        transform: &color::ColorTransform,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Self::Error> {
+        let _ = (transform, damage);
+        Ok(())
+    }
+
     /// Output transformation that is applied to this frame
     fn transformation(&self) -> Transform;
 
@@ -277,6 +359,13 @@ pub trait Renderer: fmt::Debug {
     /// Returns the current enabled [`DebugFlags`]
     fn debug_flags(&self) -> DebugFlags;
 
+    /// Returns whether frames of this renderer implement [`Frame::apply_color_transform`]
+    ///
+    /// Renderers not supporting color transformations leave the contents unchanged instead.
+    fn supports_color_transform(&self) -> bool {
+        false
+    }
+
     /// Initialize a rendering context on the current rendering target with given dimensions and transformation.
     ///
     /// The `output_size` specifies the dimensions of the display **before** the `dst_transform` is
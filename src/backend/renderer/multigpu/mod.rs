@@ -6,6 +6,7 @@
 //!
 //! smithay provides the following graphics apis:
 //! - [`egl::EglGlesBackend`]
+//! - [`pixman::PixmanBackend`], for devices without hardware acceleration
 //!
 //! A [`MultiRenderer`] gets created using two [`DrmNode`]s to identify gpus.
 //! One gpu will be referred to as the render-gpu, the other as the target-gpu.
@@ -56,7 +57,7 @@ use crate::{
     backend::{
         allocator::{dmabuf::AnyError, Allocator, Buffer as BufferTrait, Format, Fourcc},
         drm::DrmNode,
-        renderer::sync,
+        renderer::{color::ColorTransform, sync},
         SwapBuffersError,
     },
     utils::{Buffer as BufferCoords, Physical, Size},
@@ -68,6 +69,8 @@ use wayland_server::protocol::{wl_buffer, wl_surface::WlSurface};
 pub mod egl;
 #[cfg(all(feature = "backend_gbm", feature = "backend_egl", feature = "renderer_gl"))]
 pub mod gbm;
+#[cfg(feature = "renderer_pixman")]
+pub mod pixman;
 
 /// Tracks available gpus from a given [`GraphicsApi`]
 #[derive(Debug)]
@@ -956,6 +959,10 @@ where
         self.render.renderer().debug_flags()
     }
 
+    fn supports_color_transform(&self) -> bool {
+        self.render.renderer().supports_color_transform()
+    }
+
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
     fn render<'frame>(
@@ -1543,6 +1550,66 @@ where
         }
     }
 
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[profiling::function]
+    fn draw_solid_with_matrix(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        color: [f32; 4],
+    ) -> Result<(), Self::Error> {
+        self.damage.extend(damage);
+        self.frame
+            .as_mut()
+            .unwrap()
+            .draw_solid_with_matrix(dst, damage, matrix, color)
+            .map_err(Error::Render)
+    }
+
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[profiling::function]
+    fn render_texture_with_matrix(
+        &mut self,
+        texture: &MultiTexture,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Error<R, T>> {
+        if let Some(texture) = texture.get::<R>(&self.node) {
+            self.damage.extend(damage);
+            self.frame
+                .as_mut()
+                .unwrap()
+                .render_texture_with_matrix(&texture, src, dst, damage, matrix, src_transform, alpha)
+                .map_err(Error::Render)
+        } else {
+            warn!(
+                "Failed to render texture {:?}, import for wrong devices {:?}? {:?}",
+                texture.0.as_ptr(),
+                self.node,
+                texture.0.borrow(),
+            );
+            Ok(())
+        }
+    }
+
+    fn apply_color_transform(
+        &mut self,
+        transform: &ColorTransform,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Error<R, T>> {
+        self.damage.extend(damage);
+        self.frame
+            .as_mut()
+            .unwrap()
+            .apply_color_transform(transform, damage)
+            .map_err(Error::Render)
+    }
+
     fn transformation(&self) -> Transform {
         self.frame.as_ref().unwrap().transformation()
     }
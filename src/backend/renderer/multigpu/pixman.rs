@@ -0,0 +1,192 @@
+//! Implementation of the multi-gpu [`GraphicsApi`] using
+//! user provided drm devices and pixman for rendering.
+//!
+//! This api does not need any hardware acceleration and can thus drive devices,
+//! that do not support OpenGL ES, like display-only devices or the virtual gpus of
+//! some virtual machines.
+//!
+//! Buffers for displaying content on a device can be allocated as dumb buffers through
+//! [`PixmanDevice::allocator`]. Buffers are transferred between a [`PixmanDevice`] and
+//! other devices by copying them with the cpu.
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use tracing::warn;
+
+use crate::backend::{
+    allocator::{dmabuf::DmabufAllocator, dumb::DumbAllocator},
+    drm::{DrmDeviceFd, DrmNode},
+    renderer::{
+        multigpu::{ApiDevice, Error as MultiError, GraphicsApi},
+        pixman::{PixmanError, PixmanRenderer},
+        Renderer,
+    },
+};
+
+/// A [`GraphicsApi`] utilizing user-provided drm devices and pixman for rendering.
+#[derive(Debug)]
+pub struct PixmanBackend {
+    devices: HashMap<DrmNode, DrmDeviceFd>,
+    needs_enumeration: AtomicBool,
+}
+
+impl Default for PixmanBackend {
+    fn default() -> Self {
+        PixmanBackend {
+            devices: HashMap::new(),
+            needs_enumeration: AtomicBool::new(true),
+        }
+    }
+}
+
+impl PixmanBackend {
+    /// Initialize a new [`PixmanBackend`] without any devices
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new drm device for a given node to the api
+    ///
+    /// The file descriptor is used to allocate dumb buffers, so it should refer to
+    /// a primary node of the device.
+    pub fn add_node(&mut self, node: DrmNode, fd: DrmDeviceFd) {
+        if self.devices.contains_key(&node) {
+            return;
+        }
+
+        self.devices.insert(node, fd);
+        self.needs_enumeration.store(true, Ordering::SeqCst);
+    }
+
+    /// Remove a given node from the api
+    pub fn remove_node(&mut self, node: &DrmNode) {
+        if self.devices.remove(node).is_some() {
+            self.needs_enumeration.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl GraphicsApi for PixmanBackend {
+    type Device = PixmanDevice;
+    type Error = PixmanError;
+
+    fn enumerate(&self, list: &mut Vec<Self::Device>) -> Result<(), Self::Error> {
+        self.needs_enumeration.store(false, Ordering::SeqCst);
+
+        // remove old stuff
+        list.retain(|renderer| {
+            self.devices
+                .keys()
+                .any(|node| renderer.node.dev_id() == node.dev_id())
+        });
+
+        // add new stuff
+        let new_renderers = self
+            .devices
+            .iter()
+            .filter(|(node, _)| {
+                !list
+                    .iter()
+                    .any(|renderer| renderer.node.dev_id() == node.dev_id())
+            })
+            .map(|(node, fd)| {
+                Ok(PixmanDevice {
+                    node: *node,
+                    fd: fd.clone(),
+                    renderer: PixmanRenderer::new()?,
+                })
+            })
+            .flat_map(|x: Result<PixmanDevice, PixmanError>| match x {
+                Ok(x) => Some(x),
+                Err(x) => {
+                    warn!("Skipping pixman device: {}", x);
+                    None
+                }
+            })
+            .collect::<Vec<PixmanDevice>>();
+        list.extend(new_renderers);
+
+        // but don't replace already initialized renderers
+
+        Ok(())
+    }
+
+    fn needs_enumeration(&self) -> bool {
+        self.needs_enumeration.load(Ordering::Acquire)
+    }
+
+    fn identifier() -> &'static str {
+        "pixman"
+    }
+}
+
+// TODO: Replace with specialization impl in multigpu/mod once possible
+impl<T: GraphicsApi> std::convert::From<PixmanError> for MultiError<PixmanBackend, T>
+where
+    T::Error: 'static,
+    <<T::Device as ApiDevice>::Renderer as Renderer>::Error: 'static,
+{
+    fn from(err: PixmanError) -> MultiError<PixmanBackend, T> {
+        MultiError::Render(err)
+    }
+}
+
+/// [`ApiDevice`] of the [`PixmanBackend`]
+#[derive(Debug)]
+pub struct PixmanDevice {
+    node: DrmNode,
+    fd: DrmDeviceFd,
+    renderer: PixmanRenderer,
+}
+
+impl PixmanDevice {
+    /// Returns an allocator for dumb buffers on this device
+    ///
+    /// Buffers created by this allocator can be used for scanout on this device
+    /// and as a rendering target of the [`PixmanRenderer`].
+    pub fn allocator(&self) -> DmabufAllocator<DumbAllocator> {
+        DmabufAllocator(DumbAllocator::new(self.fd.clone()))
+    }
+}
+
+impl ApiDevice for PixmanDevice {
+    type Renderer = PixmanRenderer;
+
+    fn renderer(&self) -> &Self::Renderer {
+        &self.renderer
+    }
+    fn renderer_mut(&mut self) -> &mut Self::Renderer {
+        &mut self.renderer
+    }
+    fn node(&self) -> &DrmNode {
+        &self.node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{
+        allocator::dmabuf::Dmabuf,
+        renderer::{
+            multigpu::{GpuManager, MultiRenderer},
+            Bind, ExportMem, ImportDma, ImportMem,
+        },
+    };
+
+    fn assert_renderer<R: Renderer + Bind<Dmabuf> + ImportMem + ImportDma + ExportMem>() {}
+
+    #[test]
+    fn multi_renderer_is_renderer() {
+        assert_renderer::<MultiRenderer<'_, '_, '_, PixmanBackend, PixmanBackend>>();
+    }
+
+    #[test]
+    fn no_devices() {
+        let mut manager = GpuManager::new(PixmanBackend::new()).unwrap();
+        assert!(!manager.as_mut().needs_enumeration());
+    }
+}
@@ -55,6 +55,9 @@ pub enum PixmanError {
     /// No target is currently bound
     #[error("No target is currently bound")]
     NoTargetBound,
+    /// The source or destination of a blit is out of bounds or the two overlap
+    #[error("Invalid blit region")]
+    InvalidBlitRegion,
     /// The requested operation is not supported
     #[error("The requested operation is not supported")]
     Unsupported,
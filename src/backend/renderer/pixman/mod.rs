@@ -5,8 +5,9 @@ use std::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
+use cgmath::{prelude::*, Matrix3, Vector2};
 use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
-use pixman::{Filter, FormatCode, Image, Operation, Repeat};
+use pixman::{Filter, Fixed, FormatCode, Image, Operation, Repeat};
 use tracing::warn;
 
 use crate::{
@@ -15,7 +16,7 @@ use crate::{
         format::has_alpha,
         Buffer,
     },
-    utils::{Buffer as BufferCoords, Physical, Rectangle, Scale, Size, Transform},
+    utils::{Buffer as BufferCoords, Physical, Point, Rectangle, Scale, Size, Transform},
 };
 
 #[cfg(feature = "wayland_frontend")]
@@ -26,6 +27,9 @@ use crate::{
 #[cfg(feature = "wayland_frontend")]
 use wayland_server::{protocol::wl_buffer, Resource, Weak};
 
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+use crate::backend::allocator::memfd::MemfdBuffer;
+
 #[cfg(all(
     feature = "wayland_frontend",
     feature = "backend_egl",
@@ -33,13 +37,15 @@ use wayland_server::{protocol::wl_buffer, Resource, Weak};
 ))]
 use super::ImportEgl;
 use super::{
-    sync::SyncPoint, Bind, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer, Texture,
-    TextureFilter, TextureMapping, Unbind,
+    color::ColorTransform, sync::SyncPoint, utils::transform_bbox, Bind, Blit, DebugFlags, ExportMem, Frame,
+    ImportDma, ImportMem, Offscreen, Renderer, Texture, TextureFilter, TextureMapping, Unbind,
 };
 
 mod error;
+mod yuv;
 
 pub use error::*;
+pub use yuv::{YuvMatrix, YuvRange};
 
 lazy_static::lazy_static! {
     static ref SUPPORTED_FORMATS: Vec<drm_fourcc::DrmFourcc> = {
@@ -66,28 +72,183 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Number of bits of subpixel precision of the kernels used for downscaling
+const DOWNSCALE_SUBSAMPLE_BITS: i32 = 2;
+
+/// Selects the pixman filter for sampling an image
+///
+/// `scale` is the number of source pixels per destination pixel along the axes of the source image.
+fn select_filter(
+    upscale_filter: TextureFilter,
+    downscale_filter: TextureFilter,
+    scale: Scale<f64>,
+) -> (Filter, Vec<Fixed>) {
+    let downscaling = scale.x > 1.0 || scale.y > 1.0;
+    let filter = if downscaling {
+        downscale_filter
+    } else {
+        upscale_filter
+    };
+
+    match filter {
+        TextureFilter::Nearest => (Filter::Nearest, Vec::new()),
+        TextureFilter::Linear if !downscaling => (Filter::Bilinear, Vec::new()),
+        // bilinear filtering only ever looks at the four nearest source pixels,
+        // which causes heavy aliasing when downscaling, so average over the
+        // whole area a destination pixel covers instead.
+        TextureFilter::Linear => {
+            let (width, kernel_x) = box_kernel(scale.x.max(1.0));
+            let (height, kernel_y) = box_kernel(scale.y.max(1.0));
+
+            let mut params = Vec::with_capacity(4 + kernel_x.len() + kernel_y.len());
+            params.push(Fixed::from_int(width));
+            params.push(Fixed::from_int(height));
+            params.push(Fixed::from_int(DOWNSCALE_SUBSAMPLE_BITS));
+            params.push(Fixed::from_int(DOWNSCALE_SUBSAMPLE_BITS));
+            params.extend(kernel_x);
+            params.extend(kernel_y);
+            (Filter::SeparableConvolution, params)
+        }
+    }
+}
+
+/// Returns the matrix of an affine transformation given as a function
+fn affine_matrix<A, B>(f: impl Fn(Point<f64, A>) -> Point<f64, B>) -> Matrix3<f64> {
+    let origin = f(Point::from((0.0, 0.0)));
+    let x = f(Point::from((1.0, 0.0))) - origin;
+    let y = f(Point::from((0.0, 1.0))) - origin;
+    Matrix3::new(x.x, x.y, 0.0, y.x, y.y, 0.0, origin.x, origin.y, 1.0)
+}
+
+/// Converts an affine transformation into a pixman transform
+fn pixman_transform(matrix: &Matrix3<f64>) -> Result<pixman::Transform, PixmanError> {
+    let matrix = pixman::FTransform::new([
+        [matrix.x.x, matrix.y.x, matrix.z.x],
+        [matrix.x.y, matrix.y.y, matrix.z.y],
+        [matrix.x.z, matrix.y.z, matrix.z.z],
+    ]);
+    pixman::Transform::try_from(matrix).map_err(|_| PixmanError::Unsupported)
+}
+
+/// Apply a [`ColorTransform`] to a single premultiplied a8r8g8b8 pixel
+fn transform_pixel(pixel: u32, transform: &ColorTransform) -> u32 {
+    let alpha = pixel >> 24;
+    if alpha == 0 {
+        return pixel;
+    }
+
+    let a = alpha as f32 / 255.0;
+    let channel = |shift: u32| ((pixel >> shift) & 0xff) as f32 / 255.0 / a;
+    let [r, g, b] = transform.apply([channel(16), channel(8), channel(0)]);
+    let premultiply = |c: f32| (c * a * 255.0).round() as u32;
+
+    (alpha << 24) | (premultiply(r) << 16) | (premultiply(g) << 8) | premultiply(b)
+}
+
+/// Creates the one-dimensional kernel of a box filter for the given scale
+///
+/// This is the convolution of a box of one pixel (the source pixel) with a box of `scale`
+/// pixels (the area covered by the destination pixel), sampled for every subpixel phase
+/// in the layout expected by [`Filter::SeparableConvolution`].
+fn box_kernel(scale: f64) -> (i32, Vec<Fixed>) {
+    let width = (scale + 1.0).ceil() as i32;
+    let phases = 1 << DOWNSCALE_SUBSAMPLE_BITS;
+
+    let mut kernel = Vec::with_capacity((width * phases) as usize);
+    for phase in 0..phases {
+        let frac = (phase as f64 + 0.5) / phases as f64;
+        let first = (frac - width as f64 / 2.0 - 0.5).ceil() as i32;
+
+        let mut weights = (first..first + width)
+            .map(|x| {
+                let pos = x as f64 + 0.5 - frac;
+                let overlap = (pos + 0.5).min(scale / 2.0) - (pos - 0.5).max(-scale / 2.0);
+                (overlap.max(0.0) / scale * 65536.0).round() as i32
+            })
+            .collect::<Vec<_>>();
+
+        // make sure the weights add up to exactly one, so rounding does not change the brightness
+        let total: i32 = weights.iter().sum();
+        if let Some(max) = weights.iter_mut().max() {
+            *max += 65536 - total;
+        }
+
+        kernel.extend(weights.into_iter().map(Fixed::from_raw));
+    }
+
+    (width, kernel)
+}
+
 #[derive(Debug)]
 enum PixmanTarget {
     Image { dmabuf: Dmabuf, image: PixmanImage },
     RenderBuffer(PixmanRenderBuffer),
+    Texture(PixmanTexture),
 }
 
 impl PixmanTarget {
     fn image(&self) -> &pixman::Image<'static, 'static> {
         match self {
             PixmanTarget::Image { image, .. } => &image.0.image,
-            PixmanTarget::RenderBuffer(render_buffer) => &render_buffer.0,
+            PixmanTarget::RenderBuffer(render_buffer) => &render_buffer.image,
+            PixmanTarget::Texture(texture) => &texture.0 .0.image,
         }
     }
+
+    fn sync(&self, flags: DmabufSyncFlags) -> Result<(), DmabufSyncFailed> {
+        if let PixmanTarget::Image { dmabuf, .. } = self {
+            dmabuf.sync_plane(0, flags)?;
+        }
+        Ok(())
+    }
 }
 
 /// Offscreen render buffer
-#[derive(Debug)]
-pub struct PixmanRenderBuffer(pixman::Image<'static, 'static>);
+///
+/// The buffer is cheaply clonable, all clones refer to the same image.
+#[derive(Debug, Clone)]
+pub struct PixmanRenderBuffer {
+    image: Rc<pixman::Image<'static, 'static>>,
+    // keeps the memory referenced by `image` alive
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+    _memfd: Option<MemfdBuffer>,
+}
 
 impl From<pixman::Image<'static, 'static>> for PixmanRenderBuffer {
     fn from(value: pixman::Image<'static, 'static>) -> Self {
-        Self(value)
+        Self {
+            image: Rc::new(value),
+            #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+            _memfd: None,
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+impl TryFrom<&MemfdBuffer> for PixmanRenderBuffer {
+    type Error = PixmanError;
+
+    fn try_from(buffer: &MemfdBuffer) -> Result<Self, Self::Error> {
+        let format = buffer.format().code;
+        let format_code =
+            FormatCode::try_from(format).map_err(|_| PixmanError::UnsupportedPixelFormat(format))?;
+        let size = buffer.size();
+        let image = unsafe {
+            pixman::Image::from_raw_mut(
+                format_code,
+                size.w as usize,
+                size.h as usize,
+                buffer.ptr() as *mut u32,
+                buffer.stride() as usize,
+                false,
+            )
+        }
+        .map_err(|_| PixmanError::ImportFailed)?;
+
+        Ok(Self {
+            image: Rc::new(image),
+            _memfd: Some(buffer.clone()),
+        })
     }
 }
 
@@ -103,6 +264,8 @@ struct PixmanImageInner {
     buffer: Option<Weak<wl_buffer::WlBuffer>>,
     dmabuf: Option<PixmanDmabufMapping>,
     image: Image<'static, 'static>,
+    // original format of images converted from yuv buffers
+    yuv_format: Option<DrmFourcc>,
     _flipped: bool, /* TODO: What about flipped textures? */
 }
 
@@ -147,19 +310,26 @@ impl From<pixman::Image<'static, 'static>> for PixmanTexture {
             dmabuf: None,
             _flipped: false,
             image,
+            yuv_format: None,
         })))
     }
 }
 
 struct DmabufReadGuard {
     dmabuf: Dmabuf,
+    plane: usize,
 }
 
 impl DmabufReadGuard {
     #[profiling::function]
     pub fn new(dmabuf: Dmabuf) -> Result<Self, DmabufSyncFailed> {
-        dmabuf.sync_plane(0, DmabufSyncFlags::START | DmabufSyncFlags::READ)?;
-        Ok(Self { dmabuf })
+        Self::new_plane(dmabuf, 0)
+    }
+
+    #[profiling::function]
+    pub fn new_plane(dmabuf: Dmabuf, plane: usize) -> Result<Self, DmabufSyncFailed> {
+        dmabuf.sync_plane(plane, DmabufSyncFlags::START | DmabufSyncFlags::READ)?;
+        Ok(Self { dmabuf, plane })
     }
 }
 
@@ -168,7 +338,7 @@ impl Drop for DmabufReadGuard {
     fn drop(&mut self) {
         if let Err(err) = self
             .dmabuf
-            .sync_plane(0, DmabufSyncFlags::END | DmabufSyncFlags::READ)
+            .sync_plane(self.plane, DmabufSyncFlags::END | DmabufSyncFlags::READ)
         {
             tracing::warn!(?err, "failed to end sync read");
         }
@@ -233,6 +403,43 @@ pub struct PixmanFrame<'frame> {
 }
 
 impl<'frame> PixmanFrame<'frame> {
+    /// Region of the target image to draw a transformed rectangle with the given
+    /// bounding box into, damage and bounding box are given in frame coordinates
+    fn transformed_clip_region(
+        &self,
+        bbox: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> pixman::Region32 {
+        let bbox = self.transform.transform_rect_in(bbox, &self.size);
+        let clip_region =
+            pixman::Region32::init_rect(0, 0, self.output_size.w as u32, self.output_size.h as u32)
+                .intersect(&pixman::Region32::init_rect(
+                    bbox.loc.x,
+                    bbox.loc.y,
+                    bbox.size.w as u32,
+                    bbox.size.h as u32,
+                ));
+
+        let damage_boxes = damage
+            .iter()
+            .copied()
+            .map(|rect| {
+                let rect = self.transform.transform_rect_in(rect, &self.size);
+
+                let p1 = rect.loc;
+                let p2 = p1 + rect.size.to_point();
+                pixman::Box32 {
+                    x1: p1.x,
+                    y1: p1.y,
+                    x2: p2.x,
+                    y2: p2.y,
+                }
+            })
+            .collect::<Vec<_>>();
+        let damage_region = pixman::Region32::init_rects(&damage_boxes);
+        clip_region.intersect(&damage_region)
+    }
+
     fn draw_solid_color(
         &mut self,
         dst: Rectangle<i32, Physical>,
@@ -435,6 +642,8 @@ impl<'frame> Frame for PixmanFrame<'frame> {
 
         let dst_src_size = image_transform.transform_size(src.size);
         let scale = dst_src_size.to_f64() / dst.size.to_f64();
+        let src_scale = image_transform.transform_size(Size::<f64, BufferCoords>::from((scale.x, scale.y)));
+        let src_scale = Scale::from((src_scale.w, src_scale.h));
 
         let (src_x, src_y, dest_x, dest_y, width, height, transform) =
             if image_transform != Transform::Normal || scale != Scale::from(1f64) {
@@ -533,13 +742,16 @@ impl<'frame> Frame for PixmanFrame<'frame> {
                 src_image.clear_transform()?;
             }
 
-            let filter = match self.renderer.upscale_filter {
-                TextureFilter::Linear => Filter::Bilinear,
-                TextureFilter::Nearest => Filter::Nearest,
-            };
+            let (filter, filter_params) = select_filter(
+                self.renderer.upscale_filter,
+                self.renderer.downscale_filter,
+                src_scale,
+            );
 
-            src_image.set_filter(filter, &[])?;
-            src_image.set_repeat(Repeat::None);
+            src_image.set_filter(filter, &filter_params)?;
+            // clamp to the edge when filtering, like the gl renderers do,
+            // the composited area is limited by the clip region anyway
+            src_image.set_repeat(Repeat::Pad);
 
             let has_alpha = DrmFourcc::try_from(src_image.format())
                 .ok()
@@ -569,6 +781,237 @@ impl<'frame> Frame for PixmanFrame<'frame> {
             );
 
             src_image.clear_transform()?;
+            src_image.set_filter(Filter::Fast, &[])?;
+            src_image.set_repeat(Repeat::None);
+
+            Result::<(), PixmanError>::Ok(())
+        })??;
+
+        if self.renderer.debug_flags.contains(DebugFlags::TINT) {
+            target_image.composite32(
+                Operation::Over,
+                &self.renderer.tint,
+                None,
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (target_image.width() as i32, target_image.height() as i32),
+            );
+        }
+
+        target_image.set_clip_region32(None)?;
+
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn draw_solid_with_matrix(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        color: [f32; 4],
+    ) -> Result<(), Self::Error> {
+        if dst.is_empty() {
+            return Ok(());
+        }
+
+        let target_image = self
+            .renderer
+            .target
+            .as_ref()
+            .ok_or(PixmanError::NoTargetBound)?
+            .image();
+
+        let frame_to_image = affine_matrix(|point: Point<f64, Physical>| {
+            self.transform.transform_point_in(point, &self.size.to_f64())
+        });
+        let bbox = transform_bbox(dst.to_f64(), &matrix).to_i32_up();
+        let matrix = Matrix3::from_cols(
+            matrix.x.map(f64::from),
+            matrix.y.map(f64::from),
+            matrix.z.map(f64::from),
+        );
+
+        let (Some(inverse_matrix), Some(image_to_frame)) = (matrix.invert(), frame_to_image.invert()) else {
+            // the rectangle collapses to a line or a point, so there is nothing to draw
+            return Ok(());
+        };
+        let image_to_mask = Matrix3::from_translation(Vector2::new(-dst.loc.x as f64, -dst.loc.y as f64))
+            * inverse_matrix
+            * image_to_frame;
+        let mask_scale = Scale::from((
+            image_to_mask.x.truncate().magnitude(),
+            image_to_mask.y.truncate().magnitude(),
+        ));
+
+        let clip_region = self.transformed_clip_region(bbox, damage);
+
+        // Like for textures the mask limits the composited area to the transformed rectangle
+        let solid = pixman::Solid::new(color).map_err(|_| PixmanError::Unsupported)?;
+        let mask = pixman::Image::new(FormatCode::A8, dst.size.w as usize, dst.size.h as usize, false)
+            .map_err(|_| PixmanError::Unsupported)?;
+        mask.fill_boxes(
+            Operation::Src,
+            [0f32, 0f32, 0f32, 1f32],
+            &[pixman::Box32 {
+                x1: 0,
+                y1: 0,
+                x2: mask.width() as i32,
+                y2: mask.height() as i32,
+            }],
+        )?;
+
+        let (filter, filter_params) = select_filter(
+            self.renderer.upscale_filter,
+            self.renderer.downscale_filter,
+            mask_scale,
+        );
+        mask.set_transform(pixman_transform(&image_to_mask)?)?;
+        mask.set_filter(filter, &filter_params)?;
+        mask.set_repeat(Repeat::None);
+
+        target_image.set_clip_region32(Some(&clip_region))?;
+
+        target_image.composite32(
+            Operation::Over,
+            &solid,
+            Some(&mask),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (target_image.width() as i32, target_image.height() as i32),
+        );
+
+        if self.renderer.debug_flags.contains(DebugFlags::TINT) {
+            target_image.composite32(
+                Operation::Over,
+                &self.renderer.tint,
+                None,
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (target_image.width() as i32, target_image.height() as i32),
+            );
+        }
+
+        target_image.set_clip_region32(None)?;
+
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn render_texture_with_matrix(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        matrix: Matrix3<f32>,
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
+        if src.size.is_empty() || dst.is_empty() {
+            return Ok(());
+        }
+
+        let target_image = self
+            .renderer
+            .target
+            .as_ref()
+            .ok_or(PixmanError::NoTargetBound)?
+            .image();
+        let src_image_accessor = texture.accessor()?;
+
+        // Pixman transforms map from the target image into the source image, so we build
+        // the inverse of the complete transformation from the texture to the target image.
+        let dst_src_size = src_transform.transform_size(src.size);
+        let dst_scale = (
+            dst_src_size.w / dst.size.w as f64,
+            dst_src_size.h / dst.size.h as f64,
+        );
+        let buffer_transform = src_transform.invert();
+        let dst_to_buffer = affine_matrix(|point: Point<f64, Physical>| {
+            let point = Point::<f64, BufferCoords>::from((point.x * dst_scale.0, point.y * dst_scale.1));
+            src.loc + buffer_transform.transform_point_in(point, &dst_src_size)
+        });
+        let frame_to_image = affine_matrix(|point: Point<f64, Physical>| {
+            self.transform.transform_point_in(point, &self.size.to_f64())
+        });
+        let bbox = transform_bbox(dst.to_f64(), &matrix).to_i32_up();
+        let matrix = Matrix3::from_cols(
+            matrix.x.map(f64::from),
+            matrix.y.map(f64::from),
+            matrix.z.map(f64::from),
+        );
+
+        let (Some(inverse_matrix), Some(image_to_frame)) = (matrix.invert(), frame_to_image.invert()) else {
+            // the rectangle collapses to a line or a point, so there is nothing to draw
+            return Ok(());
+        };
+        let image_to_buffer = dst_to_buffer
+            * Matrix3::from_translation(Vector2::new(-dst.loc.x as f64, -dst.loc.y as f64))
+            * inverse_matrix
+            * image_to_frame;
+        let image_to_mask = Matrix3::from_translation(Vector2::new(-src.loc.x, -src.loc.y)) * image_to_buffer;
+
+        let src_scale = Scale::from((
+            image_to_buffer.x.truncate().magnitude(),
+            image_to_buffer.y.truncate().magnitude(),
+        ));
+
+        let clip_region = self.transformed_clip_region(bbox, damage);
+
+        // The mask limits the composited area to the transformed src rectangle
+        // and applies the alpha value. Filtering it smooths the edges of the rectangle.
+        let mask = pixman::Image::new(
+            FormatCode::A8,
+            src.size.w.ceil() as usize,
+            src.size.h.ceil() as usize,
+            false,
+        )
+        .map_err(|_| PixmanError::Unsupported)?;
+        mask.fill_boxes(
+            Operation::Src,
+            [0f32, 0f32, 0f32, alpha],
+            &[pixman::Box32 {
+                x1: 0,
+                y1: 0,
+                x2: mask.width() as i32,
+                y2: mask.height() as i32,
+            }],
+        )?;
+
+        let (filter, filter_params) = select_filter(
+            self.renderer.upscale_filter,
+            self.renderer.downscale_filter,
+            src_scale,
+        );
+
+        mask.set_transform(pixman_transform(&image_to_mask)?)?;
+        mask.set_filter(filter, &filter_params)?;
+        mask.set_repeat(Repeat::None);
+
+        target_image.set_clip_region32(Some(&clip_region))?;
+
+        src_image_accessor.with_image(|src_image| {
+            src_image.set_transform(pixman_transform(&image_to_buffer)?)?;
+            src_image.set_filter(filter, &filter_params)?;
+            src_image.set_repeat(Repeat::Pad);
+
+            target_image.composite32(
+                Operation::Over,
+                src_image,
+                Some(&mask),
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (target_image.width() as i32, target_image.height() as i32),
+            );
+
+            src_image.clear_transform()?;
+            src_image.set_filter(Filter::Fast, &[])?;
+            src_image.set_repeat(Repeat::None);
 
             Result::<(), PixmanError>::Ok(())
         })??;
@@ -590,6 +1033,96 @@ impl<'frame> Frame for PixmanFrame<'frame> {
         Ok(())
     }
 
+    #[profiling::function]
+    fn apply_color_transform(
+        &mut self,
+        transform: &ColorTransform,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), Self::Error> {
+        let target_image = self
+            .renderer
+            .target
+            .as_ref()
+            .ok_or(PixmanError::NoTargetBound)?
+            .image();
+
+        let damage_boxes = damage
+            .iter()
+            .map(|rect| {
+                let rect = self.transform.transform_rect_in(*rect, &self.size);
+                let p1 = rect.loc;
+                let p2 = p1 + rect.size.to_point();
+                pixman::Box32 {
+                    x1: p1.x,
+                    y1: p1.y,
+                    x2: p2.x,
+                    y2: p2.y,
+                }
+            })
+            .collect::<Vec<_>>();
+        // The region merges overlapping damage, so no pixel is transformed twice
+        let region = pixman::Region32::init_rect(0, 0, self.output_size.w as u32, self.output_size.h as u32)
+            .intersect(&pixman::Region32::init_rects(&damage_boxes));
+
+        let mut pixels = Vec::new();
+        for rect in region.rectangles() {
+            let (width, height) = (rect.x2 - rect.x1, rect.y2 - rect.y1);
+            if width <= 0 || height <= 0 {
+                continue;
+            }
+
+            pixels.clear();
+            pixels.resize((width * height) as usize, 0u32);
+
+            // copy the affected pixels into a temporary image with a known format
+            {
+                let image = pixman::Image::from_slice_mut(
+                    FormatCode::A8R8G8B8,
+                    width as usize,
+                    height as usize,
+                    &mut pixels,
+                    width as usize * 4,
+                    false,
+                )
+                .map_err(|_| PixmanError::Unsupported)?;
+                image.composite32(
+                    Operation::Src,
+                    target_image,
+                    None,
+                    (rect.x1, rect.y1),
+                    (0, 0),
+                    (0, 0),
+                    (width, height),
+                );
+            }
+
+            for pixel in pixels.iter_mut() {
+                *pixel = transform_pixel(*pixel, transform);
+            }
+
+            let image = pixman::Image::from_slice_mut(
+                FormatCode::A8R8G8B8,
+                width as usize,
+                height as usize,
+                &mut pixels,
+                width as usize * 4,
+                false,
+            )
+            .map_err(|_| PixmanError::Unsupported)?;
+            target_image.composite32(
+                Operation::Src,
+                &image,
+                None,
+                (0, 0),
+                (0, 0),
+                (rect.x1, rect.y1),
+                (width, height),
+            );
+        }
+
+        Ok(())
+    }
+
     fn transformation(&self) -> Transform {
         self.transform
     }
@@ -641,6 +1174,8 @@ pub struct PixmanRenderer {
     target: Option<PixmanTarget>,
     downscale_filter: TextureFilter,
     upscale_filter: TextureFilter,
+    yuv_matrix: Option<YuvMatrix>,
+    yuv_range: YuvRange,
     debug_flags: DebugFlags,
     tint: pixman::Solid<'static>,
 
@@ -657,6 +1192,8 @@ impl PixmanRenderer {
             target: None,
             downscale_filter: TextureFilter::Linear,
             upscale_filter: TextureFilter::Linear,
+            yuv_matrix: None,
+            yuv_range: YuvRange::Limited,
             debug_flags: DebugFlags::empty(),
             tint,
 
@@ -664,6 +1201,64 @@ impl PixmanRenderer {
             dmabuf_cache: Default::default(),
         })
     }
+
+    /// Set how YUV buffers are converted to RGB
+    ///
+    /// Buffers are converted when they are imported, so this only affects buffers imported afterwards.
+    /// If no `matrix` is set, it is guessed from the size of the buffer using [`YuvMatrix::for_size`].
+    ///
+    /// Defaults to guessing the matrix and [`YuvRange::Limited`], as used by most video content.
+    pub fn set_yuv_encoding(&mut self, matrix: Option<YuvMatrix>, range: YuvRange) {
+        self.yuv_matrix = matrix;
+        self.yuv_range = range;
+    }
+
+    fn convert_yuv(
+        &self,
+        format: DrmFourcc,
+        planes: &[yuv::Plane<'_>],
+        size: Size<i32, BufferCoords>,
+    ) -> Result<PixmanImage, PixmanError> {
+        let matrix = self.yuv_matrix.unwrap_or_else(|| YuvMatrix::for_size(size));
+        let image = yuv::convert(format, planes, size, matrix, self.yuv_range)?;
+        Ok(PixmanImage(Rc::new(PixmanImageInner {
+            #[cfg(feature = "wayland_frontend")]
+            buffer: None,
+            dmabuf: None,
+            image,
+            yuv_format: Some(format),
+            _flipped: false,
+        })))
+    }
+
+    #[profiling::function]
+    fn import_yuv_dmabuf(&self, dmabuf: &Dmabuf) -> Result<PixmanImage, PixmanError> {
+        let format = dmabuf.format();
+        if format.modifier != DrmModifier::Linear {
+            return Err(PixmanError::UnsupportedModifier(format.modifier));
+        }
+
+        let mappings = (0..dmabuf.num_planes())
+            .map(|idx| dmabuf.map_plane(idx, DmabufMappingMode::READ))
+            .collect::<Result<Vec<_>, _>>()?;
+        let planes = mappings
+            .iter()
+            .zip(dmabuf.strides())
+            .map(|(mapping, stride)| yuv::Plane {
+                // SAFETY: the mapping stays alive until the conversion is done
+                data: unsafe { std::slice::from_raw_parts(mapping.ptr() as *const u8, mapping.length()) },
+                stride: stride as usize,
+            })
+            .collect::<Vec<_>>();
+
+        let guards = (0..dmabuf.num_planes())
+            .map(|idx| DmabufReadGuard::new_plane(dmabuf.clone(), idx))
+            .collect::<Result<Vec<_>, _>>()?;
+        let image = self.convert_yuv(format.code, &planes, dmabuf.size());
+        drop(guards);
+
+        image
+    }
 }
 
 impl PixmanRenderer {
@@ -730,6 +1325,7 @@ impl PixmanRenderer {
                 _mapping: dmabuf_mapping,
             }),
             image,
+            yuv_format: None,
             _flipped: false,
         })))
     }
@@ -783,6 +1379,10 @@ impl Renderer for PixmanRenderer {
         self.debug_flags
     }
 
+    fn supports_color_transform(&self) -> bool {
+        true
+    }
+
     #[profiling::function]
     fn render(
         &mut self,
@@ -820,6 +1420,12 @@ impl ImportMem for PixmanRenderer {
         size: Size<i32, BufferCoords>,
         flipped: bool,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
+        if yuv::is_yuv(format) {
+            let stride = yuv::packed_stride(format, size.w as usize);
+            let planes = yuv::contiguous_planes(format, data, stride, size)?;
+            return self.convert_yuv(format, &planes, size).map(PixmanTexture);
+        }
+
         let format =
             pixman::FormatCode::try_from(format).map_err(|_| PixmanError::UnsupportedPixelFormat(format))?;
         let image = pixman::Image::new(format, size.w as usize, size.h as usize, false)
@@ -839,6 +1445,7 @@ impl ImportMem for PixmanRenderer {
             buffer: None,
             dmabuf: None,
             image,
+            yuv_format: None,
             _flipped: flipped,
         }))))
     }
@@ -859,6 +1466,17 @@ impl ImportMem for PixmanRenderer {
             return Err(PixmanError::ImportFailed);
         }
 
+        if let Some(format) = texture.0 .0.yuv_format {
+            // chroma samples may be shared with pixels outside of the region,
+            // so convert the whole buffer
+            let image = &texture.0 .0.image;
+            let size = (image.width() as i32, image.height() as i32).into();
+            let planes =
+                yuv::contiguous_planes(format, data, yuv::packed_stride(format, image.width()), size)?;
+            let matrix = self.yuv_matrix.unwrap_or_else(|| YuvMatrix::for_size(size));
+            return yuv::convert_into(format, &planes, matrix, self.yuv_range, image);
+        }
+
         let stride = texture.0 .0.image.stride();
         let expected_len = stride * texture.0 .0.image.height();
 
@@ -897,7 +1515,7 @@ impl ImportMem for PixmanRenderer {
     }
 
     fn mem_formats(&self) -> Box<dyn Iterator<Item = drm_fourcc::DrmFourcc>> {
-        Box::new(SUPPORTED_FORMATS.iter().copied())
+        Box::new(SUPPORTED_FORMATS.iter().chain(yuv::YUV_FORMATS).copied())
     }
 }
 
@@ -1041,11 +1659,25 @@ impl ImportMemWl for PixmanRenderer {
         _damage: &[Rectangle<i32, BufferCoords>],
     ) -> Result<PixmanTexture, PixmanError> {
         let image = shm::with_buffer_contents(buffer, |ptr, len, data| {
-            let format = FormatCode::try_from(
-                shm::shm_format_to_fourcc(data.format)
-                    .ok_or(PixmanError::UnsupportedWlPixelFormat(data.format))?,
-            )
-            .map_err(|_| PixmanError::UnsupportedWlPixelFormat(data.format))?;
+            let fourcc = shm::shm_format_to_fourcc(data.format)
+                .ok_or(PixmanError::UnsupportedWlPixelFormat(data.format))?;
+
+            if yuv::is_yuv(fourcc) {
+                // SAFETY: the pool is guaranteed to be at least `len` bytes long
+                let contents = unsafe { std::slice::from_raw_parts(ptr, len) };
+                let contents = contents
+                    .get(data.offset as usize..)
+                    .ok_or(PixmanError::IncompleteBuffer {
+                        expected: data.offset as usize,
+                        actual: len,
+                    })?;
+                let size = (data.width, data.height).into();
+                let planes = yuv::contiguous_planes(fourcc, contents, data.stride as usize, size)?;
+                return self.convert_yuv(fourcc, &planes, size);
+            }
+
+            let format = FormatCode::try_from(fourcc)
+                .map_err(|_| PixmanError::UnsupportedWlPixelFormat(data.format))?;
 
             let expected_len = (data.offset + data.stride * data.height) as usize;
             if len < expected_len {
@@ -1068,14 +1700,15 @@ impl ImportMemWl for PixmanRenderer {
                 )
             }
             .map_err(|_| PixmanError::ImportFailed)?;
-            std::result::Result::<_, PixmanError>::Ok(image)
+            std::result::Result::<_, PixmanError>::Ok(PixmanImage(Rc::new(PixmanImageInner {
+                buffer: Some(buffer.downgrade()),
+                dmabuf: None,
+                image,
+                yuv_format: None,
+                _flipped: false,
+            })))
         })??;
-        Ok(PixmanTexture(PixmanImage(Rc::new(PixmanImageInner {
-            buffer: Some(buffer.downgrade()),
-            dmabuf: None,
-            image,
-            _flipped: false,
-        }))))
+        Ok(PixmanTexture(image))
     }
 }
 
@@ -1086,6 +1719,12 @@ impl ImportDma for PixmanRenderer {
         dmabuf: &Dmabuf,
         _damage: Option<&[Rectangle<i32, BufferCoords>]>,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
+        if yuv::is_yuv(dmabuf.format().code) {
+            // the converted image is a copy, so it can not be cached
+            // and has to be recreated whenever the buffer is imported
+            return self.import_yuv_dmabuf(dmabuf).map(PixmanTexture);
+        }
+
         if let Some(image) = self.existing_dmabuf(dmabuf) {
             return Ok(PixmanTexture(image));
         };
@@ -1098,7 +1737,7 @@ impl ImportDma for PixmanRenderer {
     fn dmabuf_formats(&self) -> Box<dyn Iterator<Item = drm_fourcc::DrmFormat>> {
         lazy_static::lazy_static! {
             static ref DMABUF_FORMATS: Vec<DrmFormat> = {
-                SUPPORTED_FORMATS.iter().copied().map(|code| DrmFormat {
+                SUPPORTED_FORMATS.iter().chain(yuv::YUV_FORMATS).copied().map(|code| DrmFormat {
                     code,
                     modifier: drm_fourcc::DrmModifier::Linear,
                 }).collect()
@@ -1197,3 +1836,383 @@ impl Bind<PixmanRenderBuffer> for PixmanRenderer {
         Some(RENDER_BUFFER_FORMATS.clone())
     }
 }
+
+impl Offscreen<PixmanTexture> for PixmanRenderer {
+    #[profiling::function]
+    fn create_buffer(
+        &mut self,
+        format: DrmFourcc,
+        size: Size<i32, BufferCoords>,
+    ) -> Result<PixmanTexture, <Self as Renderer>::Error> {
+        let format_code =
+            FormatCode::try_from(format).map_err(|_| PixmanError::UnsupportedPixelFormat(format))?;
+        let image = pixman::Image::new(format_code, size.w as usize, size.h as usize, true)
+            .map_err(|_| PixmanError::Unsupported)?;
+        Ok(PixmanTexture::from(image))
+    }
+}
+
+impl Bind<PixmanTexture> for PixmanRenderer {
+    #[profiling::function]
+    fn bind(&mut self, target: PixmanTexture) -> Result<(), <Self as Renderer>::Error> {
+        // textures referencing client memory or a read-only dmabuf mapping can't be rendered into
+        #[cfg(feature = "wayland_frontend")]
+        if target.0 .0.buffer.is_some() {
+            return Err(PixmanError::Unsupported);
+        }
+        if target.0 .0.dmabuf.is_some() {
+            return Err(PixmanError::Unsupported);
+        }
+
+        self.target = Some(PixmanTarget::Texture(target));
+        Ok(())
+    }
+
+    fn supported_formats(&self) -> Option<HashSet<DrmFormat>> {
+        lazy_static::lazy_static! {
+            static ref TEXTURE_FORMATS: HashSet<DrmFormat> = {
+                SUPPORTED_FORMATS.iter().copied().map(|code| DrmFormat {
+                    code,
+                    modifier: DrmModifier::Linear,
+                }).collect()
+            };
+        }
+        Some(TEXTURE_FORMATS.clone())
+    }
+}
+
+impl<Target> Blit<Target> for PixmanRenderer
+where
+    Self: Bind<Target>,
+{
+    #[profiling::function]
+    fn blit_to(
+        &mut self,
+        to: Target,
+        src: Rectangle<i32, Physical>,
+        dst: Rectangle<i32, Physical>,
+        filter: TextureFilter,
+    ) -> Result<(), PixmanError> {
+        let src_target = self.target.take().ok_or(PixmanError::NoTargetBound)?;
+        let result = self.bind(to).and_then(|_| {
+            let dst_target = self.target.take().ok_or(PixmanError::NoTargetBound)?;
+            self.blit(&src_target, &dst_target, src, dst, filter)
+        });
+        self.target = Some(src_target);
+        result
+    }
+
+    #[profiling::function]
+    fn blit_from(
+        &mut self,
+        from: Target,
+        src: Rectangle<i32, Physical>,
+        dst: Rectangle<i32, Physical>,
+        filter: TextureFilter,
+    ) -> Result<(), PixmanError> {
+        let dst_target = self.target.take().ok_or(PixmanError::NoTargetBound)?;
+        let result = self.bind(from).and_then(|_| {
+            let src_target = self.target.take().ok_or(PixmanError::NoTargetBound)?;
+            self.blit(&src_target, &dst_target, src, dst, filter)
+        });
+        self.target = Some(dst_target);
+        result
+    }
+}
+
+impl PixmanRenderer {
+    #[profiling::function]
+    fn blit(
+        &self,
+        src_target: &PixmanTarget,
+        dst_target: &PixmanTarget,
+        src: Rectangle<i32, Physical>,
+        dst: Rectangle<i32, Physical>,
+        filter: TextureFilter,
+    ) -> Result<(), PixmanError> {
+        let src_image = src_target.image();
+        let dst_image = dst_target.image();
+
+        let src_bounds =
+            Rectangle::from_loc_and_size((0, 0), (src_image.width() as i32, src_image.height() as i32));
+        let dst_bounds =
+            Rectangle::from_loc_and_size((0, 0), (dst_image.width() as i32, dst_image.height() as i32));
+        // SAFETY: the pointers are only compared, never dereferenced
+        let same_image = unsafe { src_image.data() == dst_image.data() };
+        if src.is_empty()
+            || dst.is_empty()
+            || !src_bounds.contains_rect(src)
+            || !dst_bounds.contains_rect(dst)
+            || (same_image && src.overlaps(dst))
+        {
+            return Err(PixmanError::InvalidBlitRegion);
+        }
+
+        src_target.sync(DmabufSyncFlags::START | DmabufSyncFlags::READ)?;
+        if let Err(err) = dst_target.sync(DmabufSyncFlags::START | DmabufSyncFlags::WRITE) {
+            let _ = src_target.sync(DmabufSyncFlags::END | DmabufSyncFlags::READ);
+            return Err(err.into());
+        }
+
+        let result = (|| -> Result<(), PixmanError> {
+            if src.size == dst.size {
+                src_image.clear_transform()?;
+                dst_image.composite32(
+                    Operation::Src,
+                    src_image,
+                    None,
+                    src.loc.into(),
+                    (0, 0),
+                    dst.loc.into(),
+                    dst.size.into(),
+                );
+                return Ok(());
+            }
+
+            let scale = Scale::from((
+                src.size.w as f64 / dst.size.w as f64,
+                src.size.h as f64 / dst.size.h as f64,
+            ));
+            let transform = pixman::Transform::identity()
+                .scale(scale.x, scale.y, false)
+                .ok_or(PixmanError::Unsupported)?
+                .translate(src.loc.x, src.loc.y, false)
+                .ok_or(PixmanError::Unsupported)?;
+            let (filter, filter_params) = select_filter(filter, filter, scale);
+
+            src_image.set_transform(transform)?;
+            src_image.set_filter(filter, &filter_params)?;
+            src_image.set_repeat(Repeat::Pad);
+            dst_image.composite32(
+                Operation::Src,
+                src_image,
+                None,
+                (0, 0),
+                (0, 0),
+                dst.loc.into(),
+                dst.size.into(),
+            );
+            src_image.clear_transform()?;
+            src_image.set_filter(Filter::Fast, &[])?;
+            src_image.set_repeat(Repeat::None);
+
+            Ok(())
+        })();
+
+        let dst_sync = dst_target.sync(DmabufSyncFlags::END | DmabufSyncFlags::WRITE);
+        let src_sync = src_target.sync(DmabufSyncFlags::END | DmabufSyncFlags::READ);
+        result?;
+        dst_sync?;
+        src_sync?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a buffer with a checkerboard of single black and white pixels
+    fn checkerboard(renderer: &mut PixmanRenderer, size: i32) -> PixmanRenderBuffer {
+        let buffer: PixmanRenderBuffer = renderer
+            .create_buffer(DrmFourcc::Abgr8888, (size, size).into())
+            .unwrap();
+        renderer.bind(buffer.clone()).unwrap();
+        let white = (0..size)
+            .flat_map(|y| (0..size).map(move |x| (x, y)))
+            .filter(|(x, y)| (x + y) % 2 == 0)
+            .map(|(x, y)| Rectangle::from_loc_and_size((x, y), (1, 1)))
+            .collect::<Vec<_>>();
+        let mut frame = renderer.render((size, size).into(), Transform::Normal).unwrap();
+        frame
+            .clear(
+                [0.0, 0.0, 0.0, 1.0],
+                &[Rectangle::from_loc_and_size((0, 0), (size, size))],
+            )
+            .unwrap();
+        frame.clear([1.0, 1.0, 1.0, 1.0], &white).unwrap();
+        let _ = frame.finish().unwrap();
+        buffer
+    }
+
+    fn read_pixels(renderer: &mut PixmanRenderer, buffer: PixmanRenderBuffer, size: i32) -> Vec<u8> {
+        renderer.bind(buffer).unwrap();
+        let mapping = renderer
+            .copy_framebuffer(
+                Rectangle::from_loc_and_size((0, 0), (size, size)),
+                DrmFourcc::Abgr8888,
+            )
+            .unwrap();
+        renderer.map_texture(&mapping).unwrap().to_vec()
+    }
+
+    #[test]
+    fn box_kernel_is_normalized() {
+        for scale in [1.0, 1.5, 2.0, 3.25, 8.0] {
+            let (width, kernel) = box_kernel(scale);
+            assert_eq!(width, (scale + 1.0f64).ceil() as i32);
+            assert_eq!(kernel.len(), (width << DOWNSCALE_SUBSAMPLE_BITS) as usize);
+            for phase in kernel.chunks(width as usize) {
+                assert_eq!(phase.iter().map(|w| w.into_raw()).sum::<i32>(), 65536);
+            }
+        }
+    }
+
+    #[test]
+    fn blit_nearest_upscale() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        let src = checkerboard(&mut renderer, 2);
+        let dst: PixmanRenderBuffer = renderer
+            .create_buffer(DrmFourcc::Abgr8888, (4, 4).into())
+            .unwrap();
+
+        renderer.bind(src).unwrap();
+        renderer
+            .blit_to(
+                dst.clone(),
+                Rectangle::from_loc_and_size((0, 0), (2, 2)),
+                Rectangle::from_loc_and_size((0, 0), (4, 4)),
+                TextureFilter::Nearest,
+            )
+            .unwrap();
+
+        let pixels = read_pixels(&mut renderer, dst, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if (x / 2 + y / 2) % 2 == 0 { 255 } else { 0 };
+                assert_eq!(pixels[(y * 4 + x) * 4], expected, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn blit_linear_downscale_averages() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        let src = checkerboard(&mut renderer, 16);
+        let dst: PixmanRenderBuffer = renderer
+            .create_buffer(DrmFourcc::Abgr8888, (4, 4).into())
+            .unwrap();
+
+        renderer.bind(dst.clone()).unwrap();
+        renderer
+            .blit_from(
+                src,
+                Rectangle::from_loc_and_size((0, 0), (16, 16)),
+                Rectangle::from_loc_and_size((0, 0), (4, 4)),
+                TextureFilter::Linear,
+            )
+            .unwrap();
+
+        let pixels = read_pixels(&mut renderer, dst, 4);
+        for pixel in pixels.chunks(4) {
+            assert!(pixel[0].abs_diff(128) <= 2, "{:?} is not gray", pixel);
+        }
+    }
+
+    #[test]
+    fn blit_rejects_invalid_regions() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        let src = checkerboard(&mut renderer, 4);
+        let dst: PixmanRenderBuffer = renderer
+            .create_buffer(DrmFourcc::Abgr8888, (4, 4).into())
+            .unwrap();
+
+        renderer.bind(src.clone()).unwrap();
+        assert!(matches!(
+            renderer.blit_to(
+                dst,
+                Rectangle::from_loc_and_size((2, 2), (4, 4)),
+                Rectangle::from_loc_and_size((0, 0), (4, 4)),
+                TextureFilter::Nearest,
+            ),
+            Err(PixmanError::InvalidBlitRegion)
+        ));
+        assert!(matches!(
+            renderer.blit_to(
+                src,
+                Rectangle::from_loc_and_size((0, 0), (2, 2)),
+                Rectangle::from_loc_and_size((1, 1), (2, 2)),
+                TextureFilter::Nearest,
+            ),
+            Err(PixmanError::InvalidBlitRegion)
+        ));
+    }
+
+    #[test]
+    fn import_yuv_memory() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        assert!(renderer.mem_formats().any(|format| format == DrmFourcc::Nv12));
+
+        // 3x2 pixels, padded to 4 pixels per row: black, and white with red chroma
+        let black = [16u8, 16, 16, 0, 16, 16, 16, 0, 128, 128, 128, 128];
+        let red = [235u8, 235, 235, 0, 235, 235, 235, 0, 90, 240, 90, 240];
+        let texture = renderer
+            .import_memory(&black, DrmFourcc::Nv12, (3, 2).into(), false)
+            .unwrap();
+        assert_eq!(texture.format(), Some(DrmFourcc::Xrgb8888));
+
+        let read = |renderer: &mut PixmanRenderer| {
+            let mapping = renderer
+                .copy_texture(
+                    &texture,
+                    Rectangle::from_loc_and_size((0, 0), (3, 2)),
+                    DrmFourcc::Abgr8888,
+                )
+                .unwrap();
+            renderer.map_texture(&mapping).unwrap().to_vec()
+        };
+        assert!(read(&mut renderer).chunks(4).all(|pixel| pixel == [0, 0, 0, 255]));
+
+        renderer
+            .update_memory(&texture, &red, Rectangle::from_loc_and_size((0, 0), (3, 2)))
+            .unwrap();
+        for pixel in read(&mut renderer).chunks(4) {
+            assert!(pixel[0] == 255 && pixel[3] == 255, "{:?} is not red", pixel);
+        }
+
+        assert!(matches!(
+            renderer.import_memory(&red[..8], DrmFourcc::Nv12, (3, 2).into(), false),
+            Err(PixmanError::IncompleteBuffer { .. })
+        ));
+    }
+
+    #[test]
+    fn render_texture_uses_downscale_filter() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        let data = (0..16 * 16)
+            .flat_map(|i| {
+                let value = if (i % 16 + i / 16) % 2 == 0 { 255 } else { 0 };
+                [value, value, value, 255]
+            })
+            .collect::<Vec<u8>>();
+        let texture = renderer
+            .import_memory(&data, DrmFourcc::Abgr8888, (16, 16).into(), false)
+            .unwrap();
+
+        for (filter, gray) in [(TextureFilter::Linear, true), (TextureFilter::Nearest, false)] {
+            renderer.downscale_filter(filter).unwrap();
+            let dst: PixmanRenderBuffer = renderer
+                .create_buffer(DrmFourcc::Abgr8888, (4, 4).into())
+                .unwrap();
+            renderer.bind(dst.clone()).unwrap();
+            let mut frame = renderer.render((4, 4).into(), Transform::Normal).unwrap();
+            frame
+                .render_texture_from_to(
+                    &texture,
+                    Rectangle::from_loc_and_size((0.0, 0.0), (16.0, 16.0)),
+                    Rectangle::from_loc_and_size((0, 0), (4, 4)),
+                    &[Rectangle::from_loc_and_size((0, 0), (4, 4))],
+                    Transform::Normal,
+                    1.0,
+                )
+                .unwrap();
+            let _ = frame.finish().unwrap();
+
+            let pixels = read_pixels(&mut renderer, dst, 4);
+            for pixel in pixels.chunks(4) {
+                assert_eq!(pixel[0].abs_diff(128) <= 2, gray, "{:?} with {:?}", pixel, filter);
+            }
+        }
+    }
+}
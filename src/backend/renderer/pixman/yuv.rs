@@ -0,0 +1,395 @@
+//! Conversion of YUV buffers to RGB on the CPU
+//!
+//! pixman can not sample from (most) YUV formats, so buffers in these formats are converted
+//! into [`DrmFourcc::Xrgb8888`] images when they are imported.
+
+use drm_fourcc::DrmFourcc;
+use pixman::{FormatCode, Image};
+
+use crate::utils::{Buffer as BufferCoords, Size};
+
+use super::PixmanError;
+
+/// YUV formats supported by the [`PixmanRenderer`](super::PixmanRenderer)
+pub(super) const YUV_FORMATS: &[DrmFourcc] = &[
+    DrmFourcc::Nv12,
+    DrmFourcc::Nv21,
+    DrmFourcc::Yuv420,
+    DrmFourcc::Yvu420,
+    DrmFourcc::Yuyv,
+    DrmFourcc::Uyvy,
+];
+
+/// Matrix coefficients used for converting YUV to RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601, commonly used by standard definition content
+    Bt601,
+    /// ITU-R BT.709, commonly used by high definition content
+    Bt709,
+}
+
+impl YuvMatrix {
+    /// Guess the matrix of a buffer of the given size
+    ///
+    /// Uses [`YuvMatrix::Bt709`] for content with at least 720 lines and [`YuvMatrix::Bt601`] otherwise.
+    pub fn for_size(size: Size<i32, BufferCoords>) -> Self {
+        if size.h >= 720 {
+            YuvMatrix::Bt709
+        } else {
+            YuvMatrix::Bt601
+        }
+    }
+}
+
+/// Range of the values of YUV buffers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvRange {
+    /// Luma in `16..=235` and chroma in `16..=240`, used by most video content
+    #[default]
+    Limited,
+    /// All components use the full range of `0..=255`
+    Full,
+}
+
+/// A plane of a YUV buffer
+#[derive(Debug)]
+pub(super) struct Plane<'a> {
+    pub data: &'a [u8],
+    pub stride: usize,
+}
+
+pub(super) fn is_yuv(format: DrmFourcc) -> bool {
+    YUV_FORMATS.contains(&format)
+}
+
+/// Layout of the planes of a format: number of planes, and horizontal
+/// as well as vertical subsampling of the chroma planes
+fn layout(format: DrmFourcc) -> Option<(usize, usize, usize)> {
+    match format {
+        DrmFourcc::Nv12 | DrmFourcc::Nv21 => Some((2, 2, 2)),
+        DrmFourcc::Yuv420 | DrmFourcc::Yvu420 => Some((3, 2, 2)),
+        DrmFourcc::Yuyv | DrmFourcc::Uyvy => Some((1, 2, 1)),
+        _ => None,
+    }
+}
+
+/// Number of bytes of the pixels of a single row of the given plane
+fn row_length(format: DrmFourcc, plane: usize, width: usize) -> usize {
+    match (format, plane) {
+        // two pixels share four bytes
+        (DrmFourcc::Yuyv | DrmFourcc::Uyvy, _) => (width + 1) / 2 * 4,
+        (_, 0) => width,
+        // interleaved chroma samples
+        (DrmFourcc::Nv12 | DrmFourcc::Nv21, _) => (width + 1) / 2 * 2,
+        _ => (width + 1) / 2,
+    }
+}
+
+/// Stride of the first plane of tightly packed buffers, as used by [`ImportMem`](crate::backend::renderer::ImportMem)
+///
+/// Rows are padded to an even number of pixels, so that the chroma planes of planar formats
+/// have exactly half the stride of the luma plane.
+pub(super) fn packed_stride(format: DrmFourcc, width: usize) -> usize {
+    row_length(format, 0, (width + 1) / 2 * 2)
+}
+
+/// Splits a buffer containing all planes of a format into the individual planes
+///
+/// The planes are expected to directly follow each other. `stride` is the stride of the first plane,
+/// the strides of the other planes are derived from it the same way other compositors do
+/// for `wl_shm` buffers.
+pub(super) fn contiguous_planes(
+    format: DrmFourcc,
+    data: &[u8],
+    stride: usize,
+    size: Size<i32, BufferCoords>,
+) -> Result<Vec<Plane<'_>>, PixmanError> {
+    let (num_planes, _, vsub) = layout(format).ok_or(PixmanError::UnsupportedPixelFormat(format))?;
+    let height = size.h as usize;
+    let chroma_height = (height + vsub - 1) / vsub;
+
+    let mut planes = Vec::with_capacity(num_planes);
+    let mut offset = 0;
+    for idx in 0..num_planes {
+        let (stride, rows) = match (format, idx) {
+            (_, 0) => (stride, height),
+            (DrmFourcc::Nv12 | DrmFourcc::Nv21, _) => (stride, chroma_height),
+            _ => (stride / 2, chroma_height),
+        };
+        let len = stride * rows;
+        let data = data
+            .get(offset..offset + len)
+            .ok_or(PixmanError::IncompleteBuffer {
+                expected: offset + len,
+                actual: data.len(),
+            })?;
+        planes.push(Plane { data, stride });
+        offset += len;
+    }
+
+    Ok(planes)
+}
+
+/// Fixed-point coefficients for converting YUV to RGB
+#[derive(Debug)]
+struct Coefficients {
+    y_offset: i32,
+    y: i32,
+    r_v: i32,
+    g_u: i32,
+    g_v: i32,
+    b_u: i32,
+}
+
+impl Coefficients {
+    fn new(matrix: YuvMatrix, range: YuvRange) -> Self {
+        let (kr, kb) = match matrix {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+        };
+        let kg = 1.0 - kr - kb;
+        let (y_offset, y_scale, c_scale) = match range {
+            YuvRange::Limited => (16, 255.0 / 219.0, 255.0 / 224.0),
+            YuvRange::Full => (0, 1.0, 1.0),
+        };
+        let fixed = |value: f64| (value * 65536.0).round() as i32;
+
+        Coefficients {
+            y_offset,
+            y: fixed(y_scale),
+            r_v: fixed(c_scale * (2.0 - 2.0 * kr)),
+            g_u: fixed(c_scale * kb * (2.0 - 2.0 * kb) / kg),
+            g_v: fixed(c_scale * kr * (2.0 - 2.0 * kr) / kg),
+            b_u: fixed(c_scale * (2.0 - 2.0 * kb)),
+        }
+    }
+
+    #[inline]
+    fn xrgb(&self, y: u8, u: u8, v: u8) -> u32 {
+        let y = (y as i32 - self.y_offset) * self.y + 0x8000;
+        let u = u as i32 - 128;
+        let v = v as i32 - 128;
+
+        let r = ((y + self.r_v * v) >> 16).clamp(0, 255) as u32;
+        let g = ((y - self.g_u * u - self.g_v * v) >> 16).clamp(0, 255) as u32;
+        let b = ((y + self.b_u * u) >> 16).clamp(0, 255) as u32;
+
+        0xff00_0000 | (r << 16) | (g << 8) | b
+    }
+}
+
+/// Creates a new image with the converted contents of the given planes
+pub(super) fn convert(
+    format: DrmFourcc,
+    planes: &[Plane<'_>],
+    size: Size<i32, BufferCoords>,
+    matrix: YuvMatrix,
+    range: YuvRange,
+) -> Result<Image<'static, 'static>, PixmanError> {
+    let image = Image::new(FormatCode::X8R8G8B8, size.w as usize, size.h as usize, false)
+        .map_err(|_| PixmanError::Unsupported)?;
+    convert_into(format, planes, matrix, range, &image)?;
+    Ok(image)
+}
+
+/// Converts the contents of the given planes into an existing [`FormatCode::X8R8G8B8`] image
+pub(super) fn convert_into(
+    format: DrmFourcc,
+    planes: &[Plane<'_>],
+    matrix: YuvMatrix,
+    range: YuvRange,
+    image: &Image<'static, 'static>,
+) -> Result<(), PixmanError> {
+    let (num_planes, hsub, vsub) = layout(format).ok_or(PixmanError::UnsupportedPixelFormat(format))?;
+    if planes.len() != num_planes {
+        return Err(PixmanError::UnsupportedNumberOfPlanes);
+    }
+    if !matches!(image.format(), FormatCode::X8R8G8B8) {
+        return Err(PixmanError::Unsupported);
+    }
+
+    let width = image.width();
+    let height = image.height();
+    for (idx, plane) in planes.iter().enumerate() {
+        let rows = if idx == 0 {
+            height
+        } else {
+            (height + vsub - 1) / vsub
+        };
+        let row_length = row_length(format, idx, width);
+        if rows == 0 {
+            continue;
+        }
+        let expected = plane.stride * (rows - 1) + row_length;
+        if plane.stride < row_length || plane.data.len() < expected {
+            return Err(PixmanError::IncompleteBuffer {
+                expected,
+                actual: plane.data.len(),
+            });
+        }
+    }
+
+    let coefficients = Coefficients::new(matrix, range);
+    let dst_stride = image.stride() / 4;
+    // SAFETY: the image was created with the given size and owns its data
+    let dst = unsafe { std::slice::from_raw_parts_mut(image.data(), dst_stride * height) };
+
+    for (y, dst_row) in dst.chunks_exact_mut(dst_stride).enumerate() {
+        let dst_row = &mut dst_row[..width];
+        let luma = &planes[0].data[y * planes[0].stride..];
+
+        match format {
+            DrmFourcc::Yuyv | DrmFourcc::Uyvy => {
+                let (y0, u, y1, v) = if format == DrmFourcc::Yuyv {
+                    (0, 1, 2, 3)
+                } else {
+                    (1, 0, 3, 2)
+                };
+                for (x, pixel) in dst_row.iter_mut().enumerate() {
+                    let group = &luma[x / 2 * 4..x / 2 * 4 + 4];
+                    let luma = if x % 2 == 0 { group[y0] } else { group[y1] };
+                    *pixel = coefficients.xrgb(luma, group[u], group[v]);
+                }
+            }
+            DrmFourcc::Nv12 | DrmFourcc::Nv21 => {
+                let chroma = &planes[1].data[y / vsub * planes[1].stride..];
+                let (u, v) = if format == DrmFourcc::Nv12 { (0, 1) } else { (1, 0) };
+                for (x, pixel) in dst_row.iter_mut().enumerate() {
+                    let sample = &chroma[x / hsub * 2..x / hsub * 2 + 2];
+                    *pixel = coefficients.xrgb(luma[x], sample[u], sample[v]);
+                }
+            }
+            _ => {
+                let (u_plane, v_plane) = if format == DrmFourcc::Yuv420 {
+                    (&planes[1], &planes[2])
+                } else {
+                    (&planes[2], &planes[1])
+                };
+                let u = &u_plane.data[y / vsub * u_plane.stride..];
+                let v = &v_plane.data[y / vsub * v_plane.stride..];
+                for (x, pixel) in dst_row.iter_mut().enumerate() {
+                    *pixel = coefficients.xrgb(luma[x], u[x / hsub], v[x / hsub]);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(pixel: u32) -> [u8; 3] {
+        [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+    }
+
+    fn assert_close(actual: [u8; 3], expected: [u8; 3]) {
+        assert!(
+            actual
+                .iter()
+                .zip(expected.iter())
+                .all(|(a, b)| a.abs_diff(*b) <= 1),
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn limited_range() {
+        let bt601 = Coefficients::new(YuvMatrix::Bt601, YuvRange::Limited);
+        assert_close(rgb(bt601.xrgb(16, 128, 128)), [0, 0, 0]);
+        assert_close(rgb(bt601.xrgb(235, 128, 128)), [255, 255, 255]);
+        assert_close(rgb(bt601.xrgb(81, 90, 240)), [255, 0, 0]);
+        assert_close(rgb(bt601.xrgb(145, 54, 34)), [0, 255, 0]);
+
+        let bt709 = Coefficients::new(YuvMatrix::Bt709, YuvRange::Limited);
+        assert_close(rgb(bt709.xrgb(63, 102, 240)), [255, 0, 0]);
+        assert_close(rgb(bt709.xrgb(32, 240, 118)), [0, 0, 255]);
+    }
+
+    #[test]
+    fn full_range() {
+        let bt601 = Coefficients::new(YuvMatrix::Bt601, YuvRange::Full);
+        assert_close(rgb(bt601.xrgb(0, 128, 128)), [0, 0, 0]);
+        assert_close(rgb(bt601.xrgb(255, 128, 128)), [255, 255, 255]);
+        assert_close(rgb(bt601.xrgb(76, 85, 255)), [255, 0, 0]);
+    }
+
+    #[test]
+    fn planar_layouts_agree() {
+        // 4x2 pixels: a dark left and a bright right half, with red chroma on the right
+        let luma = [16u8, 16, 235, 235, 16, 16, 235, 235];
+        let u = [128u8, 90];
+        let v = [128u8, 240];
+
+        let nv12 = [&luma[..], &[u[0], v[0], u[1], v[1]]].concat();
+        let nv21 = [&luma[..], &[v[0], u[0], v[1], u[1]]].concat();
+        let yuv420 = [&luma[..], &u[..], &v[..]].concat();
+        let yvu420 = [&luma[..], &v[..], &u[..]].concat();
+        let yuyv = [16u8, 128, 16, 128, 235, 90, 235, 240].repeat(2);
+        let uyvy = [128u8, 16, 128, 16, 90, 235, 240, 235].repeat(2);
+
+        let size = (4, 2).into();
+        let reference = convert(
+            DrmFourcc::Nv12,
+            &contiguous_planes(DrmFourcc::Nv12, &nv12, 4, size).unwrap(),
+            size,
+            YuvMatrix::Bt601,
+            YuvRange::Limited,
+        )
+        .unwrap();
+        let pixels = |image: &Image<'static, 'static>| unsafe {
+            std::slice::from_raw_parts(image.data() as *const u32, 8).to_vec()
+        };
+        let expected = pixels(&reference);
+        assert_close(rgb(expected[0]), [0, 0, 0]);
+        assert_eq!(expected[3] & 0xff0000, 0xff0000);
+
+        for (format, data, stride) in [
+            (DrmFourcc::Nv21, &nv21, 4),
+            (DrmFourcc::Yuv420, &yuv420, 4),
+            (DrmFourcc::Yvu420, &yvu420, 4),
+            (DrmFourcc::Yuyv, &yuyv, 8),
+            (DrmFourcc::Uyvy, &uyvy, 8),
+        ] {
+            let planes = contiguous_planes(format, data, stride, size).unwrap();
+            let image = convert(format, &planes, size, YuvMatrix::Bt601, YuvRange::Limited).unwrap();
+            assert_eq!(pixels(&image), expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn incomplete_buffer() {
+        let size = (4, 4).into();
+        assert!(matches!(
+            contiguous_planes(DrmFourcc::Nv12, &[0; 20], 4, size),
+            Err(PixmanError::IncompleteBuffer { .. })
+        ));
+
+        let data = [0; 24];
+        let planes = [
+            Plane {
+                data: &data[..16],
+                stride: 4,
+            },
+            Plane {
+                data: &data[16..],
+                stride: 2,
+            },
+        ];
+        assert!(matches!(
+            convert(
+                DrmFourcc::Nv12,
+                &planes,
+                size,
+                YuvMatrix::Bt601,
+                YuvRange::Limited
+            ),
+            Err(PixmanError::IncompleteBuffer { .. })
+        ));
+    }
+}
@@ -0,0 +1,440 @@
+//! Golden-image snapshot testing of render elements
+//!
+//! A [`SnapshotTest`] renders a list of [`RenderElement`]s through an [`OutputDamageTracker`]
+//! with the [`PixmanRenderer`] into an offscreen buffer, and compares the result with a PNG
+//! stored in the `snapshots` directory next to this file.
+//!
+//! If the images differ by more than the configured tolerance, the rendered image and an image
+//! highlighting the differing pixels in red are written to `target/snapshots` and the test fails.
+//!
+//! To create or update the stored images, run the tests with `SMITHAY_UPDATE_SNAPSHOTS=1`.
+//!
+//! Additionally this module provides a few elements shared by the tests, like [`quadrants`] and [`squares`].
+
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+#[cfg(feature = "desktop")]
+use crate::{
+    backend::renderer::element::AsRenderElements,
+    desktop::space::{RenderZindex, SpaceElement},
+    output::Output,
+    utils::{IsAlive, Logical},
+};
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            color::ColorTransform,
+            damage::OutputDamageTracker,
+            element::{
+                solid::SolidColorRenderElement,
+                texture::{TextureBuffer, TextureRenderElement},
+                Id, Kind, RenderElement,
+            },
+            pixman::{PixmanRenderBuffer, PixmanRenderer, PixmanTexture},
+            Bind, ExportMem, Offscreen, Renderer, TextureFilter,
+        },
+    },
+    utils::{Buffer as BufferCoords, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+/// Environment variable to set for (re-)writing the stored snapshots
+const UPDATE_ENV: &str = "SMITHAY_UPDATE_SNAPSHOTS";
+
+/// Renders elements and compares them against stored snapshots
+#[derive(Debug)]
+pub struct SnapshotTest {
+    renderer: PixmanRenderer,
+    size: Size<i32, Physical>,
+    scale: Scale<f64>,
+    transform: Transform,
+    clear_color: [f32; 4],
+    color_transform: Option<ColorTransform>,
+    tolerance: u8,
+}
+
+impl SnapshotTest {
+    /// Create a new snapshot test rendering an output with a mode of the given size
+    ///
+    /// Like for a real output, the size is given before applying the
+    /// [`transform`](SnapshotTest::transform), and equals the size of the rendered image.
+    ///
+    /// The renderer uses nearest-neighbor filtering to keep the results independent
+    /// of implementation details of the interpolation.
+    pub fn new(size: impl Into<Size<i32, Physical>>) -> Self {
+        let mut renderer = PixmanRenderer::new().expect("Failed to create pixman renderer");
+        renderer.upscale_filter(TextureFilter::Nearest).unwrap();
+        renderer.downscale_filter(TextureFilter::Nearest).unwrap();
+
+        SnapshotTest {
+            renderer,
+            size: size.into(),
+            scale: Scale::from(1.0),
+            transform: Transform::Normal,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            color_transform: None,
+            tolerance: 2,
+        }
+    }
+
+    /// Set the scale of the rendered output
+    pub fn scale(mut self, scale: impl Into<Scale<f64>>) -> Self {
+        self.scale = scale.into();
+        self
+    }
+
+    /// Set the transform of the rendered output
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set the color the output is cleared with
+    pub fn clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Set a color transform applied to the rendered output
+    pub fn color_transform(mut self, color_transform: ColorTransform) -> Self {
+        self.color_transform = Some(color_transform);
+        self
+    }
+
+    /// Set the maximum difference per color channel for pixels to be considered equal
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Access the renderer, e.g. to import textures for the elements
+    pub fn renderer(&mut self) -> &mut PixmanRenderer {
+        &mut self.renderer
+    }
+
+    /// Bind a new offscreen buffer with the size of the output to the renderer
+    ///
+    /// This is done by [`render`](SnapshotTest::render), but can be used by tests
+    /// driving the renderer themselves.
+    pub fn bind_buffer(&mut self) {
+        let buffer: PixmanRenderBuffer = self
+            .renderer
+            .create_buffer(Fourcc::Abgr8888, self.buffer_size())
+            .expect("Failed to create offscreen buffer");
+        self.renderer
+            .bind(buffer)
+            .expect("Failed to bind offscreen buffer");
+    }
+
+    fn buffer_size(&self) -> Size<i32, BufferCoords> {
+        (self.size.w, self.size.h).into()
+    }
+
+    /// Render the elements into a new image
+    pub fn render<E: RenderElement<PixmanRenderer>>(&mut self, elements: &[E]) -> RgbaImage {
+        let buffer_size = self.buffer_size();
+        self.bind_buffer();
+
+        let mut damage_tracker = OutputDamageTracker::new(self.size, self.scale, self.transform);
+        damage_tracker.set_color_transform(self.color_transform.clone());
+        damage_tracker
+            .render_output(&mut self.renderer, 0, elements, self.clear_color)
+            .expect("Failed to render elements");
+
+        let mapping = self
+            .renderer
+            .copy_framebuffer(
+                Rectangle::from_loc_and_size((0, 0), buffer_size),
+                Fourcc::Abgr8888,
+            )
+            .expect("Failed to copy framebuffer");
+        let data = self
+            .renderer
+            .map_texture(&mapping)
+            .expect("Failed to map framebuffer");
+
+        RgbaImage::from_raw(buffer_size.w as u32, buffer_size.h as u32, data.to_vec())
+            .expect("Framebuffer has unexpected size")
+    }
+
+    /// Render the elements and compare them with the snapshot of the given name
+    ///
+    /// # Panics
+    ///
+    /// Panics if the snapshot does not exist or the rendered image does not match it.
+    #[track_caller]
+    pub fn assert_snapshot<E: RenderElement<PixmanRenderer>>(&mut self, name: &str, elements: &[E]) {
+        let actual = self.render(elements);
+        let path = snapshot_dir().join(format!("{}.png", name));
+
+        if std::env::var_os(UPDATE_ENV).is_some() {
+            actual.save(&path).expect("Failed to write snapshot");
+            return;
+        }
+
+        let expected = match image::open(&path) {
+            Ok(expected) => expected.into_rgba8(),
+            Err(err) => panic!(
+                "Failed to load snapshot {}: {}, run with {}=1 to create it",
+                path.display(),
+                err,
+                UPDATE_ENV
+            ),
+        };
+
+        if let Some((diff, mismatches)) = compare(&expected, &actual, self.tolerance) {
+            let out_dir = output_dir();
+            std::fs::create_dir_all(&out_dir).expect("Failed to create snapshot output directory");
+            let actual_path = out_dir.join(format!("{}.actual.png", name));
+            let diff_path = out_dir.join(format!("{}.diff.png", name));
+            actual.save(&actual_path).expect("Failed to write rendered image");
+            let diff_note = match diff {
+                Some(diff) => {
+                    diff.save(&diff_path).expect("Failed to write diff image");
+                    format!(", diff to {}", diff_path.display())
+                }
+                None => String::new(),
+            };
+            panic!(
+                "Snapshot {} does not match: {}, rendered image written to {}{}",
+                name,
+                mismatches,
+                actual_path.display(),
+                diff_note,
+            );
+        }
+    }
+}
+
+/// Creates a solid color element at the given location
+pub fn solid(
+    id: &Id,
+    commit: usize,
+    location: impl Into<Point<i32, Physical>>,
+    size: impl Into<Size<i32, Physical>>,
+    color: [f32; 4],
+) -> SolidColorRenderElement {
+    SolidColorRenderElement::new(
+        id.clone(),
+        Rectangle::from_loc_and_size(location, size),
+        commit,
+        color,
+        Kind::Unspecified,
+    )
+}
+
+/// Creates overlapping 8x8 blue, half-transparent green and red squares
+///
+/// The elements are ordered front to back, so blue is on top.
+pub fn squares() -> Vec<SolidColorRenderElement> {
+    [
+        ((8, 8), [0.0, 0.0, 1.0, 1.0]),
+        ((4, 4), [0.0, 0.5, 0.0, 0.5]),
+        ((0, 0), [1.0, 0.0, 0.0, 1.0]),
+    ]
+    .into_iter()
+    .map(|(location, color)| solid(&Id::new(), 0, location, (8, 8), color))
+    .collect()
+}
+
+/// Creates a 8x8 element with red, green, blue and white quadrants
+pub fn quadrants(
+    renderer: &mut PixmanRenderer,
+    location: impl Into<Point<i32, Physical>>,
+) -> TextureRenderElement<PixmanTexture> {
+    quadrants_with_opaque_regions(renderer, location, None)
+}
+
+/// Creates the same element as [`quadrants`], but marked as fully opaque
+pub fn opaque_quadrants(
+    renderer: &mut PixmanRenderer,
+    location: impl Into<Point<i32, Physical>>,
+) -> TextureRenderElement<PixmanTexture> {
+    quadrants_with_opaque_regions(
+        renderer,
+        location,
+        Some(vec![Rectangle::from_loc_and_size((0, 0), (8, 8))]),
+    )
+}
+
+fn quadrants_with_opaque_regions(
+    renderer: &mut PixmanRenderer,
+    location: impl Into<Point<i32, Physical>>,
+    opaque_regions: Option<Vec<Rectangle<i32, BufferCoords>>>,
+) -> TextureRenderElement<PixmanTexture> {
+    let mut data = Vec::with_capacity(8 * 8 * 4);
+    for y in 0..8 {
+        for x in 0..8 {
+            let color = match (x < 4, y < 4) {
+                (true, true) => [255, 0, 0, 255],
+                (false, true) => [0, 255, 0, 255],
+                (true, false) => [0, 0, 255, 255],
+                (false, false) => [255, 255, 255, 255],
+            };
+            data.extend_from_slice(&color);
+        }
+    }
+    let buffer = TextureBuffer::from_memory(
+        renderer,
+        &data,
+        Fourcc::Abgr8888,
+        (8, 8),
+        false,
+        1,
+        Transform::Normal,
+        opaque_regions,
+    )
+    .expect("Failed to import texture");
+    TextureRenderElement::from_texture_buffer(
+        location.into().to_f64(),
+        &buffer,
+        None,
+        None,
+        None,
+        Kind::Unspecified,
+    )
+}
+
+/// A 8x8 [`SpaceElement`] rendered as a solid color
+#[cfg(feature = "desktop")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestElement {
+    id: Id,
+    color: [f32; 4],
+    z_index: u8,
+}
+
+#[cfg(feature = "desktop")]
+impl TestElement {
+    /// Create a new element with the given color
+    pub fn new(color: [f32; 4]) -> Self {
+        TestElement {
+            id: Id::new(),
+            color,
+            z_index: RenderZindex::Overlay as u8,
+        }
+    }
+
+    /// Set the z-index of the element
+    pub fn with_z_index(mut self, z_index: u8) -> Self {
+        self.z_index = z_index;
+        self
+    }
+}
+
+#[cfg(feature = "desktop")]
+impl IsAlive for TestElement {
+    fn alive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "desktop")]
+impl SpaceElement for TestElement {
+    fn bbox(&self) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((0, 0), (8, 8))
+    }
+    fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+        true
+    }
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+    fn set_activate(&self, _activated: bool) {}
+    fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+    fn output_leave(&self, _output: &Output) {}
+}
+
+#[cfg(feature = "desktop")]
+impl AsRenderElements<PixmanRenderer> for TestElement {
+    type RenderElement = SolidColorRenderElement;
+
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        _renderer: &mut PixmanRenderer,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        let size = self.bbox().size.to_physical_precise_round(scale);
+        let color = self.color.map(|c| c * alpha);
+        vec![C::from(solid(&self.id, 0, location, size, color))]
+    }
+}
+
+/// Compares two images, returning a diff image and a description of the mismatch if they differ
+///
+/// No diff image is generated if the sizes of the images differ.
+fn compare(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Option<(Option<RgbaImage>, String)> {
+    if expected.dimensions() != actual.dimensions() {
+        return Some((
+            None,
+            format!(
+                "expected size {:?}, got {:?}",
+                expected.dimensions(),
+                actual.dimensions()
+            ),
+        ));
+    }
+
+    let mut mismatches = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = expected.get_pixel(x, y);
+        let b = actual.get_pixel(x, y);
+        let differs =
+            a.0.iter()
+                .zip(b.0.iter())
+                .any(|(a, b)| a.abs_diff(*b) > tolerance);
+        if differs {
+            mismatches += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // keep the image recognizable, but faded out
+            let luma = (b[0] as u32 * 299 + b[1] as u32 * 587 + b[2] as u32 * 114) / 1000;
+            let faded = (luma / 4 + 192) as u8;
+            Rgba([faded, faded, faded, 255])
+        }
+    });
+
+    if mismatches == 0 {
+        None
+    } else {
+        Some((Some(diff), format!("{} pixels differ", mismatches)))
+    }
+}
+
+fn snapshot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/backend/renderer/snapshots")
+}
+
+fn output_dir() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target"))
+        .join("snapshots")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_within_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let actual = RgbaImage::from_pixel(4, 4, Rgba([102, 99, 100, 255]));
+        assert!(compare(&expected, &actual, 2).is_none());
+
+        let (diff, _) = compare(&expected, &actual, 1).unwrap();
+        assert_eq!(diff.unwrap().get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn compare_size_mismatch() {
+        let expected = RgbaImage::new(4, 4);
+        let actual = RgbaImage::new(4, 2);
+        let (diff, _) = compare(&expected, &actual, 0).unwrap();
+        assert!(diff.is_none());
+    }
+}
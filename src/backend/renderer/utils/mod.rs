@@ -2,6 +2,7 @@
 //! and [`RenderElement`](super::element::RenderElement)s with [`Renderer`](super::Renderer)s.
 
 use crate::utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Size};
+use cgmath::Matrix3;
 use std::{collections::VecDeque, fmt, sync::Arc};
 
 #[cfg(feature = "wayland_frontend")]
@@ -303,3 +304,36 @@ pub struct SurfaceView {
     /// The logical offset for a sub-surface
     pub offset: Point<i32, Logical>,
 }
+
+/// Applies an affine transformation to a point
+pub(crate) fn transform_point<Kind>(point: Point<f64, Kind>, matrix: &Matrix3<f32>) -> Point<f64, Kind> {
+    let m = |col: usize, row: usize| matrix[col][row] as f64;
+    Point::from((
+        m(0, 0) * point.x + m(1, 0) * point.y + m(2, 0),
+        m(0, 1) * point.x + m(1, 1) * point.y + m(2, 1),
+    ))
+}
+
+/// Returns the bounding box of a rectangle after applying an affine transformation
+pub(crate) fn transform_bbox<Kind>(
+    rect: Rectangle<f64, Kind>,
+    matrix: &Matrix3<f32>,
+) -> Rectangle<f64, Kind> {
+    let corners = [
+        rect.loc,
+        rect.loc + Point::from((rect.size.w, 0.0)),
+        rect.loc + Point::from((0.0, rect.size.h)),
+        rect.loc + rect.size.to_point(),
+    ]
+    .map(|corner| transform_point(corner, matrix));
+
+    let (min, max) = corners[1..]
+        .iter()
+        .fold((corners[0], corners[0]), |(min, max), corner| {
+            (
+                Point::from((min.x.min(corner.x), min.y.min(corner.y))),
+                Point::from((max.x.max(corner.x), max.y.max(corner.y))),
+            )
+        });
+    Rectangle::from_extemities(min, max)
+}
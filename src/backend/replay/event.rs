@@ -0,0 +1,659 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::backend::input::{
+    AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device, DeviceCapability,
+    Event, GestureBeginEvent, GestureEndEvent, GestureHoldBeginEvent, GestureHoldEndEvent,
+    GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent,
+    GestureSwipeEndEvent, GestureSwipeUpdateEvent, InputBackend, KeyState, KeyboardKeyEvent,
+    PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent, ProximityState,
+    Switch, SwitchState, SwitchToggleEvent, TabletToolAxisEvent, TabletToolButtonEvent, TabletToolDescriptor,
+    TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TouchCancelEvent,
+    TouchDownEvent, TouchEvent, TouchFrameEvent, TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+};
+
+/// Marker used to define the `InputBackend` types for replayed input
+#[derive(Debug)]
+pub struct ReplayInput;
+
+/// Input device reconstructed from a recording
+///
+/// Devices compare equal if they refer to the same device of the recording.
+#[derive(Debug, Clone)]
+pub struct ReplayDevice {
+    inner: Arc<DeviceInner>,
+}
+
+#[derive(Debug)]
+struct DeviceInner {
+    index: u32,
+    id: String,
+    name: String,
+    capabilities: Vec<DeviceCapability>,
+    usb_id: Option<(u32, u32)>,
+}
+
+impl ReplayDevice {
+    pub(crate) fn new(
+        index: u32,
+        id: String,
+        name: String,
+        capabilities: Vec<DeviceCapability>,
+        usb_id: Option<(u32, u32)>,
+    ) -> ReplayDevice {
+        ReplayDevice {
+            inner: Arc::new(DeviceInner {
+                index,
+                id,
+                name,
+                capabilities,
+                usb_id,
+            }),
+        }
+    }
+
+    /// Index of the device inside the recording
+    pub fn index(&self) -> u32 {
+        self.inner.index
+    }
+}
+
+impl PartialEq for ReplayDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.index == other.inner.index
+    }
+}
+
+impl Eq for ReplayDevice {}
+
+impl std::hash::Hash for ReplayDevice {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.inner.index.hash(state);
+    }
+}
+
+impl Device for ReplayDevice {
+    fn id(&self) -> String {
+        self.inner.id.clone()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        self.inner.capabilities.contains(&capability)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        self.inner.usb_id
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Position of an absolute event in raw and normalized coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AbsolutePosition {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    /// Position transformed into a coordinate space of size 1x1
+    pub(crate) normalized_x: f64,
+    pub(crate) normalized_y: f64,
+}
+
+macro_rules! impl_event {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Event<ReplayInput> for $ty {
+                fn time(&self) -> u64 {
+                    self.time
+                }
+
+                fn device(&self) -> ReplayDevice {
+                    self.device.clone()
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_absolute_position {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl AbsolutePositionEvent<ReplayInput> for $ty {
+                fn x(&self) -> f64 {
+                    self.position.x
+                }
+
+                fn y(&self) -> f64 {
+                    self.position.y
+                }
+
+                fn x_transformed(&self, width: i32) -> f64 {
+                    self.position.normalized_x * width as f64
+                }
+
+                fn y_transformed(&self, height: i32) -> f64 {
+                    self.position.normalized_y * height as f64
+                }
+            }
+        )*
+    };
+}
+
+/// Replayed [`KeyboardKeyEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayKeyboardKeyEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) key_code: u32,
+    pub(crate) state: KeyState,
+    pub(crate) count: u32,
+}
+
+impl KeyboardKeyEvent<ReplayInput> for ReplayKeyboardKeyEvent {
+    fn key_code(&self) -> u32 {
+        self.key_code
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Replayed [`PointerMotionEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayPointerMotionEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) delta: (f64, f64),
+    pub(crate) delta_unaccel: (f64, f64),
+}
+
+impl PointerMotionEvent<ReplayInput> for ReplayPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta_unaccel.0
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta_unaccel.1
+    }
+}
+
+/// Replayed [`PointerMotionAbsoluteEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayPointerMotionAbsoluteEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) position: AbsolutePosition,
+}
+
+impl PointerMotionAbsoluteEvent<ReplayInput> for ReplayPointerMotionAbsoluteEvent {}
+
+/// Replayed [`PointerButtonEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayPointerButtonEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) button_code: u32,
+    pub(crate) state: ButtonState,
+}
+
+impl PointerButtonEvent<ReplayInput> for ReplayPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button_code
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Replayed [`PointerAxisEvent`]
+///
+/// Per-axis values are stored as `[horizontal, vertical]`.
+#[derive(Debug, Clone)]
+pub struct ReplayPointerAxisEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) source: AxisSource,
+    pub(crate) amount: [Option<f64>; 2],
+    pub(crate) amount_v120: [Option<f64>; 2],
+    pub(crate) relative_direction: [AxisRelativeDirection; 2],
+}
+
+fn axis_index(axis: Axis) -> usize {
+    match axis {
+        Axis::Horizontal => 0,
+        Axis::Vertical => 1,
+    }
+}
+
+impl PointerAxisEvent<ReplayInput> for ReplayPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        self.amount[axis_index(axis)]
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        self.amount_v120[axis_index(axis)]
+    }
+
+    fn source(&self) -> AxisSource {
+        self.source
+    }
+
+    fn relative_direction(&self, axis: Axis) -> AxisRelativeDirection {
+        self.relative_direction[axis_index(axis)]
+    }
+}
+
+/// Replayed begin event of a swipe, pinch or hold gesture
+#[derive(Debug, Clone)]
+pub struct ReplayGestureBeginEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) fingers: u32,
+}
+
+impl GestureBeginEvent<ReplayInput> for ReplayGestureBeginEvent {
+    fn fingers(&self) -> u32 {
+        self.fingers
+    }
+}
+
+impl GestureSwipeBeginEvent<ReplayInput> for ReplayGestureBeginEvent {}
+impl GesturePinchBeginEvent<ReplayInput> for ReplayGestureBeginEvent {}
+impl GestureHoldBeginEvent<ReplayInput> for ReplayGestureBeginEvent {}
+
+/// Replayed end event of a swipe, pinch or hold gesture
+#[derive(Debug, Clone)]
+pub struct ReplayGestureEndEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) cancelled: bool,
+}
+
+impl GestureEndEvent<ReplayInput> for ReplayGestureEndEvent {
+    fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+impl GestureSwipeEndEvent<ReplayInput> for ReplayGestureEndEvent {}
+impl GesturePinchEndEvent<ReplayInput> for ReplayGestureEndEvent {}
+impl GestureHoldEndEvent<ReplayInput> for ReplayGestureEndEvent {}
+
+/// Replayed [`GestureSwipeUpdateEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayGestureSwipeUpdateEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) delta: (f64, f64),
+}
+
+impl GestureSwipeUpdateEvent<ReplayInput> for ReplayGestureSwipeUpdateEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+}
+
+/// Replayed [`GesturePinchUpdateEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayGesturePinchUpdateEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) delta: (f64, f64),
+    pub(crate) scale: f64,
+    pub(crate) rotation: f64,
+}
+
+impl GesturePinchUpdateEvent<ReplayInput> for ReplayGesturePinchUpdateEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    fn rotation(&self) -> f64 {
+        self.rotation
+    }
+}
+
+/// Replayed [`TouchDownEvent`] or [`TouchMotionEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTouchPositionEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) slot: TouchSlot,
+    pub(crate) position: AbsolutePosition,
+}
+
+impl TouchEvent<ReplayInput> for ReplayTouchPositionEvent {
+    fn slot(&self) -> TouchSlot {
+        self.slot
+    }
+}
+
+impl TouchDownEvent<ReplayInput> for ReplayTouchPositionEvent {}
+impl TouchMotionEvent<ReplayInput> for ReplayTouchPositionEvent {}
+
+/// Replayed [`TouchUpEvent`] or [`TouchCancelEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTouchSlotEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) slot: TouchSlot,
+}
+
+impl TouchEvent<ReplayInput> for ReplayTouchSlotEvent {
+    fn slot(&self) -> TouchSlot {
+        self.slot
+    }
+}
+
+impl TouchUpEvent<ReplayInput> for ReplayTouchSlotEvent {}
+impl TouchCancelEvent<ReplayInput> for ReplayTouchSlotEvent {}
+
+/// Replayed [`TouchFrameEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTouchFrameEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+}
+
+impl TouchFrameEvent<ReplayInput> for ReplayTouchFrameEvent {}
+
+/// State of all tablet tool axes at the time of an event
+#[derive(Debug, Clone)]
+pub(crate) struct TabletToolAxes {
+    pub(crate) tool: TabletToolDescriptor,
+    pub(crate) delta: (f64, f64),
+    pub(crate) position: AbsolutePosition,
+    pub(crate) distance: f64,
+    pub(crate) pressure: f64,
+    pub(crate) slider: f64,
+    pub(crate) tilt: (f64, f64),
+    pub(crate) rotation: f64,
+    pub(crate) wheel_delta: f64,
+    pub(crate) wheel_delta_discrete: i32,
+    pub(crate) changed: TabletToolAxesChanged,
+}
+
+bitflags::bitflags! {
+    /// Axes updated by a tablet tool event
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) struct TabletToolAxesChanged: u32 {
+        const DISTANCE = 1;
+        const PRESSURE = 2;
+        const SLIDER = 4;
+        const TILT_X = 8;
+        const TILT_Y = 16;
+        const ROTATION = 32;
+        const WHEEL = 64;
+    }
+}
+
+macro_rules! impl_tablet_tool_event {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl TabletToolEvent<ReplayInput> for $ty {
+                fn tool(&self) -> TabletToolDescriptor {
+                    self.axes.tool.clone()
+                }
+
+                fn delta_x(&self) -> f64 {
+                    self.axes.delta.0
+                }
+
+                fn delta_y(&self) -> f64 {
+                    self.axes.delta.1
+                }
+
+                fn x(&self) -> f64 {
+                    self.axes.position.x
+                }
+
+                fn y(&self) -> f64 {
+                    self.axes.position.y
+                }
+
+                fn x_transformed(&self, width: i32) -> f64 {
+                    self.axes.position.normalized_x * width as f64
+                }
+
+                fn y_transformed(&self, height: i32) -> f64 {
+                    self.axes.position.normalized_y * height as f64
+                }
+
+                fn distance(&self) -> f64 {
+                    self.axes.distance
+                }
+
+                fn distance_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::DISTANCE)
+                }
+
+                fn pressure(&self) -> f64 {
+                    self.axes.pressure
+                }
+
+                fn pressure_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::PRESSURE)
+                }
+
+                fn slider_position(&self) -> f64 {
+                    self.axes.slider
+                }
+
+                fn slider_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::SLIDER)
+                }
+
+                fn tilt_x(&self) -> f64 {
+                    self.axes.tilt.0
+                }
+
+                fn tilt_x_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::TILT_X)
+                }
+
+                fn tilt_y(&self) -> f64 {
+                    self.axes.tilt.1
+                }
+
+                fn tilt_y_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::TILT_Y)
+                }
+
+                fn rotation(&self) -> f64 {
+                    self.axes.rotation
+                }
+
+                fn rotation_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::ROTATION)
+                }
+
+                fn wheel_delta(&self) -> f64 {
+                    self.axes.wheel_delta
+                }
+
+                fn wheel_delta_discrete(&self) -> i32 {
+                    self.axes.wheel_delta_discrete
+                }
+
+                fn wheel_has_changed(&self) -> bool {
+                    self.axes.changed.contains(TabletToolAxesChanged::WHEEL)
+                }
+            }
+        )*
+    };
+}
+
+/// Replayed [`TabletToolAxisEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTabletToolAxisEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) axes: TabletToolAxes,
+}
+
+impl TabletToolAxisEvent<ReplayInput> for ReplayTabletToolAxisEvent {}
+
+/// Replayed [`TabletToolProximityEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTabletToolProximityEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) axes: TabletToolAxes,
+    pub(crate) state: ProximityState,
+}
+
+impl TabletToolProximityEvent<ReplayInput> for ReplayTabletToolProximityEvent {
+    fn state(&self) -> ProximityState {
+        self.state
+    }
+}
+
+/// Replayed [`TabletToolTipEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTabletToolTipEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) axes: TabletToolAxes,
+    pub(crate) tip_state: TabletToolTipState,
+}
+
+impl TabletToolTipEvent<ReplayInput> for ReplayTabletToolTipEvent {
+    fn tip_state(&self) -> TabletToolTipState {
+        self.tip_state
+    }
+}
+
+/// Replayed [`TabletToolButtonEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplayTabletToolButtonEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) axes: TabletToolAxes,
+    pub(crate) button: u32,
+    pub(crate) seat_button_count: u32,
+    pub(crate) button_state: ButtonState,
+}
+
+impl TabletToolButtonEvent<ReplayInput> for ReplayTabletToolButtonEvent {
+    fn button(&self) -> u32 {
+        self.button
+    }
+
+    fn seat_button_count(&self) -> u32 {
+        self.seat_button_count
+    }
+
+    fn button_state(&self) -> ButtonState {
+        self.button_state
+    }
+}
+
+/// Replayed [`SwitchToggleEvent`]
+#[derive(Debug, Clone)]
+pub struct ReplaySwitchToggleEvent {
+    pub(crate) time: u64,
+    pub(crate) device: ReplayDevice,
+    pub(crate) switch: Option<Switch>,
+    pub(crate) state: SwitchState,
+}
+
+impl SwitchToggleEvent<ReplayInput> for ReplaySwitchToggleEvent {
+    fn switch(&self) -> Option<Switch> {
+        self.switch
+    }
+
+    fn state(&self) -> SwitchState {
+        self.state
+    }
+}
+
+impl_event!(
+    ReplayKeyboardKeyEvent,
+    ReplayPointerMotionEvent,
+    ReplayPointerMotionAbsoluteEvent,
+    ReplayPointerButtonEvent,
+    ReplayPointerAxisEvent,
+    ReplayGestureBeginEvent,
+    ReplayGestureEndEvent,
+    ReplayGestureSwipeUpdateEvent,
+    ReplayGesturePinchUpdateEvent,
+    ReplayTouchPositionEvent,
+    ReplayTouchSlotEvent,
+    ReplayTouchFrameEvent,
+    ReplayTabletToolAxisEvent,
+    ReplayTabletToolProximityEvent,
+    ReplayTabletToolTipEvent,
+    ReplayTabletToolButtonEvent,
+    ReplaySwitchToggleEvent,
+);
+
+impl_absolute_position!(ReplayPointerMotionAbsoluteEvent, ReplayTouchPositionEvent);
+
+impl_tablet_tool_event!(
+    ReplayTabletToolAxisEvent,
+    ReplayTabletToolProximityEvent,
+    ReplayTabletToolTipEvent,
+    ReplayTabletToolButtonEvent,
+);
+
+impl InputBackend for ReplayInput {
+    type Device = ReplayDevice;
+    type KeyboardKeyEvent = ReplayKeyboardKeyEvent;
+    type PointerAxisEvent = ReplayPointerAxisEvent;
+    type PointerButtonEvent = ReplayPointerButtonEvent;
+    type PointerMotionEvent = ReplayPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = ReplayPointerMotionAbsoluteEvent;
+
+    type GestureSwipeBeginEvent = ReplayGestureBeginEvent;
+    type GestureSwipeUpdateEvent = ReplayGestureSwipeUpdateEvent;
+    type GestureSwipeEndEvent = ReplayGestureEndEvent;
+    type GesturePinchBeginEvent = ReplayGestureBeginEvent;
+    type GesturePinchUpdateEvent = ReplayGesturePinchUpdateEvent;
+    type GesturePinchEndEvent = ReplayGestureEndEvent;
+    type GestureHoldBeginEvent = ReplayGestureBeginEvent;
+    type GestureHoldEndEvent = ReplayGestureEndEvent;
+
+    type TouchDownEvent = ReplayTouchPositionEvent;
+    type TouchUpEvent = ReplayTouchSlotEvent;
+    type TouchMotionEvent = ReplayTouchPositionEvent;
+    type TouchCancelEvent = ReplayTouchSlotEvent;
+    type TouchFrameEvent = ReplayTouchFrameEvent;
+    type TabletToolAxisEvent = ReplayTabletToolAxisEvent;
+    type TabletToolProximityEvent = ReplayTabletToolProximityEvent;
+    type TabletToolTipEvent = ReplayTabletToolTipEvent;
+    type TabletToolButtonEvent = ReplayTabletToolButtonEvent;
+
+    type SwitchToggleEvent = ReplaySwitchToggleEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
@@ -0,0 +1,786 @@
+//! Line based serialization of input events
+//!
+//! Every line describes a single event as whitespace separated fields, starting with the
+//! offset in microseconds since the start of the recording and the kind of the event.
+//! All events except device additions and removals continue with the index of the device
+//! and the timestamp of the event.
+
+use std::{collections::HashMap, fmt::Write as _, str::SplitWhitespace};
+
+use super::{
+    event::{
+        AbsolutePosition, ReplayDevice, ReplayGestureBeginEvent, ReplayGestureEndEvent,
+        ReplayGesturePinchUpdateEvent, ReplayGestureSwipeUpdateEvent, ReplayInput, ReplayKeyboardKeyEvent,
+        ReplayPointerAxisEvent, ReplayPointerButtonEvent, ReplayPointerMotionAbsoluteEvent,
+        ReplayPointerMotionEvent, ReplaySwitchToggleEvent, ReplayTabletToolAxisEvent,
+        ReplayTabletToolButtonEvent, ReplayTabletToolProximityEvent, ReplayTabletToolTipEvent,
+        ReplayTouchFrameEvent, ReplayTouchPositionEvent, ReplayTouchSlotEvent, TabletToolAxes,
+        TabletToolAxesChanged,
+    },
+    ReplayError,
+};
+use crate::backend::input::{
+    AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device, DeviceCapability,
+    Event, GestureBeginEvent, GestureEndEvent, GesturePinchUpdateEvent, GestureSwipeUpdateEvent,
+    InputBackend, InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+    PointerMotionEvent, ProximityState, Switch, SwitchState, SwitchToggleEvent, TabletToolButtonEvent,
+    TabletToolCapabilities, TabletToolDescriptor, TabletToolEvent, TabletToolProximityEvent,
+    TabletToolTipEvent, TabletToolTipState, TabletToolType, TouchEvent, TouchSlot,
+};
+
+/// Magic string at the start of every recording
+pub(super) const MAGIC: &str = "smithay-input-recording";
+/// Current version of the recording format
+pub const FORMAT_VERSION: u32 = 1;
+
+const CAPABILITIES: [(DeviceCapability, &str); 7] = [
+    (DeviceCapability::Keyboard, "keyboard"),
+    (DeviceCapability::Pointer, "pointer"),
+    (DeviceCapability::Touch, "touch"),
+    (DeviceCapability::TabletTool, "tablet-tool"),
+    (DeviceCapability::TabletPad, "tablet-pad"),
+    (DeviceCapability::Gesture, "gesture"),
+    (DeviceCapability::Switch, "switch"),
+];
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ' ' => escaped.push_str("\\s"),
+            c if c.is_whitespace() => {
+                let _ = write!(escaped, "\\u{:x};", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    if escaped.is_empty() {
+        escaped.push_str("\\0");
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some('t') => unescaped.push('\t'),
+                Some('s') => unescaped.push(' '),
+                Some('u') => {
+                    let code = chars.by_ref().take_while(|c| *c != ';').collect::<String>();
+                    if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                        unescaped.push(c);
+                    }
+                }
+                Some('0') => {}
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+fn opt_f64(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| String::from("-"))
+}
+
+fn key_state(state: KeyState) -> &'static str {
+    match state {
+        KeyState::Pressed => "pressed",
+        KeyState::Released => "released",
+    }
+}
+
+fn button_state(state: ButtonState) -> &'static str {
+    match state {
+        ButtonState::Pressed => "pressed",
+        ButtonState::Released => "released",
+    }
+}
+
+fn axis_source(source: AxisSource) -> &'static str {
+    match source {
+        AxisSource::Finger => "finger",
+        AxisSource::Continuous => "continuous",
+        AxisSource::Wheel => "wheel",
+        AxisSource::WheelTilt => "wheel-tilt",
+    }
+}
+
+fn relative_direction(direction: AxisRelativeDirection) -> &'static str {
+    match direction {
+        AxisRelativeDirection::Identical => "identical",
+        AxisRelativeDirection::Inverted => "inverted",
+    }
+}
+
+fn tool_type(tool_type: TabletToolType) -> &'static str {
+    match tool_type {
+        TabletToolType::Pen => "pen",
+        TabletToolType::Eraser => "eraser",
+        TabletToolType::Brush => "brush",
+        TabletToolType::Pencil => "pencil",
+        TabletToolType::Airbrush => "airbrush",
+        TabletToolType::Mouse => "mouse",
+        TabletToolType::Lens => "lens",
+        TabletToolType::Totem => "totem",
+        TabletToolType::Unknown => "unknown",
+    }
+}
+
+/// Serializes the device description of a `device-added` line
+pub(super) fn write_device_added<D: Device>(out: &mut String, index: u32, device: &D) {
+    let capabilities = CAPABILITIES
+        .iter()
+        .filter(|(cap, _)| device.has_capability(*cap))
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+    let capabilities = if capabilities.is_empty() {
+        String::from("-")
+    } else {
+        capabilities.join(",")
+    };
+    let usb_id = device
+        .usb_id()
+        .map(|(product, vendor)| format!("{}:{}", product, vendor))
+        .unwrap_or_else(|| String::from("-"));
+    let _ = write!(
+        out,
+        "device-added {} {} {} {} {}",
+        index,
+        capabilities,
+        usb_id,
+        escape(&device.id()),
+        escape(&device.name())
+    );
+}
+
+fn write_position<B: InputBackend, E: AbsolutePositionEvent<B>>(out: &mut String, event: &E) {
+    let _ = write!(
+        out,
+        " {} {} {} {}",
+        event.x(),
+        event.y(),
+        event.x_transformed(1),
+        event.y_transformed(1)
+    );
+}
+
+fn write_tablet_axes<B: InputBackend, E: TabletToolEvent<B>>(out: &mut String, event: &E) {
+    let tool = event.tool();
+    let mut changed = TabletToolAxesChanged::empty();
+    changed.set(TabletToolAxesChanged::DISTANCE, event.distance_has_changed());
+    changed.set(TabletToolAxesChanged::PRESSURE, event.pressure_has_changed());
+    changed.set(TabletToolAxesChanged::SLIDER, event.slider_has_changed());
+    changed.set(TabletToolAxesChanged::TILT_X, event.tilt_x_has_changed());
+    changed.set(TabletToolAxesChanged::TILT_Y, event.tilt_y_has_changed());
+    changed.set(TabletToolAxesChanged::ROTATION, event.rotation_has_changed());
+    changed.set(TabletToolAxesChanged::WHEEL, event.wheel_has_changed());
+    let _ = write!(
+        out,
+        " {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+        tool_type(tool.tool_type),
+        tool.hardware_serial,
+        tool.hardware_id_wacom,
+        tool.capabilities.bits(),
+        event.delta_x(),
+        event.delta_y(),
+        event.x(),
+        event.y(),
+        event.x_transformed(1),
+        event.y_transformed(1),
+        event.distance(),
+        event.pressure(),
+        event.slider_position(),
+        event.tilt_x(),
+        event.tilt_y(),
+        event.rotation(),
+        event.wheel_delta(),
+        event.wheel_delta_discrete(),
+    );
+    let _ = write!(out, " {}", changed.bits());
+}
+
+fn write_header<B: InputBackend, E: Event<B>>(out: &mut String, kind: &str, index: u32, event: &E) {
+    let _ = write!(out, "{} {} {}", kind, index, event.time());
+}
+
+/// Serializes an event referencing the device with the given index
+///
+/// Returns `false` for events that cannot be recorded.
+pub(super) fn write_event<B: InputBackend>(out: &mut String, index: u32, event: &InputEvent<B>) -> bool {
+    match event {
+        InputEvent::DeviceAdded { device } => write_device_added(out, index, device),
+        InputEvent::DeviceRemoved { .. } => {
+            let _ = write!(out, "device-removed {}", index);
+        }
+        InputEvent::Keyboard { event } => {
+            write_header(out, "key", index, event);
+            let _ = write!(
+                out,
+                " {} {} {}",
+                event.key_code(),
+                key_state(event.state()),
+                event.count()
+            );
+        }
+        InputEvent::PointerMotion { event } => {
+            write_header(out, "motion", index, event);
+            let _ = write!(
+                out,
+                " {} {} {} {}",
+                event.delta_x(),
+                event.delta_y(),
+                event.delta_x_unaccel(),
+                event.delta_y_unaccel()
+            );
+        }
+        InputEvent::PointerMotionAbsolute { event } => {
+            write_header(out, "motion-absolute", index, event);
+            write_position(out, event);
+        }
+        InputEvent::PointerButton { event } => {
+            write_header(out, "button", index, event);
+            let _ = write!(out, " {} {}", event.button_code(), button_state(event.state()));
+        }
+        InputEvent::PointerAxis { event } => {
+            write_header(out, "axis", index, event);
+            let _ = write!(
+                out,
+                " {} {} {} {} {} {} {}",
+                axis_source(event.source()),
+                opt_f64(event.amount(Axis::Horizontal)),
+                opt_f64(event.amount(Axis::Vertical)),
+                opt_f64(event.amount_v120(Axis::Horizontal)),
+                opt_f64(event.amount_v120(Axis::Vertical)),
+                relative_direction(event.relative_direction(Axis::Horizontal)),
+                relative_direction(event.relative_direction(Axis::Vertical)),
+            );
+        }
+        InputEvent::GestureSwipeBegin { event } => {
+            write_header(out, "swipe-begin", index, event);
+            let _ = write!(out, " {}", event.fingers());
+        }
+        InputEvent::GestureSwipeUpdate { event } => {
+            write_header(out, "swipe-update", index, event);
+            let _ = write!(out, " {} {}", event.delta_x(), event.delta_y());
+        }
+        InputEvent::GestureSwipeEnd { event } => {
+            write_header(out, "swipe-end", index, event);
+            let _ = write!(out, " {}", event.cancelled() as u8);
+        }
+        InputEvent::GesturePinchBegin { event } => {
+            write_header(out, "pinch-begin", index, event);
+            let _ = write!(out, " {}", event.fingers());
+        }
+        InputEvent::GesturePinchUpdate { event } => {
+            write_header(out, "pinch-update", index, event);
+            let _ = write!(
+                out,
+                " {} {} {} {}",
+                event.delta_x(),
+                event.delta_y(),
+                event.scale(),
+                event.rotation()
+            );
+        }
+        InputEvent::GesturePinchEnd { event } => {
+            write_header(out, "pinch-end", index, event);
+            let _ = write!(out, " {}", event.cancelled() as u8);
+        }
+        InputEvent::GestureHoldBegin { event } => {
+            write_header(out, "hold-begin", index, event);
+            let _ = write!(out, " {}", event.fingers());
+        }
+        InputEvent::GestureHoldEnd { event } => {
+            write_header(out, "hold-end", index, event);
+            let _ = write!(out, " {}", event.cancelled() as u8);
+        }
+        InputEvent::TouchDown { event } => {
+            write_header(out, "touch-down", index, event);
+            let _ = write!(out, " {}", i32::from(event.slot()));
+            write_position(out, event);
+        }
+        InputEvent::TouchMotion { event } => {
+            write_header(out, "touch-motion", index, event);
+            let _ = write!(out, " {}", i32::from(event.slot()));
+            write_position(out, event);
+        }
+        InputEvent::TouchUp { event } => {
+            write_header(out, "touch-up", index, event);
+            let _ = write!(out, " {}", i32::from(event.slot()));
+        }
+        InputEvent::TouchCancel { event } => {
+            write_header(out, "touch-cancel", index, event);
+            let _ = write!(out, " {}", i32::from(event.slot()));
+        }
+        InputEvent::TouchFrame { event } => {
+            write_header(out, "touch-frame", index, event);
+        }
+        InputEvent::TabletToolAxis { event } => {
+            write_header(out, "tablet-axis", index, event);
+            write_tablet_axes(out, event);
+        }
+        InputEvent::TabletToolProximity { event } => {
+            write_header(out, "tablet-proximity", index, event);
+            write_tablet_axes(out, event);
+            let _ = write!(
+                out,
+                " {}",
+                match event.state() {
+                    ProximityState::In => "in",
+                    ProximityState::Out => "out",
+                }
+            );
+        }
+        InputEvent::TabletToolTip { event } => {
+            write_header(out, "tablet-tip", index, event);
+            write_tablet_axes(out, event);
+            let _ = write!(
+                out,
+                " {}",
+                match event.tip_state() {
+                    TabletToolTipState::Up => "up",
+                    TabletToolTipState::Down => "down",
+                }
+            );
+        }
+        InputEvent::TabletToolButton { event } => {
+            write_header(out, "tablet-button", index, event);
+            write_tablet_axes(out, event);
+            let _ = write!(
+                out,
+                " {} {} {}",
+                event.button(),
+                event.seat_button_count(),
+                button_state(event.button_state())
+            );
+        }
+        InputEvent::SwitchToggle { event } => {
+            write_header(out, "switch", index, event);
+            let _ = write!(
+                out,
+                " {} {}",
+                match event.switch() {
+                    Some(Switch::Lid) => "lid",
+                    Some(Switch::TabletMode) => "tablet-mode",
+                    None => "-",
+                },
+                match event.state() {
+                    SwitchState::On => "on",
+                    SwitchState::Off => "off",
+                }
+            );
+        }
+        InputEvent::Special(_) => return false,
+    }
+    true
+}
+
+/// Returns the device an event originates from
+pub(super) fn event_device<B: InputBackend>(event: &InputEvent<B>) -> Option<B::Device> {
+    Some(match event {
+        InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. } | InputEvent::Special(_) => {
+            return None
+        }
+        InputEvent::Keyboard { event } => event.device(),
+        InputEvent::PointerMotion { event } => event.device(),
+        InputEvent::PointerMotionAbsolute { event } => event.device(),
+        InputEvent::PointerButton { event } => event.device(),
+        InputEvent::PointerAxis { event } => event.device(),
+        InputEvent::GestureSwipeBegin { event } => event.device(),
+        InputEvent::GestureSwipeUpdate { event } => event.device(),
+        InputEvent::GestureSwipeEnd { event } => event.device(),
+        InputEvent::GesturePinchBegin { event } => event.device(),
+        InputEvent::GesturePinchUpdate { event } => event.device(),
+        InputEvent::GesturePinchEnd { event } => event.device(),
+        InputEvent::GestureHoldBegin { event } => event.device(),
+        InputEvent::GestureHoldEnd { event } => event.device(),
+        InputEvent::TouchDown { event } => event.device(),
+        InputEvent::TouchMotion { event } => event.device(),
+        InputEvent::TouchUp { event } => event.device(),
+        InputEvent::TouchCancel { event } => event.device(),
+        InputEvent::TouchFrame { event } => event.device(),
+        InputEvent::TabletToolAxis { event } => event.device(),
+        InputEvent::TabletToolProximity { event } => event.device(),
+        InputEvent::TabletToolTip { event } => event.device(),
+        InputEvent::TabletToolButton { event } => event.device(),
+        InputEvent::SwitchToggle { event } => event.device(),
+    })
+}
+
+/// Parses the header line of a recording and returns the contained version
+pub(super) fn parse_header(line: &str) -> Result<u32, ReplayError> {
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some(MAGIC) {
+        return Err(ReplayError::InvalidHeader);
+    }
+    let version = fields
+        .next()
+        .and_then(|version| version.parse().ok())
+        .ok_or(ReplayError::InvalidHeader)?;
+    if version > FORMAT_VERSION {
+        return Err(ReplayError::UnsupportedVersion(version));
+    }
+    Ok(version)
+}
+
+struct Fields<'a> {
+    line: usize,
+    fields: SplitWhitespace<'a>,
+}
+
+impl<'a> Fields<'a> {
+    fn error(&self, reason: impl Into<String>) -> ReplayError {
+        ReplayError::Parse {
+            line: self.line,
+            reason: reason.into(),
+        }
+    }
+
+    fn str(&mut self) -> Result<&'a str, ReplayError> {
+        self.fields.next().ok_or_else(|| self.error("missing field"))
+    }
+
+    fn parse<T: std::str::FromStr>(&mut self) -> Result<T, ReplayError> {
+        let field = self.str()?;
+        field
+            .parse()
+            .map_err(|_| self.error(format!("invalid value `{}`", field)))
+    }
+
+    fn opt_f64(&mut self) -> Result<Option<f64>, ReplayError> {
+        match self.str()? {
+            "-" => Ok(None),
+            field => field
+                .parse()
+                .map(Some)
+                .map_err(|_| self.error(format!("invalid value `{}`", field))),
+        }
+    }
+
+    fn bool(&mut self) -> Result<bool, ReplayError> {
+        Ok(self.parse::<u8>()? != 0)
+    }
+
+    fn pair(&mut self) -> Result<(f64, f64), ReplayError> {
+        Ok((self.parse()?, self.parse()?))
+    }
+
+    fn keyword<T>(&mut self, values: &[(&str, T)]) -> Result<T, ReplayError>
+    where
+        T: Copy,
+    {
+        let field = self.str()?;
+        values
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| self.error(format!("unknown value `{}`", field)))
+    }
+
+    fn key_state(&mut self) -> Result<KeyState, ReplayError> {
+        self.keyword(&[("pressed", KeyState::Pressed), ("released", KeyState::Released)])
+    }
+
+    fn button_state(&mut self) -> Result<ButtonState, ReplayError> {
+        self.keyword(&[
+            ("pressed", ButtonState::Pressed),
+            ("released", ButtonState::Released),
+        ])
+    }
+
+    fn relative_direction(&mut self) -> Result<AxisRelativeDirection, ReplayError> {
+        self.keyword(&[
+            ("identical", AxisRelativeDirection::Identical),
+            ("inverted", AxisRelativeDirection::Inverted),
+        ])
+    }
+
+    fn slot(&mut self) -> Result<TouchSlot, ReplayError> {
+        let slot: i32 = self.parse()?;
+        Ok(TouchSlot::from(u32::try_from(slot).ok()))
+    }
+
+    fn position(&mut self) -> Result<AbsolutePosition, ReplayError> {
+        Ok(AbsolutePosition {
+            x: self.parse()?,
+            y: self.parse()?,
+            normalized_x: self.parse()?,
+            normalized_y: self.parse()?,
+        })
+    }
+
+    fn tablet_axes(&mut self) -> Result<TabletToolAxes, ReplayError> {
+        let tool = TabletToolDescriptor {
+            tool_type: self.keyword(&[
+                ("pen", TabletToolType::Pen),
+                ("eraser", TabletToolType::Eraser),
+                ("brush", TabletToolType::Brush),
+                ("pencil", TabletToolType::Pencil),
+                ("airbrush", TabletToolType::Airbrush),
+                ("mouse", TabletToolType::Mouse),
+                ("lens", TabletToolType::Lens),
+                ("totem", TabletToolType::Totem),
+                ("unknown", TabletToolType::Unknown),
+            ])?,
+            hardware_serial: self.parse()?,
+            hardware_id_wacom: self.parse()?,
+            capabilities: TabletToolCapabilities::from_bits_truncate(self.parse()?),
+        };
+        Ok(TabletToolAxes {
+            tool,
+            delta: self.pair()?,
+            position: self.position()?,
+            distance: self.parse()?,
+            pressure: self.parse()?,
+            slider: self.parse()?,
+            tilt: self.pair()?,
+            rotation: self.parse()?,
+            wheel_delta: self.parse()?,
+            wheel_delta_discrete: self.parse()?,
+            changed: TabletToolAxesChanged::from_bits_truncate(self.parse()?),
+        })
+    }
+}
+
+/// Parses a single event line
+///
+/// Returns the offset of the event since the start of the recording in microseconds
+/// and the event itself.
+pub(super) fn parse_event(
+    line_number: usize,
+    line: &str,
+    devices: &mut HashMap<u32, ReplayDevice>,
+) -> Result<(u64, InputEvent<ReplayInput>), ReplayError> {
+    let mut fields = Fields {
+        line: line_number,
+        fields: line.split_whitespace(),
+    };
+    let offset: u64 = fields.parse()?;
+    let kind = fields.str()?;
+
+    if kind == "device-added" {
+        let index = fields.parse()?;
+        let capabilities = match fields.str()? {
+            "-" => Vec::new(),
+            caps => caps
+                .split(',')
+                .map(|cap| {
+                    CAPABILITIES
+                        .iter()
+                        .find(|(_, name)| *name == cap)
+                        .map(|(cap, _)| *cap)
+                        .ok_or_else(|| fields.error(format!("unknown capability `{}`", cap)))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        let usb_id = match fields.str()? {
+            "-" => None,
+            usb_id => {
+                let (product, vendor) = usb_id
+                    .split_once(':')
+                    .and_then(|(product, vendor)| {
+                        Some((product.parse::<u32>().ok()?, vendor.parse::<u32>().ok()?))
+                    })
+                    .ok_or_else(|| fields.error(format!("invalid usb id `{}`", usb_id)))?;
+                Some((product, vendor))
+            }
+        };
+        let id = unescape(fields.str()?);
+        let name = unescape(fields.str()?);
+        let device = ReplayDevice::new(index, id, name, capabilities, usb_id);
+        devices.insert(index, device.clone());
+        return Ok((offset, InputEvent::DeviceAdded { device }));
+    }
+
+    let index: u32 = fields.parse()?;
+    let device = devices
+        .get(&index)
+        .cloned()
+        .ok_or_else(|| fields.error(format!("unknown device {}", index)))?;
+
+    if kind == "device-removed" {
+        devices.remove(&index);
+        return Ok((offset, InputEvent::DeviceRemoved { device }));
+    }
+
+    let time: u64 = fields.parse()?;
+    let event = match kind {
+        "key" => InputEvent::Keyboard {
+            event: ReplayKeyboardKeyEvent {
+                time,
+                device,
+                key_code: fields.parse()?,
+                state: fields.key_state()?,
+                count: fields.parse()?,
+            },
+        },
+        "motion" => InputEvent::PointerMotion {
+            event: ReplayPointerMotionEvent {
+                time,
+                device,
+                delta: fields.pair()?,
+                delta_unaccel: fields.pair()?,
+            },
+        },
+        "motion-absolute" => InputEvent::PointerMotionAbsolute {
+            event: ReplayPointerMotionAbsoluteEvent {
+                time,
+                device,
+                position: fields.position()?,
+            },
+        },
+        "button" => InputEvent::PointerButton {
+            event: ReplayPointerButtonEvent {
+                time,
+                device,
+                button_code: fields.parse()?,
+                state: fields.button_state()?,
+            },
+        },
+        "axis" => InputEvent::PointerAxis {
+            event: ReplayPointerAxisEvent {
+                time,
+                device,
+                source: fields.keyword(&[
+                    ("finger", AxisSource::Finger),
+                    ("continuous", AxisSource::Continuous),
+                    ("wheel", AxisSource::Wheel),
+                    ("wheel-tilt", AxisSource::WheelTilt),
+                ])?,
+                amount: [fields.opt_f64()?, fields.opt_f64()?],
+                amount_v120: [fields.opt_f64()?, fields.opt_f64()?],
+                relative_direction: [fields.relative_direction()?, fields.relative_direction()?],
+            },
+        },
+        "swipe-begin" | "pinch-begin" | "hold-begin" => {
+            let event = ReplayGestureBeginEvent {
+                time,
+                device,
+                fingers: fields.parse()?,
+            };
+            match kind {
+                "swipe-begin" => InputEvent::GestureSwipeBegin { event },
+                "pinch-begin" => InputEvent::GesturePinchBegin { event },
+                _ => InputEvent::GestureHoldBegin { event },
+            }
+        }
+        "swipe-end" | "pinch-end" | "hold-end" => {
+            let event = ReplayGestureEndEvent {
+                time,
+                device,
+                cancelled: fields.bool()?,
+            };
+            match kind {
+                "swipe-end" => InputEvent::GestureSwipeEnd { event },
+                "pinch-end" => InputEvent::GesturePinchEnd { event },
+                _ => InputEvent::GestureHoldEnd { event },
+            }
+        }
+        "swipe-update" => InputEvent::GestureSwipeUpdate {
+            event: ReplayGestureSwipeUpdateEvent {
+                time,
+                device,
+                delta: fields.pair()?,
+            },
+        },
+        "pinch-update" => InputEvent::GesturePinchUpdate {
+            event: ReplayGesturePinchUpdateEvent {
+                time,
+                device,
+                delta: fields.pair()?,
+                scale: fields.parse()?,
+                rotation: fields.parse()?,
+            },
+        },
+        "touch-down" | "touch-motion" => {
+            let event = ReplayTouchPositionEvent {
+                time,
+                device,
+                slot: fields.slot()?,
+                position: fields.position()?,
+            };
+            if kind == "touch-down" {
+                InputEvent::TouchDown { event }
+            } else {
+                InputEvent::TouchMotion { event }
+            }
+        }
+        "touch-up" | "touch-cancel" => {
+            let event = ReplayTouchSlotEvent {
+                time,
+                device,
+                slot: fields.slot()?,
+            };
+            if kind == "touch-up" {
+                InputEvent::TouchUp { event }
+            } else {
+                InputEvent::TouchCancel { event }
+            }
+        }
+        "touch-frame" => InputEvent::TouchFrame {
+            event: ReplayTouchFrameEvent { time, device },
+        },
+        "tablet-axis" => InputEvent::TabletToolAxis {
+            event: ReplayTabletToolAxisEvent {
+                time,
+                device,
+                axes: fields.tablet_axes()?,
+            },
+        },
+        "tablet-proximity" => InputEvent::TabletToolProximity {
+            event: ReplayTabletToolProximityEvent {
+                time,
+                device,
+                axes: fields.tablet_axes()?,
+                state: fields.keyword(&[("in", ProximityState::In), ("out", ProximityState::Out)])?,
+            },
+        },
+        "tablet-tip" => InputEvent::TabletToolTip {
+            event: ReplayTabletToolTipEvent {
+                time,
+                device,
+                axes: fields.tablet_axes()?,
+                tip_state: fields
+                    .keyword(&[("up", TabletToolTipState::Up), ("down", TabletToolTipState::Down)])?,
+            },
+        },
+        "tablet-button" => InputEvent::TabletToolButton {
+            event: ReplayTabletToolButtonEvent {
+                time,
+                device,
+                axes: fields.tablet_axes()?,
+                button: fields.parse()?,
+                seat_button_count: fields.parse()?,
+                button_state: fields.button_state()?,
+            },
+        },
+        "switch" => InputEvent::SwitchToggle {
+            event: ReplaySwitchToggleEvent {
+                time,
+                device,
+                switch: fields.keyword(&[
+                    ("lid", Some(Switch::Lid)),
+                    ("tablet-mode", Some(Switch::TabletMode)),
+                    ("-", None),
+                ])?,
+                state: fields.keyword(&[("on", SwitchState::On), ("off", SwitchState::Off)])?,
+            },
+        },
+        kind => return Err(fields.error(format!("unknown event kind `{}`", kind))),
+    };
+
+    Ok((offset, event))
+}
@@ -0,0 +1,561 @@
+//! Recording and replaying of input events
+//!
+//! This module allows to record the [`InputEvent`] stream of any [`InputBackend`] to a file
+//! and to replay it later on, e.g. to reproduce bugs reported by users or to drive regression
+//! tests with real-world input.
+//!
+//! Recording is done by an [`InputRecorder`], which can either be fed events manually or by
+//! wrapping any input event source into a [`RecordingSource`]:
+//!
+//! ```no_run
+//! # use smithay::backend::{
+//! #     input::{InputBackend, InputEvent},
+//! #     replay::{InputRecorder, RecordingSource},
+//! # };
+//! # fn process_input_event<B: InputBackend>(event: InputEvent<B>) {}
+//! # fn record<S, B>(input_source: S, event_loop: calloop::EventLoop<'static, ()>)
+//! # where
+//! #     S: calloop::EventSource<Event = InputEvent<B>, Metadata = (), Ret = ()> + 'static,
+//! #     B: InputBackend + 'static,
+//! # {
+//! let file = std::fs::File::create("/tmp/input.rec").unwrap();
+//! let recorder = InputRecorder::new(std::io::BufWriter::new(file)).unwrap();
+//! event_loop
+//!     .handle()
+//!     .insert_source(RecordingSource::new(input_source, recorder), |event, _, _| {
+//!         process_input_event(event)
+//!     })
+//!     .unwrap();
+//! # }
+//! ```
+//!
+//! A recording can then be loaded as a [`Recording`] and either replayed with the original timing
+//! through an [`InputReplay`] event source, or consumed directly via [`Recording::into_events`].
+//! Replayed events use the [`ReplayInput`] backend types.
+//!
+//! The recording format is a versioned, line based text format, see [`FORMAT_VERSION`].
+//! Backend specific [`InputEvent::Special`] events are not recorded.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{trace, warn};
+
+use crate::backend::input::{Device, InputBackend, InputEvent};
+
+mod event;
+mod format;
+
+pub use event::*;
+pub use format::FORMAT_VERSION;
+
+/// Error reading a recording
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    /// Reading the recording failed
+    #[error("Failed to read the recording")]
+    Io(#[from] io::Error),
+    /// The recording does not start with a valid header
+    #[error("The recording does not start with a valid header")]
+    InvalidHeader,
+    /// The recording was created with a newer, unsupported format version
+    #[error("Unsupported recording format version {0}")]
+    UnsupportedVersion(u32),
+    /// A line of the recording could not be parsed
+    #[error("Invalid event in line {line}: {reason}")]
+    Parse {
+        /// Line number of the invalid event
+        line: usize,
+        /// Reason the event could not be parsed
+        reason: String,
+    },
+}
+
+/// Records input events into a writer
+#[derive(Debug)]
+pub struct InputRecorder<W: Write> {
+    writer: W,
+    start: Instant,
+    devices: HashMap<String, u32>,
+    next_device: u32,
+    line: String,
+}
+
+impl<W: Write> InputRecorder<W> {
+    /// Create a new recorder writing to the given writer
+    ///
+    /// The timing of all recorded events is relative to the creation of the recorder.
+    pub fn new(mut writer: W) -> io::Result<InputRecorder<W>> {
+        writeln!(writer, "{} {}", format::MAGIC, FORMAT_VERSION)?;
+        Ok(InputRecorder {
+            writer,
+            start: Instant::now(),
+            devices: HashMap::new(),
+            next_device: 0,
+            line: String::new(),
+        })
+    }
+
+    /// Record a single input event
+    ///
+    /// Devices are recorded on their first appearance, so recording may start at any
+    /// point in time without missing [`InputEvent::DeviceAdded`] events.
+    pub fn record<B: InputBackend>(&mut self, event: &InputEvent<B>) -> io::Result<()> {
+        let offset = self.start.elapsed().as_micros() as u64;
+
+        let index = match event {
+            InputEvent::DeviceAdded { device } => match self.devices.get(&device.id()) {
+                // already implicitly recorded
+                Some(_) => return Ok(()),
+                None => self.add_device(device),
+            },
+            InputEvent::DeviceRemoved { device } => match self.devices.remove(&device.id()) {
+                Some(index) => index,
+                None => return Ok(()),
+            },
+            InputEvent::Special(_) => {
+                trace!("Skipping backend specific event");
+                return Ok(());
+            }
+            event => {
+                // `event_device` only returns `None` for the variants handled above
+                let device = format::event_device(event).unwrap();
+                match self.devices.get(&device.id()) {
+                    Some(index) => *index,
+                    None => {
+                        let index = self.add_device(&device);
+                        self.line.clear();
+                        format::write_device_added(&mut self.line, index, &device);
+                        writeln!(self.writer, "{} {}", offset, self.line)?;
+                        index
+                    }
+                }
+            }
+        };
+
+        self.line.clear();
+        if format::write_event(&mut self.line, index, event) {
+            writeln!(self.writer, "{} {}", offset, self.line)?;
+        }
+        Ok(())
+    }
+
+    fn add_device<D: Device>(&mut self, device: &D) -> u32 {
+        let index = self.next_device;
+        self.next_device += 1;
+        self.devices.insert(device.id(), index);
+        index
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns a reference to the underlying writer
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes the recorder, returning the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Event source recording all input events of a wrapped input event source
+///
+/// All events are passed through unchanged after being recorded. Failing to record an
+/// event is logged, but does not interrupt the event stream.
+#[derive(Debug)]
+pub struct RecordingSource<S, W: Write> {
+    source: S,
+    recorder: InputRecorder<W>,
+}
+
+impl<S, W: Write> RecordingSource<S, W> {
+    /// Wrap an input event source, recording its events with the given recorder
+    pub fn new(source: S, recorder: InputRecorder<W>) -> RecordingSource<S, W> {
+        RecordingSource { source, recorder }
+    }
+
+    /// Returns a reference to the wrapped event source
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns a mutable reference to the wrapped event source
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Returns a mutable reference to the recorder
+    pub fn recorder_mut(&mut self) -> &mut InputRecorder<W> {
+        &mut self.recorder
+    }
+
+    /// Consumes the wrapper, returning the wrapped event source and the recorder
+    pub fn into_inner(self) -> (S, InputRecorder<W>) {
+        (self.source, self.recorder)
+    }
+}
+
+impl<S, B, W> EventSource for RecordingSource<S, W>
+where
+    S: EventSource<Event = InputEvent<B>>,
+    B: InputBackend,
+    W: Write,
+{
+    type Event = InputEvent<B>;
+    type Metadata = S::Metadata;
+    type Ret = S::Ret;
+    type Error = S::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let recorder = &mut self.recorder;
+        self.source.process_events(readiness, token, |event, meta| {
+            if let Err(err) = recorder.record(&event) {
+                warn!(?err, "Failed to record input event");
+            }
+            callback(event, meta)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+/// Input events loaded from a recording
+#[derive(Debug)]
+pub struct Recording {
+    version: u32,
+    events: VecDeque<(Duration, InputEvent<ReplayInput>)>,
+}
+
+impl Recording {
+    /// Load a recording
+    pub fn read<R: BufRead>(reader: R) -> Result<Recording, ReplayError> {
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or(ReplayError::InvalidHeader)??;
+        let version = format::parse_header(&header)?;
+
+        let mut devices = HashMap::new();
+        let mut events = VecDeque::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // line numbers start at 1 and include the header
+            let (offset, event) = format::parse_event(i + 2, &line, &mut devices)?;
+            events.push_back((Duration::from_micros(offset), event));
+        }
+
+        Ok(Recording { version, events })
+    }
+
+    /// Format version of the recording
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Number of events in the recording
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if the recording contains no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Total duration of the recording
+    pub fn duration(&self) -> Duration {
+        self.events.back().map(|(offset, _)| *offset).unwrap_or_default()
+    }
+
+    /// Consumes the recording, returning all events in order without any timing
+    ///
+    /// This is useful to feed a recording synchronously into a compositor, e.g. in tests.
+    pub fn into_events(self) -> impl Iterator<Item = InputEvent<ReplayInput>> {
+        self.events.into_iter().map(|(_, event)| event)
+    }
+}
+
+/// Event source replaying a [`Recording`] with its original timing
+///
+/// The replay starts once the source is inserted into the event loop. Once all events
+/// have been replayed the source removes itself from the event loop.
+#[derive(Debug)]
+pub struct InputReplay {
+    events: VecDeque<(Duration, InputEvent<ReplayInput>)>,
+    timer: Timer,
+    start: Option<Instant>,
+}
+
+impl InputReplay {
+    /// Create a new replay of the given recording
+    pub fn new(recording: Recording) -> InputReplay {
+        InputReplay {
+            events: recording.events,
+            timer: Timer::immediate(),
+            start: None,
+        }
+    }
+
+    /// Returns `true` if all events have been replayed
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Number of events not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+}
+
+impl EventSource for InputReplay {
+    type Event = InputEvent<ReplayInput>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let events = &mut self.events;
+        let start = &mut self.start;
+        self.timer.process_events(readiness, token, |_, _| {
+            let start = *start.get_or_insert_with(Instant::now);
+            let elapsed = start.elapsed();
+            while events
+                .front()
+                .map(|(offset, _)| *offset <= elapsed)
+                .unwrap_or(false)
+            {
+                let (_, event) = events.pop_front().unwrap();
+                callback(event, &mut ());
+            }
+            match events.front() {
+                Some((offset, _)) => TimeoutAction::ToInstant(start + *offset),
+                None => TimeoutAction::Drop,
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.timer.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::input::{
+        AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, DeviceCapability, Event, KeyState,
+        KeyboardKeyEvent, PointerAxisEvent, TouchEvent, TouchSlot,
+    };
+
+    fn device() -> ReplayDevice {
+        ReplayDevice::new(
+            7,
+            String::from("event3"),
+            String::from("Some Touch Keyboard"),
+            vec![DeviceCapability::Keyboard, DeviceCapability::Touch],
+            Some((1, 2)),
+        )
+    }
+
+    fn record(events: &[InputEvent<ReplayInput>]) -> Recording {
+        let mut recorder = InputRecorder::new(Vec::new()).unwrap();
+        for event in events {
+            recorder.record(event).unwrap();
+        }
+        Recording::read(recorder.into_inner().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let device = device();
+        let events = [
+            InputEvent::DeviceAdded {
+                device: device.clone(),
+            },
+            InputEvent::Keyboard {
+                event: ReplayKeyboardKeyEvent {
+                    time: 1000,
+                    device: device.clone(),
+                    key_code: 30,
+                    state: KeyState::Pressed,
+                    count: 1,
+                },
+            },
+            InputEvent::PointerAxis {
+                event: ReplayPointerAxisEvent {
+                    time: 2000,
+                    device: device.clone(),
+                    source: AxisSource::Wheel,
+                    amount: [None, Some(-15.5)],
+                    amount_v120: [None, Some(-120.0)],
+                    relative_direction: [AxisRelativeDirection::Identical, AxisRelativeDirection::Inverted],
+                },
+            },
+            InputEvent::TouchDown {
+                event: ReplayTouchPositionEvent {
+                    time: 3000,
+                    device: device.clone(),
+                    slot: TouchSlot::from(Some(2)),
+                    position: AbsolutePosition {
+                        x: 10.25,
+                        y: 20.0,
+                        normalized_x: 0.1,
+                        normalized_y: 0.2,
+                    },
+                },
+            },
+            InputEvent::DeviceRemoved { device },
+        ];
+
+        let recording = record(&events);
+        assert_eq!(recording.version(), FORMAT_VERSION);
+        assert_eq!(recording.len(), events.len());
+
+        let mut replayed = recording.into_events();
+        let added = match replayed.next() {
+            Some(InputEvent::DeviceAdded { device }) => device,
+            event => panic!("unexpected event {:?}", event),
+        };
+        assert_eq!(added.name(), "Some Touch Keyboard");
+        assert_eq!(added.id(), "event3");
+        assert_eq!(added.usb_id(), Some((1, 2)));
+        assert!(added.has_capability(DeviceCapability::Touch));
+        assert!(!added.has_capability(DeviceCapability::Pointer));
+
+        match replayed.next() {
+            Some(InputEvent::Keyboard { event }) => {
+                assert_eq!(event.time(), 1000);
+                assert_eq!(event.device(), added);
+                assert_eq!(event.key_code(), 30);
+                assert_eq!(event.state(), KeyState::Pressed);
+                assert_eq!(event.count(), 1);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match replayed.next() {
+            Some(InputEvent::PointerAxis { event }) => {
+                assert_eq!(event.source(), AxisSource::Wheel);
+                assert_eq!(event.amount(Axis::Horizontal), None);
+                assert_eq!(event.amount(Axis::Vertical), Some(-15.5));
+                assert_eq!(event.amount_v120(Axis::Vertical), Some(-120.0));
+                assert_eq!(
+                    event.relative_direction(Axis::Vertical),
+                    AxisRelativeDirection::Inverted
+                );
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match replayed.next() {
+            Some(InputEvent::TouchDown { event }) => {
+                assert_eq!(event.slot(), TouchSlot::from(Some(2)));
+                assert_eq!(event.x(), 10.25);
+                assert_eq!(event.x_transformed(100), 10.0);
+                assert_eq!(event.y_transformed(100), 20.0);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(matches!(replayed.next(), Some(InputEvent::DeviceRemoved { device }) if device == added));
+        assert!(replayed.next().is_none());
+    }
+
+    #[test]
+    fn device_name_whitespace() {
+        let device = ReplayDevice::new(
+            0,
+            String::from("event\t0"),
+            String::from("Tab\tand  spaces\r\n\u{a0}\\s"),
+            vec![DeviceCapability::Keyboard],
+            None,
+        );
+        let events = [InputEvent::DeviceAdded {
+            device: device.clone(),
+        }];
+
+        match record(&events).into_events().next() {
+            Some(InputEvent::DeviceAdded { device: replayed }) => {
+                assert_eq!(replayed.id(), device.id());
+                assert_eq!(replayed.name(), device.name());
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn implicit_device() {
+        let events = [InputEvent::TouchFrame {
+            event: ReplayTouchFrameEvent {
+                time: 0,
+                device: device(),
+            },
+        }];
+
+        let mut replayed = record(&events).into_events();
+        assert!(matches!(replayed.next(), Some(InputEvent::DeviceAdded { .. })));
+        assert!(matches!(replayed.next(), Some(InputEvent::TouchFrame { .. })));
+    }
+
+    #[test]
+    fn invalid_recordings() {
+        assert!(matches!(
+            Recording::read("not a recording\n".as_bytes()),
+            Err(ReplayError::InvalidHeader)
+        ));
+        assert!(matches!(
+            Recording::read(format!("{} {}\n", format::MAGIC, FORMAT_VERSION + 1).as_bytes()),
+            Err(ReplayError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            Recording::read(
+                format!("{} {}\n0 key 0 0 30 pressed 1\n", format::MAGIC, FORMAT_VERSION).as_bytes()
+            ),
+            Err(ReplayError::Parse { line: 2, .. })
+        ));
+    }
+}
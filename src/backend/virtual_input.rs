@@ -0,0 +1,702 @@
+//! Virtual input backend
+//!
+//! This module provides an input backend whose devices and events are created programmatically,
+//! without any real input hardware or windowing system. It is intended to drive integration tests
+//! of a compositor through the same code paths as any other input backend.
+//!
+//! The [`VirtualInputBackend`] is a calloop event source producing [`InputEvent`]s, while a
+//! [`VirtualInputHandle`] is used to add and remove devices and to inject events:
+//!
+//! ```no_run
+//! use smithay::backend::{
+//!     input::{ButtonState, DeviceCapability, InputBackend, InputEvent, KeyState},
+//!     virtual_input::VirtualInputBackend,
+//! };
+//! # fn process_input_event<B: InputBackend>(event: InputEvent<B>) {}
+//! # let event_loop = calloop::EventLoop::<()>::try_new().unwrap();
+//!
+//! let backend = VirtualInputBackend::new();
+//! let handle = backend.handle();
+//! event_loop
+//!     .handle()
+//!     .insert_source(backend, |event, _, _| process_input_event(event))
+//!     .unwrap();
+//!
+//! let keyboard = handle.add_device("keyboard", &[DeviceCapability::Keyboard]);
+//! let mouse = handle.add_device("mouse", &[DeviceCapability::Pointer]);
+//! handle.key(&keyboard, 30, KeyState::Pressed);
+//! handle.pointer_motion(&mouse, (10.0, 5.0));
+//! handle.pointer_button(&mouse, 0x110, ButtonState::Pressed);
+//! ```
+//!
+//! Events are delivered the next time the event loop is dispatched. Their timestamps are
+//! taken from a virtual clock controlled through [`VirtualInputHandle::advance`], which makes
+//! the produced event stream fully deterministic.
+//!
+//! The events use the same types as replayed recordings (see [`ReplayInput`]), so scripted
+//! input can be recorded and replayed like input of any other backend.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use calloop::{
+    channel::{self, Channel, Sender},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+use crate::{
+    backend::{
+        input::{
+            AxisRelativeDirection, AxisSource, ButtonState, DeviceCapability, InputEvent, KeyState, Switch,
+            SwitchState, TouchSlot,
+        },
+        replay::{
+            AbsolutePosition, ReplayDevice, ReplayGestureBeginEvent, ReplayGestureEndEvent,
+            ReplayGesturePinchUpdateEvent, ReplayGestureSwipeUpdateEvent, ReplayInput,
+            ReplayKeyboardKeyEvent, ReplayPointerAxisEvent, ReplayPointerButtonEvent,
+            ReplayPointerMotionAbsoluteEvent, ReplayPointerMotionEvent, ReplaySwitchToggleEvent,
+            ReplayTouchFrameEvent, ReplayTouchPositionEvent, ReplayTouchSlotEvent,
+        },
+    },
+    input::pointer::PointerAccelerator,
+    utils::{Logical, Point},
+};
+
+/// Scroll amount of a single wheel click, matching the value reported by libinput
+const WHEEL_CLICK_ANGLE: f64 = 15.0;
+
+/// Input backend producing programmatically injected events
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct VirtualInputBackend {
+    channel: Channel<InputEvent<ReplayInput>>,
+    handle: VirtualInputHandle,
+}
+
+impl VirtualInputBackend {
+    /// Create a new virtual input backend without any devices
+    pub fn new() -> VirtualInputBackend {
+        let (sender, channel) = channel::channel();
+        VirtualInputBackend {
+            channel,
+            handle: VirtualInputHandle {
+                sender,
+                state: Arc::new(Mutex::new(HandleState::default())),
+            },
+        }
+    }
+
+    /// Returns a handle to inject events into this backend
+    pub fn handle(&self) -> VirtualInputHandle {
+        self.handle.clone()
+    }
+}
+
+impl Default for VirtualInputBackend {
+    fn default() -> Self {
+        VirtualInputBackend::new()
+    }
+}
+
+impl EventSource for VirtualInputBackend {
+    type Event = InputEvent<ReplayInput>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = channel::ChannelError;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        self.channel.process_events(readiness, token, |event, _| {
+            // the backend holds a sender itself, so the channel never gets closed
+            if let channel::Event::Msg(event) = event {
+                callback(event, &mut ());
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.channel.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.channel.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.channel.unregister(poll)
+    }
+}
+
+#[derive(Debug, Default)]
+struct HandleState {
+    time: Duration,
+    next_device: u32,
+    // pairs of device index and key code
+    pressed_keys: HashSet<(u32, u32)>,
+    // pointer accelerators by device index
+    accelerators: HashMap<u32, PointerAccelerator>,
+}
+
+/// Handle to create devices and inject events into a [`VirtualInputBackend`]
+///
+/// All injected events are timestamped with the current time of the virtual clock of the
+/// backend, which starts at zero and only moves forward through [`advance`](Self::advance)
+/// and [`set_time`](Self::set_time).
+///
+/// Events injected after the backend was dropped are silently discarded.
+#[derive(Debug, Clone)]
+pub struct VirtualInputHandle {
+    sender: Sender<InputEvent<ReplayInput>>,
+    state: Arc<Mutex<HandleState>>,
+}
+
+impl VirtualInputHandle {
+    /// Current time of the virtual clock
+    pub fn time(&self) -> Duration {
+        self.state.lock().unwrap().time
+    }
+
+    /// Set the virtual clock to the given time
+    ///
+    /// Times before the current time of the clock are ignored.
+    pub fn set_time(&self, time: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.time = state.time.max(time);
+    }
+
+    /// Advance the virtual clock by the given duration
+    pub fn advance(&self, duration: Duration) {
+        self.state.lock().unwrap().time += duration;
+    }
+
+    /// Add a new device with the given capabilities
+    ///
+    /// This emits an [`InputEvent::DeviceAdded`] event.
+    pub fn add_device(&self, name: impl Into<String>, capabilities: &[DeviceCapability]) -> ReplayDevice {
+        let index = {
+            let mut state = self.state.lock().unwrap();
+            let index = state.next_device;
+            state.next_device += 1;
+            index
+        };
+        let device = ReplayDevice::new(
+            index,
+            format!("virtual-{}", index),
+            name.into(),
+            capabilities.to_vec(),
+            None,
+        );
+        self.send(InputEvent::DeviceAdded {
+            device: device.clone(),
+        });
+        device
+    }
+
+    /// Remove a previously added device
+    ///
+    /// This emits an [`InputEvent::DeviceRemoved`] event. Keys still pressed
+    /// on the device no longer count as pressed afterwards.
+    pub fn remove_device(&self, device: &ReplayDevice) {
+        let mut state = self.state.lock().unwrap();
+        state.pressed_keys.retain(|(index, _)| *index != device.index());
+        state.accelerators.remove(&device.index());
+        drop(state);
+        self.send(InputEvent::DeviceRemoved {
+            device: device.clone(),
+        });
+    }
+
+    /// Press or release a key
+    ///
+    /// `key_code` is an evdev key code. The seat-wide count of pressed keys is tracked across
+    /// all devices of this backend.
+    pub fn key(&self, device: &ReplayDevice, key_code: u32, state: KeyState) {
+        let (time, count) = {
+            let mut handle_state = self.state.lock().unwrap();
+            match state {
+                KeyState::Pressed => handle_state.pressed_keys.insert((device.index(), key_code)),
+                KeyState::Released => handle_state.pressed_keys.remove(&(device.index(), key_code)),
+            };
+            (handle_state.time, handle_state.pressed_keys.len() as u32)
+        };
+        self.send(InputEvent::Keyboard {
+            event: ReplayKeyboardKeyEvent {
+                time: time.as_micros() as u64,
+                device: device.clone(),
+                key_code,
+                state,
+                count,
+            },
+        });
+    }
+
+    /// Set the acceleration stage used for relative motion of a device
+    ///
+    /// Passing `None` removes a previously set accelerator.
+    pub fn set_pointer_accelerator(&self, device: &ReplayDevice, accelerator: Option<PointerAccelerator>) {
+        let mut state = self.state.lock().unwrap();
+        match accelerator {
+            Some(accelerator) => state.accelerators.insert(device.index(), accelerator),
+            None => state.accelerators.remove(&device.index()),
+        };
+    }
+
+    /// Move the pointer by a relative amount
+    ///
+    /// If an accelerator was set for the device using
+    /// [`set_pointer_accelerator`](Self::set_pointer_accelerator), `delta` is the unaccelerated
+    /// delta and the reported delta is accelerated by it. Otherwise the unaccelerated delta is
+    /// reported to be the same as `delta`.
+    pub fn pointer_motion(&self, device: &ReplayDevice, delta: impl Into<Point<f64, Logical>>) {
+        let delta_unaccel = delta.into();
+        let delta = {
+            let mut state = self.state.lock().unwrap();
+            let utime = state.time.as_micros() as u64;
+            match state.accelerators.get_mut(&device.index()) {
+                Some(accelerator) => accelerator.accelerate(delta_unaccel, delta_unaccel, utime).delta,
+                None => delta_unaccel,
+            }
+        };
+        self.pointer_motion_unaccelerated(device, delta, delta_unaccel);
+    }
+
+    /// Move the pointer by a relative amount, with a distinct unaccelerated delta
+    pub fn pointer_motion_unaccelerated(
+        &self,
+        device: &ReplayDevice,
+        delta: impl Into<Point<f64, Logical>>,
+        delta_unaccel: impl Into<Point<f64, Logical>>,
+    ) {
+        let (delta, delta_unaccel) = (delta.into(), delta_unaccel.into());
+        self.send(InputEvent::PointerMotion {
+            event: ReplayPointerMotionEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                delta: (delta.x, delta.y),
+                delta_unaccel: (delta_unaccel.x, delta_unaccel.y),
+            },
+        });
+    }
+
+    /// Move the pointer to an absolute position
+    ///
+    /// `position` is given in normalized coordinates, `(0.0, 0.0)` being the top-left and
+    /// `(1.0, 1.0)` the bottom-right corner of the area the device is mapped to.
+    pub fn pointer_motion_absolute(&self, device: &ReplayDevice, position: (f64, f64)) {
+        self.send(InputEvent::PointerMotionAbsolute {
+            event: ReplayPointerMotionAbsoluteEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                position: normalized_position(position),
+            },
+        });
+    }
+
+    /// Press or release a pointer button
+    ///
+    /// `button_code` is an evdev button code, e.g. `0x110` for the left button.
+    pub fn pointer_button(&self, device: &ReplayDevice, button_code: u32, state: ButtonState) {
+        self.send(InputEvent::PointerButton {
+            event: ReplayPointerButtonEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                button_code,
+                state,
+            },
+        });
+    }
+
+    /// Emit an axis frame with continuous scroll amounts
+    ///
+    /// An axis is only part of the frame if its amount is `Some`. An amount of `0.0` marks the
+    /// end of a scroll sequence for [`AxisSource::Finger`].
+    pub fn pointer_axis(
+        &self,
+        device: &ReplayDevice,
+        source: AxisSource,
+        horizontal: Option<f64>,
+        vertical: Option<f64>,
+    ) {
+        self.send(InputEvent::PointerAxis {
+            event: ReplayPointerAxisEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                source,
+                amount: [horizontal, vertical],
+                amount_v120: [None, None],
+                relative_direction: [AxisRelativeDirection::Identical; 2],
+            },
+        });
+    }
+
+    /// Emit an axis frame of a scroll wheel
+    ///
+    /// The steps are given in fractions of 120 per wheel click, axes with zero steps are not
+    /// part of the frame. The continuous amount is derived from the steps.
+    pub fn pointer_axis_v120(&self, device: &ReplayDevice, horizontal: i32, vertical: i32) {
+        let v120 = |steps: i32| (steps != 0).then_some(steps as f64);
+        let amount = |steps: i32| v120(steps).map(|steps| steps / 120.0 * WHEEL_CLICK_ANGLE);
+        self.send(InputEvent::PointerAxis {
+            event: ReplayPointerAxisEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                source: AxisSource::Wheel,
+                amount: [amount(horizontal), amount(vertical)],
+                amount_v120: [v120(horizontal), v120(vertical)],
+                relative_direction: [AxisRelativeDirection::Identical; 2],
+            },
+        });
+    }
+
+    /// Put a finger down on a touch device
+    ///
+    /// `position` is given in normalized coordinates, see
+    /// [`pointer_motion_absolute`](Self::pointer_motion_absolute).
+    pub fn touch_down(&self, device: &ReplayDevice, slot: TouchSlot, position: (f64, f64)) {
+        self.send(InputEvent::TouchDown {
+            event: ReplayTouchPositionEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                slot,
+                position: normalized_position(position),
+            },
+        });
+    }
+
+    /// Move a finger on a touch device
+    ///
+    /// `position` is given in normalized coordinates, see
+    /// [`pointer_motion_absolute`](Self::pointer_motion_absolute).
+    pub fn touch_motion(&self, device: &ReplayDevice, slot: TouchSlot, position: (f64, f64)) {
+        self.send(InputEvent::TouchMotion {
+            event: ReplayTouchPositionEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                slot,
+                position: normalized_position(position),
+            },
+        });
+    }
+
+    /// Lift a finger from a touch device
+    pub fn touch_up(&self, device: &ReplayDevice, slot: TouchSlot) {
+        self.send(InputEvent::TouchUp {
+            event: ReplayTouchSlotEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                slot,
+            },
+        });
+    }
+
+    /// Cancel a touch point
+    pub fn touch_cancel(&self, device: &ReplayDevice, slot: TouchSlot) {
+        self.send(InputEvent::TouchCancel {
+            event: ReplayTouchSlotEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                slot,
+            },
+        });
+    }
+
+    /// End a set of touch events belonging together
+    pub fn touch_frame(&self, device: &ReplayDevice) {
+        self.send(InputEvent::TouchFrame {
+            event: ReplayTouchFrameEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+            },
+        });
+    }
+
+    /// Begin a swipe gesture
+    pub fn gesture_swipe_begin(&self, device: &ReplayDevice, fingers: u32) {
+        self.send(InputEvent::GestureSwipeBegin {
+            event: self.gesture_begin(device, fingers),
+        });
+    }
+
+    /// Update a swipe gesture
+    pub fn gesture_swipe_update(&self, device: &ReplayDevice, delta: impl Into<Point<f64, Logical>>) {
+        let delta = delta.into();
+        self.send(InputEvent::GestureSwipeUpdate {
+            event: ReplayGestureSwipeUpdateEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                delta: (delta.x, delta.y),
+            },
+        });
+    }
+
+    /// End a swipe gesture
+    pub fn gesture_swipe_end(&self, device: &ReplayDevice, cancelled: bool) {
+        self.send(InputEvent::GestureSwipeEnd {
+            event: self.gesture_end(device, cancelled),
+        });
+    }
+
+    /// Begin a pinch gesture
+    pub fn gesture_pinch_begin(&self, device: &ReplayDevice, fingers: u32) {
+        self.send(InputEvent::GesturePinchBegin {
+            event: self.gesture_begin(device, fingers),
+        });
+    }
+
+    /// Update a pinch gesture
+    ///
+    /// `scale` is relative to the start of the gesture, `rotation` is the angle in degrees
+    /// relative to the previous update.
+    pub fn gesture_pinch_update(
+        &self,
+        device: &ReplayDevice,
+        delta: impl Into<Point<f64, Logical>>,
+        scale: f64,
+        rotation: f64,
+    ) {
+        let delta = delta.into();
+        self.send(InputEvent::GesturePinchUpdate {
+            event: ReplayGesturePinchUpdateEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                delta: (delta.x, delta.y),
+                scale,
+                rotation,
+            },
+        });
+    }
+
+    /// End a pinch gesture
+    pub fn gesture_pinch_end(&self, device: &ReplayDevice, cancelled: bool) {
+        self.send(InputEvent::GesturePinchEnd {
+            event: self.gesture_end(device, cancelled),
+        });
+    }
+
+    /// Begin a hold gesture
+    pub fn gesture_hold_begin(&self, device: &ReplayDevice, fingers: u32) {
+        self.send(InputEvent::GestureHoldBegin {
+            event: self.gesture_begin(device, fingers),
+        });
+    }
+
+    /// End a hold gesture
+    pub fn gesture_hold_end(&self, device: &ReplayDevice, cancelled: bool) {
+        self.send(InputEvent::GestureHoldEnd {
+            event: self.gesture_end(device, cancelled),
+        });
+    }
+
+    /// Toggle a switch, e.g. a laptop lid
+    pub fn switch_toggle(&self, device: &ReplayDevice, switch: Switch, state: SwitchState) {
+        self.send(InputEvent::SwitchToggle {
+            event: ReplaySwitchToggleEvent {
+                time: self.timestamp(),
+                device: device.clone(),
+                switch: Some(switch),
+                state,
+            },
+        });
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.time().as_micros() as u64
+    }
+
+    fn gesture_begin(&self, device: &ReplayDevice, fingers: u32) -> ReplayGestureBeginEvent {
+        ReplayGestureBeginEvent {
+            time: self.timestamp(),
+            device: device.clone(),
+            fingers,
+        }
+    }
+
+    fn gesture_end(&self, device: &ReplayDevice, cancelled: bool) -> ReplayGestureEndEvent {
+        ReplayGestureEndEvent {
+            time: self.timestamp(),
+            device: device.clone(),
+            cancelled,
+        }
+    }
+
+    fn send(&self, event: InputEvent<ReplayInput>) {
+        // an error only means the backend is gone, in which case nobody is interested anymore
+        let _ = self.sender.send(event);
+    }
+}
+
+fn normalized_position((x, y): (f64, f64)) -> AbsolutePosition {
+    AbsolutePosition {
+        x,
+        y,
+        normalized_x: x,
+        normalized_y: y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use calloop::EventLoop;
+
+    use super::VirtualInputBackend;
+    use crate::backend::{
+        input::{
+            AbsolutePositionEvent, Axis, ButtonState, Device, DeviceCapability, Event, InputEvent, KeyState,
+            KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+        },
+        replay::ReplayInput,
+    };
+    use crate::input::pointer::{FlatProfile, PointerAccelerator};
+
+    fn dispatch(
+        event_loop: &mut EventLoop<'static, Vec<InputEvent<ReplayInput>>>,
+    ) -> Vec<InputEvent<ReplayInput>> {
+        let mut events = Vec::new();
+        event_loop.dispatch(Some(Duration::ZERO), &mut events).unwrap();
+        events
+    }
+
+    #[test]
+    fn injected_events() {
+        let mut event_loop = EventLoop::<Vec<InputEvent<ReplayInput>>>::try_new().unwrap();
+        let backend = VirtualInputBackend::new();
+        let handle = backend.handle();
+        event_loop
+            .handle()
+            .insert_source(backend, |event, _, events| events.push(event))
+            .unwrap();
+
+        let keyboard = handle.add_device("keyboard", &[DeviceCapability::Keyboard]);
+        let mouse = handle.add_device("mouse", &[DeviceCapability::Pointer]);
+        assert_ne!(keyboard, mouse);
+        assert!(mouse.has_capability(DeviceCapability::Pointer));
+        assert!(!mouse.has_capability(DeviceCapability::Keyboard));
+
+        handle.key(&keyboard, 30, KeyState::Pressed);
+        handle.advance(Duration::from_millis(10));
+        handle.key(&keyboard, 31, KeyState::Pressed);
+        handle.key(&keyboard, 30, KeyState::Released);
+        handle.pointer_button(&mouse, 0x110, ButtonState::Pressed);
+        handle.pointer_motion_absolute(&mouse, (0.25, 0.5));
+        handle.pointer_axis_v120(&mouse, 0, 240);
+        handle.remove_device(&mouse);
+
+        let events = dispatch(&mut event_loop);
+        assert_eq!(events.len(), 9);
+        assert!(matches!(&events[0], InputEvent::DeviceAdded { device } if *device == keyboard));
+        assert!(matches!(&events[1], InputEvent::DeviceAdded { device } if *device == mouse));
+
+        let keys = events[2..5]
+            .iter()
+            .map(|event| match event {
+                InputEvent::Keyboard { event } => (event.time(), event.key_code(), event.count()),
+                _ => panic!("unexpected event"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![(0, 30, 1), (10_000, 31, 2), (10_000, 30, 1)]);
+
+        match &events[5] {
+            InputEvent::PointerButton { event } => {
+                assert_eq!(event.device(), mouse);
+                assert_eq!(event.button_code(), 0x110);
+                assert_eq!(event.state(), ButtonState::Pressed);
+            }
+            _ => panic!("unexpected event"),
+        }
+        match &events[6] {
+            InputEvent::PointerMotionAbsolute { event } => {
+                assert_eq!(event.x_transformed(800), 200.0);
+                assert_eq!(event.y_transformed(600), 300.0);
+            }
+            _ => panic!("unexpected event"),
+        }
+        match &events[7] {
+            InputEvent::PointerAxis { event } => {
+                assert_eq!(event.amount(Axis::Horizontal), None);
+                assert_eq!(event.amount_v120(Axis::Vertical), Some(240.0));
+                assert_eq!(event.amount(Axis::Vertical), Some(30.0));
+            }
+            _ => panic!("unexpected event"),
+        }
+        assert!(matches!(&events[8], InputEvent::DeviceRemoved { device } if *device == mouse));
+    }
+
+    #[test]
+    fn clock_and_removed_devices() {
+        let mut event_loop = EventLoop::<Vec<InputEvent<ReplayInput>>>::try_new().unwrap();
+        let backend = VirtualInputBackend::new();
+        let handle = backend.handle();
+        event_loop
+            .handle()
+            .insert_source(backend, |event, _, events| events.push(event))
+            .unwrap();
+
+        handle.set_time(Duration::from_millis(20));
+        handle.set_time(Duration::from_millis(5));
+        assert_eq!(handle.time(), Duration::from_millis(20));
+
+        let first = handle.add_device("first", &[DeviceCapability::Keyboard]);
+        let second = handle.add_device("second", &[DeviceCapability::Keyboard]);
+        handle.key(&first, 30, KeyState::Pressed);
+        handle.key(&second, 30, KeyState::Pressed);
+        handle.key(&second, 31, KeyState::Pressed);
+        handle.remove_device(&second);
+        handle.key(&first, 32, KeyState::Pressed);
+
+        let counts = dispatch(&mut event_loop)
+            .into_iter()
+            .filter_map(|event| match event {
+                InputEvent::Keyboard { event } => Some((event.time(), event.count())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![(20_000, 1), (20_000, 2), (20_000, 3), (20_000, 2)]);
+    }
+
+    #[test]
+    fn pointer_acceleration() {
+        let mut event_loop = EventLoop::<Vec<InputEvent<ReplayInput>>>::try_new().unwrap();
+        let backend = VirtualInputBackend::new();
+        let handle = backend.handle();
+        event_loop
+            .handle()
+            .insert_source(backend, |event, _, events| events.push(event))
+            .unwrap();
+
+        let mouse = handle.add_device("mouse", &[DeviceCapability::Pointer]);
+        handle.pointer_motion(&mouse, (1.0, 2.0));
+        handle.set_pointer_accelerator(&mouse, Some(PointerAccelerator::new(FlatProfile::new(1.0))));
+        handle.pointer_motion(&mouse, (1.0, 2.0));
+        handle.set_pointer_accelerator(&mouse, None);
+        handle.pointer_motion(&mouse, (1.0, 2.0));
+
+        let deltas = dispatch(&mut event_loop)
+            .into_iter()
+            .filter_map(|event| match event {
+                InputEvent::PointerMotion { event } => Some((event.delta(), event.delta_unaccel())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deltas,
+            vec![
+                ((1.0, 2.0).into(), (1.0, 2.0).into()),
+                ((2.0, 4.0).into(), (1.0, 2.0).into()),
+                ((1.0, 2.0).into(), (1.0, 2.0).into()),
+            ]
+        );
+    }
+}
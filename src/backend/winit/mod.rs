@@ -52,6 +52,7 @@ use crate::{
             Bind,
         },
     },
+    input::pointer::CursorImageStatus,
     utils::{Physical, Rectangle, Size},
 };
 
@@ -267,6 +268,29 @@ where
         &self.window
     }
 
+    /// Show the cursor on the host window
+    ///
+    /// Named cursors are shown using the cursor theme of the host. Cursor surfaces cannot be shown
+    /// by winit, so `false` is returned for them and the compositor has to draw them itself.
+    pub fn set_cursor(&self, status: &CursorImageStatus) -> bool {
+        match status {
+            CursorImageStatus::Hidden => {
+                self.window.set_cursor_visible(false);
+                true
+            }
+            CursorImageStatus::Named(icon) => {
+                self.window.set_cursor_icon(*icon);
+                self.window.set_cursor_visible(true);
+                true
+            }
+            #[cfg(feature = "wayland_frontend")]
+            CursorImageStatus::Surface(_) => {
+                self.window.set_cursor_visible(false);
+                false
+            }
+        }
+    }
+
     /// Access the underlying renderer
     pub fn renderer(&mut self) -> &mut R {
         &mut self.renderer
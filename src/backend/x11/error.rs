@@ -52,6 +52,10 @@ pub enum X11Error {
     /// Error while presenting to a window.
     #[error(transparent)]
     Present(#[from] PresentError),
+
+    /// The X server does not provide a 32-bit ARGB picture format for cursor images.
+    #[error("The X server does not provide an ARGB picture format for cursor images")]
+    NoArgbPictFormat,
 }
 
 impl From<ReplyError> for X11Error {
@@ -84,7 +84,8 @@ use crate::{
         egl::{native::X11DefaultDisplay, EGLDevice, EGLDisplay, Error as EGLError},
         input::{Axis, ButtonState, InputEvent, KeyState},
     },
-    utils::{x11rb::X11Source, Logical, Size},
+    input::pointer::CursorIcon,
+    utils::{x11rb::X11Source, Buffer, Logical, Point, Size},
 };
 use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
 use drm_fourcc::{DrmFourcc, DrmModifier};
@@ -511,6 +512,24 @@ impl Window {
         self.0.set_cursor_visible(visible);
     }
 
+    /// Sets the cursor of the window to a named cursor of the host's cursor theme.
+    pub fn set_cursor_icon(&self, icon: CursorIcon) -> Result<(), X11Error> {
+        self.0.set_cursor_icon(icon)
+    }
+
+    /// Sets the cursor of the window to an image.
+    ///
+    /// The pixels are expected in the `Argb8888` format with premultiplied alpha, without padding
+    /// between rows.
+    pub fn set_cursor_image(
+        &self,
+        pixels: &[u8],
+        size: Size<u16, Buffer>,
+        hotspot: Point<u16, Buffer>,
+    ) -> Result<(), X11Error> {
+        self.0.set_cursor_image(pixels, size, hotspot)
+    }
+
     /// Returns the XID of the window.
     pub fn id(&self) -> u32 {
         self.0.id
@@ -4,7 +4,7 @@ use std::{
 };
 
 use drm_fourcc::DrmFourcc;
-use tracing::instrument;
+use tracing::{instrument, warn};
 use x11rb::{connection::Connection, protocol::xproto::PixmapWrapper, rust_connection::RustConnection};
 
 use crate::{
@@ -15,8 +15,21 @@ use crate::{
         },
         x11::{buffer::PixmapWrapperExt, window_inner::WindowInner, AllocateBuffersError, Window},
     },
+    input::pointer::CursorImageStatus,
     utils::{Logical, Size},
 };
+#[cfg(feature = "wayland_frontend")]
+use crate::{
+    backend::{renderer::utils::RendererSurfaceStateUserData, x11::window_inner::CursorImageKey},
+    input::pointer::CursorImageSurfaceData,
+    utils::{Buffer, Point},
+    wayland::{compositor, shm},
+};
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::{
+    protocol::{wl_shm, wl_surface::WlSurface},
+    Resource,
+};
 
 use super::{WindowTemporary, X11Error};
 
@@ -121,6 +134,42 @@ impl X11Surface {
         Ok(())
     }
 
+    /// Shows the cursor on the host window.
+    ///
+    /// Named cursors are loaded from the cursor theme of the host, cursor surfaces are uploaded
+    /// to the X server, if they use shm buffers. Surfaces are only uploaded again after they
+    /// committed a new state.
+    ///
+    /// Returns `false` if the cursor cannot be shown by the host, in which case the host cursor is
+    /// hidden and the compositor has to draw the cursor itself. Otherwise the cursor does not need
+    /// to be drawn into the frame, which avoids damaging the output on every pointer motion.
+    pub fn set_cursor(&self, status: &CursorImageStatus) -> bool {
+        let Some(window) = self.window.upgrade() else {
+            return false;
+        };
+
+        let result = match status {
+            CursorImageStatus::Hidden => {
+                window.set_cursor_visible(false);
+                return true;
+            }
+            CursorImageStatus::Named(icon) => window.set_cursor_icon(*icon).map(|()| true),
+            // the image is only uploaded again, once the surface commits or the hotspot changes
+            #[cfg(feature = "wayland_frontend")]
+            CursorImageStatus::Surface(surface) => match cursor_surface_key(surface) {
+                Some(key) => window.set_cursor_surface_image(key, || cursor_surface_image(surface)),
+                None => Ok(false),
+            },
+        };
+
+        let visible = result.unwrap_or_else(|err| {
+            warn!("Failed to set the host cursor: {}", err);
+            false
+        });
+        window.set_cursor_visible(visible);
+        visible
+    }
+
     /// Resets the internal buffers.
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     pub fn reset_buffers(&mut self) {
@@ -144,3 +193,72 @@ impl X11Surface {
         self.height = size.h;
     }
 }
+
+/// Identifies the current buffer contents and hotspot of a cursor surface
+#[cfg(feature = "wayland_frontend")]
+fn cursor_surface_key(surface: &WlSurface) -> Option<CursorImageKey> {
+    compositor::with_states(surface, |states| {
+        let hotspot = states
+            .data_map
+            .get::<CursorImageSurfaceData>()
+            .map(|attributes| attributes.lock().unwrap().hotspot)
+            .unwrap_or_default();
+        let surface_state = states.data_map.get::<RendererSurfaceStateUserData>()?.borrow();
+        Some(CursorImageKey {
+            buffer: surface_state.buffer()?.id(),
+            commit: surface_state.current_commit(),
+            hotspot,
+        })
+    })
+}
+
+/// Copies the contents of a cursor surface with a `Argb8888` or `Xrgb8888` shm buffer
+#[cfg(feature = "wayland_frontend")]
+fn cursor_surface_image(surface: &WlSurface) -> Option<(Vec<u8>, Size<u16, Buffer>, Point<u16, Buffer>)> {
+    compositor::with_states(surface, |states| {
+        let hotspot = states
+            .data_map
+            .get::<CursorImageSurfaceData>()
+            .map(|attributes| attributes.lock().unwrap().hotspot)
+            .unwrap_or_default();
+        let surface_state = states.data_map.get::<RendererSurfaceStateUserData>()?.borrow();
+        let scale = surface_state.buffer_scale;
+        let buffer = surface_state.buffer()?;
+
+        shm::with_buffer_contents(buffer, |ptr, len, data| {
+            let has_alpha = match data.format {
+                wl_shm::Format::Argb8888 => true,
+                wl_shm::Format::Xrgb8888 => false,
+                _ => return None,
+            };
+            let width = u16::try_from(data.width).ok()?;
+            let height = u16::try_from(data.height).ok()?;
+            let (offset, stride) = (data.offset as usize, data.stride as usize);
+            let row_len = width as usize * 4;
+            if stride < row_len || offset + stride * height as usize > len {
+                return None;
+            }
+
+            // SAFETY: the pool is mapped for `len` bytes while in `with_buffer_contents`
+            let pool = unsafe { std::slice::from_raw_parts(ptr, len) };
+            let mut pixels = Vec::with_capacity(row_len * height as usize);
+            for row in 0..height as usize {
+                let start = offset + row * stride;
+                pixels.extend_from_slice(&pool[start..start + row_len]);
+            }
+            if !has_alpha {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel[3] = 0xff;
+                }
+            }
+
+            let hotspot = (
+                (hotspot.x * scale).clamp(0, width.saturating_sub(1) as i32) as u16,
+                (hotspot.y * scale).clamp(0, height.saturating_sub(1) as i32) as u16,
+            );
+            Some((pixels, (width, height).into(), hotspot.into()))
+        })
+        .ok()
+        .flatten()
+    })
+}
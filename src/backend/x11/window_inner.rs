@@ -9,27 +9,42 @@ Pay particular attention to "Section 4: Client to Window Manager Communication"
 
 A link to the ICCCM Section 4: https://tronche.com/gui/x/icccm/sec-4.html
 */
-use crate::utils::{Logical, Size};
+use crate::{
+    input::pointer::CursorIcon,
+    utils::{Buffer, Logical, Point, Size},
+};
 
 use super::{extension::Extensions, Atoms, Window, X11Error};
+#[cfg(feature = "wayland_frontend")]
+use crate::backend::renderer::utils::CommitCounter;
 use drm_fourcc::DrmFourcc;
-use std::sync::{
-    atomic::{AtomicU32, AtomicU64},
-    mpsc::Sender,
-    Arc, Mutex, Weak,
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, AtomicU64},
+        mpsc::Sender,
+        Arc, Mutex, Weak,
+    },
 };
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::backend::ObjectId;
 use x11rb::{
     connection::Connection,
+    cursor,
     protocol::{
         present::{self, ConnectionExt as _},
+        render::{self, ConnectionExt as _, PictType},
         xfixes::ConnectionExt as _,
         xproto::{
-            self as x11, AtomEnum, ConnectionExt, CreateWindowAux, Depth, EventMask, PropMode, Screen,
-            UnmapNotifyEvent, WindowClass,
+            self as x11, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, CreateWindowAux, Depth,
+            EventMask, GcontextWrapper, ImageFormat, PixmapWrapper, PropMode, Screen, UnmapNotifyEvent,
+            WindowClass,
         },
     },
+    resource_manager,
     rust_connection::RustConnection,
     wrapper::ConnectionExt as _,
+    NONE,
 };
 
 impl From<Arc<WindowInner>> for Window {
@@ -53,6 +68,28 @@ impl Default for CursorState {
     }
 }
 
+/// Cursors created on the host for the window
+#[derive(Debug, Default)]
+pub struct HostCursors {
+    handle: Option<cursor::Handle>,
+    named: HashMap<CursorIcon, x11::Cursor>,
+    /// Cursor created from the last image, if any
+    image: Option<x11::Cursor>,
+    /// Cursor surface contents the image was created from
+    #[cfg(feature = "wayland_frontend")]
+    image_key: Option<CursorImageKey>,
+    argb_format: Option<render::Pictformat>,
+}
+
+/// Identifies the contents of a cursor surface, to avoid uploading the same image again
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorImageKey {
+    pub buffer: ObjectId,
+    pub commit: CommitCounter,
+    pub hotspot: Point<i32, Logical>,
+}
+
 #[derive(Debug)]
 pub(crate) struct WindowInner {
     pub connection: Weak<RustConnection>,
@@ -60,6 +97,7 @@ pub(crate) struct WindowInner {
     root: x11::Window,
     pub atoms: Atoms,
     pub cursor_state: Arc<Mutex<CursorState>>,
+    pub host_cursors: Mutex<HostCursors>,
     pub size: Mutex<Size<u16, Logical>>,
     /// Channel used to send resize notifications to the surface that presents to this window.
     ///
@@ -154,6 +192,7 @@ impl WindowInner {
             root: screen.root,
             atoms,
             cursor_state: Arc::new(Mutex::new(CursorState::default())),
+            host_cursors: Mutex::new(HostCursors::default()),
             size: Mutex::new(size),
             next_serial: AtomicU32::new(0),
             last_msc: Arc::new(AtomicU64::new(0)),
@@ -249,14 +288,164 @@ impl WindowInner {
         if let Some(connection) = self.connection.upgrade() {
             let mut state = self.cursor_state.lock().unwrap();
             let changed = state.visible != visible;
+            state.visible = visible;
 
             if changed && state.inside_window {
-                state.visible = visible;
                 self.update_cursor(&*connection, state.visible);
             }
         }
     }
 
+    pub fn set_cursor_icon(&self, icon: CursorIcon) -> Result<(), X11Error> {
+        let connection = self.connection.upgrade().ok_or(X11Error::ConnectionLost)?;
+        let mut cursors = self.host_cursors.lock().unwrap();
+
+        let cursor = match cursors.named.get(&icon) {
+            Some(cursor) => *cursor,
+            None => {
+                if cursors.handle.is_none() {
+                    let screen = connection
+                        .setup()
+                        .roots
+                        .iter()
+                        .position(|screen| screen.root == self.root)
+                        .unwrap_or(0);
+                    let database = resource_manager::new_from_default(&*connection)?;
+                    cursors.handle = Some(cursor::Handle::new(&*connection, screen, &database)?.reply()?);
+                }
+                let handle = cursors.handle.as_ref().unwrap();
+
+                // Fall back to the alternative names used by older cursor themes
+                let mut cursor = NONE;
+                for name in std::iter::once(icon.name()).chain(icon.alt_names().iter().copied()) {
+                    cursor = handle.load_cursor(&*connection, name)?;
+                    if cursor != NONE {
+                        break;
+                    }
+                }
+                cursors.named.insert(icon, cursor);
+                cursor
+            }
+        };
+
+        self.set_window_cursor(&connection, &mut cursors, cursor)
+    }
+
+    pub fn set_cursor_image(
+        &self,
+        pixels: &[u8],
+        size: Size<u16, Buffer>,
+        hotspot: Point<u16, Buffer>,
+    ) -> Result<(), X11Error> {
+        let connection = self.connection.upgrade().ok_or(X11Error::ConnectionLost)?;
+        let mut cursors = self.host_cursors.lock().unwrap();
+        self.upload_cursor_image(&connection, &mut cursors, pixels, size, hotspot)
+    }
+
+    /// Sets the cursor to the image returned by `image`, unless the current cursor was already
+    /// created from the contents identified by `key`.
+    ///
+    /// Returns `false` if `image` returns `None`.
+    #[cfg(feature = "wayland_frontend")]
+    pub fn set_cursor_surface_image<F>(&self, key: CursorImageKey, image: F) -> Result<bool, X11Error>
+    where
+        F: FnOnce() -> Option<(Vec<u8>, Size<u16, Buffer>, Point<u16, Buffer>)>,
+    {
+        let connection = self.connection.upgrade().ok_or(X11Error::ConnectionLost)?;
+        let mut cursors = self.host_cursors.lock().unwrap();
+        if cursors.image.is_some() && cursors.image_key.as_ref() == Some(&key) {
+            return Ok(true);
+        }
+
+        let Some((pixels, size, hotspot)) = image() else {
+            return Ok(false);
+        };
+        self.upload_cursor_image(&connection, &mut cursors, &pixels, size, hotspot)?;
+        cursors.image_key = Some(key);
+        Ok(true)
+    }
+
+    fn upload_cursor_image(
+        &self,
+        connection: &RustConnection,
+        cursors: &mut HostCursors,
+        pixels: &[u8],
+        size: Size<u16, Buffer>,
+        hotspot: Point<u16, Buffer>,
+    ) -> Result<(), X11Error> {
+        let format = match cursors.argb_format {
+            Some(format) => format,
+            None => {
+                let formats = connection.render_query_pict_formats()?.reply()?;
+                let format = formats
+                    .formats
+                    .iter()
+                    .find(|format| {
+                        format.type_ == PictType::DIRECT
+                            && format.depth == 32
+                            && format.direct.alpha_shift == 24
+                            && format.direct.alpha_mask == 0xff
+                            && format.direct.red_shift == 16
+                            && format.direct.red_mask == 0xff
+                            && format.direct.green_shift == 8
+                            && format.direct.green_mask == 0xff
+                            && format.direct.blue_shift == 0
+                            && format.direct.blue_mask == 0xff
+                    })
+                    .map(|format| format.id)
+                    .ok_or(X11Error::NoArgbPictFormat)?;
+                cursors.argb_format = Some(format);
+                format
+            }
+        };
+
+        // Upload the image into a pixmap and turn that into a cursor
+        let pixmap = PixmapWrapper::create_pixmap(connection, 32, self.root, size.w, size.h)?;
+        let gc = GcontextWrapper::create_gc(connection, pixmap.pixmap(), &Default::default())?;
+        connection.put_image(
+            ImageFormat::Z_PIXMAP,
+            pixmap.pixmap(),
+            gc.gcontext(),
+            size.w,
+            size.h,
+            0,
+            0,
+            0,
+            32,
+            pixels,
+        )?;
+        let picture =
+            render::PictureWrapper::create_picture(connection, pixmap.pixmap(), format, &Default::default())?;
+        let cursor = connection.generate_id()?;
+        connection.render_create_cursor(cursor, picture.picture(), hotspot.x, hotspot.y)?;
+
+        if let Err(err) = self.set_window_cursor(connection, cursors, cursor) {
+            let _ = connection.free_cursor(cursor);
+            return Err(err);
+        }
+        cursors.image = Some(cursor);
+        Ok(())
+    }
+
+    fn set_window_cursor(
+        &self,
+        connection: &RustConnection,
+        cursors: &mut HostCursors,
+        cursor: x11::Cursor,
+    ) -> Result<(), X11Error> {
+        connection.change_window_attributes(self.id, &ChangeWindowAttributesAux::new().cursor(cursor))?;
+        // The server keeps the cursor alive as long as it is in use
+        #[cfg(feature = "wayland_frontend")]
+        {
+            cursors.image_key = None;
+        }
+        if let Some(image) = cursors.image.take() {
+            connection.free_cursor(image)?;
+        }
+        connection.flush()?;
+        Ok(())
+    }
+
     pub fn cursor_enter(&self) {
         if let Some(connection) = self.connection.upgrade() {
             let mut state = self.cursor_state.lock().unwrap();
@@ -300,6 +489,12 @@ impl PartialEq for WindowInner {
 impl Drop for WindowInner {
     fn drop(&mut self) {
         if let Some(connection) = self.connection.upgrade() {
+            let cursors = self.host_cursors.get_mut().unwrap();
+            for cursor in cursors.named.values().chain(cursors.image.iter()) {
+                if *cursor != NONE {
+                    let _ = connection.free_cursor(*cursor);
+                }
+            }
             let _ = connection.destroy_window(self.id);
         }
     }
@@ -0,0 +1,653 @@
+//! Helpers for animating desktop elements
+//!
+//! An [`Animation`] interpolates between two values over time. Time is measured
+//! with the [`Monotonic`] clock, so the current value is queried by passing the
+//! result of [`Clock::now`](crate::utils::Clock::now), usually once per frame.
+//!
+//! The progress of an animation is controlled by a [`Curve`], which is either
+//! an [`Easing`] function with a fixed duration or a physically based [`Spring`].
+//! Springs may overshoot their target, which is why values are also interpolated
+//! outside of the range between the start and end values.
+//!
+//! Values implementing [`Interpolate`] can be animated. This includes floating point
+//! values like alpha, as well as [`Point`]s, [`Size`]s and [`Rectangle`]s.
+//!
+//! ## Integration with [`Space`](crate::desktop::Space)
+//!
+//! An [`ElementAnimation`] animates the alpha, scale and location of a single element
+//! in a [`Space`](crate::desktop::Space), see [`Space::animate_element`](crate::desktop::Space::animate_element).
+//! To animate a window out, [`Space::unmap_elem_animated`](crate::desktop::Space::unmap_elem_animated)
+//! keeps the last frame of an element in a [`ClosingSnapshots`](crate::desktop::space::ClosingSnapshots)
+//! until its animation is finished. Scaled elements and these frames are only rendered by
+//! [`space_render_elements_with_snapshots`](crate::desktop::space::space_render_elements_with_snapshots)
+//! and [`render_output_with_snapshots`](crate::desktop::space::render_output_with_snapshots).
+//!
+//! Animations in a [`Space`](crate::desktop::Space) advance when calling
+//! [`Space::update_animations`](crate::desktop::Space::update_animations), which
+//! also tells if another frame needs to be rendered.
+//!
+//! ```
+//! use std::time::Duration;
+//! use smithay::desktop::animation::{Animation, Curve, Easing};
+//! use smithay::utils::{Clock, Monotonic};
+//!
+//! let clock = Clock::<Monotonic>::new();
+//! let fade_in = Animation::new(0.0f32, 1.0, clock.now(), Curve::Easing(Easing::EaseOutCubic, Duration::from_millis(150)));
+//!
+//! // while rendering a frame
+//! let alpha = fade_in.value(clock.now());
+//! if !fade_in.is_done(clock.now()) {
+//!     // schedule another frame
+//! }
+//! ```
+
+use std::time::Duration;
+
+use crate::utils::{Coordinate, Logical, Monotonic, Point, Rectangle, Size, Time};
+
+/// Easing functions mapping the linear progress of an animation to the displayed progress
+///
+/// See <https://easings.net> for a visualization of most of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Quadratic acceleration from zero velocity
+    EaseInQuad,
+    /// Quadratic deceleration to zero velocity
+    EaseOutQuad,
+    /// Quadratic acceleration until halfway, then deceleration
+    EaseInOutQuad,
+    /// Cubic acceleration from zero velocity
+    EaseInCubic,
+    /// Cubic deceleration to zero velocity
+    EaseOutCubic,
+    /// Cubic acceleration until halfway, then deceleration
+    EaseInOutCubic,
+    /// Exponential deceleration to zero velocity
+    EaseOutExpo,
+    /// Cubic bézier curve from `(0, 0)` to `(1, 1)` with the two given control points,
+    /// like the `cubic-bezier` timing function of CSS
+    ///
+    /// The x coordinates of the control points have to be within `0..=1`.
+    CubicBezier {
+        /// x coordinate of the first control point
+        x1: f64,
+        /// y coordinate of the first control point
+        y1: f64,
+        /// x coordinate of the second control point
+        x2: f64,
+        /// y coordinate of the second control point
+        y2: f64,
+    },
+}
+
+impl Easing {
+    /// Apply the easing to a linear progress in the range `0..=1`
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t).powi(2),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::EaseInCubic => t.powi(3),
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::EaseOutExpo => {
+                if t >= 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2f64.powf(-10.0 * t)
+                }
+            }
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                let s = bezier_solve_x(t, x1, x2);
+                bezier(s, y1, y2)
+            }
+        }
+    }
+}
+
+// coordinate of a cubic bézier curve from 0 to 1 with the control points p1 and p2
+fn bezier(s: f64, p1: f64, p2: f64) -> f64 {
+    let r = 1.0 - s;
+    3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+}
+
+fn bezier_derivative(s: f64, p1: f64, p2: f64) -> f64 {
+    let r = 1.0 - s;
+    3.0 * r * r * p1 + 6.0 * r * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+}
+
+// finds the curve parameter for a given x coordinate
+fn bezier_solve_x(x: f64, x1: f64, x2: f64) -> f64 {
+    const EPSILON: f64 = 1e-7;
+
+    // newton's method converges quickly for most curves
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(s, x1, x2) - x;
+        if error.abs() < EPSILON {
+            return s;
+        }
+        let derivative = bezier_derivative(s, x1, x2);
+        if derivative.abs() < EPSILON {
+            break;
+        }
+        s -= error / derivative;
+    }
+
+    // fall back to bisection, x is monotonic for control points within 0..=1
+    let (mut low, mut high) = (0.0, 1.0);
+    s = x;
+    while high - low > EPSILON {
+        if bezier(s, x1, x2) < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    s
+}
+
+/// A damped spring, moving the progress of an animation towards its end
+///
+/// The spring starts at rest, so the animation starts slowly, and may overshoot
+/// the end value depending on the damping ratio:
+/// - A damping ratio below `1.0` results in an oscillation around the end value
+/// - A damping ratio of `1.0` reaches the end value as fast as possible without overshooting
+/// - A damping ratio above `1.0` approaches the end value more slowly
+///
+/// A higher stiffness makes the spring faster.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    damping_ratio: f64,
+    stiffness: f64,
+    epsilon: f64,
+}
+
+impl Default for Spring {
+    /// A critically damped spring, settling in about a third of a second
+    fn default() -> Self {
+        Spring::new(1.0, 800.0)
+    }
+}
+
+impl Spring {
+    /// Create a new spring with the given damping ratio and stiffness
+    ///
+    /// # Panics
+    ///
+    /// Panics if the damping ratio or the stiffness are not positive
+    pub fn new(damping_ratio: f64, stiffness: f64) -> Self {
+        assert!(
+            damping_ratio > 0.0 && stiffness > 0.0,
+            "damping ratio and stiffness of a spring have to be positive"
+        );
+        Spring {
+            damping_ratio,
+            stiffness,
+            epsilon: 0.001,
+        }
+    }
+
+    /// Set the distance to the end value, at which the spring is considered to be at rest
+    ///
+    /// The distance is relative to the distance between the start and end values.
+    /// Defaults to `0.001`.
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon.abs().max(f64::EPSILON);
+        self
+    }
+
+    /// Returns the damping ratio of the spring
+    pub fn damping_ratio(&self) -> f64 {
+        self.damping_ratio
+    }
+
+    /// Returns the stiffness of the spring
+    pub fn stiffness(&self) -> f64 {
+        self.stiffness
+    }
+
+    /// Returns the progress of the spring after some time
+    ///
+    /// This is `0.0` at the start and converges to `1.0`.
+    pub fn progress(&self, elapsed: Duration) -> f64 {
+        1.0 - self.displacement(elapsed.as_secs_f64())
+    }
+
+    /// Returns the time until the spring comes to rest
+    pub fn duration(&self) -> Duration {
+        let omega = self.stiffness.sqrt();
+        let zeta = self.damping_ratio;
+
+        let secs = if zeta < 1.0 {
+            // the oscillation is bounded by an exponentially decaying envelope
+            let amplitude = 1.0 / (1.0 - zeta * zeta).sqrt();
+            (amplitude / self.epsilon).ln() / (zeta * omega)
+        } else {
+            // without oscillation the displacement decreases monotonically
+            let mut high = 1.0 / omega;
+            while self.displacement(high) > self.epsilon {
+                high *= 2.0;
+            }
+            let mut low = 0.0;
+            while high - low > 1e-4 {
+                let mid = (low + high) / 2.0;
+                if self.displacement(mid) > self.epsilon {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            high
+        };
+
+        Duration::from_secs_f64(secs.max(0.0))
+    }
+
+    // displacement from the end value of a spring starting at 1.0 at rest
+    fn displacement(&self, t: f64) -> f64 {
+        let omega = self.stiffness.sqrt();
+        let zeta = self.damping_ratio;
+
+        if (zeta - 1.0).abs() < 1e-6 {
+            (1.0 + omega * t) * (-omega * t).exp()
+        } else if zeta < 1.0 {
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            (-zeta * omega * t).exp() * ((omega_d * t).cos() + zeta * omega / omega_d * (omega_d * t).sin())
+        } else {
+            let root = (zeta * zeta - 1.0).sqrt();
+            let r1 = -omega * (zeta - root);
+            let r2 = -omega * (zeta + root);
+            (r2 * (r1 * t).exp() - r1 * (r2 * t).exp()) / (r2 - r1)
+        }
+    }
+}
+
+/// Describes how the progress of an [`Animation`] develops over time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    /// An easing function applied over a fixed duration
+    Easing(Easing, Duration),
+    /// A spring, taking as long as it needs to come to rest
+    Spring(Spring),
+}
+
+impl Curve {
+    /// Returns the duration of the curve
+    pub fn duration(&self) -> Duration {
+        match self {
+            Curve::Easing(_, duration) => *duration,
+            Curve::Spring(spring) => spring.duration(),
+        }
+    }
+
+    /// Returns the progress after some time
+    ///
+    /// The progress starts at `0.0` and ends at `1.0`, but may exceed
+    /// this range in between, e.g. for springs.
+    pub fn progress(&self, elapsed: Duration) -> f64 {
+        match self {
+            Curve::Easing(easing, duration) => {
+                if elapsed >= *duration {
+                    1.0
+                } else {
+                    easing.apply(elapsed.as_secs_f64() / duration.as_secs_f64())
+                }
+            }
+            Curve::Spring(spring) => {
+                if elapsed >= spring.duration() {
+                    1.0
+                } else {
+                    spring.progress(elapsed)
+                }
+            }
+        }
+    }
+}
+
+/// Values that can be interpolated by an [`Animation`]
+pub trait Interpolate: Sized {
+    /// Interpolate between `self` and `to`
+    ///
+    /// A progress of `0.0` returns `self`, a progress of `1.0` returns `to`.
+    /// Values outside of this range extrapolate beyond the two values.
+    fn interpolate(&self, to: &Self, progress: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, to: &Self, progress: f64) -> Self {
+        self + (to - self) * progress
+    }
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, to: &Self, progress: f64) -> Self {
+        (*self as f64).interpolate(&(*to as f64), progress) as f32
+    }
+}
+
+impl Interpolate for i32 {
+    fn interpolate(&self, to: &Self, progress: f64) -> Self {
+        (*self as f64).interpolate(&(*to as f64), progress).round() as i32
+    }
+}
+
+impl<N: Coordinate + Interpolate, Kind> Interpolate for Point<N, Kind> {
+    fn interpolate(&self, to: &Self, progress: f64) -> Self {
+        Point::from((
+            self.x.interpolate(&to.x, progress),
+            self.y.interpolate(&to.y, progress),
+        ))
+    }
+}
+
+impl<N: Coordinate + Interpolate, Kind> Interpolate for Size<N, Kind> {
+    fn interpolate(&self, to: &Self, progress: f64) -> Self {
+        // overshooting may not result in a negative size
+        Size::from((
+            self.w.interpolate(&to.w, progress).max(N::default()),
+            self.h.interpolate(&to.h, progress).max(N::default()),
+        ))
+    }
+}
+
+impl<N: Coordinate + Interpolate, Kind> Interpolate for Rectangle<N, Kind> {
+    fn interpolate(&self, to: &Self, progress: f64) -> Self {
+        Rectangle::from_loc_and_size(
+            self.loc.interpolate(&to.loc, progress),
+            self.size.interpolate(&to.size, progress),
+        )
+    }
+}
+
+/// Animation of a value between a start and an end value
+#[derive(Debug, Clone)]
+pub struct Animation<T> {
+    from: T,
+    to: T,
+    start: Time<Monotonic>,
+    curve: Curve,
+}
+
+impl<T> Animation<T> {
+    /// Create a new animation from `from` to `to`, starting at the given time
+    pub fn new(from: T, to: T, start: Time<Monotonic>, curve: Curve) -> Self {
+        Animation {
+            from,
+            to,
+            start,
+            curve,
+        }
+    }
+
+    /// Returns the start value
+    pub fn from(&self) -> &T {
+        &self.from
+    }
+
+    /// Returns the end value
+    pub fn to(&self) -> &T {
+        &self.to
+    }
+
+    /// Returns the start time
+    pub fn start(&self) -> Time<Monotonic> {
+        self.start
+    }
+
+    /// Returns the curve of the animation
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    /// Returns the progress of the animation at the given time
+    ///
+    /// See [`Curve::progress`].
+    pub fn progress(&self, time: Time<Monotonic>) -> f64 {
+        self.curve.progress(self.elapsed(time))
+    }
+
+    /// Returns whether the animation is finished at the given time
+    pub fn is_done(&self, time: Time<Monotonic>) -> bool {
+        self.elapsed(time) >= self.curve.duration()
+    }
+
+    fn elapsed(&self, time: Time<Monotonic>) -> Duration {
+        if time <= self.start {
+            Duration::ZERO
+        } else {
+            Time::elapsed(&self.start, time)
+        }
+    }
+}
+
+impl<T: Interpolate> Animation<T> {
+    /// Returns the value at the given time
+    ///
+    /// Times before the start of the animation return the start value,
+    /// times after its end return the end value.
+    pub fn value(&self, time: Time<Monotonic>) -> T {
+        self.from.interpolate(&self.to, self.progress(time))
+    }
+
+    /// Create a new animation to another end value, starting at the current value
+    ///
+    /// This allows to smoothly change the target of a running animation,
+    /// e.g. when a window is moved again before the last move finished.
+    pub fn retarget(&self, time: Time<Monotonic>, to: T) -> Self {
+        Animation {
+            from: self.value(time),
+            to,
+            start: time,
+            curve: self.curve,
+        }
+    }
+}
+
+/// Animation of an element in a [`Space`](crate::desktop::Space)
+///
+/// All parts are optional and are applied on top of the state of the element.
+/// Once finished the element is displayed without any modifications again,
+/// so for mapped elements the animations should end at their neutral value.
+#[derive(Debug, Clone, Default)]
+pub struct ElementAnimation {
+    /// Alpha multiplied with the alpha of the element
+    pub alpha: Option<Animation<f32>>,
+    /// Scale applied around the center of the element
+    pub scale: Option<Animation<f64>>,
+    /// Offset added to the location of the element
+    pub offset: Option<Animation<Point<f64, Logical>>>,
+}
+
+impl ElementAnimation {
+    /// Animation fading an element in
+    pub fn fade_in(start: Time<Monotonic>, curve: Curve) -> Self {
+        ElementAnimation {
+            alpha: Some(Animation::new(0.0, 1.0, start, curve)),
+            ..Default::default()
+        }
+    }
+
+    /// Animation fading an element out
+    pub fn fade_out(start: Time<Monotonic>, curve: Curve) -> Self {
+        ElementAnimation {
+            alpha: Some(Animation::new(1.0, 0.0, start, curve)),
+            ..Default::default()
+        }
+    }
+
+    /// Animation moving an element from a previous location to its current one
+    pub fn moved_from(
+        previous: Point<i32, Logical>,
+        current: Point<i32, Logical>,
+        start: Time<Monotonic>,
+        curve: Curve,
+    ) -> Self {
+        ElementAnimation {
+            offset: Some(Animation::new(
+                (previous - current).to_f64(),
+                Point::default(),
+                start,
+                curve,
+            )),
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether all parts of the animation are finished at the given time
+    pub fn is_done(&self, time: Time<Monotonic>) -> bool {
+        self.alpha.as_ref().map(|a| a.is_done(time)).unwrap_or(true)
+            && self.scale.as_ref().map(|a| a.is_done(time)).unwrap_or(true)
+            && self.offset.as_ref().map(|a| a.is_done(time)).unwrap_or(true)
+    }
+
+    pub(crate) fn state(&self, time: Time<Monotonic>) -> AnimationState {
+        AnimationState {
+            alpha: self
+                .alpha
+                .as_ref()
+                .map(|a| a.value(time).clamp(0.0, 1.0))
+                .unwrap_or(1.0),
+            scale: self.scale.as_ref().map(|a| a.value(time).max(0.0)).unwrap_or(1.0),
+            offset: self.offset.as_ref().map(|a| a.value(time)).unwrap_or_default(),
+        }
+    }
+}
+
+// values of an `ElementAnimation` at a specific time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AnimationState {
+    pub alpha: f32,
+    pub scale: f64,
+    pub offset: Point<f64, Logical>,
+}
+
+impl Default for AnimationState {
+    fn default() -> Self {
+        AnimationState {
+            alpha: 1.0,
+            scale: 1.0,
+            offset: Point::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(millis: u64) -> Time<Monotonic> {
+        Time::from(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn easing_bounds() {
+        let easings = [
+            Easing::Linear,
+            Easing::EaseInQuad,
+            Easing::EaseOutQuad,
+            Easing::EaseInOutQuad,
+            Easing::EaseInCubic,
+            Easing::EaseOutCubic,
+            Easing::EaseInOutCubic,
+            Easing::EaseOutExpo,
+            Easing::CubicBezier {
+                x1: 0.25,
+                y1: 0.1,
+                x2: 0.25,
+                y2: 1.0,
+            },
+        ];
+        for easing in easings {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", easing);
+            let mut last = 0.0;
+            for i in 1..=100 {
+                let value = easing.apply(i as f64 / 100.0);
+                assert!(value >= last - 1e-9, "{:?} is not monotonic", easing);
+                last = value;
+            }
+        }
+    }
+
+    #[test]
+    fn cubic_bezier_matches_linear() {
+        let easing = Easing::CubicBezier {
+            x1: 1.0 / 3.0,
+            y1: 1.0 / 3.0,
+            x2: 2.0 / 3.0,
+            y2: 2.0 / 3.0,
+        };
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!((easing.apply(t) - t).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn spring_settles() {
+        for damping_ratio in [0.3, 1.0, 2.0] {
+            let spring = Spring::new(damping_ratio, 400.0);
+            let duration = spring.duration();
+            assert!(duration > Duration::ZERO);
+            assert!((spring.progress(duration) - 1.0).abs() <= 0.001 + 1e-9);
+            assert_eq!(spring.progress(Duration::ZERO), 0.0);
+        }
+
+        // only underdamped springs overshoot
+        let overshoots = |spring: Spring| {
+            (0..1000).any(|millis| spring.progress(Duration::from_millis(millis)) > 1.0 + 1e-9)
+        };
+        assert!(overshoots(Spring::new(0.3, 400.0)));
+        assert!(!overshoots(Spring::new(1.0, 400.0)));
+        assert!(!overshoots(Spring::new(2.0, 400.0)));
+    }
+
+    #[test]
+    fn animation_values() {
+        let curve = Curve::Easing(Easing::Linear, Duration::from_millis(100));
+        let animation = Animation::new(
+            Rectangle::<i32, Logical>::from_loc_and_size((0, 0), (10, 10)),
+            Rectangle::from_loc_and_size((100, -50), (20, 30)),
+            time(1000),
+            curve,
+        );
+
+        assert_eq!(animation.value(time(500)), *animation.from());
+        assert_eq!(
+            animation.value(time(1050)),
+            Rectangle::from_loc_and_size((50, -25), (15, 20))
+        );
+        assert_eq!(animation.value(time(2000)), *animation.to());
+        assert!(!animation.is_done(time(1099)));
+        assert!(animation.is_done(time(1100)));
+
+        let retargeted = animation
+            .retarget(time(1050), Rectangle::from_loc_and_size((0, 0), (15, 20)))
+            .value(time(1100));
+        assert_eq!(retargeted, Rectangle::from_loc_and_size((25, -13), (15, 20)));
+    }
+
+    #[test]
+    fn overshooting_size_is_not_negative() {
+        let size = Size::<f64, Logical>::from((10.0, 10.0)).interpolate(&(0.0, 0.0).into(), 1.5);
+        assert_eq!(size, Size::from((0.0, 0.0)));
+    }
+}
@@ -0,0 +1,442 @@
+//! XCursor theme helpers
+//!
+//! Clients may ask the compositor to display one of the well-known cursor shapes
+//! (see [`CursorImageStatus::Named`](crate::input::pointer::CursorImageStatus::Named)) instead
+//! of providing a cursor surface. The [`CursorThemeManager`] resolves these [`CursorIcon`]s from an
+//! XCursor theme, including the legacy names used by older themes, and provides ready-to-draw
+//! render elements for them.
+//!
+//! ```no_run
+//! # use smithay::{
+//! #     backend::renderer::{ImportMem, Renderer},
+//! #     desktop::cursor::CursorThemeManager,
+//! #     input::pointer::CursorIcon,
+//! #     utils::{Physical, Point},
+//! # };
+//! # fn render<R: Renderer + ImportMem>(renderer: &mut R, pointer_location: Point<f64, Physical>)
+//! # where <R as Renderer>::TextureId: 'static
+//! # {
+//! let mut cursors = CursorThemeManager::from_env();
+//! # let time = std::time::Duration::ZERO;
+//! let element = cursors
+//!     .render_element(renderer, CursorIcon::Pointer, pointer_location, 2.0, time, 1.0)
+//!     .unwrap();
+//! // render the element, and schedule a redraw for animated cursors
+//! let next_frame = cursors.time_until_next_frame(CursorIcon::Pointer, 2.0, time);
+//! # }
+//! ```
+//!
+//! This module is gated by the `cursor_theme` cargo feature.
+
+use std::{collections::HashMap, io, time::Duration};
+
+use once_cell::unsync::OnceCell;
+use tracing::{debug, warn};
+use xcursor::{parser::parse_xcursor, CursorTheme};
+
+pub use cursor_icon::CursorIcon;
+pub use xcursor::parser::Image;
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                Kind,
+            },
+            ImportMem, Renderer,
+        },
+    },
+    utils::{Logical, Physical, Point, Size, Transform},
+};
+
+/// Theme used if `XCURSOR_THEME` is not set
+const DEFAULT_THEME: &str = "default";
+/// Nominal cursor size used if `XCURSOR_SIZE` is not set
+const DEFAULT_SIZE: u32 = 24;
+
+/// Errors that can occur when loading a cursor from a theme
+#[derive(Debug, thiserror::Error)]
+pub enum CursorThemeError {
+    /// The theme does not contain a cursor of that name
+    #[error("The cursor theme has no cursor named {0}")]
+    NotFound(String),
+    /// The cursor file could not be read
+    #[error("Failed to read the cursor file")]
+    Io(#[from] io::Error),
+    /// The cursor file is not a valid XCursor file
+    #[error("Failed to parse the XCursor file")]
+    Parse,
+}
+
+/// A single image of an XCursor
+#[derive(Debug)]
+pub struct CursorFrame {
+    image: Image,
+    buffer: OnceCell<MemoryRenderBuffer>,
+}
+
+impl CursorFrame {
+    /// Nominal size of the cursor this image was designed for
+    pub fn nominal_size(&self) -> u32 {
+        self.image.size
+    }
+
+    /// Size of the image in pixels
+    pub fn size(&self) -> Size<i32, Physical> {
+        (self.image.width as i32, self.image.height as i32).into()
+    }
+
+    /// Hotspot of the image in pixels, relative to its top-left corner
+    pub fn hotspot(&self) -> Point<i32, Physical> {
+        (self.image.xhot as i32, self.image.yhot as i32).into()
+    }
+
+    /// Duration this image is shown for in an animation
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.image.delay as u64)
+    }
+
+    /// The raw image
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Buffer containing the pixels of this image
+    ///
+    /// The buffer is created on first access and then reused.
+    pub fn buffer(&self) -> &MemoryRenderBuffer {
+        self.buffer.get_or_init(|| {
+            MemoryRenderBuffer::from_slice(
+                &self.image.pixels_rgba,
+                Fourcc::Argb8888,
+                self.size().to_logical(1).to_buffer(1, Transform::Normal),
+                1,
+                Transform::Normal,
+                None,
+            )
+        })
+    }
+}
+
+/// A cursor loaded from an XCursor file
+///
+/// A cursor usually contains images for several nominal sizes, and one or more
+/// animation frames per nominal size.
+#[derive(Debug)]
+pub struct XCursor {
+    frames: Vec<CursorFrame>,
+}
+
+impl XCursor {
+    /// Create a cursor from a set of images
+    ///
+    /// Returns `None` if `images` is empty.
+    pub fn new(images: Vec<Image>) -> Option<XCursor> {
+        if images.is_empty() {
+            return None;
+        }
+        Some(XCursor {
+            frames: images
+                .into_iter()
+                .map(|image| CursorFrame {
+                    image,
+                    buffer: OnceCell::new(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Load a cursor from the contents of an XCursor file
+    pub fn from_bytes(data: &[u8]) -> Result<XCursor, CursorThemeError> {
+        parse_xcursor(data)
+            .and_then(XCursor::new)
+            .ok_or(CursorThemeError::Parse)
+    }
+
+    /// Nominal size of the images closest to the requested nominal size
+    pub fn nearest_size(&self, size: u32) -> u32 {
+        self.frames
+            .iter()
+            .map(|frame| frame.image.size)
+            .min_by_key(|&nominal| (size as i64 - nominal as i64).abs())
+            .unwrap()
+    }
+
+    /// Animation frames of the images closest to the requested nominal size
+    pub fn frames(&self, size: u32) -> impl Iterator<Item = &CursorFrame> {
+        let nearest = self.nearest_size(size);
+        self.frames
+            .iter()
+            .filter(move |frame| frame.image.size == nearest)
+    }
+
+    /// Whether the cursor is animated at the requested nominal size
+    pub fn is_animated(&self, size: u32) -> bool {
+        self.frames(size).nth(1).is_some()
+    }
+
+    /// Frame to display at the given time of the animation
+    ///
+    /// `time` may be any monotonic timestamp, the animation is looped.
+    pub fn frame(&self, size: u32, time: Duration) -> &CursorFrame {
+        self.frame_at(size, time).0
+    }
+
+    /// Time left until the next frame of the animation, `None` for static cursors
+    pub fn time_until_next_frame(&self, size: u32, time: Duration) -> Option<Duration> {
+        if !self.is_animated(size) {
+            return None;
+        }
+        Some(self.frame_at(size, time).1).filter(|delay| !delay.is_zero())
+    }
+
+    // returns the frame and the time left until the next one
+    fn frame_at(&self, size: u32, time: Duration) -> (&CursorFrame, Duration) {
+        let total = self
+            .frames(size)
+            .map(|frame| frame.image.delay as u64)
+            .sum::<u64>();
+        let mut millis = match total {
+            0 => 0,
+            total => time.as_millis() as u64 % total,
+        };
+
+        for frame in self.frames(size) {
+            let delay = frame.image.delay as u64;
+            if millis < delay {
+                return (frame, Duration::from_millis(delay - millis));
+            }
+            millis -= delay;
+        }
+
+        // all delays are zero
+        (self.frames(size).next().unwrap(), Duration::ZERO)
+    }
+}
+
+/// Loader and cache for the cursors of an XCursor theme
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct CursorThemeManager {
+    theme: CursorTheme,
+    theme_name: String,
+    size: u32,
+    cursors: HashMap<CursorIcon, Option<XCursor>>,
+    fallback: Option<XCursor>,
+}
+
+impl CursorThemeManager {
+    /// Create a manager for the given theme and nominal cursor size in logical pixels
+    pub fn new(theme: impl Into<String>, size: u32) -> CursorThemeManager {
+        let theme_name = theme.into();
+        CursorThemeManager {
+            theme: CursorTheme::load(&theme_name),
+            theme_name,
+            size,
+            cursors: HashMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// Create a manager for the theme and size given by the `XCURSOR_THEME` and `XCURSOR_SIZE`
+    /// environment variables
+    pub fn from_env() -> CursorThemeManager {
+        let theme = std::env::var("XCURSOR_THEME").unwrap_or_else(|_| DEFAULT_THEME.into());
+        let size = std::env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(DEFAULT_SIZE);
+        CursorThemeManager::new(theme, size)
+    }
+
+    /// Name of the theme
+    pub fn theme(&self) -> &str {
+        &self.theme_name
+    }
+
+    /// Nominal cursor size in logical pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Change the theme and nominal size, dropping all loaded cursors
+    pub fn set_theme(&mut self, theme: impl Into<String>, size: u32) {
+        let theme_name = theme.into();
+        self.theme = CursorTheme::load(&theme_name);
+        self.theme_name = theme_name;
+        self.size = size;
+        self.cursors.clear();
+    }
+
+    /// Set a cursor to use if neither the requested cursor nor the default cursor are
+    /// part of the theme
+    pub fn set_fallback(&mut self, fallback: Option<XCursor>) {
+        self.fallback = fallback;
+    }
+
+    /// Get the cursor for an icon
+    ///
+    /// The cursor is looked up by its name and its legacy aliases. If the theme does not
+    /// contain the icon, the default cursor of the theme is used instead, and then the
+    /// fallback cursor, if any.
+    pub fn get(&mut self, icon: CursorIcon) -> Option<&XCursor> {
+        self.ensure_loaded(icon);
+        if self.cursors[&icon].is_none() && icon != CursorIcon::Default {
+            self.ensure_loaded(CursorIcon::Default);
+        }
+
+        let cursor = match self.cursors[&icon] {
+            Some(ref cursor) => Some(cursor),
+            None => self.cursors[&CursorIcon::Default].as_ref(),
+        };
+        cursor.or(self.fallback.as_ref())
+    }
+
+    /// Nominal size in pixels to use for an output scale
+    pub fn scaled_size(&self, scale: f64) -> u32 {
+        (self.size as f64 * scale).round() as u32
+    }
+
+    /// Time left until the cursor for an icon needs to be redrawn, `None` for static cursors
+    ///
+    /// Compositors should schedule a redraw after this duration to animate the cursor.
+    pub fn time_until_next_frame(
+        &mut self,
+        icon: CursorIcon,
+        scale: f64,
+        time: Duration,
+    ) -> Option<Duration> {
+        let size = self.scaled_size(scale);
+        self.get(icon)?.time_until_next_frame(size, time)
+    }
+
+    /// Create a render element for the cursor of an icon
+    ///
+    /// `location` is the position of the pointer on the output, the element is offset by the
+    /// hotspot of the cursor. The image closest to the nominal size at the given output `scale`
+    /// is chosen and scaled to the nominal size of the manager. `time` selects the frame of
+    /// animated cursors.
+    ///
+    /// Returns `None` if the cursor could not be found.
+    pub fn render_element<R>(
+        &mut self,
+        renderer: &mut R,
+        icon: CursorIcon,
+        location: Point<f64, Physical>,
+        scale: f64,
+        time: Duration,
+        alpha: f32,
+    ) -> Result<Option<MemoryRenderBufferRenderElement<R>>, <R as Renderer>::Error>
+    where
+        R: Renderer + ImportMem,
+        <R as Renderer>::TextureId: 'static,
+    {
+        let nominal_size = self.size;
+        let size = self.scaled_size(scale);
+        let Some(cursor) = self.get(icon) else {
+            return Ok(None);
+        };
+        let frame = cursor.frame(size, time);
+
+        // images of a different nominal size are scaled to the configured size
+        let image_scale = nominal_size as f64 / frame.nominal_size().max(1) as f64;
+        let logical_size: Size<i32, Logical> =
+            frame.size().to_f64().to_logical(1.0 / image_scale).to_i32_round();
+        let hotspot = frame
+            .hotspot()
+            .to_f64()
+            .to_logical(1.0 / image_scale)
+            .to_physical(scale);
+
+        MemoryRenderBufferRenderElement::from_buffer(
+            renderer,
+            location - hotspot,
+            frame.buffer(),
+            Some(alpha),
+            None,
+            Some(logical_size),
+            Kind::Cursor,
+        )
+        .map(Some)
+    }
+
+    fn ensure_loaded(&mut self, icon: CursorIcon) {
+        if self.cursors.contains_key(&icon) {
+            return;
+        }
+
+        let cursor = std::iter::once(icon.name())
+            .chain(icon.alt_names().iter().copied())
+            .find_map(|name| match self.load(name) {
+                Ok(cursor) => Some(cursor),
+                Err(CursorThemeError::NotFound(_)) => None,
+                Err(err) => {
+                    warn!(?err, name, theme = self.theme_name, "Failed to load cursor");
+                    None
+                }
+            });
+        if cursor.is_none() {
+            debug!(?icon, theme = self.theme_name, "Cursor not found in theme");
+        }
+        self.cursors.insert(icon, cursor);
+    }
+
+    fn load(&self, name: &str) -> Result<XCursor, CursorThemeError> {
+        let path = self
+            .theme
+            .load_icon(name)
+            .ok_or_else(|| CursorThemeError::NotFound(name.into()))?;
+        let data = std::fs::read(path)?;
+        XCursor::from_bytes(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Image, XCursor};
+
+    fn image(size: u32, delay: u32) -> Image {
+        Image {
+            size,
+            width: size,
+            height: size,
+            xhot: 1,
+            yhot: 1,
+            delay,
+            pixels_rgba: vec![0; (size * size * 4) as usize],
+            pixels_argb: vec![],
+        }
+    }
+
+    #[test]
+    fn nearest_size() {
+        let cursor = XCursor::new(vec![image(24, 0), image(48, 0), image(32, 0)]).unwrap();
+        assert_eq!(cursor.nearest_size(24), 24);
+        assert_eq!(cursor.nearest_size(30), 32);
+        assert_eq!(cursor.nearest_size(64), 48);
+        assert!(!cursor.is_animated(24));
+        assert_eq!(cursor.time_until_next_frame(24, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn animation() {
+        let cursor = XCursor::new(vec![image(24, 10), image(24, 30), image(48, 50)]).unwrap();
+        assert!(cursor.is_animated(24));
+        assert!(!cursor.is_animated(48));
+
+        let frame_delay = |millis| cursor.frame(24, Duration::from_millis(millis)).delay();
+        assert_eq!(frame_delay(0), Duration::from_millis(10));
+        assert_eq!(frame_delay(10), Duration::from_millis(30));
+        assert_eq!(frame_delay(39), Duration::from_millis(30));
+        assert_eq!(frame_delay(40), Duration::from_millis(10));
+        assert_eq!(
+            cursor.time_until_next_frame(24, Duration::from_millis(45)),
+            Some(Duration::from_millis(5))
+        );
+    }
+}
@@ -43,12 +43,27 @@
 //! relations to one-another. Popups are then automatically rendered with their matching toplevel surfaces,
 //! when either [`crate::backend::renderer::element::AsRenderElements::render_elements`] or [`render_output`](crate::desktop::space::render_output) is called.
 //!
+//! ### Animations
+//!
+//! The [`animation`] module provides [`Animation`](animation::Animation)s with easing curves and springs
+//! to interpolate positions, sizes and alpha values over time. Elements in a [`Space`] can be animated
+//! through [`Space::animate_element`], and keep being displayed after being unmapped through
+//! [`Space::unmap_elem_animated`].
+//!
+//! ### Cursor themes
+//!
+//! The [`cursor`] module, gated by the `cursor_theme` cargo feature, loads cursors from XCursor themes
+//! to display named cursor icons requested by clients.
+//!
 //! ## Remarks
 //!
 //! Note that the desktop abstractions are concerned with easing rendering different clients and therefore need to be able
 //! to manage client buffers to do so. If you plan to use the provided drawing functions, you need to use
 //! [`on_commit_buffer_handler`](crate::backend::renderer::utils::on_commit_buffer_handler).
 
+pub mod animation;
+#[cfg(feature = "cursor_theme")]
+pub mod cursor;
 pub mod space;
 pub use self::space::Space;
 
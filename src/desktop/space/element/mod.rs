@@ -138,7 +138,7 @@ where
                 bbox.loc += *output_location;
                 bbox
             }
-            SpaceElements::Element(inner) => inner.bbox(),
+            SpaceElements::Element(inner) => inner.rendered_bbox(),
         }
     }
 
@@ -155,7 +155,7 @@ impl<
         'a,
         #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
         #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
-        E: AsRenderElements<R>,
+        E: SpaceElement + AsRenderElements<R>,
     > AsRenderElements<R> for SpaceElements<'a, E>
 where
     <R as Renderer>::TextureId: Texture + 'static,
@@ -182,19 +182,35 @@ where
             .map(SpaceRenderElements::Surface)
             .map(C::from)
             .collect(),
-            SpaceElements::Element(element) => element
-                .element
-                .render_elements::<Wrap<<E as AsRenderElements<R>>::RenderElement>>(
-                    renderer, location, scale, alpha,
-                )
-                .into_iter()
-                .map(SpaceRenderElements::Element)
-                .map(C::from)
-                .collect(),
+            SpaceElements::Element(inner) => {
+                let state = inner.animation_state;
+                inner
+                    .element
+                    .render_elements::<Wrap<<E as AsRenderElements<R>>::RenderElement>>(
+                        renderer,
+                        location + state.offset.to_physical_precise_round(scale),
+                        scale,
+                        alpha * state.alpha,
+                    )
+                    .into_iter()
+                    .map(SpaceRenderElements::Element)
+                    .map(C::from)
+                    .collect()
+            }
         }
     }
 }
 
+// the center of a bounding box relative to the render location, which animations scale around
+pub(super) fn animation_origin(
+    bbox: Rectangle<i32, Logical>,
+    render_location: Point<i32, Logical>,
+    scale: Scale<f64>,
+) -> Point<i32, Physical> {
+    ((bbox.loc - render_location).to_f64() + bbox.size.to_f64().downscale(2.0).to_point())
+        .to_physical_precise_round(scale)
+}
+
 #[macro_export]
 #[doc(hidden)]
 macro_rules! space_elements_internal {
@@ -1,22 +1,33 @@
 //! This module contains the [`Space`] helper class as well has related
 //! rendering helpers to add custom elements or different clients to a space.
 
-use crate::{
-    backend::renderer::{
-        damage::{Error as OutputDamageTrackerError, OutputDamageTracker, RenderOutputResult},
-        element::{AsRenderElements, RenderElement, Wrap},
-        Renderer, Texture,
-    },
-    output::{Output, OutputModeSource, OutputNoMode},
-    utils::{IsAlive, Logical, Point, Rectangle, Scale, Transform},
-};
 #[cfg(feature = "wayland_frontend")]
 use crate::{
     backend::renderer::{element::surface::WaylandSurfaceRenderElement, ImportAll},
     desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType},
     wayland::shell::wlr_layer::Layer,
 };
-use std::{collections::HashMap, fmt};
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{Error as OutputDamageTrackerError, OutputDamageTracker, RenderOutputResult},
+            element::{
+                offscreen::OffscreenBuffer, texture::TextureRenderElement, utils::RescaleRenderElement,
+                AsRenderElements, Element, Kind, RenderElement, Wrap,
+            },
+            Offscreen, Renderer, Texture,
+        },
+    },
+    desktop::animation::{AnimationState, ElementAnimation},
+    output::{Output, OutputModeSource, OutputNoMode},
+    utils::{Clock, IsAlive, Logical, Monotonic, Physical, Point, Rectangle, Scale, Time, Transform},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Weak},
+};
 use tracing::{debug, debug_span, instrument};
 #[cfg(feature = "wayland_frontend")]
 use wayland_server::protocol::wl_surface::WlSurface;
@@ -39,6 +50,98 @@ struct InnerElement<E> {
     element: E,
     location: Point<i32, Logical>,
     outputs: HashMap<Output, Rectangle<i32, Logical>>,
+    animation: Option<ElementAnimation>,
+    animation_state: AnimationState,
+}
+
+// The last frame of an element unmapped with an animation
+#[derive(Debug)]
+struct ClosingElement {
+    // render location of the element when it was unmapped
+    render_location: Point<i32, Logical>,
+    // location of the snapshot relative to the render location
+    offset: Point<f64, Logical>,
+    bbox: Rectangle<i32, Logical>,
+    // z-index of the element, the snapshot is stacked on top of mapped elements with the same z-index
+    z_index: u8,
+    animation: ElementAnimation,
+    animation_state: AnimationState,
+    // identifies the snapshot in the `ClosingSnapshots`, which drops it once this is dropped
+    snapshot: Arc<()>,
+}
+
+impl ClosingElement {
+    // the bounding box in space coordinates as currently rendered
+    fn rendered_bbox(&self) -> Rectangle<i32, Logical> {
+        animated_bbox(self.bbox, &self.animation_state)
+    }
+
+    fn render_element<T: Texture + Clone>(
+        &self,
+        snapshots: &ClosingSnapshots<T>,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Option<RescaleRenderElement<TextureRenderElement<T>>> {
+        let state = self.animation_state;
+        let location = location + state.offset.to_physical_precise_round(scale);
+        let origin = location + animation_origin(self.bbox, self.render_location, scale);
+        snapshots
+            .get(&self.snapshot)?
+            .render_element(
+                location.to_f64() + self.offset.to_physical(scale),
+                alpha * state.alpha,
+                Kind::Unspecified,
+            )
+            .map(|element| RescaleRenderElement::from_element(element, origin, state.scale))
+    }
+}
+
+/// Last frames of elements unmapped through [`Space::unmap_elem_animated`]
+///
+/// The frames are textures of a specific renderer, so a compositor keeps one
+/// store per renderer and passes it to [`Space::unmap_elem_animated`] and
+/// [`space_render_elements_with_snapshots`] to display them.
+///
+/// Frames are released by [`ClosingSnapshots::cleanup`] once their
+/// animation finished.
+#[derive(Debug)]
+pub struct ClosingSnapshots<T> {
+    snapshots: Vec<(Weak<()>, OffscreenBuffer<T>)>,
+}
+
+impl<T> Default for ClosingSnapshots<T> {
+    fn default() -> Self {
+        ClosingSnapshots {
+            snapshots: Vec::new(),
+        }
+    }
+}
+
+impl<T> ClosingSnapshots<T> {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Release the frames of elements, whose animation finished
+    ///
+    /// Should be called after [`Space::update_animations`].
+    pub fn cleanup(&mut self) {
+        self.snapshots.retain(|(snapshot, _)| snapshot.strong_count() > 0);
+    }
+
+    /// Returns whether the store holds no frames
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    fn get(&self, snapshot: &Arc<()>) -> Option<&OffscreenBuffer<T>> {
+        self.snapshots
+            .iter()
+            .find(|(weak, _)| std::ptr::eq(weak.as_ptr(), Arc::as_ptr(snapshot)))
+            .map(|(_, buffer)| buffer)
+    }
 }
 
 /// Represents two dimensional plane to map windows and outputs upon.
@@ -52,7 +155,10 @@ pub struct Space<E: SpaceElement> {
     pub(super) id: usize,
     // in z-order, back to front
     elements: Vec<InnerElement<E>>,
+    // unmapped elements, that are still animated
+    closing: Vec<ClosingElement>,
     outputs: Vec<Output>,
+    animation_time: Time<Monotonic>,
     span: tracing::Span,
 }
 
@@ -76,7 +182,9 @@ impl<E: SpaceElement> Default for Space<E> {
         Self {
             id,
             elements: Default::default(),
+            closing: Default::default(),
             outputs: Default::default(),
+            animation_time: Clock::<Monotonic>::new().now(),
             span,
         }
     }
@@ -100,16 +208,20 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     where
         P: Into<Point<i32, Logical>>,
     {
-        let outputs = if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
-            self.elements.remove(pos).outputs
-        } else {
-            HashMap::new()
-        };
+        let (outputs, animation, animation_state) =
+            if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
+                let inner = self.elements.remove(pos);
+                (inner.outputs, inner.animation, inner.animation_state)
+            } else {
+                (HashMap::new(), None, AnimationState::default())
+            };
 
         let inner = InnerElement {
             element,
             location: location.into(),
             outputs,
+            animation,
+            animation_state,
         };
         self.insert_elem(inner, activate);
     }
@@ -153,6 +265,137 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         }
     }
 
+    /// Unmap a [`SpaceElement`] from this space and keep displaying its last frame
+    /// while the given animation is running
+    ///
+    /// The current contents of the element are rendered into a texture with the given
+    /// scale, which should be the highest scale of the outputs the element is visible on.
+    /// The texture is kept in `snapshots` and displayed by [`space_render_elements_with_snapshots`]
+    /// at the z-index of the element, until the animation finishes in [`Space::update_animations`].
+    ///
+    /// This function does nothing for already unmapped windows
+    pub fn unmap_elem_animated<R>(
+        &mut self,
+        element: &E,
+        renderer: &mut R,
+        snapshots: &mut ClosingSnapshots<<R as Renderer>::TextureId>,
+        scale: impl Into<Scale<f64>>,
+        animation: ElementAnimation,
+    ) -> Result<(), OutputDamageTrackerError<R>>
+    where
+        R: Renderer + Offscreen<<R as Renderer>::TextureId>,
+        <R as Renderer>::TextureId: Texture + Clone + 'static,
+        E: AsRenderElements<R>,
+    {
+        let inner = match self.elements.iter().find(|inner| &inner.element == element) {
+            Some(inner) => inner,
+            None => return Ok(()),
+        };
+
+        let scale = scale.into();
+        let render_location = inner.render_location();
+        let elements = inner
+            .element
+            .render_elements::<<E as AsRenderElements<R>>::RenderElement>(
+                renderer,
+                (0, 0).into(),
+                scale,
+                1.0,
+            );
+
+        let mut buffer = OffscreenBuffer::new(Fourcc::Abgr8888);
+        if let Some(element) = buffer.render_elements(renderer, scale, &elements, 1.0, Kind::Unspecified)? {
+            let snapshot = Arc::new(());
+            let geometry = element.geometry(scale).to_f64().to_logical(scale);
+            let mut bbox = geometry.to_i32_up();
+            bbox.loc += render_location;
+
+            // keep the closing elements sorted by z-index like the mapped ones
+            let z_index = inner.element.z_index();
+            let pos = self.closing.partition_point(|closing| closing.z_index <= z_index);
+            self.closing.insert(
+                pos,
+                ClosingElement {
+                    render_location,
+                    offset: geometry.loc,
+                    bbox,
+                    z_index,
+                    animation_state: animation.state(self.animation_time),
+                    animation,
+                    snapshot: snapshot.clone(),
+                },
+            );
+            snapshots.cleanup();
+            snapshots.snapshots.push((Arc::downgrade(&snapshot), buffer));
+        }
+
+        self.unmap_elem(element);
+        Ok(())
+    }
+
+    /// Animate a mapped [`SpaceElement`]
+    ///
+    /// Replaces any running animation of the element and does nothing for
+    /// unmapped elements. See [`Space::update_animations`] for advancing the
+    /// animation.
+    ///
+    /// The animation only affects rendering, input handling and output tracking
+    /// keep using the unanimated location of the element.
+    /// *Note:* [`Space::render_elements_for_region`] can not apply the scale of
+    /// the animation.
+    pub fn animate_element(&mut self, element: &E, animation: ElementAnimation) {
+        let time = self.animation_time;
+        if let Some(inner) = self.elements.iter_mut().find(|inner| &inner.element == element) {
+            inner.animation_state = animation.state(time);
+            inner.animation = Some(animation);
+        }
+    }
+
+    /// Returns the running animation of a mapped [`SpaceElement`]
+    pub fn element_animation(&self, element: &E) -> Option<&ElementAnimation> {
+        self.elements
+            .iter()
+            .find(|inner| &inner.element == element)
+            .and_then(|inner| inner.animation.as_ref())
+    }
+
+    /// Advance all animations of this space to the given time
+    ///
+    /// Should be called before rendering a frame with the current time of a
+    /// [`Clock<Monotonic>`]. Finished animations are removed, including the
+    /// last frames of unmapped elements.
+    ///
+    /// Returns `true` as long as any animation is running, in which case
+    /// the outputs of the space should be redrawn for the next frame.
+    pub fn update_animations(&mut self, time: Time<Monotonic>) -> bool {
+        self.animation_time = time;
+
+        for inner in &mut self.elements {
+            if let Some(animation) = inner.animation.as_ref() {
+                if animation.is_done(time) {
+                    inner.animation = None;
+                    inner.animation_state = AnimationState::default();
+                } else {
+                    inner.animation_state = animation.state(time);
+                }
+            }
+        }
+
+        self.closing.retain_mut(|closing| {
+            closing.animation_state = closing.animation.state(time);
+            !closing.animation.is_done(time)
+        });
+
+        self.animations_running()
+    }
+
+    /// Returns whether any animation of this space is running
+    ///
+    /// See [`Space::update_animations`].
+    pub fn animations_running(&self) -> bool {
+        !self.closing.is_empty() || self.elements.iter().any(|inner| inner.animation.is_some())
+    }
+
     /// Iterate elements in z-order back to front
     pub fn elements(&self) -> impl DoubleEndedIterator<Item = &E> {
         self.elements.iter().map(|e| &e.element)
@@ -374,6 +617,10 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     /// *Note:* Because this is not rendering a specific output,
     /// this will not contain layer surfaces.
     /// Use [`Space::render_elements_for_output`], if you care about this.
+    ///
+    /// *Note:* The returned elements do not include the last frames of
+    /// animated, unmapped elements and animated elements are not scaled.
+    /// Use [`space_render_elements_with_snapshots`], if you care about this.
     #[instrument(level = "trace", skip(self, renderer, scale), parent = &self.span)]
     #[profiling::function]
     pub fn render_elements_for_region<'a, R: Renderer, S: Into<Scale<f64>>>(
@@ -394,23 +641,27 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             .iter()
             .rev()
             .filter(|e| {
-                let geometry = e.bbox();
+                let geometry = e.rendered_bbox();
                 region.overlaps(geometry)
             })
             .flat_map(|e| {
-                let location = e.render_location() - region.loc;
+                let location = (e.render_location() - region.loc).to_f64() + e.animation_state.offset;
                 e.element
                     .render_elements::<<E as AsRenderElements<R>>::RenderElement>(
                         renderer,
                         location.to_physical_precise_round(scale),
                         scale,
-                        alpha,
+                        alpha * e.animation_state.alpha,
                     )
             })
             .collect::<Vec<_>>()
     }
 
     /// Retrieve the render elements for an output
+    ///
+    /// *Note:* The returned elements do not include the last frames of
+    /// animated, unmapped elements and animated elements are not scaled.
+    /// Use [`space_render_elements_with_snapshots`], if you care about this.
     #[instrument(level = "trace", skip(self, renderer), parent = &self.span)]
     #[profiling::function]
     pub fn render_elements_for_output<
@@ -482,6 +733,18 @@ pub enum OutputError {
     Unmapped,
 }
 
+// the bounding box of an element after applying the scale and offset of an animation
+fn animated_bbox(bbox: Rectangle<i32, Logical>, state: &AnimationState) -> Rectangle<i32, Logical> {
+    if state.scale == 1.0 && state.offset == Point::default() {
+        return bbox;
+    }
+
+    let bbox = bbox.to_f64();
+    let center = bbox.loc + bbox.size.downscale(2.0).to_point();
+    let size = bbox.size.upscale(state.scale);
+    Rectangle::from_loc_and_size(center - size.downscale(2.0).to_point() + state.offset, size).to_i32_up()
+}
+
 impl<E: IsAlive> IsAlive for InnerElement<E> {
     fn alive(&self) -> bool {
         self.element.alive()
@@ -506,6 +769,11 @@ impl<E: SpaceElement> InnerElement<E> {
     fn render_location(&self) -> Point<i32, Logical> {
         self.location - self.element.geometry().loc
     }
+
+    // the bounding box of the element in space coordinates as currently rendered
+    fn rendered_bbox(&self) -> Rectangle<i32, Logical> {
+        animated_bbox(self.bbox(), &self.animation_state)
+    }
 }
 
 #[cfg(feature = "wayland_frontend")]
@@ -548,6 +816,52 @@ impl<
     }
 }
 
+#[cfg(feature = "wayland_frontend")]
+crate::backend::renderer::element::render_elements! {
+    /// Defines the render elements of a [`Space`] including its animations
+    ///
+    /// Use them in place of `E` in `space_render_elements_with_snapshots` or
+    /// `render_output_with_snapshots` if you do not need custom render elements
+    pub AnimatedSpaceRenderElements<R, E> where
+        R: ImportAll;
+    /// An element of the space
+    Space=SpaceRenderElements<R, E>,
+    /// An element of the space scaled by an animation
+    Rescaled=RescaleRenderElement<SpaceRenderElements<R, E>>,
+    /// The last frame of an unmapped element
+    Snapshot=RescaleRenderElement<TextureRenderElement<<R as Renderer>::TextureId>>,
+}
+#[cfg(not(feature = "wayland_frontend"))]
+crate::backend::renderer::element::render_elements! {
+    /// Defines the render elements of a [`Space`] including its animations
+    ///
+    /// Use them in place of `E` in `space_render_elements_with_snapshots` or
+    /// `render_output_with_snapshots` if you do not need custom render elements
+    pub AnimatedSpaceRenderElements<R, E>;
+    /// An element of the space
+    Space=SpaceRenderElements<R, E>,
+    /// An element of the space scaled by an animation
+    Rescaled=RescaleRenderElement<SpaceRenderElements<R, E>>,
+    /// The last frame of an unmapped element
+    Snapshot=RescaleRenderElement<TextureRenderElement<<R as Renderer>::TextureId>>,
+}
+
+impl<
+        #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
+        #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
+        E: RenderElement<R> + std::fmt::Debug,
+    > std::fmt::Debug for AnimatedSpaceRenderElements<R, E>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Space(arg0) => f.debug_tuple("Space").field(arg0).finish(),
+            Self::Rescaled(arg0) => f.debug_tuple("Rescaled").field(arg0).finish(),
+            Self::Snapshot(arg0) => f.debug_tuple("Snapshot").field(arg0).finish(),
+            Self::_GenericCatcher(_) => unreachable!(),
+        }
+    }
+}
+
 #[cfg(feature = "wayland_frontend")]
 crate::backend::renderer::element::render_elements! {
     OutputRenderElements<'a, R, E, C> where
@@ -562,6 +876,20 @@ crate::backend::renderer::element::render_elements! {
     Custom=&'a C,
 }
 
+#[cfg(feature = "wayland_frontend")]
+crate::backend::renderer::element::render_elements! {
+    AnimatedOutputRenderElements<'a, R, E, C> where
+        R: ImportAll;
+    Space=AnimatedSpaceRenderElements<R, E>,
+    Custom=&'a C,
+}
+#[cfg(not(feature = "wayland_frontend"))]
+crate::backend::renderer::element::render_elements! {
+    AnimatedOutputRenderElements<'a, R, E, C>;
+    Space=AnimatedSpaceRenderElements<R, E>,
+    Custom=&'a C,
+}
+
 /// Get the render elements for a specific output
 ///
 /// If multiple spaces are given their elements will be stacked
@@ -570,6 +898,10 @@ crate::backend::renderer::element::render_elements! {
 /// *Note*: If the `wayland_frontend`-feature is enabled
 /// this will include layer-shell surfaces added to this
 /// outputs [`LayerMap`](crate::desktop::LayerMap).
+///
+/// *Note*: The returned elements do not include the last frames of
+/// animated, unmapped elements and animated elements are not scaled.
+/// Use [`space_render_elements_with_snapshots`], if you care about this.
 #[instrument(level = "trace", skip(spaces, renderer))]
 #[profiling::function]
 pub fn space_render_elements<
@@ -655,10 +987,153 @@ where
     Ok(render_elements)
 }
 
+/// Get the render elements for a specific output including all animations
+///
+/// Works like [`space_render_elements`], but additionally scales animated elements
+/// and stacks the last frames of elements unmapped by [`Space::unmap_elem_animated`]
+/// at their z-index. The frames are taken from `snapshots`.
+#[instrument(level = "trace", skip(spaces, renderer, snapshots))]
+#[profiling::function]
+pub fn space_render_elements_with_snapshots<
+    'a,
+    #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
+    #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
+    E: SpaceElement + PartialEq + AsRenderElements<R> + 'a,
+    S: IntoIterator<Item = &'a Space<E>>,
+>(
+    renderer: &mut R,
+    spaces: S,
+    snapshots: &ClosingSnapshots<<R as Renderer>::TextureId>,
+    output: &Output,
+    alpha: f32,
+) -> Result<Vec<AnimatedSpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>>, OutputNoMode>
+where
+    <R as Renderer>::TextureId: Texture + Clone + 'static,
+    <E as AsRenderElements<R>>::RenderElement: 'a,
+    SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>:
+        From<Wrap<<E as AsRenderElements<R>>::RenderElement>>,
+{
+    let mut render_elements = Vec::new();
+    let output_scale = output.current_scale().fractional_scale();
+    let scale = Scale::from(output_scale);
+
+    #[cfg(feature = "wayland_frontend")]
+    let layer_map = layer_map_for_output(output);
+    #[cfg(feature = "wayland_frontend")]
+    let lower = {
+        let (lower, upper): (Vec<&LayerSurface>, Vec<&LayerSurface>) = layer_map
+            .layers()
+            .rev()
+            .partition(|s| matches!(s.layer(), Layer::Background | Layer::Bottom));
+
+        render_elements.extend(
+            upper
+                .into_iter()
+                .filter_map(|surface| layer_map.layer_geometry(surface).map(|geo| (geo.loc, surface)))
+                .flat_map(|(loc, surface)| {
+                    AsRenderElements::<R>::render_elements::<WaylandSurfaceRenderElement<R>>(
+                        surface,
+                        renderer,
+                        loc.to_physical_precise_round(output_scale),
+                        scale,
+                        alpha,
+                    )
+                    .into_iter()
+                    .map(|e| AnimatedSpaceRenderElements::Space(SpaceRenderElements::Surface(e)))
+                }),
+        );
+
+        lower
+    };
+
+    for space in spaces {
+        let _guard = space.span.enter();
+        let Some(output_geo) = space.output_geometry(output) else {
+            continue;
+        };
+
+        let render_closing = |closing: &ClosingElement| {
+            let location = closing.render_location - output_geo.loc;
+            closing
+                .render_element(
+                    snapshots,
+                    location.to_physical_precise_round(output_scale),
+                    scale,
+                    alpha,
+                )
+                .map(AnimatedSpaceRenderElements::Snapshot)
+        };
+
+        // both lists are sorted by z-index, merge them front to back
+        let mut closing = space
+            .closing
+            .iter()
+            .rev()
+            .filter(|closing| output_geo.overlaps(closing.rendered_bbox()))
+            .peekable();
+        for inner in space.elements.iter().rev() {
+            let z_index = inner.element.z_index();
+            while let Some(closing) = closing.next_if(|closing| closing.z_index >= z_index) {
+                render_elements.extend(render_closing(closing));
+            }
+
+            if !output_geo.overlaps(inner.rendered_bbox()) {
+                continue;
+            }
+
+            let location = (inner.render_location() - output_geo.loc).to_physical_precise_round(output_scale);
+            let elements = SpaceElements::Element(inner)
+                .render_elements::<SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>>(
+                    renderer, location, scale, alpha,
+                );
+            let state = inner.animation_state;
+            if state.scale == 1.0 {
+                render_elements.extend(elements.into_iter().map(AnimatedSpaceRenderElements::Space));
+            } else {
+                let origin = location
+                    + state.offset.to_physical_precise_round(scale)
+                    + animation_origin(inner.bbox(), inner.render_location(), scale);
+                render_elements.extend(elements.into_iter().map(|element| {
+                    AnimatedSpaceRenderElements::Rescaled(RescaleRenderElement::from_element(
+                        element,
+                        origin,
+                        state.scale,
+                    ))
+                }));
+            }
+        }
+        render_elements.extend(closing.filter_map(render_closing));
+    }
+
+    #[cfg(feature = "wayland_frontend")]
+    render_elements.extend(
+        lower
+            .into_iter()
+            .filter_map(|surface| layer_map.layer_geometry(surface).map(|geo| (geo.loc, surface)))
+            .flat_map(|(loc, surface)| {
+                AsRenderElements::<R>::render_elements::<WaylandSurfaceRenderElement<R>>(
+                    surface,
+                    renderer,
+                    loc.to_physical_precise_round(output_scale),
+                    scale,
+                    alpha,
+                )
+                .into_iter()
+                .map(|e| AnimatedSpaceRenderElements::Space(SpaceRenderElements::Surface(e)))
+            }),
+    );
+
+    Ok(render_elements)
+}
+
 /// Render a output
 ///
 /// If multiple spaces are given their elements will be stacked
 /// the same way.
+///
+/// *Note*: Animated elements are not scaled and the last frames of animated,
+/// unmapped elements are not rendered. Use [`render_output_with_snapshots`],
+/// if you care about this.
 #[allow(clippy::too_many_arguments)]
 #[profiling::function]
 pub fn render_output<
@@ -698,3 +1173,231 @@ where
 
     damage_tracker.render_output(renderer, age, &render_elements, clear_color)
 }
+
+/// Render a output including all animations
+///
+/// Works like [`render_output`], but renders the space elements returned
+/// by [`space_render_elements_with_snapshots`].
+#[allow(clippy::too_many_arguments)]
+#[profiling::function]
+pub fn render_output_with_snapshots<
+    'a,
+    #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
+    #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
+    C: RenderElement<R>,
+    E: SpaceElement + PartialEq + AsRenderElements<R> + 'a,
+    S: IntoIterator<Item = &'a Space<E>>,
+>(
+    output: &Output,
+    renderer: &mut R,
+    alpha: f32,
+    age: usize,
+    spaces: S,
+    snapshots: &ClosingSnapshots<<R as Renderer>::TextureId>,
+    custom_elements: &'a [C],
+    damage_tracker: &mut OutputDamageTracker,
+    clear_color: [f32; 4],
+) -> Result<RenderOutputResult, OutputDamageTrackerError<R>>
+where
+    <R as Renderer>::TextureId: Texture + Clone + 'static,
+    <E as AsRenderElements<R>>::RenderElement: 'a,
+    SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>:
+        From<Wrap<<E as AsRenderElements<R>>::RenderElement>>,
+{
+    if let OutputModeSource::Auto(renderer_output) = damage_tracker.mode() {
+        assert!(renderer_output == output);
+    }
+
+    let space_render_elements =
+        space_render_elements_with_snapshots(renderer, spaces, snapshots, output, alpha)?;
+
+    let mut render_elements: Vec<
+        AnimatedOutputRenderElements<'a, R, <E as AsRenderElements<R>>::RenderElement, C>,
+    > = Vec::with_capacity(custom_elements.len() + space_render_elements.len());
+
+    render_elements.extend(custom_elements.iter().map(AnimatedOutputRenderElements::Custom));
+    render_elements.extend(
+        space_render_elements
+            .into_iter()
+            .map(AnimatedOutputRenderElements::Space),
+    );
+
+    damage_tracker.render_output(renderer, age, &render_elements, clear_color)
+}
+
+#[cfg(all(test, feature = "renderer_pixman"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        backend::renderer::snapshot::{SnapshotTest, TestElement},
+        desktop::animation::{Animation, Curve, Easing},
+        output::{Mode, PhysicalProperties, Subpixel},
+    };
+
+    fn time(millis: u64) -> Time<Monotonic> {
+        Time::from(Duration::from_millis(millis))
+    }
+
+    fn output() -> Output {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "smithay".into(),
+                model: "test".into(),
+            },
+        );
+        let mode = Mode {
+            size: (32, 32).into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+        output
+    }
+
+    fn curve() -> Curve {
+        Curve::Easing(Easing::Linear, Duration::from_millis(100))
+    }
+
+    #[test]
+    fn animations_request_redraws() {
+        let mut space = Space::default();
+        let element = TestElement::new([1.0, 0.0, 0.0, 1.0]);
+        space.map_element(element.clone(), (0, 0), false);
+        assert!(!space.update_animations(time(0)));
+
+        space.animate_element(&element, ElementAnimation::fade_in(time(0), curve()));
+        assert!(space.animations_running());
+        assert!(space.update_animations(time(50)));
+        assert!(space.element_animation(&element).is_some());
+
+        // remapping keeps the animation
+        space.map_element(element.clone(), (4, 4), false);
+        assert!(space.update_animations(time(99)));
+
+        assert!(!space.update_animations(time(100)));
+        assert!(space.element_animation(&element).is_none());
+    }
+
+    #[test]
+    fn unmapped_element_is_animated_out() {
+        let mut test = SnapshotTest::new((32, 32));
+        let output = output();
+        let mut space = Space::default();
+        space.map_output(&output, (0, 0));
+
+        let element = TestElement::new([1.0, 0.0, 0.0, 1.0]);
+        space.map_element(element.clone(), (12, 12), false);
+        space.map_element(TestElement::new([0.0, 0.0, 1.0, 1.0]), (0, 0), false);
+
+        let animation = ElementAnimation {
+            alpha: Some(Animation::new(1.0, 0.0, time(0), curve())),
+            scale: Some(Animation::new(1.0, 0.0, time(0), curve())),
+            offset: Some(Animation::new(
+                Point::default(),
+                Point::from((8.0, 0.0)),
+                time(0),
+                curve(),
+            )),
+        };
+        let mut snapshots = ClosingSnapshots::new();
+        space
+            .unmap_elem_animated(&element, test.renderer(), &mut snapshots, 1.0, animation)
+            .unwrap();
+        assert_eq!(space.elements().count(), 1);
+
+        assert!(space.update_animations(time(50)));
+        let elements =
+            space_render_elements_with_snapshots(test.renderer(), [&space], &snapshots, &output, 1.0)
+                .unwrap();
+        assert_eq!(elements.len(), 2);
+        assert!(matches!(elements[0], AnimatedSpaceRenderElements::Snapshot(_)));
+        assert_eq!(
+            elements[0].geometry(Scale::from(1.0)),
+            Rectangle::from_loc_and_size((18, 14), (4, 4))
+        );
+        test.assert_snapshot("space_unmap_animated", &elements);
+
+        assert!(!space.update_animations(time(100)));
+        let elements =
+            space_render_elements_with_snapshots(test.renderer(), [&space], &snapshots, &output, 1.0)
+                .unwrap();
+        assert_eq!(elements.len(), 1);
+        snapshots.cleanup();
+        assert!(snapshots.is_empty());
+    }
+
+    #[test]
+    fn snapshot_keeps_z_index() {
+        let mut test = SnapshotTest::new((32, 32));
+        let output = output();
+        let mut space = Space::default();
+        space.map_output(&output, (0, 0));
+
+        let low = TestElement::new([1.0, 0.0, 0.0, 1.0]).with_z_index(RenderZindex::Shell as u8);
+        space.map_element(TestElement::new([0.0, 0.0, 1.0, 1.0]), (4, 4), false);
+        space.map_element(low.clone(), (0, 0), false);
+
+        let mut snapshots = ClosingSnapshots::new();
+        space
+            .unmap_elem_animated(
+                &low,
+                test.renderer(),
+                &mut snapshots,
+                1.0,
+                ElementAnimation::fade_out(time(0), curve()),
+            )
+            .unwrap();
+
+        // the snapshot stays behind the element with the higher z-index
+        let elements =
+            space_render_elements_with_snapshots(test.renderer(), [&space], &snapshots, &output, 1.0)
+                .unwrap();
+        assert_eq!(elements.len(), 2);
+        assert!(matches!(elements[0], AnimatedSpaceRenderElements::Space(_)));
+        assert!(matches!(elements[1], AnimatedSpaceRenderElements::Snapshot(_)));
+    }
+
+    #[test]
+    fn space_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Space<TestElement>>();
+    }
+
+    #[test]
+    fn scaled_element() {
+        let mut test = SnapshotTest::new((32, 32));
+        let output = output();
+        let mut space = Space::default();
+        space.map_output(&output, (0, 0));
+
+        let element = TestElement::new([1.0, 0.0, 0.0, 1.0]);
+        space.map_element(element.clone(), (8, 8), false);
+        space.animate_element(
+            &element,
+            ElementAnimation {
+                scale: Some(Animation::new(2.0, 1.0, time(0), curve())),
+                ..Default::default()
+            },
+        );
+        space.update_animations(time(0));
+
+        let elements = space_render_elements_with_snapshots(
+            test.renderer(),
+            [&space],
+            &ClosingSnapshots::new(),
+            &output,
+            1.0,
+        )
+        .unwrap();
+        assert!(matches!(elements[0], AnimatedSpaceRenderElements::Rescaled(_)));
+        assert_eq!(
+            elements[0].geometry(Scale::from(1.0)),
+            Rectangle::from_loc_and_size((4, 4), (16, 16))
+        );
+    }
+}
@@ -0,0 +1,640 @@
+//! Declarative keybindings
+//!
+//! A [`KeyBindings`] table maps chords of modifiers and keys to actions, grouped in named modes.
+//! It is used as filter of [`KeyboardHandle::input`](super::KeyboardHandle::input), intercepting
+//! the keys of matched bindings and forwarding all others to the focused client.
+
+use std::collections::HashMap;
+
+use xkbcommon::xkb::{Keycode, Keysym};
+
+use crate::backend::input::KeyState;
+
+use super::{FilterResult, KeysymHandle, ModifiersState};
+
+/// Name of the mode a [`KeyBindings`] table starts in
+pub const DEFAULT_MODE: &str = "default";
+
+/// Default time in milliseconds between the keys of a chord
+const DEFAULT_CHORD_TIMEOUT: u32 = 1000;
+
+bitflags::bitflags! {
+    /// Modifiers that have to be held for a binding
+    ///
+    /// Lock modifiers like caps lock and num lock are ignored for matching bindings.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct BindingModifiers: u8 {
+        /// The "control" key
+        const CTRL = 0b0001;
+        /// The "alt" key
+        const ALT = 0b0010;
+        /// The "shift" key
+        const SHIFT = 0b0100;
+        /// The "logo" key
+        const LOGO = 0b1000;
+    }
+}
+
+impl From<&ModifiersState> for BindingModifiers {
+    fn from(state: &ModifiersState) -> Self {
+        let mut modifiers = BindingModifiers::empty();
+        modifiers.set(BindingModifiers::CTRL, state.ctrl);
+        modifiers.set(BindingModifiers::ALT, state.alt);
+        modifiers.set(BindingModifiers::SHIFT, state.shift);
+        modifiers.set(BindingModifiers::LOGO, state.logo);
+        modifiers
+    }
+}
+
+/// Key of a binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingKey {
+    /// Match a keysym
+    ///
+    /// Keysyms are matched against [`KeysymHandle::raw_latin_sym_or_raw_current_sym`], so bindings
+    /// work independently of the active layout and of the shift level. Use the unshifted keysym,
+    /// e.g. `Keysym::a` for `Shift+a`.
+    Keysym(Keysym),
+    /// Match a hardware keycode
+    Keycode(Keycode),
+}
+
+impl From<Keysym> for BindingKey {
+    fn from(keysym: Keysym) -> Self {
+        BindingKey::Keysym(keysym)
+    }
+}
+
+impl From<Keycode> for BindingKey {
+    fn from(keycode: Keycode) -> Self {
+        BindingKey::Keycode(keycode)
+    }
+}
+
+/// A single key combined with modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    /// Modifiers that have to be held, no other modifiers may be active
+    pub modifiers: BindingModifiers,
+    /// The key that has to be pressed
+    pub key: BindingKey,
+}
+
+impl KeyCombo {
+    /// Create a new key combination
+    pub fn new(modifiers: BindingModifiers, key: impl Into<BindingKey>) -> KeyCombo {
+        KeyCombo {
+            modifiers,
+            key: key.into(),
+        }
+    }
+
+    fn matches(&self, modifiers: BindingModifiers, keysym: Option<Keysym>, keycode: Keycode) -> bool {
+        self.modifiers == modifiers
+            && match self.key {
+                BindingKey::Keysym(sym) => Some(sym) == keysym,
+                BindingKey::Keycode(code) => code == keycode,
+            }
+    }
+}
+
+/// Key event triggering a binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingTrigger {
+    /// Trigger when the last key of the chord is pressed
+    Press,
+    /// Trigger when the last key of the chord is released
+    Release,
+    /// Trigger when the last key of the chord is pressed and on every key repeat,
+    /// see [`KeyBindings::repeat`]
+    Repeat,
+}
+
+#[derive(Debug)]
+struct Binding<A> {
+    chord: Vec<KeyCombo>,
+    trigger: BindingTrigger,
+    action: A,
+}
+
+/// Table of keybindings
+///
+/// Bindings map a chord, a sequence of one or more [`KeyCombo`]s, to an action. They are grouped
+/// in named modes of which only one is active at a time, starting with [`DEFAULT_MODE`].
+///
+/// The table is meant to be used as filter of [`KeyboardHandle::input`](super::KeyboardHandle::input):
+///
+/// ```no_run
+/// use smithay::backend::input::KeyState;
+/// use smithay::input::{keyboard::KeyboardHandle, SeatHandler};
+/// use smithay::input::keyboard::{
+///     BindingModifiers, BindingTrigger, KeyBindings, KeyCombo, Keysym,
+/// };
+/// use smithay::utils::SERIAL_COUNTER;
+///
+/// #[derive(Debug, Clone)]
+/// enum Action {
+///     Quit,
+///     Resize,
+/// }
+///
+/// let mut bindings = KeyBindings::new();
+/// bindings.add(
+///     "default",
+///     [KeyCombo::new(BindingModifiers::LOGO, Keysym::q)],
+///     BindingTrigger::Press,
+///     Action::Quit,
+/// );
+/// // Ctrl+x followed by r
+/// bindings.add(
+///     "default",
+///     [
+///         KeyCombo::new(BindingModifiers::CTRL, Keysym::x),
+///         KeyCombo::new(BindingModifiers::empty(), Keysym::r),
+///     ],
+///     BindingTrigger::Press,
+///     Action::Resize,
+/// );
+///
+/// fn handle_key<D: SeatHandler + 'static>(
+///     data: &mut D,
+///     keyboard: &KeyboardHandle<D>,
+///     bindings: &mut KeyBindings<Action>,
+///     keycode: u32,
+///     state: KeyState,
+///     time: u32,
+/// ) {
+///     let serial = SERIAL_COUNTER.next_serial();
+///     let action = keyboard.input(data, keycode, state, serial, time, |_, modifiers, handle| {
+///         bindings.process(modifiers, &handle, state, time)
+///     });
+///     if let Some(Some(action)) = action {
+///         // run the action
+///     }
+/// }
+/// ```
+///
+/// Keys that triggered a binding or continued a chord are intercepted, including their release.
+/// All other keys are forwarded. A chord is aborted if a key not continuing it is pressed, or
+/// if the next key is not pressed within the chord timeout.
+#[derive(Debug)]
+pub struct KeyBindings<A> {
+    modes: HashMap<String, Vec<Binding<A>>>,
+    mode: String,
+    chord_timeout: u32,
+    /// Keys of the chord in progress and the time of the last one
+    pending: Vec<KeyCombo>,
+    pending_time: u32,
+    /// Intercepted keys that are still pressed, with the action to trigger on release or repeat
+    intercepted: Vec<(Keycode, Option<(BindingTrigger, A)>)>,
+}
+
+impl<A> Default for KeyBindings<A> {
+    fn default() -> Self {
+        KeyBindings {
+            modes: HashMap::new(),
+            mode: DEFAULT_MODE.to_owned(),
+            chord_timeout: DEFAULT_CHORD_TIMEOUT,
+            pending: Vec::new(),
+            pending_time: 0,
+            intercepted: Vec::new(),
+        }
+    }
+}
+
+impl<A: Clone> KeyBindings<A> {
+    /// Create an empty binding table
+    pub fn new() -> KeyBindings<A> {
+        KeyBindings::default()
+    }
+
+    /// Add a binding to the given mode
+    ///
+    /// An existing binding of the mode with the same chord and trigger is replaced.
+    pub fn add(
+        &mut self,
+        mode: &str,
+        chord: impl IntoIterator<Item = KeyCombo>,
+        trigger: BindingTrigger,
+        action: A,
+    ) {
+        let chord = chord.into_iter().collect::<Vec<_>>();
+        assert!(!chord.is_empty(), "A binding needs at least one key");
+
+        let bindings = self.modes.entry(mode.to_owned()).or_default();
+        bindings.retain(|binding| binding.chord != chord || binding.trigger != trigger);
+        bindings.push(Binding {
+            chord,
+            trigger,
+            action,
+        });
+    }
+
+    /// Remove a binding from the given mode, returning its action
+    pub fn remove(&mut self, mode: &str, chord: &[KeyCombo], trigger: BindingTrigger) -> Option<A> {
+        let bindings = self.modes.get_mut(mode)?;
+        let index = bindings
+            .iter()
+            .position(|binding| binding.chord == chord && binding.trigger == trigger)?;
+        Some(bindings.remove(index).action)
+    }
+
+    /// Remove all bindings of the given mode
+    pub fn clear_mode(&mut self, mode: &str) {
+        self.modes.remove(mode);
+    }
+
+    /// The currently active mode
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    /// Switch to another mode
+    ///
+    /// This aborts any chord in progress.
+    pub fn set_mode(&mut self, mode: &str) {
+        self.mode = mode.to_owned();
+        self.pending.clear();
+    }
+
+    /// Set the maximum time in milliseconds between the keys of a chord
+    pub fn set_chord_timeout(&mut self, timeout: u32) {
+        self.chord_timeout = timeout;
+    }
+
+    /// Whether a chord was started and is waiting for further keys
+    pub fn chord_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Process a key event
+    ///
+    /// Returns [`FilterResult::Intercept`] with the triggered action, or `None` if the key was
+    /// consumed without triggering an action, e.g. as part of an unfinished chord. Unmatched keys
+    /// return [`FilterResult::Forward`].
+    pub fn process(
+        &mut self,
+        modifiers: &ModifiersState,
+        handle: &KeysymHandle<'_>,
+        state: KeyState,
+        time: u32,
+    ) -> FilterResult<Option<A>> {
+        self.process_key(
+            modifiers.into(),
+            handle.raw_latin_sym_or_raw_current_sym(),
+            handle.raw_code(),
+            state,
+            time,
+        )
+    }
+
+    /// Get the action to run for a key repeat of the given key
+    ///
+    /// Smithay does not generate key repeats for the compositor itself. Compositors wanting
+    /// repeating bindings have to drive a timer with the repeat info of the keyboard and call
+    /// this function for the key that is held.
+    pub fn repeat(&self, keycode: Keycode) -> Option<A> {
+        self.intercepted.iter().find_map(|(code, action)| match action {
+            Some((BindingTrigger::Repeat, action)) if *code == keycode => Some(action.clone()),
+            _ => None,
+        })
+    }
+
+    fn process_key(
+        &mut self,
+        modifiers: BindingModifiers,
+        keysym: Option<Keysym>,
+        keycode: Keycode,
+        state: KeyState,
+        time: u32,
+    ) -> FilterResult<Option<A>> {
+        if state == KeyState::Released {
+            let Some(index) = self.intercepted.iter().position(|(code, _)| *code == keycode) else {
+                return FilterResult::Forward;
+            };
+            return match self.intercepted.remove(index).1 {
+                Some((BindingTrigger::Release, action)) => FilterResult::Intercept(Some(action)),
+                _ => FilterResult::Intercept(None),
+            };
+        }
+
+        if !self.pending.is_empty() && time.wrapping_sub(self.pending_time) > self.chord_timeout {
+            self.pending.clear();
+        }
+
+        let result = match self.match_chord(modifiers, keysym, keycode) {
+            Some(result) => Some(result),
+            // modifiers pressed while entering a chord do not abort it
+            None if keysym.map(|sym| sym.is_modifier_key()).unwrap_or(false) => {
+                return FilterResult::Forward;
+            }
+            None if !self.pending.is_empty() => {
+                // the chord was aborted, the key may start a new one
+                self.pending.clear();
+                self.match_chord(modifiers, keysym, keycode)
+            }
+            None => None,
+        };
+
+        let Some((combo, binding)) = result else {
+            return FilterResult::Forward;
+        };
+
+        self.intercepted.retain(|(code, _)| *code != keycode);
+        match binding {
+            Some((trigger, action)) => {
+                self.pending.clear();
+                self.intercepted.push((keycode, Some((trigger, action.clone()))));
+                match trigger {
+                    BindingTrigger::Press | BindingTrigger::Repeat => FilterResult::Intercept(Some(action)),
+                    BindingTrigger::Release => FilterResult::Intercept(None),
+                }
+            }
+            None => {
+                self.pending.push(combo);
+                self.pending_time = time;
+                self.intercepted.push((keycode, None));
+                FilterResult::Intercept(None)
+            }
+        }
+    }
+
+    /// Match the pending chord extended by the given key
+    ///
+    /// Returns the matched key and the completed binding, or `None` as binding if the chord
+    /// is a prefix of longer bindings.
+    #[allow(clippy::type_complexity)]
+    fn match_chord(
+        &self,
+        modifiers: BindingModifiers,
+        keysym: Option<Keysym>,
+        keycode: Keycode,
+    ) -> Option<(KeyCombo, Option<(BindingTrigger, A)>)> {
+        let bindings = self.modes.get(&self.mode)?;
+        let depth = self.pending.len();
+
+        let mut prefix = None;
+        for binding in bindings {
+            if binding.chord.len() <= depth
+                || binding.chord[..depth] != self.pending[..]
+                || !binding.chord[depth].matches(modifiers, keysym, keycode)
+            {
+                continue;
+            }
+
+            let combo = binding.chord[depth];
+            if binding.chord.len() == depth + 1 {
+                return Some((combo, Some((binding.trigger, binding.action.clone()))));
+            }
+            prefix = Some((combo, None));
+        }
+        prefix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xkbcommon::xkb::{Keycode, Keysym};
+
+    use super::{BindingModifiers, BindingTrigger, KeyBindings, KeyCombo, DEFAULT_MODE};
+    use crate::{backend::input::KeyState, input::keyboard::FilterResult};
+
+    const KEY_X: Keycode = Keycode::new(53);
+    const KEY_R: Keycode = Keycode::new(27);
+    const KEY_A: Keycode = Keycode::new(38);
+    const KEY_SHIFT: Keycode = Keycode::new(50);
+
+    fn press(
+        bindings: &mut KeyBindings<u32>,
+        mods: BindingModifiers,
+        sym: Keysym,
+        code: Keycode,
+        time: u32,
+    ) -> Option<Option<u32>> {
+        match bindings.process_key(mods, Some(sym), code, KeyState::Pressed, time) {
+            FilterResult::Forward => None,
+            FilterResult::Intercept(action) => Some(action),
+        }
+    }
+
+    fn release(
+        bindings: &mut KeyBindings<u32>,
+        sym: Keysym,
+        code: Keycode,
+        time: u32,
+    ) -> Option<Option<u32>> {
+        match bindings.process_key(
+            BindingModifiers::empty(),
+            Some(sym),
+            code,
+            KeyState::Released,
+            time,
+        ) {
+            FilterResult::Forward => None,
+            FilterResult::Intercept(action) => Some(action),
+        }
+    }
+
+    fn ctrl_x_r() -> [KeyCombo; 2] {
+        [
+            KeyCombo::new(BindingModifiers::CTRL, Keysym::x),
+            KeyCombo::new(BindingModifiers::empty(), Keysym::r),
+        ]
+    }
+
+    #[test]
+    fn chords() {
+        let mut bindings = KeyBindings::new();
+        bindings.add("default", ctrl_x_r(), BindingTrigger::Press, 1);
+
+        // unbound keys are forwarded
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 0),
+            None
+        );
+        assert_eq!(release(&mut bindings, Keysym::r, KEY_R, 5), None);
+
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 10),
+            Some(None)
+        );
+        assert!(bindings.chord_pending());
+        // the release of an intercepted key is intercepted as well
+        assert_eq!(release(&mut bindings, Keysym::x, KEY_X, 20), Some(None));
+        // modifiers do not abort the chord
+        assert_eq!(
+            press(
+                &mut bindings,
+                BindingModifiers::empty(),
+                Keysym::Shift_L,
+                KEY_SHIFT,
+                25
+            ),
+            None
+        );
+        assert!(bindings.chord_pending());
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 30),
+            Some(Some(1))
+        );
+        assert!(!bindings.chord_pending());
+
+        // other keys abort the chord and are forwarded
+        press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 40);
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::x, KEY_X, 50),
+            None
+        );
+        assert!(!bindings.chord_pending());
+    }
+
+    #[test]
+    fn chord_timeout() {
+        let mut bindings = KeyBindings::new();
+        bindings.add("default", ctrl_x_r(), BindingTrigger::Press, 1);
+        bindings.set_chord_timeout(100);
+
+        // the next key is pressed right at the timeout
+        press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 1000);
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 1100),
+            Some(Some(1))
+        );
+
+        // the chord expired
+        press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 2000);
+        assert!(bindings.chord_pending());
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 2101),
+            None
+        );
+        assert!(!bindings.chord_pending());
+
+        // an expired chord can be started again
+        press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 3000);
+        press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 3200);
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 3250),
+            Some(Some(1))
+        );
+
+        // timestamps wrapping around do not expire the chord
+        press(
+            &mut bindings,
+            BindingModifiers::CTRL,
+            Keysym::x,
+            KEY_X,
+            u32::MAX - 10,
+        );
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 10),
+            Some(Some(1))
+        );
+    }
+
+    #[test]
+    fn triggers() {
+        let mut bindings = KeyBindings::new();
+        let combo = |sym| [KeyCombo::new(BindingModifiers::LOGO, sym)];
+        bindings.add("default", combo(Keysym::x), BindingTrigger::Press, 1);
+        bindings.add("default", combo(Keysym::r), BindingTrigger::Release, 2);
+        bindings.add("default", combo(Keysym::a), BindingTrigger::Repeat, 3);
+
+        // press bindings trigger once, on press
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::LOGO, Keysym::x, KEY_X, 0),
+            Some(Some(1))
+        );
+        assert_eq!(bindings.repeat(KEY_X), None);
+        assert_eq!(release(&mut bindings, Keysym::x, KEY_X, 10), Some(None));
+
+        // release bindings trigger on release, even if the modifiers were released first
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::LOGO, Keysym::r, KEY_R, 20),
+            Some(None)
+        );
+        assert_eq!(bindings.repeat(KEY_R), None);
+        assert_eq!(release(&mut bindings, Keysym::r, KEY_R, 30), Some(Some(2)));
+
+        // repeat bindings trigger on press and every repeat until released
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::LOGO, Keysym::a, KEY_A, 40),
+            Some(Some(3))
+        );
+        assert_eq!(bindings.repeat(KEY_A), Some(3));
+        assert_eq!(bindings.repeat(KEY_A), Some(3));
+        assert_eq!(release(&mut bindings, Keysym::a, KEY_A, 50), Some(None));
+        assert_eq!(bindings.repeat(KEY_A), None);
+
+        // the modifiers have to match exactly
+        assert_eq!(
+            press(
+                &mut bindings,
+                BindingModifiers::LOGO | BindingModifiers::SHIFT,
+                Keysym::x,
+                KEY_X,
+                60
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let mut bindings = KeyBindings::new();
+        bindings.add("default", ctrl_x_r(), BindingTrigger::Press, 1);
+        bindings.add(
+            "resize",
+            [KeyCombo::new(BindingModifiers::empty(), KEY_R)],
+            BindingTrigger::Press,
+            2,
+        );
+        assert_eq!(bindings.mode(), DEFAULT_MODE);
+
+        // bindings of inactive modes are ignored
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 0),
+            None
+        );
+
+        // switching modes aborts a pending chord
+        press(&mut bindings, BindingModifiers::CTRL, Keysym::x, KEY_X, 10);
+        bindings.set_mode("resize");
+        assert!(!bindings.chord_pending());
+        assert_eq!(bindings.mode(), "resize");
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 20),
+            Some(Some(2))
+        );
+        // keys intercepted in another mode are still intercepted on release
+        bindings.set_mode(DEFAULT_MODE);
+        assert_eq!(release(&mut bindings, Keysym::r, KEY_R, 30), Some(None));
+        assert_eq!(release(&mut bindings, Keysym::x, KEY_X, 40), Some(None));
+
+        // replaced and removed bindings
+        bindings.add(
+            "resize",
+            [KeyCombo::new(BindingModifiers::empty(), KEY_R)],
+            BindingTrigger::Press,
+            3,
+        );
+        bindings.set_mode("resize");
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 50),
+            Some(Some(3))
+        );
+        assert_eq!(
+            bindings.remove(
+                "resize",
+                &[KeyCombo::new(BindingModifiers::empty(), KEY_R)],
+                BindingTrigger::Press
+            ),
+            Some(3)
+        );
+        bindings.clear_mode("resize");
+        assert_eq!(
+            press(&mut bindings, BindingModifiers::empty(), Keysym::r, KEY_R, 60),
+            None
+        );
+    }
+}
@@ -1,8 +1,8 @@
 //! Keyboard-related types for smithay's input abstraction
 
-use crate::backend::input::KeyState;
+use crate::backend::input::{Device, KeyState};
 use crate::utils::{IsAlive, Serial, SERIAL_COUNTER};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 #[cfg(feature = "wayland_frontend")]
 use std::sync::RwLock;
 use std::{
@@ -18,6 +18,9 @@ pub use xkbcommon::xkb::{self, keysyms, Keycode, Keysym};
 
 use super::{Seat, SeatHandler};
 
+mod bindings;
+pub use bindings::{BindingKey, BindingModifiers, BindingTrigger, KeyBindings, KeyCombo, DEFAULT_MODE};
+
 #[cfg(feature = "wayland_frontend")]
 mod keymap_file;
 #[cfg(feature = "wayland_frontend")]
@@ -120,6 +123,28 @@ impl LedState {
     }
 }
 
+/// Keymap of a keyboard device, which is currently not driving the keyboard
+struct DeviceKeymap {
+    keymap: xkb::Keymap,
+    led_mapping: LedMapping,
+    /// Locked layout of the device the last time it was active
+    layout: xkb::LayoutIndex,
+    #[cfg(feature = "wayland_frontend")]
+    keymap_file: KeymapFile,
+}
+
+impl DeviceKeymap {
+    fn new(keymap: xkb::Keymap) -> DeviceKeymap {
+        DeviceKeymap {
+            led_mapping: LedMapping::from_keymap(&keymap),
+            layout: 0,
+            #[cfg(feature = "wayland_frontend")]
+            keymap_file: KeymapFile::new(&keymap),
+            keymap,
+        }
+    }
+}
+
 pub(crate) struct KbdInternal<D: SeatHandler> {
     pub(crate) focus: Option<(<D as SeatHandler>::KeyboardFocus, Serial)>,
     pending_focus: Option<<D as SeatHandler>::KeyboardFocus>,
@@ -134,6 +159,17 @@ pub(crate) struct KbdInternal<D: SeatHandler> {
     led_mapping: LedMapping,
     pub(crate) led_state: LedState,
     grab: GrabStatus<D>,
+    /// Id of the device whose keymap is active, `None` for the default keymap
+    active_device: Option<String>,
+    /// Inactive keymaps, keyed by device id or `None` for the default keymap
+    device_keymaps: HashMap<Option<String>, DeviceKeymap>,
+    layout_policy: LayoutPolicy,
+    /// Layouts remembered for focus targets with [`LayoutPolicy::PerFocus`]
+    focus_layouts: Vec<(<D as SeatHandler>::KeyboardFocus, Layout)>,
+    /// Layout last reported to the compositor
+    reported_layout: Layout,
+    /// Whether a led state change still needs to be reported to the compositor
+    led_state_pending: bool,
 }
 
 // focus_hook does not implement debug, so we have to impl Debug manually
@@ -149,6 +185,10 @@ impl<D: SeatHandler> fmt::Debug for KbdInternal<D> {
             .field("state", &self.state.get_raw_ptr())
             .field("repeat_rate", &self.repeat_rate)
             .field("repeat_delay", &self.repeat_delay)
+            .field("active_device", &self.active_device)
+            .field("device_keymaps", &self.device_keymaps.keys().collect::<Vec<_>>())
+            .field("layout_policy", &self.layout_policy)
+            .field("focus_layouts", &self.focus_layouts)
             .finish()
     }
 }
@@ -170,6 +210,7 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
         let state = xkb::State::new(&keymap);
         let led_mapping = LedMapping::from_keymap(&keymap);
         let led_state = LedState::from_state(&state, &led_mapping);
+        let reported_layout = Layout(state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE));
         Ok(KbdInternal {
             focus: None,
             pending_focus: None,
@@ -184,9 +225,123 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             led_mapping,
             led_state,
             grab: GrabStatus::None,
+            active_device: None,
+            device_keymaps: HashMap::new(),
+            layout_policy: LayoutPolicy::default(),
+            focus_layouts: Vec::new(),
+            reported_layout,
+            led_state_pending: false,
+        })
+    }
+
+    // returns whether the led state has changed
+    fn set_layout(&mut self, layout: Layout) -> bool {
+        let mods = self.mods_state.serialized;
+        let state_components =
+            self.state
+                .update_mask(mods.depressed, mods.latched, mods.locked, 0, 0, layout.0);
+        if state_components != 0 {
+            self.mods_state.update_with(&self.state);
+        }
+        self.led_state.update_with(&self.state, &self.led_mapping)
+    }
+
+    // returns the led state and layout changes, that still need to be reported to the compositor
+    fn take_pending_changes(&mut self) -> (Option<LedState>, Option<Layout>) {
+        let led_state = std::mem::take(&mut self.led_state_pending).then_some(self.led_state);
+        (led_state, self.take_layout_change())
+    }
+
+    // returns the active layout, if it changed since the last call
+    fn take_layout_change(&mut self) -> Option<Layout> {
+        let layout = Layout(self.state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE));
+        (layout != self.reported_layout).then(|| {
+            self.reported_layout = layout;
+            layout
         })
     }
 
+    // store the active layout for the current focus
+    fn remember_focus_layout(&mut self) {
+        self.focus_layouts.retain(|(focus, _)| focus.alive());
+        let Some((focus, _)) = self.focus.as_ref() else {
+            return;
+        };
+        let layout = Layout(self.state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE));
+        match self.focus_layouts.iter_mut().find(|(target, _)| target == focus) {
+            Some((_, remembered)) => *remembered = layout,
+            None => self.focus_layouts.push((focus.clone(), layout)),
+        }
+    }
+
+    /// Key of the keymap used for events of the given device,
+    /// `None` if the keymap does not need to change
+    fn keymap_key(&self, device: &impl Device) -> Option<Option<String>> {
+        // without any device keymaps the default keymap stays active, avoid querying the id
+        if self.device_keymaps.is_empty() {
+            return None;
+        }
+        let device_id = device.id();
+        if self.active_device.as_deref() == Some(device_id.as_str()) {
+            return None;
+        }
+        let device_id = Some(device_id);
+        if self.device_keymaps.contains_key(&device_id) {
+            Some(device_id)
+        } else {
+            self.active_device.is_some().then_some(None)
+        }
+    }
+
+    // Makes the keymap stored under `key` the active one.
+    //
+    // The new state is derived from the currently pressed keys and locked modifiers,
+    // so that modifiers stay coherent across devices, while the layout is the one last
+    // used by the device. Returns the previously active keymap if the keymap changed.
+    fn switch_keymap(&mut self, key: Option<String>) -> Option<&mut DeviceKeymap> {
+        if key == self.active_device {
+            return None;
+        }
+        let next = self.device_keymaps.remove(&key)?;
+
+        let mut state = xkb::State::new(&next.keymap);
+        for keycode in &self.pressed_keys {
+            state.update_key((keycode + 8).into(), xkb::KeyDirection::Down);
+        }
+        let latched = translate_mods(
+            &self.keymap,
+            &next.keymap,
+            self.state.serialize_mods(xkb::STATE_MODS_LATCHED),
+        );
+        let locked = translate_mods(
+            &self.keymap,
+            &next.keymap,
+            self.state.serialize_mods(xkb::STATE_MODS_LOCKED),
+        );
+        state.update_mask(
+            state.serialize_mods(xkb::STATE_MODS_DEPRESSED),
+            latched,
+            locked,
+            state.serialize_layout(xkb::STATE_LAYOUT_DEPRESSED),
+            0,
+            next.layout,
+        );
+
+        let previous = DeviceKeymap {
+            layout: self.state.serialize_layout(xkb::STATE_LAYOUT_LOCKED),
+            keymap: std::mem::replace(&mut self.keymap, next.keymap),
+            led_mapping: std::mem::replace(&mut self.led_mapping, next.led_mapping),
+            // swapped with the currently active file by the caller
+            #[cfg(feature = "wayland_frontend")]
+            keymap_file: next.keymap_file,
+        };
+        self.state = state;
+        self.mods_state.update_with(&self.state);
+
+        let previous_key = std::mem::replace(&mut self.active_device, key);
+        Some(self.device_keymaps.entry(previous_key).or_insert(previous))
+    }
+
     // returns whether the modifiers or led state has changed
     fn key_input(&mut self, keycode: u32, state: KeyState) -> (bool, bool) {
         // track pressed keys as xkbcommon does not seem to expose it :(
@@ -243,6 +398,15 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
     }
 }
 
+// Translate a modifier mask between keymaps by the names of the modifiers
+fn translate_mods(from: &xkb::Keymap, to: &xkb::Keymap, mask: xkb::ModMask) -> xkb::ModMask {
+    (0..from.num_mods())
+        .filter(|idx| mask & (1 << idx) != 0)
+        .map(|idx| to.mod_get_index(from.mod_get_name(idx)))
+        .filter(|&idx| idx != xkb::MOD_INVALID)
+        .fold(0, |mask, idx| mask | (1 << idx))
+}
+
 /// Errors that can be encountered when creating a keyboard handler
 #[derive(Debug, Error)]
 pub enum Error {
@@ -481,6 +645,22 @@ impl<'a> XkbContextHandler for KeysymHandle<'a> {
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Layout(pub xkb::LayoutIndex);
 
+/// Policy deciding how the active [`Layout`] is kept across focus changes
+///
+/// Layout changes are reported to the compositor through [`SeatHandler::layout_changed`]
+/// regardless of the policy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutPolicy {
+    /// The active layout is shared by all focus targets
+    #[default]
+    Global,
+    /// Every focus target remembers the layout that was active when it lost focus,
+    /// which is restored once it gets focused again
+    ///
+    /// Targets that were never focused before keep the currently active layout.
+    PerFocus,
+}
+
 /// Result for key input filtering (see [`KeyboardHandle::input`])
 #[derive(Debug)]
 pub enum FilterResult<T> {
@@ -689,9 +869,17 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         true
     }
 
-    fn update_xkb_state(&self, data: &mut D, keymap: xkb::Keymap) {
+    fn update_xkb_state(&self, data: &mut D, keymap: xkb::Keymap, device_id: Option<String>) {
         let mut internal = self.arc.internal.lock().unwrap();
 
+        // keymaps of inactive devices are only applied once the device becomes active
+        if internal.active_device != device_id {
+            internal
+                .device_keymaps
+                .insert(device_id, DeviceKeymap::new(keymap));
+            return;
+        }
+
         let mut state = xkb::State::new(&keymap);
         for key in &internal.pressed_keys {
             state.update_key((key + 8).into(), xkb::KeyDirection::Down);
@@ -716,12 +904,10 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         #[cfg(feature = "wayland_frontend")]
         self.change_keymap(data, &focus, &keymap, mods);
 
-        if leds_changed {
-            let led_state = internal.led_state;
-            std::mem::drop(internal);
-            let seat = self.get_seat(data);
-            data.led_state_changed(&seat, led_state);
-        }
+        let led_state = leds_changed.then_some(internal.led_state);
+        let layout = internal.take_layout_change();
+        std::mem::drop(internal);
+        self.notify_changes(data, led_state, layout);
     }
 
     /// Change the [`Keymap`](xkb::Keymap) used by the keyboard.
@@ -740,20 +926,152 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
             debug!("Loading keymap from string failed");
             Error::BadKeymap
         })?;
-        self.update_xkb_state(data, keymap);
+        self.update_xkb_state(data, keymap, None);
         Ok(())
     }
 
     /// Change the [`XkbConfig`] used by the keyboard.
+    ///
+    /// This changes the default keymap, used for all devices without their own keymap
+    /// (see [`KeyboardHandle::set_device_xkb_config`]).
     pub fn set_xkb_config(&self, data: &mut D, xkb_config: XkbConfig<'_>) -> Result<(), Error> {
-        let keymap = xkb_config
+        let keymap = self.compile_keymap(xkb_config)?;
+        self.update_xkb_state(data, keymap, None);
+        Ok(())
+    }
+
+    /// Use a separate [`XkbConfig`] for key events of the given device.
+    ///
+    /// Every device with its own keymap also has its own layout. Key events use the keymap of
+    /// the device selected with [`KeyboardHandle::set_active_device`].
+    pub fn set_device_xkb_config(
+        &self,
+        data: &mut D,
+        device: &impl Device,
+        xkb_config: XkbConfig<'_>,
+    ) -> Result<(), Error> {
+        let keymap = self.compile_keymap(xkb_config)?;
+        self.update_xkb_state(data, keymap, Some(device.id()));
+        Ok(())
+    }
+
+    /// Remove the keymap of the given device, making it use the default keymap again.
+    pub fn clear_device_keymap(&self, data: &mut D, device: &impl Device) {
+        let device_id = Some(device.id());
+        let is_active = self.arc.internal.lock().unwrap().active_device == device_id;
+        if is_active {
+            self.switch_keymap(data, None);
+        }
+        self.arc
+            .internal
+            .lock()
+            .unwrap()
+            .device_keymaps
+            .remove(&device_id);
+    }
+
+    /// Select the device subsequent key events originate from.
+    ///
+    /// If the device has its own keymap (see [`KeyboardHandle::set_device_xkb_config`]) and
+    /// it is not already active, its keymap is sent to the clients. Otherwise the default keymap
+    /// is used. Pressed keys and locked modifiers are carried over to the new keymap.
+    ///
+    /// This should be called before [`KeyboardHandle::input`] with the device of every key event,
+    /// if devices use different keymaps. It returns early without allocating, as long as no device
+    /// has its own keymap.
+    pub fn set_active_device(&self, data: &mut D, device: &impl Device) {
+        let key = self.arc.internal.lock().unwrap().keymap_key(device);
+        if let Some(key) = key {
+            self.switch_keymap(data, key);
+        }
+    }
+
+    /// Id of the device whose keymap is currently active, if any.
+    ///
+    /// Returns `None` if the default keymap is active.
+    pub fn active_device(&self) -> Option<String> {
+        self.arc.internal.lock().unwrap().active_device.clone()
+    }
+
+    fn compile_keymap(&self, xkb_config: XkbConfig<'_>) -> Result<xkb::Keymap, Error> {
+        xkb_config
             .compile_keymap(&self.arc.internal.lock().unwrap().context)
             .map_err(|_| {
                 debug!("Loading keymap from XkbConfig failed");
                 Error::BadKeymap
-            })?;
-        self.update_xkb_state(data, keymap);
-        Ok(())
+            })
+    }
+
+    #[instrument(level = "debug", parent = &self.arc.span, skip(self, data))]
+    fn switch_keymap(&self, data: &mut D, key: Option<String>) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        let old_mods = guard.mods_state;
+        let Some(previous) = guard.switch_keymap(key) else {
+            return;
+        };
+
+        #[cfg(feature = "wayland_frontend")]
+        std::mem::swap(&mut previous.keymap_file, &mut *self.arc.keymap.lock().unwrap());
+        #[cfg(not(feature = "wayland_frontend"))]
+        let _ = previous;
+
+        let internal = &mut *guard;
+        let leds_changed = internal
+            .led_state
+            .update_with(&internal.state, &internal.led_mapping);
+        let mods = internal.mods_state;
+        let focus = internal.focus.as_mut().map(|(focus, _)| focus);
+
+        #[cfg(feature = "wayland_frontend")]
+        let keymap_sent = {
+            let keymap_file = self.arc.keymap.lock().unwrap();
+            self.send_keymap(data, &focus, &keymap_file, mods)
+        };
+        #[cfg(not(feature = "wayland_frontend"))]
+        let keymap_sent = false;
+
+        // send_keymap already sends the modifiers along with the keymap
+        if !keymap_sent && mods != old_mods {
+            if let Some(focus) = focus {
+                let seat = self.get_seat(data);
+                focus.modifiers(&seat, data, mods, SERIAL_COUNTER.next_serial());
+            }
+        }
+
+        let led_state = leds_changed.then_some(internal.led_state);
+        let layout = internal.take_layout_change();
+        std::mem::drop(guard);
+        self.notify_changes(data, led_state, layout);
+    }
+
+    // Notify the compositor about led and layout changes, must not be called with the internal lock held
+    fn notify_changes(&self, data: &mut D, led_state: Option<LedState>, layout: Option<Layout>) {
+        if led_state.is_none() && layout.is_none() {
+            return;
+        }
+        let seat = self.get_seat(data);
+        if let Some(led_state) = led_state {
+            data.led_state_changed(&seat, led_state);
+        }
+        if let Some(layout) = layout {
+            data.layout_changed(&seat, layout);
+        }
+    }
+
+    /// Change the [`LayoutPolicy`] of this keyboard.
+    ///
+    /// Switching back to [`LayoutPolicy::Global`] forgets all remembered layouts.
+    pub fn set_layout_policy(&self, policy: LayoutPolicy) {
+        let mut internal = self.arc.internal.lock().unwrap();
+        internal.layout_policy = policy;
+        if policy == LayoutPolicy::Global {
+            internal.focus_layouts.clear();
+        }
+    }
+
+    /// Get the [`LayoutPolicy`] of this keyboard.
+    pub fn layout_policy(&self) -> LayoutPolicy {
+        self.arc.internal.lock().unwrap().layout_policy
     }
 
     /// Access the underlying Xkb state and perform mutable operations on it, like
@@ -764,7 +1082,7 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
     where
         F: FnMut(XkbContext<'_>) -> T,
     {
-        let (result, new_led_state) = {
+        let (result, new_led_state, new_layout) = {
             let internal = &mut *self.arc.internal.lock().unwrap();
             let mut mods_changed = false;
             let mut leds_changed = false;
@@ -787,13 +1105,14 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
                 };
             }
 
-            (result, leds_changed.then_some(internal.led_state))
+            (
+                result,
+                leds_changed.then_some(internal.led_state),
+                internal.take_layout_change(),
+            )
         };
 
-        if let Some(led_state) = new_led_state {
-            let seat = self.get_seat(data);
-            data.led_state_changed(&seat, led_state)
-        }
+        self.notify_changes(data, new_led_state, new_layout);
 
         result
     }
@@ -897,12 +1216,10 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         trace!(mods_state = ?guard.mods_state, sym = xkb::keysym_get_name(key_handle.modified_sym()), "Calling input filter");
         let filter_result = filter(data, &guard.mods_state, key_handle);
 
-        if leds_changed {
-            let led_state = guard.led_state;
-            std::mem::drop(guard);
-            let seat = self.get_seat(data);
-            data.led_state_changed(&seat, led_state);
-        }
+        let led_state = leds_changed.then_some(guard.led_state);
+        let layout = guard.take_layout_change();
+        std::mem::drop(guard);
+        self.notify_changes(data, led_state, layout);
 
         (filter_result, mods_changed)
     }
@@ -940,6 +1257,10 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         } else {
             trace!("No client currently focused");
         }
+
+        let (led_state, layout) = guard.take_pending_changes();
+        std::mem::drop(guard);
+        self.notify_changes(data, led_state, layout);
     }
 
     /// Set the current focus of this keyboard
@@ -956,6 +1277,10 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         guard.with_grab(&seat, |handle, grab| {
             grab.set_focus(data, handle, focus, serial);
         });
+
+        let (led_state, layout) = guard.take_pending_changes();
+        std::mem::drop(guard);
+        self.notify_changes(data, led_state, layout);
     }
 
     /// Return the key codes of the currently pressed keys.
@@ -1182,6 +1507,22 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
                 focus.leave(self.seat, data, serial);
             };
 
+            // restore the layout of the new focus, before it receives the modifiers
+            let mut leds_changed = false;
+            if self.inner.layout_policy == LayoutPolicy::PerFocus {
+                self.inner.remember_focus_layout();
+                let layout = focus.as_ref().and_then(|focus| {
+                    self.inner
+                        .focus_layouts
+                        .iter()
+                        .find(|(target, _)| target == focus)
+                        .map(|(_, layout)| *layout)
+                });
+                if let Some(layout) = layout {
+                    leds_changed = self.inner.set_layout(layout);
+                }
+            }
+
             // set new focus
             self.inner.focus = focus.map(|f| (f, serial));
             if let Some((focus, _)) = self.inner.focus.as_mut() {
@@ -1206,6 +1547,8 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
                 let KbdInternal { ref focus, .. } = *self.inner;
                 data.focus_changed(self.seat, focus.as_ref().map(|f| &f.0));
             }
+            // the changes are reported by the keyboard handle, once the internal lock is released
+            self.inner.led_state_pending |= leds_changed;
             if self.inner.focus.is_some() {
                 trace!("Focus set to new surface");
             } else {
@@ -1248,3 +1591,251 @@ impl<D: SeatHandler + 'static> KeyboardGrab<D> for DefaultGrab {
         unreachable!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::backend::input::DeviceCapability;
+    use crate::input::pointer::{
+        AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+        GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+        GestureSwipeUpdateEvent, MotionEvent, PointerTarget, RelativeMotionEvent,
+    };
+    use crate::input::SeatState;
+
+    const KEY_SHIFT: u32 = 42;
+    const KEY_CAPSLOCK: u32 = 58;
+    const KEY_Y: u32 = 21;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestFocus(u32);
+
+    impl IsAlive for TestFocus {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    impl KeyboardTarget<TestState> for TestFocus {
+        fn enter(&self, _: &Seat<TestState>, _: &mut TestState, _: Vec<KeysymHandle<'_>>, _: Serial) {}
+        fn leave(&self, _: &Seat<TestState>, _: &mut TestState, _: Serial) {}
+        fn key(
+            &self,
+            _: &Seat<TestState>,
+            _: &mut TestState,
+            _: KeysymHandle<'_>,
+            _: KeyState,
+            _: Serial,
+            _: u32,
+        ) {
+        }
+        fn modifiers(&self, _: &Seat<TestState>, _: &mut TestState, _: ModifiersState, _: Serial) {}
+    }
+
+    impl PointerTarget<TestState> for TestFocus {
+        fn enter(&self, _: &Seat<TestState>, _: &mut TestState, _: &MotionEvent) {}
+        fn motion(&self, _: &Seat<TestState>, _: &mut TestState, _: &MotionEvent) {}
+        fn relative_motion(&self, _: &Seat<TestState>, _: &mut TestState, _: &RelativeMotionEvent) {}
+        fn button(&self, _: &Seat<TestState>, _: &mut TestState, _: &ButtonEvent) {}
+        fn axis(&self, _: &Seat<TestState>, _: &mut TestState, _: AxisFrame) {}
+        fn frame(&self, _: &Seat<TestState>, _: &mut TestState) {}
+        fn gesture_swipe_begin(&self, _: &Seat<TestState>, _: &mut TestState, _: &GestureSwipeBeginEvent) {}
+        fn gesture_swipe_update(&self, _: &Seat<TestState>, _: &mut TestState, _: &GestureSwipeUpdateEvent) {}
+        fn gesture_swipe_end(&self, _: &Seat<TestState>, _: &mut TestState, _: &GestureSwipeEndEvent) {}
+        fn gesture_pinch_begin(&self, _: &Seat<TestState>, _: &mut TestState, _: &GesturePinchBeginEvent) {}
+        fn gesture_pinch_update(&self, _: &Seat<TestState>, _: &mut TestState, _: &GesturePinchUpdateEvent) {}
+        fn gesture_pinch_end(&self, _: &Seat<TestState>, _: &mut TestState, _: &GesturePinchEndEvent) {}
+        fn gesture_hold_begin(&self, _: &Seat<TestState>, _: &mut TestState, _: &GestureHoldBeginEvent) {}
+        fn gesture_hold_end(&self, _: &Seat<TestState>, _: &mut TestState, _: &GestureHoldEndEvent) {}
+        fn leave(&self, _: &Seat<TestState>, _: &mut TestState, _: Serial, _: u32) {}
+    }
+
+    struct TestState {
+        seat_state: SeatState<TestState>,
+        layouts: Vec<Layout>,
+    }
+
+    impl SeatHandler for TestState {
+        type KeyboardFocus = TestFocus;
+        type PointerFocus = TestFocus;
+
+        fn seat_state(&mut self) -> &mut SeatState<TestState> {
+            &mut self.seat_state
+        }
+
+        // querying the keyboard deadlocks, if the callbacks are invoked with the keyboard locked
+        fn led_state_changed(&mut self, seat: &Seat<Self>, led_state: LedState) {
+            let keyboard = seat.get_keyboard().unwrap();
+            assert_eq!(keyboard.led_state(), led_state);
+        }
+
+        fn layout_changed(&mut self, seat: &Seat<Self>, layout: Layout) {
+            let keyboard = seat.get_keyboard().unwrap();
+            let active = keyboard.with_xkb_state(self, |context| context.active_layout());
+            assert_eq!(active, layout);
+            self.layouts.push(layout);
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct TestDevice(&'static str);
+
+    impl Device for TestDevice {
+        fn id(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn has_capability(&self, capability: DeviceCapability) -> bool {
+            capability == DeviceCapability::Keyboard
+        }
+
+        fn usb_id(&self) -> Option<(u32, u32)> {
+            None
+        }
+
+        fn syspath(&self) -> Option<PathBuf> {
+            None
+        }
+    }
+
+    fn keyboard_with_config(xkb_config: XkbConfig<'_>) -> (TestState, KeyboardHandle<TestState>) {
+        let mut state = TestState {
+            seat_state: SeatState::new(),
+            layouts: Vec::new(),
+        };
+        let mut seat = state.seat_state.new_seat("seat-0");
+        let keyboard = seat.add_keyboard(xkb_config, 200, 25).unwrap();
+        (state, keyboard)
+    }
+
+    fn keyboard() -> (TestState, KeyboardHandle<TestState>) {
+        keyboard_with_config(XkbConfig::default())
+    }
+
+    fn key(state: &mut TestState, keyboard: &KeyboardHandle<TestState>, keycode: u32, key_state: KeyState) {
+        let serial = SERIAL_COUNTER.next_serial();
+        keyboard.input::<(), _>(state, keycode, key_state, serial, 0, |_, _, _| {
+            FilterResult::Forward
+        });
+    }
+
+    fn sym(state: &mut TestState, keyboard: &KeyboardHandle<TestState>, keycode: u32) -> Keysym {
+        let serial = SERIAL_COUNTER.next_serial();
+        keyboard
+            .input(state, keycode, KeyState::Pressed, serial, 0, |_, _, handle| {
+                FilterResult::Intercept(handle.modified_sym())
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn device_keymap_keeps_pressed_keys_and_modifiers() {
+        let (mut state, keyboard) = keyboard();
+        let builtin = TestDevice("builtin");
+        let external = TestDevice("external");
+        keyboard
+            .set_device_xkb_config(
+                &mut state,
+                &external,
+                XkbConfig {
+                    layout: "de",
+                    ..XkbConfig::default()
+                },
+            )
+            .unwrap();
+
+        keyboard.set_active_device(&mut state, &builtin);
+        assert_eq!(keyboard.active_device(), None);
+        key(&mut state, &keyboard, KEY_CAPSLOCK, KeyState::Pressed);
+        key(&mut state, &keyboard, KEY_CAPSLOCK, KeyState::Released);
+        key(&mut state, &keyboard, KEY_SHIFT, KeyState::Pressed);
+        assert_eq!(sym(&mut state, &keyboard, KEY_Y), Keysym::y);
+
+        keyboard.set_active_device(&mut state, &external);
+        assert_eq!(keyboard.active_device().as_deref(), Some("external"));
+        let mods = keyboard.modifier_state();
+        assert!(mods.shift && mods.caps_lock);
+        let pressed = keyboard.pressed_keys();
+        assert!(pressed.contains(&KEY_SHIFT.into()) && pressed.contains(&KEY_Y.into()));
+        // shift and caps lock cancel out
+        assert_eq!(sym(&mut state, &keyboard, KEY_Y), Keysym::z);
+
+        // the shift key pressed on the previous keymap is released on the new one
+        key(&mut state, &keyboard, KEY_SHIFT, KeyState::Released);
+        assert!(!keyboard.modifier_state().shift);
+        assert_eq!(sym(&mut state, &keyboard, KEY_Y), Keysym::Z);
+
+        keyboard.set_active_device(&mut state, &builtin);
+        assert_eq!(keyboard.active_device(), None);
+        let mods = keyboard.modifier_state();
+        assert!(!mods.shift && mods.caps_lock);
+        assert_eq!(sym(&mut state, &keyboard, KEY_Y), Keysym::Y);
+    }
+
+    #[test]
+    fn active_device_without_device_keymaps() {
+        let (mut state, keyboard) = keyboard();
+        let device = TestDevice("builtin");
+
+        keyboard.set_active_device(&mut state, &device);
+        assert_eq!(keyboard.active_device(), None);
+        assert!(keyboard
+            .arc
+            .internal
+            .lock()
+            .unwrap()
+            .keymap_key(&device)
+            .is_none());
+
+        keyboard
+            .set_device_xkb_config(&mut state, &device, XkbConfig::default())
+            .unwrap();
+        assert_eq!(
+            keyboard.arc.internal.lock().unwrap().keymap_key(&device),
+            Some(Some("builtin".to_string()))
+        );
+        keyboard.set_active_device(&mut state, &device);
+        assert_eq!(keyboard.active_device().as_deref(), Some("builtin"));
+        // the active device does not switch the keymap again
+        assert!(keyboard
+            .arc
+            .internal
+            .lock()
+            .unwrap()
+            .keymap_key(&device)
+            .is_none());
+        // devices without a keymap switch back to the default keymap
+        let other = TestDevice("other");
+        assert_eq!(
+            keyboard.arc.internal.lock().unwrap().keymap_key(&other),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn focus_change_notifies_without_lock() {
+        let (mut state, keyboard) = keyboard_with_config(XkbConfig {
+            layout: "us,de",
+            ..XkbConfig::default()
+        });
+        keyboard.set_layout_policy(LayoutPolicy::PerFocus);
+
+        keyboard.set_focus(&mut state, Some(TestFocus(1)), SERIAL_COUNTER.next_serial());
+        keyboard.with_xkb_state(&mut state, |mut context| context.cycle_next_layout());
+        assert_eq!(state.layouts, [Layout(1)]);
+
+        keyboard.set_focus(&mut state, Some(TestFocus(2)), SERIAL_COUNTER.next_serial());
+        keyboard.with_xkb_state(&mut state, |mut context| context.set_layout(Layout(0)));
+        assert_eq!(state.layouts, [Layout(1), Layout(0)]);
+
+        // restoring the layout of the first focus reports the change
+        keyboard.set_focus(&mut state, Some(TestFocus(1)), SERIAL_COUNTER.next_serial());
+        assert_eq!(state.layouts, [Layout(1), Layout(0), Layout(1)]);
+    }
+}
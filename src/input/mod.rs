@@ -102,6 +102,8 @@
 //! [`seat`](crate::wayland::seat) also provides an abstraction to send touch-events to client,
 //! further helpers are not provided at this point.
 //! [`tablet_manager`](crate::wayland::tablet_manager) also provides client interaction for drawing tablets.
+//! Gestures on touchscreens can be recognized from raw touch events with the
+//! [`touch_gestures`] module.
 //!
 //! You can add these capabilities via methods of the [`Seat`] struct:
 //! [`Seat::add_keyboard`] and [`Seat::add_pointer`].
@@ -117,12 +119,16 @@ use std::{
 
 use tracing::{info_span, instrument};
 
-use self::keyboard::{Error as KeyboardError, KeyboardHandle, KeyboardTarget, LedState};
+use self::keyboard::{Error as KeyboardError, KeyboardHandle, KeyboardTarget, Layout, LedState};
 use self::pointer::{CursorImageStatus, PointerHandle, PointerTarget};
+use self::switch::{SwitchStates, SwitchTracker};
+use crate::backend::input::{Device, Event, InputBackend, SwitchToggleEvent};
 use crate::utils::user_data::UserDataMap;
 
 pub mod keyboard;
 pub mod pointer;
+pub mod switch;
+pub mod touch_gestures;
 
 /// Handler trait for Seats
 pub trait SeatHandler: Sized {
@@ -142,6 +148,13 @@ pub trait SeatHandler: Sized {
 
     /// Callback that will be notified whenever the keyboard led state changes.
     fn led_state_changed(&mut self, _seat: &Seat<Self>, _led_state: LedState) {}
+
+    /// Callback that will be notified whenever the active keyboard layout changes.
+    fn layout_changed(&mut self, _seat: &Seat<Self>, _layout: Layout) {}
+
+    /// Callback that will be notified whenever the lid or tablet-mode switches change,
+    /// see [`Seat::process_switch_event`].
+    fn switches_changed(&mut self, _seat: &Seat<Self>, _states: SwitchStates) {}
 }
 /// Delegate type for all [Seat] globals.
 ///
@@ -190,6 +203,7 @@ impl<D: SeatHandler> Hash for Seat<D> {
 pub(crate) struct Inner<D: SeatHandler> {
     pub(crate) pointer: Option<PointerHandle<D>>,
     pub(crate) keyboard: Option<KeyboardHandle<D>>,
+    pub(crate) switches: SwitchTracker,
 
     #[cfg(feature = "wayland_frontend")]
     pub(crate) touch: Option<crate::wayland::seat::TouchHandle>,
@@ -205,6 +219,7 @@ impl<D: SeatHandler> fmt::Debug for Inner<D> {
         f.debug_struct("Inner")
             .field("pointer", &self.pointer)
             .field("keyboard", &self.keyboard)
+            .field("switches", &self.switches)
             .finish()
     }
 }
@@ -215,6 +230,7 @@ impl<D: SeatHandler> fmt::Debug for Inner<D> {
         f.debug_struct("Inner")
             .field("pointer", &self.pointer)
             .field("keyboard", &self.keyboard)
+            .field("switches", &self.switches)
             .field("touch", &self.touch)
             .field("global", &self.global)
             .field("known_seats", &self.known_seats)
@@ -273,6 +289,7 @@ impl<D: SeatHandler> SeatState<D> {
             inner: Mutex::new(Inner {
                 pointer: None,
                 keyboard: None,
+                switches: SwitchTracker::new(),
 
                 #[cfg(feature = "wayland_frontend")]
                 touch: None,
@@ -528,6 +545,47 @@ impl<D: SeatHandler + 'static> Seat<D> {
         }
     }
 
+    /// Current state of the lid and tablet-mode switches of this seat
+    pub fn switch_states(&self) -> SwitchStates {
+        self.arc.inner.lock().unwrap().switches.states()
+    }
+
+    /// Update the switch state of this seat from a switch toggle event
+    ///
+    /// [`SeatHandler::switches_changed`] is called, if the combined state changed.
+    pub fn process_switch_event<B: InputBackend>(&self, data: &mut D, event: &B::SwitchToggleEvent) {
+        let Some(switch) = event.switch() else {
+            return;
+        };
+        let changed =
+            self.arc
+                .inner
+                .lock()
+                .unwrap()
+                .switches
+                .toggle(&event.device().id(), switch, event.state());
+        if let Some(states) = changed {
+            data.switches_changed(self, states);
+        }
+    }
+
+    /// Forget the switches of a removed device
+    ///
+    /// [`SeatHandler::switches_changed`] is called, if the combined state changed,
+    /// e.g. because a detached keyboard cover was reporting a closed lid.
+    pub fn remove_switch_device(&self, data: &mut D, device: &impl Device) {
+        let changed = self
+            .arc
+            .inner
+            .lock()
+            .unwrap()
+            .switches
+            .remove_device(&device.id());
+        if let Some(states) = changed {
+            data.switches_changed(self, states);
+        }
+    }
+
     /// Gets this seat's name
     pub fn name(&self) -> &str {
         &self.arc.name
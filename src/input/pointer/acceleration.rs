@@ -0,0 +1,299 @@
+use std::{collections::VecDeque, fmt};
+
+use crate::{
+    backend::input::{
+        config::{AccelProfile, DeviceConfig},
+        InputBackend, PointerMotionEvent,
+    },
+    utils::{Logical, Point},
+};
+
+use super::RelativeMotionEvent;
+
+/// Time window in microseconds used to determine the pointer velocity
+const VELOCITY_WINDOW: u64 = 100_000;
+
+/// Acceleration curve for relative pointer motion
+///
+/// Implementations map the current pointer velocity, in logical pixels per millisecond,
+/// to the factor the motion delta gets multiplied with.
+pub trait AccelerationProfile: fmt::Debug + Send {
+    /// Acceleration factor for the given pointer velocity
+    fn factor(&self, velocity: f64) -> f64;
+}
+
+/// Constant acceleration, independent of the pointer velocity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlatProfile {
+    speed: f64,
+}
+
+impl FlatProfile {
+    /// Create a flat profile
+    ///
+    /// `speed` ranges from `-1.0` to `1.0`, resulting in factors from `0.0` to `2.0`.
+    pub fn new(speed: f64) -> FlatProfile {
+        FlatProfile {
+            speed: speed.clamp(-1.0, 1.0),
+        }
+    }
+}
+
+impl AccelerationProfile for FlatProfile {
+    fn factor(&self, _velocity: f64) -> f64 {
+        1.0 + self.speed
+    }
+}
+
+/// Acceleration increasing with the pointer velocity
+///
+/// Slow motion is left unaccelerated for precise pointing. Above a threshold the factor grows
+/// linearly with the velocity, up to a maximum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveProfile {
+    speed: f64,
+}
+
+impl AdaptiveProfile {
+    /// Create an adaptive profile
+    ///
+    /// `speed` ranges from `-1.0` to `1.0`. Higher values lower the velocity at which
+    /// acceleration kicks in and raise the maximum acceleration.
+    pub fn new(speed: f64) -> AdaptiveProfile {
+        AdaptiveProfile {
+            speed: speed.clamp(-1.0, 1.0),
+        }
+    }
+}
+
+impl AccelerationProfile for AdaptiveProfile {
+    fn factor(&self, velocity: f64) -> f64 {
+        let threshold = 0.4 - 0.25 * self.speed;
+        let max_factor = 2.0 + self.speed;
+        let incline = 1.1;
+
+        if velocity <= threshold {
+            1.0
+        } else {
+            (1.0 + (velocity - threshold) * incline).min(max_factor)
+        }
+    }
+}
+
+/// User-defined acceleration curve
+///
+/// The curve is given as output velocities sampled at fixed steps of the input velocity,
+/// starting at zero. Velocities between the points are linearly interpolated, velocities
+/// past the last point are extrapolated from the last two points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomProfile {
+    step: f64,
+    points: Vec<f64>,
+}
+
+impl CustomProfile {
+    /// Create a custom profile
+    ///
+    /// `points[i]` is the output velocity for an input velocity of `i * step`.
+    ///
+    /// Returns `None` if `step` is not positive or less than two points are given.
+    pub fn new(step: f64, points: Vec<f64>) -> Option<CustomProfile> {
+        (step > 0.0 && points.len() >= 2).then_some(CustomProfile { step, points })
+    }
+
+    fn output_velocity(&self, velocity: f64) -> f64 {
+        let position = velocity / self.step;
+        let index = (position.floor() as usize).min(self.points.len() - 2);
+        let (low, high) = (self.points[index], self.points[index + 1]);
+        low + (high - low) * (position - index as f64)
+    }
+}
+
+impl AccelerationProfile for CustomProfile {
+    fn factor(&self, velocity: f64) -> f64 {
+        if velocity <= 0.0 {
+            // slope of the curve at its origin
+            return (self.points[1] - self.points[0]) / self.step;
+        }
+        self.output_velocity(velocity) / velocity
+    }
+}
+
+/// Acceleration stage for relative pointer motion
+///
+/// Input backends other than libinput report pointer motion unaccelerated. A `PointerAccelerator`
+/// applies an [`AccelerationProfile`] to these deltas, before they are used to move the pointer
+/// with [`PointerHandle::motion`](super::PointerHandle::motion). The unaccelerated deltas are kept
+/// intact, so the resulting [`RelativeMotionEvent`] can be passed to
+/// [`PointerHandle::relative_motion`](super::PointerHandle::relative_motion) as is.
+///
+/// The pointer velocity is tracked across events, so a separate accelerator should be used
+/// for every device.
+///
+/// Devices of the [`VirtualInputBackend`](crate::backend::virtual_input::VirtualInputBackend) can
+/// use an accelerator through
+/// [`VirtualInputHandle::set_pointer_accelerator`](crate::backend::virtual_input::VirtualInputHandle::set_pointer_accelerator).
+#[derive(Debug)]
+pub struct PointerAccelerator {
+    profile: Box<dyn AccelerationProfile>,
+    /// Timestamps and distances of recent motion events
+    history: VecDeque<(u64, f64)>,
+}
+
+impl PointerAccelerator {
+    /// Create an accelerator using the given profile
+    pub fn new(profile: impl AccelerationProfile + 'static) -> PointerAccelerator {
+        PointerAccelerator {
+            profile: Box::new(profile),
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Create an accelerator from the acceleration settings of a [`DeviceConfig`]
+    ///
+    /// Unset settings default to an adaptive profile with a speed of `0.0`.
+    pub fn from_config(config: &DeviceConfig) -> PointerAccelerator {
+        let speed = config.accel_speed.unwrap_or(0.0);
+        match config.accel_profile.unwrap_or(AccelProfile::Adaptive) {
+            AccelProfile::Flat => PointerAccelerator::new(FlatProfile::new(speed)),
+            AccelProfile::Adaptive => PointerAccelerator::new(AdaptiveProfile::new(speed)),
+        }
+    }
+
+    /// Change the profile of this accelerator
+    pub fn set_profile(&mut self, profile: impl AccelerationProfile + 'static) {
+        self.profile = Box::new(profile);
+    }
+
+    /// Forget the tracked pointer velocity
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// Accelerate a pointer motion
+    ///
+    /// `utime` is the timestamp of the motion in microseconds.
+    pub fn accelerate(
+        &mut self,
+        delta: Point<f64, Logical>,
+        delta_unaccel: Point<f64, Logical>,
+        utime: u64,
+    ) -> RelativeMotionEvent {
+        let distance = (delta_unaccel.x.powi(2) + delta_unaccel.y.powi(2)).sqrt();
+        let velocity = self.velocity(distance, utime);
+        let factor = self.profile.factor(velocity);
+
+        RelativeMotionEvent {
+            delta: delta.upscale(factor),
+            delta_unaccel,
+            utime,
+        }
+    }
+
+    /// Accelerate the motion of a [`PointerMotionEvent`]
+    pub fn accelerate_event<B: InputBackend, E: PointerMotionEvent<B>>(
+        &mut self,
+        event: &E,
+    ) -> RelativeMotionEvent {
+        self.accelerate(event.delta(), event.delta_unaccel(), event.time())
+    }
+
+    // velocity in logical pixels per millisecond over the recent motion events
+    fn velocity(&mut self, distance: f64, utime: u64) -> f64 {
+        // a timestamp going backwards means the device was reset
+        if self
+            .history
+            .back()
+            .map(|&(time, _)| time > utime)
+            .unwrap_or(false)
+        {
+            self.history.clear();
+        }
+        while let Some(&(time, _)) = self.history.front() {
+            if time + VELOCITY_WINDOW >= utime {
+                break;
+            }
+            self.history.pop_front();
+        }
+
+        let velocity = match self.history.front() {
+            Some(&(oldest, _)) if oldest < utime => {
+                let travelled = self.history.iter().skip(1).map(|(_, d)| d).sum::<f64>() + distance;
+                travelled / ((utime - oldest) as f64 / 1000.0)
+            }
+            _ => 0.0,
+        };
+        self.history.push_back((utime, distance));
+        velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccelerationProfile, AdaptiveProfile, CustomProfile, FlatProfile, PointerAccelerator};
+    use crate::backend::{
+        input::DeviceCapability,
+        replay::{ReplayDevice, ReplayPointerMotionEvent},
+    };
+
+    #[test]
+    fn profiles() {
+        assert_eq!(FlatProfile::new(0.5).factor(10.0), 1.5);
+        assert_eq!(FlatProfile::new(-3.0).factor(10.0), 0.0);
+
+        let adaptive = AdaptiveProfile::new(0.0);
+        assert_eq!(adaptive.factor(0.1), 1.0);
+        assert!(adaptive.factor(1.0) > 1.0);
+        assert_eq!(adaptive.factor(100.0), 2.0);
+
+        assert!(CustomProfile::new(1.0, vec![0.0]).is_none());
+        let custom = CustomProfile::new(1.0, vec![0.0, 1.0, 4.0]).unwrap();
+        assert_eq!(custom.factor(0.0), 1.0);
+        assert_eq!(custom.factor(1.0), 1.0);
+        assert_eq!(custom.factor(2.0), 2.0);
+        // extrapolated from the last two points
+        assert_eq!(custom.factor(3.0), 7.0 / 3.0);
+    }
+
+    #[test]
+    fn keeps_unaccelerated_delta() {
+        let mut accel = PointerAccelerator::new(FlatProfile::new(1.0));
+        let event = accel.accelerate((3.0, 4.0).into(), (3.0, 4.0).into(), 1000);
+        assert_eq!(event.delta, (6.0, 8.0).into());
+        assert_eq!(event.delta_unaccel, (3.0, 4.0).into());
+        assert_eq!(event.utime, 1000);
+    }
+
+    #[test]
+    fn accelerate_event() {
+        let mut accel = PointerAccelerator::new(FlatProfile::new(1.0));
+        let event = ReplayPointerMotionEvent {
+            time: 1000,
+            device: ReplayDevice::new(
+                0,
+                "0".into(),
+                "mouse".into(),
+                vec![DeviceCapability::Pointer],
+                None,
+            ),
+            delta: (1.0, 0.0),
+            delta_unaccel: (3.0, 4.0),
+        };
+        let event = accel.accelerate_event(&event);
+        assert_eq!(event.delta, (2.0, 0.0).into());
+        assert_eq!(event.delta_unaccel, (3.0, 4.0).into());
+        assert_eq!(event.utime, 1000);
+    }
+
+    #[test]
+    fn velocity() {
+        let mut accel = PointerAccelerator::new(FlatProfile::new(0.0));
+        assert_eq!(accel.velocity(5.0, 0), 0.0);
+        // 5 pixels travelled within 2ms since the previous event
+        assert_eq!(accel.velocity(5.0, 2_000), 2.5);
+        // old events are outside of the window
+        assert_eq!(accel.velocity(5.0, 1_000_000), 0.0);
+        // time going backwards resets the history
+        assert_eq!(accel.velocity(5.0, 10), 0.0);
+    }
+}
@@ -0,0 +1,503 @@
+//! Barriers constraining the motion of the pointer
+//!
+//! A [`PointerBarrier`] is a line segment the pointer can not cross in the blocked directions,
+//! optionally releasing it after it was pushed against the barrier far enough. [`PointerBarriers`]
+//! manages a set of barriers, including ones generated from the edges of the outputs, and applies
+//! them to pointer motion.
+
+use crate::utils::{Logical, Point, Rectangle};
+
+#[cfg(feature = "desktop")]
+use crate::desktop::{space::SpaceElement, Space};
+
+/// Distance the pointer is kept away from a barrier it was moving towards
+///
+/// This matches the precision of `wl_fixed`, so clients see the pointer on the correct side.
+const BARRIER_EPSILON: f64 = 1.0 / 256.0;
+
+/// Maximum number of barriers the pointer can slide along during a single motion
+const MAX_BARRIER_HITS: usize = 4;
+
+bitflags::bitflags! {
+    /// Directions of motion blocked by a [`PointerBarrier`]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct BarrierDirections: u8 {
+        /// Motion towards increasing x coordinates
+        const POSITIVE_X = 0b0001;
+        /// Motion towards decreasing x coordinates
+        const NEGATIVE_X = 0b0010;
+        /// Motion towards increasing y coordinates
+        const POSITIVE_Y = 0b0100;
+        /// Motion towards decreasing y coordinates
+        const NEGATIVE_Y = 0b1000;
+    }
+}
+
+/// Axis-aligned line segment blocking pointer motion
+///
+/// A pointer hitting a barrier is stopped in front of it and slides along it with the
+/// remaining motion. Barriers with a release pressure let the pointer pass once it was pushed
+/// against them far enough.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerBarrier {
+    vertical: bool,
+    /// x coordinate of vertical, y coordinate of horizontal barriers
+    position: f64,
+    /// Range along the barrier
+    start: f64,
+    end: f64,
+    blocked: BarrierDirections,
+    release_pressure: Option<f64>,
+}
+
+impl PointerBarrier {
+    /// Create a vertical barrier at `x`, spanning from `y1` to `y2`
+    pub fn vertical(x: f64, y1: f64, y2: f64, blocked: BarrierDirections) -> PointerBarrier {
+        PointerBarrier {
+            vertical: true,
+            position: x,
+            start: y1.min(y2),
+            end: y1.max(y2),
+            blocked,
+            release_pressure: None,
+        }
+    }
+
+    /// Create a horizontal barrier at `y`, spanning from `x1` to `x2`
+    pub fn horizontal(y: f64, x1: f64, x2: f64, blocked: BarrierDirections) -> PointerBarrier {
+        PointerBarrier {
+            vertical: false,
+            position: y,
+            start: x1.min(x2),
+            end: x1.max(x2),
+            blocked,
+            release_pressure: None,
+        }
+    }
+
+    /// Let the pointer pass after pushing it against the barrier by `pressure` logical pixels
+    pub fn with_release_pressure(mut self, pressure: f64) -> PointerBarrier {
+        self.release_pressure = Some(pressure);
+        self
+    }
+
+    /// Check whether the motion hits this barrier
+    ///
+    /// Returns the fraction of the motion until the barrier is hit, and the distance the
+    /// motion goes past it.
+    fn hit(&self, from: Point<f64, Logical>, to: Point<f64, Logical>) -> Option<(f64, f64)> {
+        let (from_main, from_cross, to_main, to_cross) = self.split(from, to);
+        let positive = to_main > from_main;
+        let crosses = if positive {
+            from_main < self.position && to_main >= self.position
+        } else {
+            from_main >= self.position && to_main < self.position
+        };
+        let direction = match (self.vertical, positive) {
+            (true, true) => BarrierDirections::POSITIVE_X,
+            (true, false) => BarrierDirections::NEGATIVE_X,
+            (false, true) => BarrierDirections::POSITIVE_Y,
+            (false, false) => BarrierDirections::NEGATIVE_Y,
+        };
+        if !crosses || !self.blocked.contains(direction) {
+            return None;
+        }
+
+        let t = (self.position - from_main) / (to_main - from_main);
+        let cross = from_cross + (to_cross - from_cross) * t;
+        (self.start..=self.end)
+            .contains(&cross)
+            .then_some((t, (to_main - self.position).abs()))
+    }
+
+    /// Position where the motion is stopped and the remaining motion along the barrier
+    fn clamp(
+        &self,
+        from: Point<f64, Logical>,
+        to: Point<f64, Logical>,
+        t: f64,
+    ) -> (Point<f64, Logical>, Point<f64, Logical>) {
+        let (from_main, from_cross, to_main, to_cross) = self.split(from, to);
+        let main = if to_main > from_main {
+            self.position - BARRIER_EPSILON
+        } else {
+            self.position
+        };
+        let cross = from_cross + (to_cross - from_cross) * t;
+        if self.vertical {
+            ((main, cross).into(), (main, to_cross).into())
+        } else {
+            ((cross, main).into(), (to_cross, main).into())
+        }
+    }
+
+    // coordinates across and along the barrier
+    fn split(&self, from: Point<f64, Logical>, to: Point<f64, Logical>) -> (f64, f64, f64, f64) {
+        if self.vertical {
+            (from.x, from.y, to.x, to.y)
+        } else {
+            (from.y, from.x, to.y, to.x)
+        }
+    }
+}
+
+/// Identifier of a barrier added to [`PointerBarriers`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BarrierId(usize);
+
+#[derive(Debug)]
+struct BarrierEntry {
+    /// `None` for barriers generated from output edges
+    id: Option<BarrierId>,
+    barrier: PointerBarrier,
+    pressure: f64,
+}
+
+/// Set of barriers constraining pointer motion
+///
+/// Besides custom barriers, the edges of the union of all outputs can be turned into barriers
+/// with [`PointerBarriers::set_outputs`]. This keeps the pointer from getting lost in dead zones
+/// between outputs of different sizes, and optionally makes the edges between outputs sticky.
+///
+/// Barriers are applied with [`PointerBarriers::apply`] to the new pointer location before
+/// computing the focus, so the constrained location is passed to
+/// [`PointerHandle::motion`](super::PointerHandle::motion):
+///
+/// ```
+/// use smithay::input::pointer::PointerBarriers;
+/// use smithay::utils::{Logical, Point, Rectangle};
+///
+/// let mut barriers = PointerBarriers::new();
+/// // a smaller output right of a bigger one, leaving a dead zone below it
+/// barriers.set_outputs(
+///     [
+///         Rectangle::from_loc_and_size((0, 0), (1920, 1080)),
+///         Rectangle::from_loc_and_size((1920, 0), (1280, 720)),
+///     ],
+///     None,
+/// );
+///
+/// let location: Point<f64, Logical> = (1900.0, 900.0).into();
+/// let delta: Point<f64, Logical> = (40.0, 10.0).into();
+/// let new_location = barriers.apply(location, location + delta);
+/// // the pointer is stopped at the edge of the dead zone
+/// assert!(new_location.x < 1920.0);
+/// assert_eq!(new_location.y, 910.0);
+/// // find the focus under `new_location` and send the motion to the pointer
+/// ```
+#[derive(Debug, Default)]
+pub struct PointerBarriers {
+    barriers: Vec<BarrierEntry>,
+    next_id: usize,
+}
+
+impl PointerBarriers {
+    /// Create an empty set of barriers
+    pub fn new() -> PointerBarriers {
+        PointerBarriers::default()
+    }
+
+    /// Add a barrier
+    pub fn add(&mut self, barrier: PointerBarrier) -> BarrierId {
+        let id = BarrierId(self.next_id);
+        self.next_id += 1;
+        self.barriers.push(BarrierEntry {
+            id: Some(id),
+            barrier,
+            pressure: 0.0,
+        });
+        id
+    }
+
+    /// Remove a barrier
+    pub fn remove(&mut self, id: BarrierId) -> Option<PointerBarrier> {
+        let index = self.barriers.iter().position(|entry| entry.id == Some(id))?;
+        Some(self.barriers.remove(index).barrier)
+    }
+
+    /// Replace the barriers along output edges
+    ///
+    /// The outer edges of the union of the given output geometries block the pointer from leaving
+    /// it. If `sticky_pressure` is set, the edges between adjacent outputs only let the pointer
+    /// pass after pushing it this far into the edge.
+    pub fn set_outputs(
+        &mut self,
+        outputs: impl IntoIterator<Item = Rectangle<i32, Logical>>,
+        sticky_pressure: Option<f64>,
+    ) {
+        self.barriers.retain(|entry| entry.id.is_some());
+
+        let outputs = outputs.into_iter().collect::<Vec<_>>();
+        for geo in &outputs {
+            let (left, top) = (geo.loc.x, geo.loc.y);
+            let (right, bottom) = (geo.loc.x + geo.size.w, geo.loc.y + geo.size.h);
+
+            // position, range along the edge, neighbor ranges, blocked direction
+            let edges = [
+                (
+                    true,
+                    right,
+                    (top, bottom),
+                    neighbors(&outputs, |o| o.loc.x == right, true),
+                    BarrierDirections::POSITIVE_X,
+                ),
+                (
+                    true,
+                    left,
+                    (top, bottom),
+                    neighbors(&outputs, |o| o.loc.x + o.size.w == left, true),
+                    BarrierDirections::NEGATIVE_X,
+                ),
+                (
+                    false,
+                    bottom,
+                    (left, right),
+                    neighbors(&outputs, |o| o.loc.y == bottom, false),
+                    BarrierDirections::POSITIVE_Y,
+                ),
+                (
+                    false,
+                    top,
+                    (left, right),
+                    neighbors(&outputs, |o| o.loc.y + o.size.h == top, false),
+                    BarrierDirections::NEGATIVE_Y,
+                ),
+            ];
+
+            for (vertical, position, range, neighbors, blocked) in edges {
+                let mut outer = vec![range];
+                for neighbor in neighbors {
+                    let shared = (range.0.max(neighbor.0), range.1.min(neighbor.1));
+                    if shared.0 >= shared.1 {
+                        continue;
+                    }
+                    outer = outer
+                        .into_iter()
+                        .flat_map(|(start, end)| [(start, end.min(shared.0)), (start.max(shared.1), end)])
+                        .filter(|(start, end)| start < end)
+                        .collect();
+
+                    if let Some(pressure) = sticky_pressure {
+                        self.push_edge(vertical, position, shared, blocked, Some(pressure));
+                    }
+                }
+                for segment in outer {
+                    self.push_edge(vertical, position, segment, blocked, None);
+                }
+            }
+        }
+    }
+
+    /// Replace the barriers along output edges with the outputs of a [`Space`]
+    ///
+    /// See [`PointerBarriers::set_outputs`].
+    #[cfg(feature = "desktop")]
+    pub fn set_space_outputs<E: SpaceElement + PartialEq>(
+        &mut self,
+        space: &Space<E>,
+        sticky_pressure: Option<f64>,
+    ) {
+        let outputs = space
+            .outputs()
+            .filter_map(|output| space.output_geometry(output))
+            .collect::<Vec<_>>();
+        self.set_outputs(outputs, sticky_pressure);
+    }
+
+    /// Constrain a pointer motion
+    ///
+    /// Returns the location the pointer moving from `from` to `to` ends up at.
+    pub fn apply(&mut self, from: Point<f64, Logical>, to: Point<f64, Logical>) -> Point<f64, Logical> {
+        let (mut from, mut to) = (from, to);
+        let mut hit = Vec::new();
+        let mut released = Vec::new();
+
+        for _ in 0..MAX_BARRIER_HITS {
+            let closest = self
+                .barriers
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !released.contains(index))
+                .filter_map(|(index, entry)| entry.barrier.hit(from, to).map(|hit| (index, hit)))
+                .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b));
+            let Some((index, (t, overshoot))) = closest else {
+                break;
+            };
+
+            let entry = &mut self.barriers[index];
+            hit.push(index);
+            if let Some(limit) = entry.barrier.release_pressure {
+                entry.pressure += overshoot;
+                if entry.pressure >= limit {
+                    entry.pressure = 0.0;
+                    released.push(index);
+                    continue;
+                }
+            }
+            (from, to) = entry.barrier.clamp(from, to, t);
+        }
+
+        // pressure only builds up while continuously pushing against a barrier
+        for (index, entry) in self.barriers.iter_mut().enumerate() {
+            if !hit.contains(&index) {
+                entry.pressure = 0.0;
+            }
+        }
+        to
+    }
+
+    fn push_edge(
+        &mut self,
+        vertical: bool,
+        position: i32,
+        (start, end): (i32, i32),
+        blocked: BarrierDirections,
+        release_pressure: Option<f64>,
+    ) {
+        let barrier = if vertical {
+            PointerBarrier::vertical(position as f64, start as f64, end as f64, blocked)
+        } else {
+            PointerBarrier::horizontal(position as f64, start as f64, end as f64, blocked)
+        };
+        self.barriers.push(BarrierEntry {
+            id: None,
+            barrier: PointerBarrier {
+                release_pressure,
+                ..barrier
+            },
+            pressure: 0.0,
+        });
+    }
+}
+
+// ranges along the edge covered by the outputs matching the predicate
+fn neighbors(
+    outputs: &[Rectangle<i32, Logical>],
+    adjacent: impl Fn(&Rectangle<i32, Logical>) -> bool,
+    vertical: bool,
+) -> Vec<(i32, i32)> {
+    outputs
+        .iter()
+        .filter(|o| adjacent(o))
+        .map(|o| {
+            if vertical {
+                (o.loc.y, o.loc.y + o.size.h)
+            } else {
+                (o.loc.x, o.loc.x + o.size.w)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BarrierDirections, PointerBarrier, PointerBarriers, BARRIER_EPSILON};
+    use crate::utils::Rectangle;
+    #[cfg(all(feature = "desktop", feature = "renderer_pixman"))]
+    use crate::{
+        backend::renderer::snapshot::TestElement,
+        desktop::Space,
+        output::{Mode, Output, PhysicalProperties, Subpixel},
+    };
+
+    #[test]
+    fn slides_along_barrier() {
+        let mut barriers = PointerBarriers::new();
+        let id = barriers.add(PointerBarrier::vertical(
+            100.0,
+            0.0,
+            100.0,
+            BarrierDirections::POSITIVE_X,
+        ));
+        assert_eq!(
+            barriers.apply((90.0, 50.0).into(), (110.0, 60.0).into()),
+            (100.0 - BARRIER_EPSILON, 60.0).into()
+        );
+        // other directions pass
+        assert_eq!(
+            barriers.apply((110.0, 50.0).into(), (90.0, 50.0).into()),
+            (90.0, 50.0).into()
+        );
+        barriers.remove(id);
+        assert_eq!(
+            barriers.apply((90.0, 50.0).into(), (110.0, 60.0).into()),
+            (110.0, 60.0).into()
+        );
+    }
+
+    #[test]
+    fn output_edges() {
+        let mut barriers = PointerBarriers::new();
+        barriers.set_outputs(
+            [
+                Rectangle::from_loc_and_size((0, 0), (100, 100)),
+                Rectangle::from_loc_and_size((100, 0), (100, 50)),
+            ],
+            Some(10.0),
+        );
+
+        // the dead zone below the smaller output is blocked
+        assert_eq!(
+            barriers.apply((90.0, 80.0).into(), (120.0, 80.0).into()),
+            (100.0 - BARRIER_EPSILON, 80.0).into()
+        );
+        // the edge between the outputs is sticky
+        assert_eq!(
+            barriers.apply((95.0, 20.0).into(), (105.0, 20.0).into()),
+            (100.0 - BARRIER_EPSILON, 20.0).into()
+        );
+        assert_eq!(
+            barriers.apply((100.0 - BARRIER_EPSILON, 20.0).into(), (106.0, 20.0).into()),
+            (106.0, 20.0).into()
+        );
+        // leaving the union is blocked
+        assert_eq!(
+            barriers.apply((150.0, 40.0).into(), (150.0, 60.0).into()),
+            (150.0, 50.0 - BARRIER_EPSILON).into()
+        );
+    }
+
+    #[cfg(all(feature = "desktop", feature = "renderer_pixman"))]
+    fn output(size: (i32, i32)) -> Output {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "smithay".into(),
+                model: "test".into(),
+            },
+        );
+        let mode = Mode {
+            size: size.into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+        output
+    }
+
+    #[cfg(all(feature = "desktop", feature = "renderer_pixman"))]
+    #[test]
+    fn focus_under_constrained_location() {
+        // the smaller output leaves a dead zone below it
+        let mut space = Space::default();
+        space.map_output(&output((32, 32)), (0, 0));
+        space.map_output(&output((32, 16)), (32, 0));
+        let inside = TestElement::new([1.0, 0.0, 0.0, 1.0]);
+        let dead_zone = TestElement::new([0.0, 0.0, 1.0, 1.0]);
+        space.map_element(inside.clone(), (24, 20), false);
+        space.map_element(dead_zone.clone(), (32, 20), false);
+
+        let mut barriers = PointerBarriers::new();
+        barriers.set_space_outputs(&space, None);
+        let location = barriers.apply((28.0, 24.0).into(), (36.0, 24.0).into());
+        assert_eq!(location, (32.0 - BARRIER_EPSILON, 24.0).into());
+
+        // the focus is looked up at the constrained location, not inside the dead zone
+        assert_eq!(space.element_under(location).map(|(e, _)| e), Some(&inside));
+        assert_eq!(
+            space.element_under((36.0, 24.0)).map(|(e, _)| e),
+            Some(&dead_zone)
+        );
+    }
+}
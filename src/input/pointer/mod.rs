@@ -12,6 +12,14 @@ use crate::{
     utils::{IsAlive, Logical, Point},
 };
 
+mod acceleration;
+pub use acceleration::{
+    AccelerationProfile, AdaptiveProfile, CustomProfile, FlatProfile, PointerAccelerator,
+};
+
+mod barriers;
+pub use barriers::{BarrierDirections, BarrierId, PointerBarrier, PointerBarriers};
+
 mod cursor_image;
 pub use cursor_icon::CursorIcon;
 pub use cursor_image::{CursorImageAttributes, CursorImageStatus, CursorImageSurfaceData};
@@ -44,6 +52,9 @@ pub struct PointerHandle<D: SeatHandler> {
     pub(crate) known_pinch_gestures: Arc<Mutex<Vec<wayland_protocols::wp::pointer_gestures::zv1::server::zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1>>>,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) known_hold_gestures: Arc<Mutex<Vec<wayland_protocols::wp::pointer_gestures::zv1::server::zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1>>>,
+    /// Surface with an active pointer constraint, if any
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) constrained_surface: Arc<Mutex<Option<wayland_server::protocol::wl_surface::WlSurface>>>,
     pub(crate) span: tracing::Span,
 }
 
@@ -67,6 +78,7 @@ impl<D: SeatHandler> fmt::Debug for PointerHandle<D> {
             .field("known_swipe_gestures", &self.known_swipe_gestures)
             .field("known_pinch_gestures", &self.known_pinch_gestures)
             .field("known_hold_gestures", &self.known_hold_gestures)
+            .field("constrained_surface", &self.constrained_surface)
             .finish()
     }
 }
@@ -87,6 +99,8 @@ impl<D: SeatHandler> Clone for PointerHandle<D> {
             known_pinch_gestures: self.known_pinch_gestures.clone(),
             #[cfg(feature = "wayland_frontend")]
             known_hold_gestures: self.known_hold_gestures.clone(),
+            #[cfg(feature = "wayland_frontend")]
+            constrained_surface: self.constrained_surface.clone(),
             span: self.span.clone(),
         }
     }
@@ -159,6 +173,8 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
             known_pinch_gestures: Arc::new(Mutex::new(Vec::new())),
             #[cfg(feature = "wayland_frontend")]
             known_hold_gestures: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "wayland_frontend")]
+            constrained_surface: Arc::new(Mutex::new(None)),
             span: info_span!("input_pointer"),
         }
     }
@@ -222,6 +238,11 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
     ///
     /// This will internally take care of notifying the appropriate client objects
     /// of enter/motion/leave events.
+    ///
+    /// Active [pointer constraints](crate::wayland::pointer_constraints) of the focused surface
+    /// are enforced: while the pointer is locked the motion is ignored, while it is confined
+    /// the motion is clipped to the confinement region and the focus is kept. The location of
+    /// the current focus is used as the origin of the constrained surface.
     #[instrument(level = "trace", parent = &self.span, skip(self, data, focus), fields(focus = ?focus.as_ref().map(|(_, loc)| ("...", loc))))]
     pub fn motion(
         &self,
@@ -230,6 +251,30 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         event: &MotionEvent,
     ) {
         let mut inner = self.inner.lock().unwrap();
+        #[cfg(feature = "wayland_frontend")]
+        let constrained_event;
+        #[cfg(feature = "wayland_frontend")]
+        let (focus, event) = {
+            use crate::wayland::pointer_constraints::{constrain_motion, ConstrainedMotion};
+
+            let surface = self.constrained_surface.lock().unwrap().clone();
+            match (surface, inner.focus.clone()) {
+                (Some(surface), Some(current)) => {
+                    match constrain_motion(self, &surface, current.1, inner.location, event.location) {
+                        ConstrainedMotion::Unconstrained => (focus, event),
+                        ConstrainedMotion::Locked => return,
+                        ConstrainedMotion::Confined(location) => {
+                            constrained_event = MotionEvent {
+                                location,
+                                ..event.clone()
+                            };
+                            (Some(current), &constrained_event)
+                        }
+                    }
+                }
+                _ => (focus, event),
+            }
+        };
         inner.pending_focus = focus.clone();
         let seat = self.get_seat(data);
         inner.with_grab(&seat, |handle, grab| {
@@ -409,6 +454,15 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         self.inner.lock().unwrap().location
     }
 
+    // Move the pointer relative to the focus without notifying the clients
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) fn warp_in_focus(&self, offset: Point<f64, Logical>) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((_, origin)) = inner.focus {
+            inner.location = origin.to_f64() + offset;
+        }
+    }
+
     /// Access the [`Serial`] of the last `pointer_enter` event, if that focus is still active.
     ///
     /// In other words this will return `None` again, once a `pointer_leave` event occured.
@@ -0,0 +1,131 @@
+//! Tracking of lid and tablet-mode switches
+//!
+//! Every [`Seat`](super::Seat) tracks the state of the switches of its devices, which gets updated
+//! by passing switch events to [`Seat::process_switch_event`](super::Seat::process_switch_event).
+//! Changes are reported through [`SeatHandler::switches_changed`](super::SeatHandler::switches_changed)
+//! and the current state can be queried with [`Seat::switch_states`](super::Seat::switch_states).
+//!
+//! A closed lid usually means the internal panel should be turned off, see
+//! [`SwitchStates::output_enabled`] (requires the `backend_drm` feature). Tablet mode has no
+//! meaning to smithay itself, compositors may use it to adapt their interface or publish it to
+//! clients.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "backend_drm")]
+use drm::control::connector;
+
+use crate::backend::input::{Switch, SwitchState};
+
+/// Combined state of the switches of a seat
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SwitchStates {
+    /// Whether the lid is closed
+    pub lid_closed: bool,
+    /// Whether the device is in tablet mode
+    pub tablet_mode: bool,
+}
+
+#[cfg(feature = "backend_drm")]
+impl SwitchStates {
+    /// Whether an output driven by a connector of the given type should be enabled in this state
+    ///
+    /// This returns `false` for internal panels (see [`is_internal_connector`]) while the lid
+    /// is closed and `true` otherwise.
+    pub fn output_enabled(&self, interface: connector::Interface) -> bool {
+        !(self.lid_closed && is_internal_connector(interface))
+    }
+}
+
+/// Returns whether the connector type is used for the internal panel of a laptop or tablet
+///
+/// The type is available from the [`connector::Info`] of the connector driving an output.
+#[cfg(feature = "backend_drm")]
+pub fn is_internal_connector(interface: connector::Interface) -> bool {
+    matches!(
+        interface,
+        connector::Interface::EmbeddedDisplayPort | connector::Interface::LVDS | connector::Interface::DSI
+    )
+}
+
+/// Tracker for the switches of multiple devices
+///
+/// A switch is considered to be on, if it is on for any of the devices.
+#[derive(Debug, Default)]
+pub struct SwitchTracker {
+    devices: HashMap<(String, Switch), SwitchState>,
+}
+
+impl SwitchTracker {
+    /// Create a new tracker
+    pub fn new() -> SwitchTracker {
+        SwitchTracker::default()
+    }
+
+    /// The combined state of all tracked switches
+    pub fn states(&self) -> SwitchStates {
+        let is_on = |switch: Switch| {
+            self.devices
+                .iter()
+                .any(|((_, s), state)| *s == switch && *state == SwitchState::On)
+        };
+        SwitchStates {
+            lid_closed: is_on(Switch::Lid),
+            tablet_mode: is_on(Switch::TabletMode),
+        }
+    }
+
+    /// Update the state of a switch of the device with the given id
+    ///
+    /// Returns the new combined state, if it changed.
+    pub fn toggle(&mut self, device_id: &str, switch: Switch, state: SwitchState) -> Option<SwitchStates> {
+        let old = self.states();
+        self.devices.insert((device_id.to_owned(), switch), state);
+        Some(self.states()).filter(|new| *new != old)
+    }
+
+    /// Forget the switches of the device with the given id
+    ///
+    /// Returns the new combined state, if it changed.
+    pub fn remove_device(&mut self, device_id: &str) -> Option<SwitchStates> {
+        let old = self.states();
+        self.devices.retain(|(id, _), _| id != device_id);
+        Some(self.states()).filter(|new| *new != old)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SwitchStates, SwitchTracker};
+    use crate::backend::input::{Switch, SwitchState};
+
+    #[test]
+    fn combined_state() {
+        let mut tracker = SwitchTracker::new();
+        assert_eq!(
+            tracker.toggle("lid", Switch::Lid, SwitchState::On),
+            Some(SwitchStates {
+                lid_closed: true,
+                tablet_mode: false,
+            })
+        );
+        assert_eq!(tracker.toggle("other", Switch::Lid, SwitchState::On), None);
+        assert_eq!(tracker.toggle("lid", Switch::Lid, SwitchState::Off), None);
+        assert_eq!(tracker.remove_device("other"), Some(SwitchStates::default()));
+    }
+
+    #[cfg(feature = "backend_drm")]
+    #[test]
+    fn internal_connectors() {
+        use drm::control::connector::Interface;
+
+        let closed = SwitchStates {
+            lid_closed: true,
+            tablet_mode: false,
+        };
+        assert!(!closed.output_enabled(Interface::EmbeddedDisplayPort));
+        assert!(!closed.output_enabled(Interface::LVDS));
+        assert!(closed.output_enabled(Interface::HDMIA));
+        assert!(SwitchStates::default().output_enabled(Interface::DSI));
+    }
+}
@@ -0,0 +1,606 @@
+//! Gesture recognition for touchscreens
+//!
+//! libinput only reports gestures for touchpads. For touchscreens, the [`TouchGestureRecognizer`]
+//! derives gestures from the raw touch events of any input backend:
+//!
+//! - multi-finger swipes, reported with the same structures as touchpad swipes
+//!   (e.g. [`GestureSwipeBeginEvent`])
+//! - multi-finger pinches, reported with the same structures as touchpad pinches
+//!   (e.g. [`GesturePinchBeginEvent`])
+//! - single-finger swipes starting at an edge of the screen, reported as [`EdgeSwipeBeginEvent`],
+//!   [`EdgeSwipeUpdateEvent`] and [`EdgeSwipeEndEvent`]
+//!
+//! The thresholds deciding when a gesture starts can be tuned through a [`TouchGestureConfig`].
+//!
+//! A gesture ends when one of its fingers is lifted. It is cancelled if another finger touches
+//! the screen, if the touch sequence is cancelled by the backend or by calling
+//! [`TouchGestureRecognizer::cancel`]. No further gesture is recognized until all fingers are
+//! lifted.
+//!
+//! ```no_run
+//! use smithay::{
+//!     backend::input::{InputBackend, InputEvent},
+//!     input::touch_gestures::{TouchGestureEvent, TouchGestureRecognizer},
+//!     utils::Rectangle,
+//! };
+//! # fn handle<B: InputBackend>(event: InputEvent<B>) {
+//!
+//! let mut recognizer = TouchGestureRecognizer::new(Default::default());
+//! recognizer.set_area(Rectangle::from_loc_and_size((0, 0), (1920, 1080)));
+//!
+//! for gesture in recognizer.process_event(&event) {
+//!     match gesture {
+//!         TouchGestureEvent::EdgeSwipeEnd(end) if !end.cancelled => { /* switch workspace */ }
+//!         _ => {}
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{
+    backend::input::{AbsolutePositionEvent, Event, InputBackend, InputEvent, TouchEvent, TouchSlot},
+    input::pointer::{
+        GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent,
+        GestureSwipeEndEvent, GestureSwipeUpdateEvent,
+    },
+    utils::{Logical, Point, Rectangle, Serial, SERIAL_COUNTER},
+};
+
+/// Thresholds used to recognize touch gestures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchGestureConfig {
+    /// Minimum number of fingers of a swipe gesture
+    pub swipe_fingers: u32,
+    /// Minimum number of fingers of a pinch gesture
+    ///
+    /// Gestures with [`swipe_fingers`](Self::swipe_fingers) or more fingers are always swipes.
+    pub pinch_fingers: u32,
+    /// Distance in logical pixels the center of the fingers has to move to start a swipe
+    pub swipe_threshold: f64,
+    /// Relative change of the distance between the fingers required to start a pinch
+    pub pinch_threshold: f64,
+    /// Width in logical pixels of the area along the screen edges where edge swipes start
+    pub edge_size: f64,
+    /// Distance in logical pixels a finger has to move away from the edge to start an edge swipe
+    pub edge_swipe_threshold: f64,
+}
+
+impl Default for TouchGestureConfig {
+    fn default() -> Self {
+        TouchGestureConfig {
+            swipe_fingers: 3,
+            pinch_fingers: 2,
+            swipe_threshold: 24.0,
+            pinch_threshold: 0.15,
+            edge_size: 16.0,
+            edge_swipe_threshold: 32.0,
+        }
+    }
+}
+
+/// Edge of the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScreenEdge {
+    /// The top edge
+    Top,
+    /// The bottom edge
+    Bottom,
+    /// The left edge
+    Left,
+    /// The right edge
+    Right,
+}
+
+impl ScreenEdge {
+    // unit vector pointing away from the edge, into the screen
+    fn inward(self) -> Point<f64, Logical> {
+        match self {
+            ScreenEdge::Top => (0.0, 1.0),
+            ScreenEdge::Bottom => (0.0, -1.0),
+            ScreenEdge::Left => (1.0, 0.0),
+            ScreenEdge::Right => (-1.0, 0.0),
+        }
+        .into()
+    }
+}
+
+/// Edge swipe begin event
+#[derive(Debug, Clone)]
+pub struct EdgeSwipeBeginEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Edge the swipe started at
+    pub edge: ScreenEdge,
+}
+
+/// Edge swipe update event
+#[derive(Debug, Clone)]
+pub struct EdgeSwipeUpdateEvent {
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Offset of the finger relative to the previous event
+    pub delta: Point<f64, Logical>,
+}
+
+/// Edge swipe end event
+#[derive(Debug, Clone)]
+pub struct EdgeSwipeEndEvent {
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+    /// Whether the gesture was cancelled
+    pub cancelled: bool,
+}
+
+/// Gesture recognized from touch events
+#[derive(Debug, Clone)]
+pub enum TouchGestureEvent {
+    /// A swipe gesture started
+    SwipeBegin(GestureSwipeBeginEvent),
+    /// A swipe gesture moved
+    SwipeUpdate(GestureSwipeUpdateEvent),
+    /// A swipe gesture ended
+    SwipeEnd(GestureSwipeEndEvent),
+    /// A pinch gesture started
+    PinchBegin(GesturePinchBeginEvent),
+    /// A pinch gesture changed
+    PinchUpdate(GesturePinchUpdateEvent),
+    /// A pinch gesture ended
+    PinchEnd(GesturePinchEndEvent),
+    /// An edge swipe started
+    EdgeSwipeBegin(EdgeSwipeBeginEvent),
+    /// An edge swipe moved
+    EdgeSwipeUpdate(EdgeSwipeUpdateEvent),
+    /// An edge swipe ended
+    EdgeSwipeEnd(EdgeSwipeEndEvent),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TouchPoint {
+    slot: TouchSlot,
+    start: Point<f64, Logical>,
+    current: Point<f64, Logical>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum GestureState {
+    /// No gesture recognized yet
+    Pending,
+    /// A single finger started at an edge
+    EdgeCandidate(ScreenEdge),
+    EdgeSwipe {
+        last: Point<f64, Logical>,
+    },
+    Swipe {
+        last_center: Point<f64, Logical>,
+    },
+    Pinch {
+        initial_spread: f64,
+        last_center: Point<f64, Logical>,
+        last_angle: f64,
+    },
+    /// No gesture is recognized until all fingers are lifted
+    Ignored,
+}
+
+/// Recognizer for touchscreen gestures
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct TouchGestureRecognizer {
+    config: TouchGestureConfig,
+    area: Rectangle<i32, Logical>,
+    touches: Vec<TouchPoint>,
+    state: GestureState,
+}
+
+impl TouchGestureRecognizer {
+    /// Create a new recognizer
+    pub fn new(config: TouchGestureConfig) -> TouchGestureRecognizer {
+        TouchGestureRecognizer {
+            config,
+            area: Rectangle::default(),
+            touches: Vec::new(),
+            state: GestureState::Pending,
+        }
+    }
+
+    /// Get the thresholds of this recognizer
+    pub fn config(&self) -> &TouchGestureConfig {
+        &self.config
+    }
+
+    /// Change the thresholds of this recognizer
+    pub fn set_config(&mut self, config: TouchGestureConfig) {
+        self.config = config;
+    }
+
+    /// Set the area the touchscreen is mapped to
+    ///
+    /// Touch positions of input events are transformed into this area, and edge swipes
+    /// are recognized at its edges.
+    pub fn set_area(&mut self, area: Rectangle<i32, Logical>) {
+        self.area = area;
+    }
+
+    /// Whether a gesture is currently in progress
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.state,
+            GestureState::EdgeSwipe { .. } | GestureState::Swipe { .. } | GestureState::Pinch { .. }
+        )
+    }
+
+    /// Process an input event
+    ///
+    /// Only touch events are taken into account, all other events are ignored.
+    pub fn process_event<B: InputBackend>(&mut self, event: &InputEvent<B>) -> Vec<TouchGestureEvent> {
+        match event {
+            InputEvent::TouchDown { event } => {
+                let position = self.transform(event);
+                self.touch_down(event.slot(), position, event.time_msec())
+            }
+            InputEvent::TouchMotion { event } => {
+                let position = self.transform(event);
+                self.touch_motion(event.slot(), position, event.time_msec())
+            }
+            InputEvent::TouchUp { event } => self.touch_up(event.slot(), event.time_msec()),
+            InputEvent::TouchCancel { event } => {
+                let events = self.cancel(event.time_msec());
+                self.touches.clear();
+                self.state = GestureState::Pending;
+                events
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// A finger touched the screen at the given position
+    pub fn touch_down(
+        &mut self,
+        slot: TouchSlot,
+        position: Point<f64, Logical>,
+        time: u32,
+    ) -> Vec<TouchGestureEvent> {
+        self.touches.retain(|touch| touch.slot != slot);
+        self.touches.push(TouchPoint {
+            slot,
+            start: position,
+            current: position,
+        });
+
+        if self.is_active() {
+            // the number of fingers changed
+            return self.cancel(time);
+        }
+
+        match self.state {
+            GestureState::Pending | GestureState::EdgeCandidate(_) => {
+                // restart recognition with the new set of fingers
+                for touch in &mut self.touches {
+                    touch.start = touch.current;
+                }
+                self.state = match self.touches.len() {
+                    1 => self
+                        .edge_at(position)
+                        .map(GestureState::EdgeCandidate)
+                        .unwrap_or(GestureState::Pending),
+                    _ => GestureState::Pending,
+                };
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    /// A finger moved to the given position
+    pub fn touch_motion(
+        &mut self,
+        slot: TouchSlot,
+        position: Point<f64, Logical>,
+        time: u32,
+    ) -> Vec<TouchGestureEvent> {
+        let Some(touch) = self.touches.iter_mut().find(|touch| touch.slot == slot) else {
+            return Vec::new();
+        };
+        touch.current = position;
+
+        let fingers = self.touches.len() as u32;
+        let center = self.center(|touch| touch.current);
+        let mut events = Vec::new();
+
+        match self.state {
+            GestureState::EdgeCandidate(edge) => {
+                let travelled = position - self.touches[0].start;
+                let inward = edge.inward();
+                if travelled.x * inward.x + travelled.y * inward.y >= self.config.edge_swipe_threshold {
+                    events.push(TouchGestureEvent::EdgeSwipeBegin(EdgeSwipeBeginEvent {
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                        edge,
+                    }));
+                    events.push(TouchGestureEvent::EdgeSwipeUpdate(EdgeSwipeUpdateEvent {
+                        time,
+                        delta: travelled,
+                    }));
+                    self.state = GestureState::EdgeSwipe { last: position };
+                }
+            }
+            GestureState::EdgeSwipe { last } => {
+                events.push(TouchGestureEvent::EdgeSwipeUpdate(EdgeSwipeUpdateEvent {
+                    time,
+                    delta: position - last,
+                }));
+                self.state = GestureState::EdgeSwipe { last: position };
+            }
+            GestureState::Pending if fingers >= 2 => {
+                let start_center = self.center(|touch| touch.start);
+                let initial_spread = self.spread(start_center, |touch| touch.start);
+                let scale = self.spread(center, |touch| touch.current) / initial_spread;
+                let moved = center - start_center;
+                let distance = (moved.x.powi(2) + moved.y.powi(2)).sqrt();
+
+                if (self.config.pinch_fingers..self.config.swipe_fingers).contains(&fingers)
+                    && initial_spread > 0.0
+                    && (scale - 1.0).abs() >= self.config.pinch_threshold
+                {
+                    events.push(TouchGestureEvent::PinchBegin(GesturePinchBeginEvent {
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                        fingers,
+                    }));
+                    events.push(TouchGestureEvent::PinchUpdate(GesturePinchUpdateEvent {
+                        time,
+                        delta: moved,
+                        scale,
+                        rotation: normalize_degrees(self.angle() - self.start_angle()),
+                    }));
+                    self.state = GestureState::Pinch {
+                        initial_spread,
+                        last_center: center,
+                        last_angle: self.angle(),
+                    };
+                } else if fingers >= self.config.swipe_fingers && distance >= self.config.swipe_threshold {
+                    events.push(TouchGestureEvent::SwipeBegin(GestureSwipeBeginEvent {
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time,
+                        fingers,
+                    }));
+                    events.push(TouchGestureEvent::SwipeUpdate(GestureSwipeUpdateEvent {
+                        time,
+                        delta: moved,
+                    }));
+                    self.state = GestureState::Swipe { last_center: center };
+                }
+            }
+            GestureState::Swipe { last_center } => {
+                events.push(TouchGestureEvent::SwipeUpdate(GestureSwipeUpdateEvent {
+                    time,
+                    delta: center - last_center,
+                }));
+                self.state = GestureState::Swipe { last_center: center };
+            }
+            GestureState::Pinch {
+                initial_spread,
+                last_center,
+                last_angle,
+            } => {
+                let angle = self.angle();
+                events.push(TouchGestureEvent::PinchUpdate(GesturePinchUpdateEvent {
+                    time,
+                    delta: center - last_center,
+                    scale: self.spread(center, |touch| touch.current) / initial_spread,
+                    rotation: normalize_degrees(angle - last_angle),
+                }));
+                self.state = GestureState::Pinch {
+                    initial_spread,
+                    last_center: center,
+                    last_angle: angle,
+                };
+            }
+            GestureState::Pending | GestureState::Ignored => {}
+        }
+        events
+    }
+
+    /// A finger was lifted from the screen
+    ///
+    /// This ends any gesture in progress.
+    pub fn touch_up(&mut self, slot: TouchSlot, time: u32) -> Vec<TouchGestureEvent> {
+        self.touches.retain(|touch| touch.slot != slot);
+        let events = self.end(time, false);
+
+        self.state = if self.touches.is_empty() {
+            GestureState::Pending
+        } else {
+            GestureState::Ignored
+        };
+        events
+    }
+
+    /// Cancel the gesture in progress, if any
+    ///
+    /// No new gesture is recognized until all fingers are lifted.
+    pub fn cancel(&mut self, time: u32) -> Vec<TouchGestureEvent> {
+        let events = self.end(time, true);
+        self.state = GestureState::Ignored;
+        events
+    }
+
+    fn end(&mut self, time: u32, cancelled: bool) -> Vec<TouchGestureEvent> {
+        let serial = SERIAL_COUNTER.next_serial();
+        let event = match self.state {
+            GestureState::EdgeSwipe { .. } => TouchGestureEvent::EdgeSwipeEnd(EdgeSwipeEndEvent {
+                serial,
+                time,
+                cancelled,
+            }),
+            GestureState::Swipe { .. } => TouchGestureEvent::SwipeEnd(GestureSwipeEndEvent {
+                serial,
+                time,
+                cancelled,
+            }),
+            GestureState::Pinch { .. } => TouchGestureEvent::PinchEnd(GesturePinchEndEvent {
+                serial,
+                time,
+                cancelled,
+            }),
+            _ => return Vec::new(),
+        };
+        vec![event]
+    }
+
+    fn transform<B: InputBackend, E: AbsolutePositionEvent<B> + TouchEvent<B>>(
+        &self,
+        event: &E,
+    ) -> Point<f64, Logical> {
+        self.area.loc.to_f64()
+            + Point::from((
+                event.x_transformed(self.area.size.w),
+                event.y_transformed(self.area.size.h),
+            ))
+    }
+
+    fn edge_at(&self, position: Point<f64, Logical>) -> Option<ScreenEdge> {
+        let area = self.area.to_f64();
+        let size = self.config.edge_size;
+        if position.y - area.loc.y < size {
+            Some(ScreenEdge::Top)
+        } else if area.loc.y + area.size.h - position.y < size {
+            Some(ScreenEdge::Bottom)
+        } else if position.x - area.loc.x < size {
+            Some(ScreenEdge::Left)
+        } else if area.loc.x + area.size.w - position.x < size {
+            Some(ScreenEdge::Right)
+        } else {
+            None
+        }
+    }
+
+    fn center(&self, position: impl Fn(&TouchPoint) -> Point<f64, Logical>) -> Point<f64, Logical> {
+        let sum = self
+            .touches
+            .iter()
+            .fold(Point::default(), |sum, touch| sum + position(touch));
+        sum.downscale(self.touches.len().max(1) as f64)
+    }
+
+    // average distance of the fingers to the center
+    fn spread(
+        &self,
+        center: Point<f64, Logical>,
+        position: impl Fn(&TouchPoint) -> Point<f64, Logical>,
+    ) -> f64 {
+        let sum = self
+            .touches
+            .iter()
+            .map(|touch| {
+                let offset = position(touch) - center;
+                (offset.x.powi(2) + offset.y.powi(2)).sqrt()
+            })
+            .sum::<f64>();
+        sum / self.touches.len().max(1) as f64
+    }
+
+    // angle in degrees of the line between the first two fingers
+    fn angle(&self) -> f64 {
+        let offset = self.touches[1].current - self.touches[0].current;
+        offset.y.atan2(offset.x).to_degrees()
+    }
+
+    fn start_angle(&self) -> f64 {
+        let offset = self.touches[1].start - self.touches[0].start;
+        offset.y.atan2(offset.x).to_degrees()
+    }
+}
+
+// normalize an angle difference to the range of -180 to 180 degrees
+fn normalize_degrees(angle: f64) -> f64 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScreenEdge, TouchGestureConfig, TouchGestureEvent, TouchGestureRecognizer};
+    use crate::{backend::input::TouchSlot, utils::Rectangle};
+
+    fn recognizer() -> TouchGestureRecognizer {
+        let mut recognizer = TouchGestureRecognizer::new(TouchGestureConfig::default());
+        recognizer.set_area(Rectangle::from_loc_and_size((0, 0), (1000, 1000)));
+        recognizer
+    }
+
+    fn slot(id: u32) -> TouchSlot {
+        Some(id).into()
+    }
+
+    #[test]
+    fn edge_swipe() {
+        let mut recognizer = recognizer();
+        assert!(recognizer
+            .touch_down(slot(0), (500.0, 995.0).into(), 0)
+            .is_empty());
+        // moving along the edge does not start a swipe
+        assert!(recognizer
+            .touch_motion(slot(0), (540.0, 990.0).into(), 1)
+            .is_empty());
+
+        let events = recognizer.touch_motion(slot(0), (540.0, 950.0).into(), 2);
+        assert!(matches!(
+            events[0],
+            TouchGestureEvent::EdgeSwipeBegin(ref begin) if begin.edge == ScreenEdge::Bottom
+        ));
+        assert!(recognizer.is_active());
+
+        let events = recognizer.touch_motion(slot(0), (540.0, 900.0).into(), 3);
+        assert!(matches!(
+            events[..],
+            [TouchGestureEvent::EdgeSwipeUpdate(ref update)] if update.delta == (0.0, -50.0).into()
+        ));
+
+        let events = recognizer.touch_up(slot(0), 4);
+        assert!(matches!(
+            events[..],
+            [TouchGestureEvent::EdgeSwipeEnd(ref end)] if !end.cancelled
+        ));
+        assert!(!recognizer.is_active());
+    }
+
+    #[test]
+    fn swipe_and_cancel() {
+        let mut recognizer = recognizer();
+        for i in 0..3 {
+            recognizer.touch_down(slot(i), (400.0 + i as f64 * 50.0, 500.0).into(), 0);
+        }
+        for i in 0..3 {
+            recognizer.touch_motion(slot(i), (400.0 + i as f64 * 50.0, 450.0).into(), 1);
+        }
+        assert!(recognizer.is_active());
+
+        // another finger cancels the gesture
+        let events = recognizer.touch_down(slot(3), (700.0, 500.0).into(), 2);
+        assert!(matches!(events[..], [TouchGestureEvent::SwipeEnd(ref end)] if end.cancelled));
+        assert!(recognizer
+            .touch_motion(slot(0), (400.0, 300.0).into(), 3)
+            .is_empty());
+    }
+
+    #[test]
+    fn pinch() {
+        let mut recognizer = recognizer();
+        recognizer.touch_down(slot(0), (400.0, 500.0).into(), 0);
+        recognizer.touch_down(slot(1), (600.0, 500.0).into(), 0);
+        assert!(recognizer
+            .touch_motion(slot(0), (390.0, 500.0).into(), 1)
+            .is_empty());
+        let events = recognizer.touch_motion(slot(1), (710.0, 500.0).into(), 2);
+        assert!(matches!(events[0], TouchGestureEvent::PinchBegin(ref begin) if begin.fingers == 2));
+        assert!(matches!(
+            events[1],
+            TouchGestureEvent::PinchUpdate(ref update) if update.scale == 1.6 && update.rotation == 0.0
+        ));
+    }
+}
@@ -3,6 +3,7 @@
 #[cfg(feature = "backend_vulkan")]
 pub use ash;
 pub use calloop;
+pub use cgmath;
 #[cfg(feature = "dbus")]
 pub use dbus;
 #[cfg(feature = "backend_drm")]
@@ -32,6 +32,9 @@ pub(crate) struct InputMethod {
     pub instance: Option<Instance>,
     pub popup_handle: PopupHandle,
     pub keyboard_grab: InputMethodKeyboardGrab,
+    /// Text to type into an XWayland surface on the next commit
+    #[cfg(feature = "xwayland")]
+    pub xwayland_commit: Option<String>,
 }
 
 #[derive(Debug)]
@@ -194,9 +197,16 @@ where
     ) {
         match request {
             zwp_input_method_v2::Request::CommitString { text } => {
+                #[allow(unused_mut)]
+                let mut text = Some(text);
                 data.text_input_handle.with_focused_text_input(|ti, _surface| {
-                    ti.commit_string(Some(text.clone()));
+                    ti.commit_string(text.take());
                 });
+                // X11 clients have no text-input, the text gets typed into them on commit instead.
+                #[cfg(feature = "xwayland")]
+                if text.is_some() {
+                    data.handle.inner.lock().unwrap().xwayland_commit = text;
+                }
             }
             zwp_input_method_v2::Request::SetPreeditString {
                 text,
@@ -227,6 +237,14 @@ where
                     .unwrap_or(0);
 
                 data.text_input_handle.done(serial != current_serial);
+
+                #[cfg(feature = "xwayland")]
+                {
+                    let text = data.handle.inner.lock().unwrap().xwayland_commit.take();
+                    if let Some(text) = text.filter(|_| serial == current_serial) {
+                        super::xwayland::commit_string(&data.keyboard_handle, &text);
+                    }
+                }
             }
             zwp_input_method_v2::Request::GetInputPopupSurface { id, surface } => {
                 if compositor::give_role(&surface, INPUT_POPUP_SURFACE_ROLE).is_err()
@@ -3,6 +3,10 @@
 //! This module provides you with utilities to handle input methods,
 //! it must be used in conjunction with the text input module to work.
 //!
+//! With the `xwayland` feature, input methods also work for X11 clients, which don't support
+//! text-input. The input method gets activated while an XWayland surface has keyboard focus
+//! and committed text is typed into it using synthetic key events.
+//!
 //! ```
 //! use smithay::{
 //!     delegate_seat, delegate_input_method_manager, delegate_text_input_manager,
@@ -82,7 +86,11 @@ pub const INPUT_POPUP_SURFACE_ROLE: &str = "zwp_input_popup_surface_v2";
 mod input_method_handle;
 mod input_method_keyboard_grab;
 mod input_method_popup_surface;
+#[cfg(feature = "xwayland")]
+mod xwayland;
 pub use input_method_popup_surface::{PopupParent, PopupSurface};
+#[cfg(feature = "xwayland")]
+pub(crate) use xwayland::is_xwayland_surface;
 
 /// Adds input method popup to compositor state
 pub trait InputMethodHandler {
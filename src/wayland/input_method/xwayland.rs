@@ -0,0 +1,182 @@
+//! Input method support for X11 clients running through XWayland
+//!
+//! XWayland doesn't implement text-input, so X11 clients never enable it and input methods
+//! can't reach them through the regular path. Instead, the input method is activated whenever
+//! an XWayland surface gains keyboard focus, and the committed text is typed into the focused
+//! window as synthetic key presses. For this a temporary keymap containing the keysyms of the
+//! committed characters is sent to XWayland, which gets replaced by the keymap of the seat
+//! right afterwards.
+//!
+//! Preedit strings and requests to delete surrounding text can't be represented this way and
+//! are ignored for X11 clients.
+
+use std::{fmt::Write, os::unix::io::AsFd, time::Duration};
+
+use tracing::warn;
+use wayland_server::{
+    protocol::{
+        wl_keyboard::{KeyState, KeymapFormat, WlKeyboard},
+        wl_surface::WlSurface,
+    },
+    Resource,
+};
+use xkbcommon::xkb::{self, Keysym};
+
+use crate::{
+    input::{
+        keyboard::{KeyboardHandle, KeymapFile, ModifiersState},
+        SeatHandler,
+    },
+    utils::{Clock, Monotonic, SERIAL_COUNTER},
+    wayland::seat::WaylandFocus,
+    xwayland::XWaylandClientData,
+};
+
+/// Highest keycode XWayland can deliver to X11 clients
+const MAX_KEYCODE: u32 = 255;
+
+/// First keycode of the temporary keymap, keycode 8 translates to the reserved evdev code 0
+const MIN_KEYCODE: u32 = 9;
+
+/// Returns whether the surface belongs to the XWayland client
+pub(crate) fn is_xwayland_surface(surface: &WlSurface) -> bool {
+    surface
+        .client()
+        .map(|client| client.get_data::<XWaylandClientData>().is_some())
+        .unwrap_or(false)
+}
+
+/// Type `text` into the keyboard focus, if it is an XWayland surface
+///
+/// Returns `false` if the focus is not an XWayland surface.
+///
+/// The temporary keymaps are only sent to the keyboards of the XWayland client, other clients
+/// keep the keymap of the seat.
+pub(crate) fn commit_string<D>(keyboard: &KeyboardHandle<D>, text: &str) -> bool
+where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::KeyboardFocus: WaylandFocus,
+{
+    let (surface, mods) = {
+        let internal = keyboard.arc.internal.lock().unwrap();
+        let surface = internal.focus.as_ref().and_then(|(focus, _)| focus.wl_surface());
+        (surface, internal.mods_state)
+    };
+    let Some(surface) = surface.filter(is_xwayland_surface) else {
+        return false;
+    };
+    let xwayland_kbds = keyboard
+        .arc
+        .known_kbds
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|kbd| kbd.id().same_client_as(&surface.id()))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let keysyms = text
+        .chars()
+        .map(|c| xkb::utf32_to_keysym(c as u32))
+        .filter(|keysym| keysym.raw() != xkb::keysyms::KEY_NoSymbol)
+        .collect::<Vec<_>>();
+    let time = Duration::from(Clock::<Monotonic>::new().now()).as_millis() as u32;
+
+    for chunk in keysyms.chunks((MAX_KEYCODE - MIN_KEYCODE + 1) as usize) {
+        let keymap = match text_keymap(chunk) {
+            Some(keymap) => KeymapFile::new(&keymap),
+            None => {
+                warn!("Failed to compile keymap for input method text");
+                break;
+            }
+        };
+        send_keymap(&xwayland_kbds, &keymap, ModifiersState::default());
+
+        for kbd in &xwayland_kbds {
+            for key in (MIN_KEYCODE..).take(chunk.len()) {
+                // wl_keyboard uses evdev keycodes, which are offset by 8 from xkb keycodes
+                kbd.key(SERIAL_COUNTER.next_serial().0, time, key - 8, KeyState::Pressed);
+                kbd.key(SERIAL_COUNTER.next_serial().0, time, key - 8, KeyState::Released);
+            }
+        }
+    }
+
+    // Restore the keymap of the seat.
+    let keymap_file = keyboard.arc.keymap.lock().unwrap();
+    send_keymap(&xwayland_kbds, &keymap_file, mods);
+
+    true
+}
+
+/// Send a keymap followed by the modifier state to the given keyboards
+fn send_keymap(kbds: &[WlKeyboard], keymap_file: &KeymapFile, mods: ModifiersState) {
+    let serial = SERIAL_COUNTER.next_serial();
+    let mods = mods.serialized;
+    for kbd in kbds {
+        let res = keymap_file.with_fd(kbd.version() >= 7, |fd, size| {
+            kbd.keymap(KeymapFormat::XkbV1, fd.as_fd(), size as u32)
+        });
+        if let Err(err) = res {
+            warn!(?err, "Failed to send keymap to client");
+        }
+        kbd.modifiers(
+            serial.into(),
+            mods.depressed,
+            mods.latched,
+            mods.locked,
+            mods.layout_effective,
+        );
+    }
+}
+
+/// Compile a keymap assigning the keysyms to consecutive keycodes starting at [`MIN_KEYCODE`]
+fn text_keymap(keysyms: &[Keysym]) -> Option<xkb::Keymap> {
+    let max_keycode = MIN_KEYCODE + keysyms.len() as u32 - 1;
+    let mut keycodes = String::new();
+    let mut symbols = String::new();
+    for (keycode, keysym) in (MIN_KEYCODE..).zip(keysyms) {
+        let _ = writeln!(keycodes, "<K{keycode}> = {keycode};");
+        let _ = writeln!(
+            symbols,
+            "key <K{keycode}> {{ [ {} ] }};",
+            xkb::keysym_get_name(*keysym)
+        );
+    }
+
+    let keymap = format!(
+        "xkb_keymap {{\n\
+         xkb_keycodes \"(unnamed)\" {{\nminimum = 8;\nmaximum = {max_keycode};\n{keycodes}}};\n\
+         xkb_types \"(unnamed)\" {{ include \"complete\" }};\n\
+         xkb_compatibility \"(unnamed)\" {{ include \"complete\" }};\n\
+         xkb_symbols \"(unnamed)\" {{\n{symbols}}};\n\
+         }};\n"
+    );
+
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    xkb::Keymap::new_from_string(
+        &context,
+        keymap,
+        xkb::KEYMAP_FORMAT_TEXT_V1,
+        xkb::KEYMAP_COMPILE_NO_FLAGS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use xkbcommon::xkb;
+
+    use super::{text_keymap, MIN_KEYCODE};
+
+    #[test]
+    fn keymap_contains_text() {
+        let keysyms = "a你"
+            .chars()
+            .map(|c| xkb::utf32_to_keysym(c as u32))
+            .collect::<Vec<_>>();
+        let keymap = text_keymap(&keysyms).unwrap();
+        for (keycode, keysym) in (MIN_KEYCODE..).zip(&keysyms) {
+            let syms = keymap.key_get_syms_by_level(xkb::Keycode::new(keycode), 0, 0);
+            assert_eq!(syms, &[*keysym]);
+        }
+    }
+}
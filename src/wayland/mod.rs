@@ -59,6 +59,8 @@ pub mod keyboard_shortcuts_inhibit;
 pub mod output;
 pub mod pointer_constraints;
 pub mod pointer_gestures;
+#[cfg(feature = "wayland_pointer_warp")]
+pub mod pointer_warp;
 pub mod presentation;
 pub mod relative_pointer;
 pub mod seat;
@@ -2,6 +2,12 @@
 //!
 //! This provides a way for the client to request that the pointer is confined to a region or
 //! locked in place.
+//!
+//! Constraints are not activated automatically, compositors decide when to activate them with
+//! [`PointerConstraintRef::activate`], e.g. from [`PointerConstraintsHandler::new_constraint`]
+//! or once the pointer enters the region of the constraint. Active constraints are enforced by
+//! [`PointerHandle::motion`]: a locked pointer does not move, a confined pointer is kept inside
+//! the confinement region.
 use std::{
     collections::{hash_map, HashMap},
     ops,
@@ -11,6 +17,7 @@ use std::{
     },
 };
 
+use tracing::debug;
 use wayland_protocols::wp::pointer_constraints::zv1::server::{
     zwp_confined_pointer_v1::{self, ZwpConfinedPointerV1},
     zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
@@ -21,11 +28,12 @@ use wayland_server::{
     GlobalDispatch, New, Resource, WEnum,
 };
 
-use super::compositor::{self, RegionAttributes};
+use super::compositor::{self, BufferAssignment, RegionAttributes, SurfaceData};
 use crate::{
+    backend::renderer::{buffer_dimensions, utils::RendererSurfaceStateUserData},
     input::{pointer::PointerHandle, SeatHandler},
-    utils::{Logical, Point},
-    wayland::seat::PointerUserData,
+    utils::{Logical, Point, Rectangle, Size},
+    wayland::{seat::PointerUserData, viewporter::ViewportCachedState},
 };
 
 const VERSION: u32 = 1;
@@ -94,6 +102,7 @@ pub enum PointerConstraint {
 #[derive(Debug)]
 pub struct PointerConstraintRef<'a, D: SeatHandler + 'static> {
     entry: hash_map::OccupiedEntry<'a, PointerHandle<D>, PointerConstraint>,
+    surface: &'a WlSurface,
 }
 
 impl<'a, D: SeatHandler + 'static> ops::Deref for PointerConstraintRef<'a, D> {
@@ -110,6 +119,7 @@ impl<'a, D: SeatHandler + 'static> PointerConstraintRef<'a, D> {
     /// This is not sent automatically since compositors may have different
     /// policies about when to allow and activate constraints.
     pub fn activate(&self) {
+        *self.entry.key().constrained_surface.lock().unwrap() = Some(self.surface.clone());
         match self.entry.get() {
             PointerConstraint::Confined(confined) => {
                 confined.handle.confined();
@@ -127,8 +137,29 @@ impl<'a, D: SeatHandler + 'static> PointerConstraintRef<'a, D> {
     /// For oneshot constraints, will destroy the constraint.
     ///
     /// This is sent automatically when the surface loses pointer focus, but
-    /// may also be invoked while the surface is focused.
+    /// may also be invoked while the surface is focused. In that case, the pointer is moved to
+    /// the cursor position hint of an active lock, if the client provided one. Like any other
+    /// method of the [`PointerHandle`], this must not be called from within a pointer grab.
     pub fn deactivate(self) {
+        let hint = match self.entry.get() {
+            PointerConstraint::Locked(locked) if self.is_active() => locked.cursor_position_hint,
+            _ => None,
+        };
+        let pointer = self.entry.key().clone();
+        self.deactivate_without_warp();
+        if let Some(hint) = hint {
+            pointer.warp_in_focus(hint);
+        }
+    }
+
+    // deactivate without moving the pointer, used when the surface loses focus during a pointer grab
+    pub(crate) fn deactivate_without_warp(self) {
+        {
+            let mut constrained_surface = self.entry.key().constrained_surface.lock().unwrap();
+            if constrained_surface.as_ref() == Some(self.surface) {
+                *constrained_surface = None;
+            }
+        }
         match self.entry.get() {
             PointerConstraint::Confined(confined) => {
                 confined.handle.unconfined();
@@ -236,7 +267,7 @@ pub fn with_pointer_constraint<
 ) -> T {
     with_constraint_data::<D, _, _>(surface, |data| {
         let constraint = data.and_then(|data| match data.constraints.entry(pointer.clone()) {
-            hash_map::Entry::Occupied(entry) => Some(PointerConstraintRef { entry }),
+            hash_map::Entry::Occupied(entry) => Some(PointerConstraintRef { entry, surface }),
             hash_map::Entry::Vacant(_) => None,
         });
         f(constraint)
@@ -309,6 +340,213 @@ fn remove_constraint<D: SeatHandler + 'static>(surface: &WlSurface, pointer: &Po
             data.constraints.remove(pointer);
         }
     });
+    let mut constrained_surface = pointer.constrained_surface.lock().unwrap();
+    if constrained_surface.as_ref() == Some(surface) {
+        *constrained_surface = None;
+    }
+}
+
+/// Distance the pointer is kept inside the edges of a confinement region
+///
+/// This matches the precision of `wl_fixed`, so clients see the pointer inside the region.
+const CONFINE_EPSILON: f64 = 1.0 / 256.0;
+
+/// Effect of the constraint of a surface on a pointer motion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ConstrainedMotion {
+    /// The surface has no active constraint
+    Unconstrained,
+    /// The pointer is locked and must not move
+    Locked,
+    /// The pointer is confined and moves to the given location
+    Confined(Point<f64, Logical>),
+}
+
+/// Apply the constraint of a surface to a pointer motion
+///
+/// `surface_origin` is the location of the constrained surface in the same coordinate space as
+/// the pointer location. A confined motion is clipped to the intersection of the surface, its input
+/// region and the region of the constraint. The pointer stops at the edges of the region and slides
+/// along them.
+///
+/// The size of the surface is taken from its [`RendererSurfaceStateUserData`] or, if the
+/// compositor does not use it, from the buffer of its current
+/// [`SurfaceAttributes`](compositor::SurfaceAttributes). If the compositor took the buffer from
+/// the attributes without tracking it otherwise, only the regions apply.
+pub(crate) fn constrain_motion<D: SeatHandler + 'static>(
+    pointer: &PointerHandle<D>,
+    surface: &WlSurface,
+    surface_origin: Point<i32, Logical>,
+    from: Point<f64, Logical>,
+    to: Point<f64, Logical>,
+) -> ConstrainedMotion {
+    if !surface.is_alive() {
+        return ConstrainedMotion::Unconstrained;
+    }
+    let confine_region = with_pointer_constraint(surface, pointer, |constraint| match constraint {
+        Some(constraint) if constraint.is_active() => match &*constraint {
+            PointerConstraint::Locked(_) => Err(()),
+            PointerConstraint::Confined(confined) => Ok(Some(confined.region().cloned())),
+        },
+        _ => Ok(None),
+    });
+    let region = match confine_region {
+        Err(()) => return ConstrainedMotion::Locked,
+        Ok(None) => return ConstrainedMotion::Unconstrained,
+        Ok(Some(region)) => region,
+    };
+
+    let (surface_size, input_region) = compositor::with_states(surface, |states| {
+        let surface_size = surface_size(states);
+        let input_region = states
+            .cached_state
+            .current::<compositor::SurfaceAttributes>()
+            .input_region
+            .clone();
+        (surface_size, input_region)
+    });
+
+    // without a buffer, the size of the surface is unknown and only the regions apply
+    let mut rects = surface_size.map(|size| vec![Rectangle::from_loc_and_size((0, 0), size)]);
+    for region in [input_region, region].iter().flatten() {
+        let region = region_rects(region);
+        rects = Some(match rects {
+            Some(rects) => rects
+                .iter()
+                .flat_map(|rect| region.iter().filter_map(move |other| rect.intersection(*other)))
+                .collect(),
+            None => region,
+        });
+    }
+    let Some(rects) = rects else {
+        debug!(
+            ?surface,
+            "Size of the confining surface is unknown, not confining the pointer"
+        );
+        return ConstrainedMotion::Confined(to);
+    };
+
+    let origin = surface_origin.to_f64();
+    ConstrainedMotion::Confined(clip_motion(&rects, from - origin, to - origin) + origin)
+}
+
+/// Size of the surface in logical coordinates, if it has a buffer
+fn surface_size(states: &SurfaceData) -> Option<Size<i32, Logical>> {
+    let renderer_size = states
+        .data_map
+        .get::<RendererSurfaceStateUserData>()
+        .and_then(|data| data.borrow().surface_size());
+    if renderer_size.is_some() {
+        return renderer_size;
+    }
+
+    // the buffer stays in the surface state, unless it is taken by the compositor
+    let attributes = states.cached_state.current::<compositor::SurfaceAttributes>();
+    let size = match &attributes.buffer {
+        Some(BufferAssignment::NewBuffer(buffer)) => {
+            buffer_dimensions(buffer)?.to_logical(attributes.buffer_scale, attributes.buffer_transform.into())
+        }
+        _ => return None,
+    };
+    let viewport = states.cached_state.current::<ViewportCachedState>();
+    Some(viewport.size().unwrap_or(size))
+}
+
+/// Rectangles whose union forms the region
+fn region_rects(region: &RegionAttributes) -> Vec<Rectangle<i32, Logical>> {
+    let mut rects = Vec::new();
+    for (kind, rect) in &region.rects {
+        match kind {
+            compositor::RectangleKind::Add => rects.push(*rect),
+            compositor::RectangleKind::Subtract => {
+                rects = rects.into_iter().flat_map(|r| r.subtract_rect(*rect)).collect();
+            }
+        }
+    }
+    rects
+}
+
+/// Clip a motion to the union of the rectangles
+///
+/// The motion is followed through adjacent rectangles, until it either ends inside of one or
+/// leaves the region. In the latter case, the pointer slides along the edge of the last
+/// rectangle it was in, so it cannot jump across gaps of concave regions.
+fn clip_motion(
+    rects: &[Rectangle<i32, Logical>],
+    from: Point<f64, Logical>,
+    to: Point<f64, Logical>,
+) -> Point<f64, Logical> {
+    let rects = rects.iter().map(|rect| rect.to_f64()).collect::<Vec<_>>();
+    let contains = |rect: &Rectangle<f64, Logical>, point: Point<f64, Logical>| rect.contains(point);
+    let clamp = |rect: &Rectangle<f64, Logical>, point: Point<f64, Logical>| -> Point<f64, Logical> {
+        (
+            point
+                .x
+                .clamp(rect.loc.x, rect.loc.x + rect.size.w - CONFINE_EPSILON),
+            point
+                .y
+                .clamp(rect.loc.y, rect.loc.y + rect.size.h - CONFINE_EPSILON),
+        )
+            .into()
+    };
+
+    let Some(mut current) = rects.iter().find(|rect| contains(rect, from)) else {
+        // not inside the region yet, move to the closest point of it
+        return rects
+            .iter()
+            .map(|rect| clamp(rect, to))
+            .min_by(|a, b| {
+                let distance = |p: Point<f64, Logical>| (p.x - to.x).powi(2) + (p.y - to.y).powi(2);
+                distance(*a).total_cmp(&distance(*b))
+            })
+            .unwrap_or(from);
+    };
+
+    let delta = to - from;
+    let mut from = from;
+    for _ in 0..=rects.len() {
+        if contains(current, to) {
+            return to;
+        }
+
+        // parameter of the motion at which it leaves the current rectangle
+        let exit = |position: f64, delta: f64, start: f64, size: f64| {
+            if delta > 0.0 {
+                (start + size - position) / delta
+            } else if delta < 0.0 {
+                (start - position) / delta
+            } else {
+                f64::INFINITY
+            }
+        };
+        let t_x = exit(from.x, delta.x, current.loc.x, current.size.w);
+        let t_y = exit(from.y, delta.y, current.loc.y, current.size.h);
+
+        // the point just across the edge that is crossed first
+        let mut next = from + delta.upscale(t_x.min(t_y));
+        if t_x <= t_y {
+            next.x = if delta.x > 0.0 {
+                current.loc.x + current.size.w
+            } else {
+                current.loc.x - CONFINE_EPSILON
+            };
+        } else {
+            next.y = if delta.y > 0.0 {
+                current.loc.y + current.size.h
+            } else {
+                current.loc.y - CONFINE_EPSILON
+            };
+        }
+
+        match rects.iter().find(|rect| contains(rect, next)) {
+            Some(rect) => {
+                current = rect;
+                from = next;
+            }
+            None => break,
+        }
+    }
+    clamp(current, to)
 }
 
 impl<D> Dispatch<ZwpPointerConstraintsV1, (), D> for PointerConstraintsState
@@ -518,3 +756,333 @@ macro_rules! delegate_pointer_constraints {
         ] => $crate::wayland::pointer_constraints::PointerConstraintsState);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::unix::{io::AsFd, net::UnixStream},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    use wayland_client::{
+        protocol::{
+            wl_buffer, wl_callback, wl_compositor, wl_pointer, wl_registry, wl_seat, wl_shm, wl_shm_pool,
+            wl_surface,
+        },
+        Connection, Dispatch as ClientDispatch, EventQueue, Proxy, QueueHandle,
+    };
+    use wayland_protocols::wp::pointer_constraints::zv1::client::{
+        zwp_confined_pointer_v1, zwp_pointer_constraints_v1,
+    };
+    use wayland_server::{backend::ClientData, protocol::wl_surface::WlSurface, Client, Display};
+
+    use super::{
+        clip_motion, with_pointer_constraint, PointerConstraintsHandler, PointerConstraintsState,
+        CONFINE_EPSILON,
+    };
+    use crate::{
+        input::{
+            pointer::{MotionEvent, PointerHandle},
+            SeatHandler, SeatState,
+        },
+        utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+        wayland::{
+            buffer::BufferHandler,
+            compositor::{CompositorClientState, CompositorHandler, CompositorState},
+            shm::{ShmHandler, ShmState},
+        },
+    };
+
+    struct ServerState {
+        compositor_state: CompositorState,
+        seat_state: SeatState<Self>,
+        shm_state: ShmState,
+    }
+
+    #[derive(Default)]
+    struct ServerClientData {
+        compositor_state: CompositorClientState,
+    }
+
+    impl ClientData for ServerClientData {}
+
+    impl CompositorHandler for ServerState {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+            &client.get_data::<ServerClientData>().unwrap().compositor_state
+        }
+
+        // the buffers are not handed to a renderer
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    impl SeatHandler for ServerState {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    impl PointerConstraintsHandler for ServerState {
+        fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+            with_pointer_constraint(surface, pointer, |constraint| constraint.unwrap().activate());
+        }
+    }
+
+    impl BufferHandler for ServerState {
+        fn buffer_destroyed(&mut self, _buffer: &wayland_server::protocol::wl_buffer::WlBuffer) {}
+    }
+
+    impl ShmHandler for ServerState {
+        fn shm_state(&self) -> &ShmState {
+            &self.shm_state
+        }
+    }
+
+    crate::delegate_compositor!(ServerState);
+    crate::delegate_seat!(ServerState);
+    crate::delegate_shm!(ServerState);
+    crate::delegate_pointer_constraints!(ServerState);
+
+    #[derive(Default)]
+    struct ClientState {
+        globals: Vec<(u32, String, u32)>,
+    }
+
+    impl ClientDispatch<wl_registry::WlRegistry, ()> for ClientState {
+        fn event(
+            state: &mut Self,
+            _proxy: &wl_registry::WlRegistry,
+            event: wl_registry::Event,
+            _data: &(),
+            _conn: &Connection,
+            _qhandle: &QueueHandle<Self>,
+        ) {
+            if let wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } = event
+            {
+                state.globals.push((name, interface, version));
+            }
+        }
+    }
+
+    impl ClientDispatch<wl_callback::WlCallback, Arc<AtomicBool>> for ClientState {
+        fn event(
+            _state: &mut Self,
+            _proxy: &wl_callback::WlCallback,
+            _event: wl_callback::Event,
+            done: &Arc<AtomicBool>,
+            _conn: &Connection,
+            _qhandle: &QueueHandle<Self>,
+        ) {
+            done.store(true, Ordering::SeqCst);
+        }
+    }
+
+    wayland_client::delegate_noop!(ClientState: ignore wl_compositor::WlCompositor);
+    wayland_client::delegate_noop!(ClientState: ignore wl_surface::WlSurface);
+    wayland_client::delegate_noop!(ClientState: ignore wl_seat::WlSeat);
+    wayland_client::delegate_noop!(ClientState: ignore wl_pointer::WlPointer);
+    wayland_client::delegate_noop!(ClientState: ignore wl_shm::WlShm);
+    wayland_client::delegate_noop!(ClientState: ignore wl_shm_pool::WlShmPool);
+    wayland_client::delegate_noop!(ClientState: ignore wl_buffer::WlBuffer);
+    wayland_client::delegate_noop!(ClientState: ignore zwp_pointer_constraints_v1::ZwpPointerConstraintsV1);
+    wayland_client::delegate_noop!(ClientState: ignore zwp_confined_pointer_v1::ZwpConfinedPointerV1);
+
+    /// A display with a single client connected over a socket pair
+    struct Fixture {
+        display: Display<ServerState>,
+        server: ServerState,
+        server_client: Client,
+        pointer: PointerHandle<ServerState>,
+        queue: EventQueue<ClientState>,
+        client: ClientState,
+        registry: wl_registry::WlRegistry,
+        connection: Connection,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let display = Display::<ServerState>::new().unwrap();
+            let mut dh = display.handle();
+            let mut seat_state = SeatState::new();
+            let mut seat = seat_state.new_wl_seat(&dh, "seat");
+            let pointer = seat.add_pointer();
+            let server = ServerState {
+                compositor_state: CompositorState::new::<ServerState>(&dh),
+                seat_state,
+                shm_state: ShmState::new::<ServerState>(&dh, Vec::new()),
+            };
+            PointerConstraintsState::new::<ServerState>(&dh);
+
+            let (server_stream, client_stream) = UnixStream::pair().unwrap();
+            let server_client = dh
+                .insert_client(server_stream, Arc::new(ServerClientData::default()))
+                .unwrap();
+            let connection = Connection::from_socket(client_stream).unwrap();
+            let queue = connection.new_event_queue();
+            let registry = connection.display().get_registry(&queue.handle(), ());
+
+            let mut fixture = Fixture {
+                display,
+                server,
+                server_client,
+                pointer,
+                queue,
+                client: ClientState::default(),
+                registry,
+                connection,
+            };
+            fixture.roundtrip();
+            fixture
+        }
+
+        fn bind<I>(&self) -> I
+        where
+            I: Proxy + 'static,
+            ClientState: ClientDispatch<I, ()>,
+        {
+            let (name, _, version) = self
+                .client
+                .globals
+                .iter()
+                .find(|(_, interface, _)| interface == I::interface().name)
+                .unwrap();
+            self.registry.bind(
+                *name,
+                (*version).min(I::interface().version),
+                &self.queue.handle(),
+                (),
+            )
+        }
+
+        /// Dispatch requests and events until the server answered all previous requests
+        fn roundtrip(&mut self) {
+            let done = Arc::new(AtomicBool::new(false));
+            self.connection.display().sync(&self.queue.handle(), done.clone());
+            while !done.load(Ordering::SeqCst) {
+                self.queue.flush().unwrap();
+                self.display.dispatch_clients(&mut self.server).unwrap();
+                self.display.flush_clients().unwrap();
+                if let Some(guard) = self.queue.prepare_read() {
+                    guard.read().unwrap();
+                }
+                self.queue.dispatch_pending(&mut self.client).unwrap();
+            }
+        }
+
+        fn motion(&mut self, focus: Option<(WlSurface, Point<i32, Logical>)>, location: (f64, f64)) {
+            self.pointer.motion(
+                &mut self.server,
+                focus,
+                &MotionEvent {
+                    location: location.into(),
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: 0,
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn confine_without_renderer_state() {
+        let mut fixture = Fixture::new();
+        let compositor = fixture.bind::<wl_compositor::WlCompositor>();
+        let seat = fixture.bind::<wl_seat::WlSeat>();
+        let shm = fixture.bind::<wl_shm::WlShm>();
+        let constraints = fixture.bind::<zwp_pointer_constraints_v1::ZwpPointerConstraintsV1>();
+        let qh = fixture.queue.handle();
+        let pointer = seat.get_pointer(&qh, ());
+
+        // a 20x10 buffer with a scale of 2
+        let file = tempfile::tempfile().unwrap();
+        file.set_len(20 * 10 * 4).unwrap();
+        let pool = shm.create_pool(file.as_fd(), 20 * 10 * 4, &qh, ());
+        let buffer = pool.create_buffer(0, 20, 10, 20 * 4, wl_shm::Format::Argb8888, &qh, ());
+        let surface = compositor.create_surface(&qh, ());
+        surface.attach(Some(&buffer), 0, 0);
+        surface.set_buffer_scale(2);
+        surface.commit();
+        let _confined = constraints.confine_pointer(
+            &surface,
+            &pointer,
+            None,
+            zwp_pointer_constraints_v1::Lifetime::Persistent,
+            &qh,
+            (),
+        );
+        fixture.roundtrip();
+
+        let surface = fixture
+            .server_client
+            .object_from_protocol_id::<WlSurface>(&fixture.display.handle(), surface.id().protocol_id())
+            .unwrap();
+        fixture.motion(Some((surface.clone(), (100, 100).into())), (102.0, 102.0));
+        fixture.motion(Some((surface, (100, 100).into())), (150.0, 103.0));
+        assert_eq!(
+            fixture.pointer.current_location(),
+            (110.0 - CONFINE_EPSILON, 103.0).into()
+        );
+    }
+
+    #[test]
+    fn concave_region() {
+        // L-shaped region
+        let rects = [
+            Rectangle::from_loc_and_size((0, 0), (100, 20)),
+            Rectangle::from_loc_and_size((0, 0), (20, 100)),
+        ];
+
+        // motion inside the region is unaffected
+        assert_eq!(
+            clip_motion(&rects, (10.0, 90.0).into(), (10.0, 5.0).into()),
+            (10.0, 5.0).into()
+        );
+        // the pointer slides along the inner edge instead of cutting the corner
+        assert_eq!(
+            clip_motion(&rects, (10.0, 50.0).into(), (50.0, 10.0).into()),
+            (20.0 - CONFINE_EPSILON, 10.0).into()
+        );
+        // motion continues into the adjacent part of the region
+        assert_eq!(
+            clip_motion(&rects, (15.0, 21.0).into(), (60.0, 5.0).into()),
+            (60.0, 5.0).into()
+        );
+        // outer edges stop the pointer
+        assert_eq!(
+            clip_motion(&rects, (50.0, 10.0).into(), (150.0, -10.0).into()),
+            (100.0 - CONFINE_EPSILON, 0.0).into()
+        );
+    }
+
+    #[test]
+    fn no_jumping_across_gaps() {
+        // U-shaped region
+        let rects = [
+            Rectangle::from_loc_and_size((0, 0), (20, 100)),
+            Rectangle::from_loc_and_size((0, 80), (100, 20)),
+            Rectangle::from_loc_and_size((80, 0), (20, 100)),
+        ];
+
+        assert_eq!(
+            clip_motion(&rects, (10.0, 10.0).into(), (90.0, 10.0).into()),
+            (20.0 - CONFINE_EPSILON, 10.0).into()
+        );
+        // the straight path leaves the region, the pointer slides along the bottom instead
+        assert_eq!(
+            clip_motion(&rects, (10.0, 90.0).into(), (90.0, 10.0).into()),
+            (90.0, 80.0).into()
+        );
+    }
+}
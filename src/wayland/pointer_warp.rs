@@ -0,0 +1,641 @@
+//! Utilities for pointer warp support
+//!
+//! This protocol allows clients, like games or remote desktop viewers, to request the pointer to
+//! be moved to a location on one of their surfaces.
+//!
+//! Requests are only passed on to the [`PointerWarpHandler`] if the surface has pointer focus,
+//! the serial matches the last pointer enter and the location is allowed by the active
+//! [pointer constraint](super::pointer_constraints), if any. Whether the pointer is actually
+//! moved is up to the compositor.
+//!
+//! The protocol is generated from a vendored copy of its definition and requires the
+//! `wayland_pointer_warp` feature.
+//!
+//! ```
+//! use smithay::delegate_pointer_warp;
+//! use smithay::input::{SeatState, SeatHandler, pointer::PointerHandle};
+//! use smithay::wayland::pointer_warp::{PointerWarpHandler, PointerWarpManagerState};
+//! use smithay::reexports::wayland_server::{Display, protocol::wl_surface::WlSurface};
+//! use smithay::utils::{Logical, Point};
+//!
+//! # struct State { seat_state: SeatState<Self> };
+//! # let mut display = Display::<State>::new().unwrap();
+//! # let display_handle = display.handle();
+//! impl SeatHandler for State {
+//!     type KeyboardFocus = WlSurface;
+//!     type PointerFocus = WlSurface;
+//!     fn seat_state(&mut self) -> &mut SeatState<Self> {
+//!         &mut self.seat_state
+//!     }
+//! }
+//!
+//! impl PointerWarpHandler for State {
+//!     fn warp_pointer(
+//!         &mut self,
+//!         surface: WlSurface,
+//!         pointer: PointerHandle<Self>,
+//!         location: Point<f64, Logical>,
+//!     ) {
+//!         // Translate the location into global coordinates, check that it lies within the surface
+//!         // and move the pointer by calling `PointerHandle::motion`.
+//!     }
+//! }
+//!
+//! let state = PointerWarpManagerState::new::<State>(&display_handle);
+//!
+//! delegate_pointer_warp!(State);
+//! ```
+
+use wayland_server::{
+    backend::GlobalId, protocol::wl_surface::WlSurface, Client, DataInit, Dispatch, DisplayHandle,
+    GlobalDispatch, New, Resource,
+};
+
+use super::{
+    compositor,
+    pointer_constraints::{with_pointer_constraint, PointerConstraint},
+    seat::PointerUserData,
+};
+use crate::{
+    input::{pointer::PointerHandle, SeatHandler},
+    utils::{Logical, Point},
+    wayland::seat::WaylandFocus,
+};
+
+use self::protocol::wp_pointer_warp_v1::{self, WpPointerWarpV1};
+
+/// Server-side bindings of the pointer warp protocol
+///
+/// The protocol is not part of the version of `wayland-protocols` smithay depends on yet.
+#[allow(missing_docs, clippy::all)]
+#[allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+#[allow(non_upper_case_globals, non_snake_case, unused_imports)]
+pub mod protocol {
+    use wayland_server;
+    use wayland_server::protocol::*;
+
+    pub mod __interfaces {
+        use wayland_server::backend as wayland_backend;
+        use wayland_server::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/pointer-warp-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!("protocols/pointer-warp-v1.xml");
+}
+
+const VERSION: u32 = 1;
+
+/// Handler for pointer warp requests
+pub trait PointerWarpHandler: SeatHandler {
+    /// A client requested to move the `pointer` to `location` on `surface`
+    ///
+    /// The location is relative to the origin of the surface. Compositors should ignore requests
+    /// for locations outside of the surface, which can't be checked by smithay.
+    /// Otherwise the pointer can be moved using [`PointerHandle::motion`], which will also update
+    /// the location of pointer constraints.
+    fn warp_pointer(
+        &mut self,
+        surface: WlSurface,
+        pointer: PointerHandle<Self>,
+        location: Point<f64, Logical>,
+    );
+}
+
+/// State of the pointer warp manager
+#[derive(Debug)]
+pub struct PointerWarpManagerState {
+    global: GlobalId,
+}
+
+impl PointerWarpManagerState {
+    /// Register a new [WpPointerWarpV1] global
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<WpPointerWarpV1, ()>,
+        D: Dispatch<WpPointerWarpV1, ()>,
+        D: PointerWarpHandler,
+        D: 'static,
+    {
+        let global = display.create_global::<D, WpPointerWarpV1, _>(VERSION, ());
+        Self { global }
+    }
+
+    /// [WpPointerWarpV1] GlobalId getter
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+impl<D> GlobalDispatch<WpPointerWarpV1, (), D> for PointerWarpManagerState
+where
+    D: GlobalDispatch<WpPointerWarpV1, ()>,
+    D: Dispatch<WpPointerWarpV1, ()>,
+    D: PointerWarpHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpPointerWarpV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WpPointerWarpV1, (), D> for PointerWarpManagerState
+where
+    D: Dispatch<WpPointerWarpV1, ()>,
+    D: PointerWarpHandler,
+    <D as SeatHandler>::PointerFocus: WaylandFocus,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &WpPointerWarpV1,
+        request: wp_pointer_warp_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_pointer_warp_v1::Request::WarpPointer {
+                surface,
+                pointer,
+                x,
+                y,
+                serial,
+            } => {
+                let Some(handle) = pointer
+                    .data::<PointerUserData<D>>()
+                    .and_then(|data| data.handle.clone())
+                else {
+                    return;
+                };
+
+                // Ignore mismatches in serial.
+                if handle.last_enter().map(|last_serial| last_serial.0) != Some(serial) {
+                    return;
+                }
+
+                // Check that the surface has pointer focus, the focus may be one of its subsurfaces.
+                let focused = handle
+                    .current_focus()
+                    .and_then(|focus| focus.wl_surface())
+                    .map(|focus| root_surface(focus) == root_surface(surface.clone()))
+                    .unwrap_or(false);
+                if !focused {
+                    return;
+                }
+
+                let location = Point::<f64, Logical>::from((x, y));
+                if location.x < 0.0 || location.y < 0.0 {
+                    return;
+                }
+
+                // Locked pointers must not move and confined ones must stay inside their region.
+                let allowed = with_pointer_constraint(&surface, &handle, |constraint| match constraint {
+                    Some(constraint) if constraint.is_active() => match &*constraint {
+                        PointerConstraint::Locked(_) => false,
+                        PointerConstraint::Confined(confined) => confined
+                            .region()
+                            .map(|region| region.contains(location.to_i32_floor()))
+                            .unwrap_or(true),
+                    },
+                    _ => true,
+                });
+                if !allowed {
+                    return;
+                }
+
+                state.warp_pointer(surface, handle, location);
+            }
+            wp_pointer_warp_v1::Request::Destroy => {}
+        }
+    }
+}
+
+fn root_surface(mut surface: WlSurface) -> WlSurface {
+    while let Some(parent) = compositor::get_parent(&surface) {
+        surface = parent;
+    }
+    surface
+}
+
+/// Macro to delegate implementation of the pointer warp protocol
+#[macro_export]
+macro_rules! delegate_pointer_warp {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::pointer_warp::protocol::wp_pointer_warp_v1::WpPointerWarpV1: ()
+        ] => $crate::wayland::pointer_warp::PointerWarpManagerState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::pointer_warp::protocol::wp_pointer_warp_v1::WpPointerWarpV1: ()
+        ] => $crate::wayland::pointer_warp::PointerWarpManagerState);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::unix::net::UnixStream,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    use wayland_client::{
+        protocol::{
+            wl_callback, wl_compositor, wl_pointer, wl_registry, wl_seat, wl_subcompositor, wl_subsurface,
+            wl_surface,
+        },
+        Connection, Dispatch as ClientDispatch, EventQueue, Proxy, QueueHandle,
+    };
+    use wayland_protocols::wp::pointer_constraints::zv1::client::{
+        zwp_locked_pointer_v1, zwp_pointer_constraints_v1,
+    };
+    use wayland_server::{backend::ClientData, protocol::wl_surface::WlSurface, Client, Display};
+
+    use super::{PointerWarpHandler, PointerWarpManagerState};
+    use crate::{
+        input::{
+            pointer::{MotionEvent, PointerHandle},
+            SeatHandler, SeatState,
+        },
+        utils::{Logical, Point, SERIAL_COUNTER},
+        wayland::{
+            compositor::{CompositorClientState, CompositorHandler, CompositorState},
+            pointer_constraints::{
+                with_pointer_constraint, PointerConstraintsHandler, PointerConstraintsState,
+            },
+        },
+    };
+
+    use self::client_protocol::wp_pointer_warp_v1::WpPointerWarpV1;
+
+    mod client_protocol {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::backend as wayland_backend;
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/pointer-warp-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/pointer-warp-v1.xml");
+    }
+
+    struct ServerState {
+        compositor_state: CompositorState,
+        seat_state: SeatState<Self>,
+        warps: Vec<(WlSurface, Point<f64, Logical>)>,
+    }
+
+    #[derive(Default)]
+    struct ServerClientData {
+        compositor_state: CompositorClientState,
+    }
+
+    impl ClientData for ServerClientData {}
+
+    impl CompositorHandler for ServerState {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+            &client.get_data::<ServerClientData>().unwrap().compositor_state
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    impl SeatHandler for ServerState {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    impl PointerConstraintsHandler for ServerState {
+        fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+            with_pointer_constraint(surface, pointer, |constraint| constraint.unwrap().activate());
+        }
+    }
+
+    impl PointerWarpHandler for ServerState {
+        fn warp_pointer(
+            &mut self,
+            surface: WlSurface,
+            _pointer: PointerHandle<Self>,
+            location: Point<f64, Logical>,
+        ) {
+            self.warps.push((surface, location));
+        }
+    }
+
+    crate::delegate_compositor!(ServerState);
+    crate::delegate_seat!(ServerState);
+    crate::delegate_pointer_constraints!(ServerState);
+    crate::delegate_pointer_warp!(ServerState);
+
+    #[derive(Default)]
+    struct ClientState {
+        globals: Vec<(u32, String, u32)>,
+        enter_serial: Option<u32>,
+    }
+
+    impl ClientDispatch<wl_registry::WlRegistry, ()> for ClientState {
+        fn event(
+            state: &mut Self,
+            _proxy: &wl_registry::WlRegistry,
+            event: wl_registry::Event,
+            _data: &(),
+            _conn: &Connection,
+            _qhandle: &QueueHandle<Self>,
+        ) {
+            if let wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } = event
+            {
+                state.globals.push((name, interface, version));
+            }
+        }
+    }
+
+    impl ClientDispatch<wl_pointer::WlPointer, ()> for ClientState {
+        fn event(
+            state: &mut Self,
+            _proxy: &wl_pointer::WlPointer,
+            event: wl_pointer::Event,
+            _data: &(),
+            _conn: &Connection,
+            _qhandle: &QueueHandle<Self>,
+        ) {
+            if let wl_pointer::Event::Enter { serial, .. } = event {
+                state.enter_serial = Some(serial);
+            }
+        }
+    }
+
+    impl ClientDispatch<wl_callback::WlCallback, Arc<AtomicBool>> for ClientState {
+        fn event(
+            _state: &mut Self,
+            _proxy: &wl_callback::WlCallback,
+            _event: wl_callback::Event,
+            done: &Arc<AtomicBool>,
+            _conn: &Connection,
+            _qhandle: &QueueHandle<Self>,
+        ) {
+            done.store(true, Ordering::SeqCst);
+        }
+    }
+
+    wayland_client::delegate_noop!(ClientState: ignore wl_compositor::WlCompositor);
+    wayland_client::delegate_noop!(ClientState: ignore wl_subcompositor::WlSubcompositor);
+    wayland_client::delegate_noop!(ClientState: ignore wl_subsurface::WlSubsurface);
+    wayland_client::delegate_noop!(ClientState: ignore wl_surface::WlSurface);
+    wayland_client::delegate_noop!(ClientState: ignore wl_seat::WlSeat);
+    wayland_client::delegate_noop!(ClientState: ignore WpPointerWarpV1);
+    wayland_client::delegate_noop!(ClientState: ignore zwp_pointer_constraints_v1::ZwpPointerConstraintsV1);
+    wayland_client::delegate_noop!(ClientState: ignore zwp_locked_pointer_v1::ZwpLockedPointerV1);
+
+    /// A display with a single client connected over a socket pair
+    struct Fixture {
+        display: Display<ServerState>,
+        server: ServerState,
+        server_client: Client,
+        pointer: PointerHandle<ServerState>,
+        queue: EventQueue<ClientState>,
+        client: ClientState,
+        registry: wl_registry::WlRegistry,
+        connection: Connection,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let display = Display::<ServerState>::new().unwrap();
+            let mut dh = display.handle();
+            let mut seat_state = SeatState::new();
+            let mut seat = seat_state.new_wl_seat(&dh, "seat");
+            let pointer = seat.add_pointer();
+            let server = ServerState {
+                compositor_state: CompositorState::new::<ServerState>(&dh),
+                seat_state,
+                warps: Vec::new(),
+            };
+            PointerConstraintsState::new::<ServerState>(&dh);
+            PointerWarpManagerState::new::<ServerState>(&dh);
+
+            let (server_stream, client_stream) = UnixStream::pair().unwrap();
+            let server_client = dh
+                .insert_client(server_stream, Arc::new(ServerClientData::default()))
+                .unwrap();
+            let connection = Connection::from_socket(client_stream).unwrap();
+            let queue = connection.new_event_queue();
+            let registry = connection.display().get_registry(&queue.handle(), ());
+
+            let mut fixture = Fixture {
+                display,
+                server,
+                server_client,
+                pointer,
+                queue,
+                client: ClientState::default(),
+                registry,
+                connection,
+            };
+            fixture.roundtrip();
+            fixture
+        }
+
+        fn bind<I>(&self) -> I
+        where
+            I: Proxy + 'static,
+            ClientState: ClientDispatch<I, ()>,
+        {
+            let (name, _, version) = self
+                .client
+                .globals
+                .iter()
+                .find(|(_, interface, _)| interface == I::interface().name)
+                .unwrap();
+            self.registry.bind(
+                *name,
+                (*version).min(I::interface().version),
+                &self.queue.handle(),
+                (),
+            )
+        }
+
+        /// Dispatch requests and events until the server answered all previous requests
+        fn roundtrip(&mut self) {
+            let done = Arc::new(AtomicBool::new(false));
+            self.connection.display().sync(&self.queue.handle(), done.clone());
+            while !done.load(Ordering::SeqCst) {
+                self.queue.flush().unwrap();
+                self.display.dispatch_clients(&mut self.server).unwrap();
+                self.display.flush_clients().unwrap();
+                if let Some(guard) = self.queue.prepare_read() {
+                    guard.read().unwrap();
+                }
+                self.queue.dispatch_pending(&mut self.client).unwrap();
+            }
+        }
+
+        fn server_surface(&self, surface: &wl_surface::WlSurface) -> WlSurface {
+            self.server_client
+                .object_from_protocol_id(&self.display.handle(), surface.id().protocol_id())
+                .unwrap()
+        }
+
+        /// Move the pointer onto the surface and return the serial of the enter event
+        fn focus(&mut self, surface: &wl_surface::WlSurface) -> u32 {
+            self.roundtrip();
+            let surface = self.server_surface(surface);
+            self.pointer.motion(
+                &mut self.server,
+                Some((surface, (0, 0).into())),
+                &MotionEvent {
+                    location: (0.0, 0.0).into(),
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: 0,
+                },
+            );
+            self.roundtrip();
+            self.client.enter_serial.unwrap()
+        }
+    }
+
+    struct TestClient {
+        compositor: wl_compositor::WlCompositor,
+        pointer: wl_pointer::WlPointer,
+        warp: WpPointerWarpV1,
+    }
+
+    impl TestClient {
+        fn new(fixture: &mut Fixture) -> Self {
+            let compositor = fixture.bind::<wl_compositor::WlCompositor>();
+            let seat = fixture.bind::<wl_seat::WlSeat>();
+            let pointer = seat.get_pointer(&fixture.queue.handle(), ());
+            let warp = fixture.bind::<WpPointerWarpV1>();
+            fixture.roundtrip();
+            TestClient {
+                compositor,
+                pointer,
+                warp,
+            }
+        }
+
+        fn surface(&self, fixture: &Fixture) -> wl_surface::WlSurface {
+            self.compositor.create_surface(&fixture.queue.handle(), ())
+        }
+    }
+
+    #[test]
+    fn warp_focused_surface() {
+        let mut fixture = Fixture::new();
+        let client = TestClient::new(&mut fixture);
+        let surface = client.surface(&fixture);
+        let serial = fixture.focus(&surface);
+
+        client
+            .warp
+            .warp_pointer(&surface, &client.pointer, 10.0, 20.0, serial);
+        fixture.roundtrip();
+
+        assert_eq!(
+            fixture.server.warps,
+            vec![(fixture.server_surface(&surface), (10.0, 20.0).into())]
+        );
+    }
+
+    #[test]
+    fn ignore_invalid_requests() {
+        let mut fixture = Fixture::new();
+        let client = TestClient::new(&mut fixture);
+        let surface = client.surface(&fixture);
+        let other = client.surface(&fixture);
+        let serial = fixture.focus(&surface);
+
+        // serial mismatch
+        client
+            .warp
+            .warp_pointer(&surface, &client.pointer, 10.0, 20.0, serial + 1);
+        // surface without pointer focus
+        client
+            .warp
+            .warp_pointer(&other, &client.pointer, 10.0, 20.0, serial);
+        // location outside of the surface
+        client
+            .warp
+            .warp_pointer(&surface, &client.pointer, -1.0, 20.0, serial);
+        fixture.roundtrip();
+
+        assert!(fixture.server.warps.is_empty());
+    }
+
+    #[test]
+    fn warp_subsurface_tree() {
+        let mut fixture = Fixture::new();
+        let client = TestClient::new(&mut fixture);
+        let subcompositor = fixture.bind::<wl_subcompositor::WlSubcompositor>();
+        let parent = client.surface(&fixture);
+        let child = client.surface(&fixture);
+        let _subsurface = subcompositor.get_subsurface(&child, &parent, &fixture.queue.handle(), ());
+
+        // the focus is a subsurface of the requested surface
+        let serial = fixture.focus(&child);
+        client
+            .warp
+            .warp_pointer(&parent, &client.pointer, 10.0, 20.0, serial);
+        // and the other way around
+        client
+            .warp
+            .warp_pointer(&child, &client.pointer, 1.0, 2.0, serial);
+        fixture.roundtrip();
+
+        assert_eq!(
+            fixture.server.warps,
+            vec![
+                (fixture.server_surface(&parent), (10.0, 20.0).into()),
+                (fixture.server_surface(&child), (1.0, 2.0).into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignore_locked_pointer() {
+        let mut fixture = Fixture::new();
+        let client = TestClient::new(&mut fixture);
+        let constraints = fixture.bind::<zwp_pointer_constraints_v1::ZwpPointerConstraintsV1>();
+        let surface = client.surface(&fixture);
+        let serial = fixture.focus(&surface);
+
+        let _locked = constraints.lock_pointer(
+            &surface,
+            &client.pointer,
+            None,
+            zwp_pointer_constraints_v1::Lifetime::Persistent,
+            &fixture.queue.handle(),
+            (),
+        );
+        client
+            .warp
+            .warp_pointer(&surface, &client.pointer, 10.0, 20.0, serial);
+        fixture.roundtrip();
+
+        assert!(fixture.server.warps.is_empty());
+    }
+}
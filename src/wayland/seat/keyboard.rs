@@ -169,6 +169,13 @@ impl<D: SeatHandler + 'static> KeyboardTarget<D> for WlSurface {
         if input_method.has_instance() {
             text_input.enter();
         }
+
+        // X11 clients can't enable text-input, so the IME is activated right away for them.
+        #[cfg(feature = "xwayland")]
+        if input_method.has_instance() && crate::wayland::input_method::is_xwayland_surface(self) {
+            input_method.activate_input_method(state, self);
+            input_method.with_instance(|instance| instance.done());
+        }
     }
 
     fn leave(&self, seat: &Seat<D>, state: &mut D, serial: Serial) {
@@ -200,7 +200,7 @@ where
             *pointer.last_enter.lock().unwrap() = None;
             with_pointer_constraint(self, &pointer, |constraint| {
                 if let Some(constraint) = constraint {
-                    constraint.deactivate();
+                    constraint.deactivate_without_warp();
                 }
             });
         }